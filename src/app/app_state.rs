@@ -1,76 +1,1886 @@
-use std::{io, sync::Arc, time::Duration};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
+use base64::{Engine, engine::general_purpose::STANDARD};
 use clap::Parser;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{
     DefaultTerminal, Frame,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
 };
 use tokio::sync::mpsc::{self, UnboundedReceiver};
 
 use crate::{
-    command::{Cli, Command, EndpointAction},
-    server::ServerState,
-    util::result::InternalResult,
+    command::{
+        AuthAction, AuthScheme, ChaosAction, Cli, Command, ConfigAction, ConnectionAction,
+        CorsAction, CoverageAction, EndpointAction, ExportFormat, FallbackAction, FilterAction,
+        GrpcAction, HostAction, InboxAction, JournalAction, LogAction, LogLevel, MetricsAction,
+        MiddlewareAction, MutateAction, OauthAction, PresetAction, RateLimitAction, RedactAction,
+        RewriteAction, ScenarioAction, ServerAction, SpecAction, SuggestAction, TenantAction,
+        TlsAction, VerifyAction, split_input,
+    },
+    config::{self, EndpointFile, ScenarioFile, StartupConfig},
+    formats,
+    grpc::{GrpcListener, GrpcMock},
+    logger::{self, LogEntry, LogSink, LoggerHandle, SinkTarget},
+    preset::{self, PresetSource},
+    server::{
+        AuthRule, Budget, Callback, ChaosRule, CorsConfig, Credentials, Definition, EndpointScope,
+        EventStream, Expectation, Fault, Hangup, ImportConflict, MiddlewareChain, MutationMode,
+        OAuthMock, OnConflict, Pagination, RateLimiter, Redirect, ReplayFilter, ReplaySelection,
+        ReplayTarget, Representation, RequestRecord, RewriteRule, ScenarioRun, ServerHealth,
+        ServerState, ServerSupervisor, Stage, Stub, StubSuggestion, TlsListener, Transform,
+        curl_command, replay, suggest_stubs,
+    },
+    util::{build, error::InternalError, result::InternalResult, units::format_size},
+    watch::FileWatch,
+};
+
+use super::{
+    completion::{self, Completion},
+    control::ControlSocket,
+    input::InputLine,
+    tutorial::Tutorial,
+    ui::{
+        CommandPane, EndpointsPane, HelpPane, InboxPane, InputMode, LogFilter, LogPane,
+        ProfileBanner, RequestInspector, RequestsPane, StatusBar, Tab, TabBar, TutorialPane,
+        UsagePane, log_groups, shown_entries,
+    },
+    usage::Usage,
 };
 
-use super::ui::{CommandPane, InputMode, LogPane};
+/// Default number of log entries kept in memory
+const DEFAULT_MESSAGE_CAPACITY: usize = 10_000;
+/// How often headless mode prints logs and checks the server health
+const HEADLESS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Exit status of headless mode, so pipelines can tell kinds of failures apart. The most severe
+/// failure wins, in the reverse order of declaration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExitStatus {
+    Success = 0,
+    /// A verification run on exit failed
+    VerificationFailed = 1,
+    /// The startup configuration or a command run on exit was invalid or failed, e.g. loading
+    /// a file
+    ConfigError = 2,
+    /// The server went down, e.g. because its address is in use
+    ServerFailure = 3,
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
 
 #[derive(Debug)]
 pub struct App {
-    pub input: String,
-    pub messages: Vec<String>,
+    pub input: InputLine,
+    pub messages: VecDeque<LogEntry>,
+    /// Maximum number of kept log entries, oldest are dropped first
+    message_capacity: usize,
+    log_filter: LogFilter,
+    /// Index into `messages` of the selected log entry
+    log_selected: Option<usize>,
+    /// Groups of repeated log entries shown one by one, by the index of their first entry
+    log_expanded: HashSet<usize>,
+    /// Journal id of the request shown in the inspector
+    inspected: Option<u64>,
+    /// Number of the captured request shown on the inbox tab
+    inbox_shown: Option<usize>,
+    /// Endpoints proposed by the last `suggest`, referenced by number when accepting
+    suggestions: Vec<StubSuggestion>,
     history: Vec<String>,
     history_index: Option<usize>,
+    /// Which of several completion candidates is offered as ghost text
+    completion_index: usize,
     mode: InputMode,
+    tab: Tab,
+    show_endpoints: bool,
+    /// See [`LogPane::guide`]
+    log_guide: bool,
+    /// Name of the project loaded from the working directory, if any
+    project: Option<String>,
     exit: bool,
-    log_rx: UnboundedReceiver<String>,
+    log_rx: UnboundedReceiver<LogEntry>,
+    logger: LoggerHandle,
     server_state: Arc<ServerState>,
+    supervisor: ServerSupervisor,
+    health: ServerHealth,
+    started: Instant,
+    /// Endpoint files re-applied when they change
+    watches: Vec<FileWatch>,
+    tutorial: Option<Tutorial>,
+    usage: Usage,
+    /// Whether the TUI is drawn, as opposed to running headless
+    tui: bool,
+    control: Option<ControlSocket>,
+    /// Methods and replies answered by the gRPC listener
+    grpc: Arc<GrpcMock>,
+    grpc_listener: Option<GrpcListener>,
 }
 
 impl App {
-    pub fn new(log_rx: mpsc::UnboundedReceiver<String>, server_state: Arc<ServerState>) -> Self {
+    pub fn new(
+        log_rx: mpsc::UnboundedReceiver<LogEntry>,
+        logger: LoggerHandle,
+        server_state: Arc<ServerState>,
+        supervisor: ServerSupervisor,
+    ) -> Self {
         Self {
-            input: String::new(),
-            messages: Vec::new(),
+            input: InputLine::default(),
+            messages: VecDeque::new(),
+            message_capacity: DEFAULT_MESSAGE_CAPACITY,
+            log_filter: LogFilter::default(),
+            log_selected: None,
+            log_expanded: HashSet::new(),
+            inspected: None,
+            inbox_shown: None,
+            suggestions: Vec::new(),
             mode: InputMode::default(),
+            tab: Tab::default(),
+            show_endpoints: false,
+            log_guide: false,
+            project: None,
             exit: false,
             log_rx,
+            logger,
             server_state,
+            supervisor,
+            health: ServerHealth::Starting,
+            started: Instant::now(),
+            watches: Vec::new(),
+            tutorial: None,
+            usage: Usage::default(),
+            tui: false,
+            control: None,
+            grpc: Arc::new(GrpcMock::default()),
+            grpc_listener: None,
             history: Vec::new(),
             history_index: None,
+            completion_index: 0,
         }
     }
 
+    /// Apply the parts of the startup configuration read from `path` that live in the TUI, and
+    /// its endpoints. The server parts are applied before the server starts.
+    pub fn apply_startup_config(&mut self, path: &Path, config: StartupConfig) {
+        if let Some(level) = config.log_level {
+            self.logger.set_level(level.into());
+        }
+        for def in config.log_sinks {
+            self.add_log_sink(def.target, def.level);
+        }
+        if let Some(tab) = config.tui.tab {
+            self.tab = tab;
+        }
+        self.show_endpoints = config.tui.show_endpoints;
+        self.log_guide = config.tui.log_guide;
+        if let Some(capacity) = config.tui.log_capacity {
+            self.message_capacity = capacity.get();
+        }
+        if let Some(mut profile) = config.profile {
+            if profile.name.is_empty() {
+                profile.name = path
+                    .file_stem()
+                    .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+            }
+            // Headless runs have no banner to show it in
+            match &profile.banner {
+                Some(banner) => log::info!("Profile {}: {}", profile.name, banner),
+                None => log::info!("Profile {}", profile.name),
+            }
+            *self.server_state.profile_mut() = Some(profile);
+        }
+        if config.endpoints.is_empty() {
+            return;
+        }
+        let file = EndpointFile {
+            endpoints: config.endpoints,
+            ..Default::default()
+        };
+        if let Err(e) = self.import_endpoints(path, file, OnConflict::Overwrite, None) {
+            report_error(&e);
+        }
+    }
+
+    /// Load the endpoint files of a project in `dir`, if there is one. Files loaded later win
+    /// conflicts, and a broken file doesn't prevent loading the others.
+    pub fn load_project(&mut self, dir: &Path) {
+        let files = match config::project_files(dir) {
+            Ok(files) => files,
+            Err(e) => return report_error(&e),
+        };
+        if files.is_empty() {
+            return;
+        }
+        for file in &files {
+            if let Err(e) = self.import_file(file, OnConflict::Overwrite, None) {
+                report_error(&e);
+            }
+        }
+        let name = dir.file_name().map_or_else(
+            || dir.display().to_string(),
+            |n| n.to_string_lossy().into_owned(),
+        );
+        log::info!(
+            "Loaded project {} ({} file(s), {} endpoints)",
+            name,
+            files.len(),
+            self.server_state.read_endpoints().len()
+        );
+        self.server_state.datasets.set_dir(dir);
+        self.project = Some(name);
+    }
+
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        self.tui = true;
+        if self.logger.remove_sink(&SinkTarget::StdoutJson) {
+            log::warn!("Not logging to stdout, it only works in headless mode");
+        }
         while !self.exit {
             while let Ok(msg) = self.log_rx.try_recv() {
-                self.messages.push(msg)
+                self.push_message(msg)
             }
+            self.check_health();
+            self.check_tutorial();
+            self.answer_control_commands();
             terminal.draw(|frame| self.draw(frame))?;
             self.handle_events()?;
         }
         Ok(())
     }
 
+    /// Serve without the TUI until `exit` is set, e.g. by a signal, or the server goes down,
+    /// writing logs to stderr. With `stdin`, commands are read from it one per line and answered
+    /// on stdout until it is closed, which ends the run as well. Runs the `on_exit` commands
+    /// before returning, each given as its arguments or as a single line.
+    pub fn run_headless(
+        &mut self,
+        exit: &AtomicBool,
+        stdin: bool,
+        on_exit: &[Vec<String>],
+    ) -> ExitStatus {
+        let mut status = ExitStatus::Success;
+        let commands = stdin.then(read_stdin_lines);
+        while !exit.load(Ordering::Relaxed) {
+            self.check_health();
+            self.answer_control_commands();
+            self.print_messages();
+            if matches!(self.health, ServerHealth::Down(_)) {
+                status = ExitStatus::ServerFailure;
+                break;
+            }
+            let Some(commands) = &commands else {
+                thread::sleep(HEADLESS_POLL_INTERVAL);
+                continue;
+            };
+            match commands.recv_timeout(HEADLESS_POLL_INTERVAL) {
+                Ok(line) => {
+                    if let Some(answer) = self.answer_command(&line) {
+                        let mut stdout = io::stdout().lock();
+                        // Scripts wait for the answer, so it can't sit in a buffer
+                        let _ = writeln!(stdout, "{}", answer);
+                        let _ = stdout.flush();
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        for args in on_exit {
+            log::info!("> {}", args.join(" "));
+            let args = match args.as_slice() {
+                [line] => split_input(line),
+                args => Ok(args.to_vec()),
+            };
+            let result = match args.map(|args| parse_command(&args)) {
+                Ok(Ok(command)) => self.dispatch(command),
+                Ok(Err(e)) => {
+                    log::error!("{}", e);
+                    status = status.max(ExitStatus::ConfigError);
+                    continue;
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                report_error(&e);
+                status = status.max(match e {
+                    InternalError::VerificationFailed { .. } => ExitStatus::VerificationFailed,
+                    _ => ExitStatus::ConfigError,
+                });
+            }
+        }
+        self.print_messages();
+        status
+    }
+
+    /// Accept commands on a control socket at `path`, see [`ControlSocket`]
+    pub fn open_control_socket(&mut self, path: &Path) {
+        match ControlSocket::bind(path) {
+            Ok(socket) => {
+                log::info!("Accepting commands on {}", socket.path().display());
+                self.control = Some(socket);
+            }
+            Err(e) => report_error(&e),
+        }
+    }
+
+    fn answer_control_commands(&mut self) {
+        while let Some(command) = self.control.as_ref().and_then(ControlSocket::try_recv) {
+            if let Some(answer) = self.answer_command(&command.line) {
+                // The client may have left in the meantime
+                let _ = command.reply.send(answer);
+            }
+        }
+    }
+
+    /// Run a command read from stdin or the control socket and answer with a JSON line, e.g.
+    /// `{"command":"endpoint list","ok":true,"messages":[{"level":"INFO","message":"..."}]}`.
+    /// Blank lines get no answer.
+    fn answer_command(&mut self, line: &str) -> Option<String> {
+        if line.trim().is_empty() {
+            return None;
+        }
+        log::debug!("> {}", line);
+        let (result, captured) = logger::capture(|| self.run_scripted_command(line));
+        let messages: Vec<serde_json::Value> = captured
+            .into_iter()
+            .map(|entry| {
+                serde_json::json!({"level": entry.level.as_str(), "message": entry.message})
+            })
+            .collect();
+        let mut answer = serde_json::json!({
+            "command": line,
+            "ok": result.is_ok(),
+            "messages": messages,
+        });
+        if let Err(e) = result {
+            answer["error"] = e.to_string().into();
+            if let Some(help) = e.help() {
+                answer["help"] = help.into();
+            }
+        }
+        Some(answer.to_string())
+    }
+
+    fn run_scripted_command(&mut self, line: &str) -> InternalResult<()> {
+        let args = split_input(line)?;
+        match parse_command(&args) {
+            Ok(command) => {
+                self.usage.record(&args);
+                self.dispatch(command)
+            }
+            Err(e)
+                if e.kind() == clap::error::ErrorKind::DisplayHelp
+                    || e.kind() == clap::error::ErrorKind::DisplayVersion =>
+            {
+                log::info!("{}", e);
+                Ok(())
+            }
+            Err(e) => Err(InternalError::InvalidArgument {
+                argument: "command",
+                reason: e.to_string().trim_end().to_owned(),
+            }),
+        }
+    }
+
+    fn print_messages(&mut self) {
+        while let Ok(entry) = self.log_rx.try_recv() {
+            eprintln!("{}", entry.line());
+        }
+    }
+
     fn draw(&self, frame: &mut Frame) {
-        let chunks = Layout::default()
+        let profile = self.server_state.profile().clone();
+        let banner = profile.as_ref().map(|profile| ProfileBanner { profile });
+        let tutorial = self.tutorial.as_ref().map(|tutorial| {
+            let url = format!(
+                "http://{}{}",
+                self.supervisor.addr(),
+                self.server_state.base_path()
+            );
+            (tutorial.title(), tutorial.instruction(&url))
+        });
+        let areas = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(banner.as_ref().map_or(0, ProfileBanner::height)),
+                Constraint::Length(tutorial.as_ref().map_or(0, |_| TutorialPane::HEIGHT)),
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
             .split(frame.area());
+        if let Some(banner) = &banner {
+            frame.render_widget(banner, areas[1]);
+        }
+        if let Some((title, instruction)) = &tutorial {
+            let pane = TutorialPane { title, instruction };
+            frame.render_widget(&pane, areas[2]);
+        }
+        let chunks = [areas[0], areas[3], areas[4], areas[5]];
+        let ghost = match self.mode {
+            InputMode::Insert if self.input.is_cursor_at_end() => self.ghost_text(),
+            _ => String::new(),
+        };
+        // Scroll horizontally so the cursor stays inside the borders
+        let width = chunks[0].width.saturating_sub(2) as usize;
+        let column = self.input.cursor_column();
+        let scroll = column.saturating_sub(width.saturating_sub(1));
         let input_widget = CommandPane {
-            input: &self.input,
+            input: self.input.as_str(),
+            ghost: &ghost,
             mode: &self.mode,
+            scroll: u16::try_from(scroll).unwrap_or(u16::MAX),
         };
         frame.render_widget(&input_widget, chunks[0]);
         if matches!(self.mode, InputMode::Insert) {
-            frame.set_cursor_position((chunks[0].x + 1 + self.input.len() as u16, chunks[0].y + 1));
+            let x = u16::try_from(column - scroll).unwrap_or(u16::MAX);
+            frame.set_cursor_position((chunks[0].x + 1 + x, chunks[0].y + 1));
+        }
+        frame.render_widget(&TabBar { selected: self.tab }, chunks[1]);
+
+        // Panes re-read server state on every frame so they reflect changes immediately
+        match self.tab {
+            Tab::Logs => self.draw_logs(frame, chunks[2]),
+            Tab::Endpoints => {
+                let store = self.server_state.read_endpoints();
+                let stats = self.server_state.stats();
+                let pane = EndpointsPane {
+                    store: &store,
+                    stats: &stats,
+                };
+                frame.render_widget(&pane, chunks[2]);
+            }
+            Tab::Requests => self.draw_requests(frame, chunks[2]),
+            Tab::Inbox => self.draw_inbox(frame, chunks[2]),
+            Tab::Usage => {
+                let store = self.server_state.read_endpoints();
+                let stats = self.server_state.stats();
+                let pane = UsagePane {
+                    usage: &self.usage,
+                    store: &store,
+                    stats: &stats,
+                };
+                frame.render_widget(&pane, chunks[2]);
+            }
+            Tab::Help => frame.render_widget(&HelpPane, chunks[2]),
+        }
+        let uptime = build::format_duration(self.started.elapsed());
+        let status = StatusBar {
+            health: &self.health,
+            addr: self.supervisor.addr(),
+            uptime: &uptime,
+            endpoints: self.server_state.read_endpoints().len(),
+            requests: self.server_state.journal().total(),
+            project: self.project.as_deref(),
+        };
+        frame.render_widget(&status, chunks[3]);
+    }
+
+    fn list_suggestions(&mut self) {
+        self.suggestions = suggest_stubs(
+            &self.server_state.journal(),
+            &self.server_state.read_endpoints(),
+        );
+        if self.suggestions.is_empty() {
+            log::info!("No unmatched requests to suggest endpoints for");
+            return;
+        }
+        let lines: Vec<String> = self
+            .suggestions
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                format!(
+                    "{:>3}. {} {} ({} hits, e.g. {})",
+                    i + 1,
+                    s.method,
+                    s.path,
+                    s.hits,
+                    s.examples.join(", ")
+                )
+            })
+            .collect();
+        log::info!(
+            "Suggested endpoints, add with `suggest accept <n>...` or `suggest accept --all`\n{}",
+            lines.join("\n")
+        );
+    }
+
+    fn accept_suggestions(
+        &mut self,
+        numbers: &[usize],
+        all: bool,
+        body: &str,
+    ) -> InternalResult<()> {
+        if self.suggestions.is_empty() {
+            log::warn!("No suggestions to accept, run `suggest` first");
+            return Ok(());
+        }
+        let selected: Vec<&StubSuggestion> = if all {
+            self.suggestions.iter().collect()
+        } else {
+            numbers
+                .iter()
+                .filter_map(|n| {
+                    let suggestion = n.checked_sub(1).and_then(|i| self.suggestions.get(i));
+                    if suggestion.is_none() {
+                        log::warn!("No suggestion number {}", n);
+                    }
+                    suggestion
+                })
+                .collect()
+        };
+        for suggestion in selected {
+            self.server_state.add_endpoint(
+                suggestion.method.clone(),
+                &suggestion.path,
+                body.to_owned(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn configure_scenario(&self, action: ScenarioAction) -> InternalResult<()> {
+        if let ScenarioAction::Start { file } = &action {
+            let scenario = ScenarioFile::load(file)?;
+            let run = ScenarioRun::start(scenario).ok_or_else(|| InternalError::Import {
+                path: file.clone(),
+                line: 1,
+                reason: "scenario has no steps".to_owned(),
+            })?;
+            log::info!(
+                "Started scenario {} at step {}",
+                run.name(),
+                run.describe_step()
+            );
+            *self.server_state.scenario() = Some(run);
+            return Ok(());
+        }
+        let mut scenario = self.server_state.scenario();
+        let Some(run) = scenario.as_mut() else {
+            log::info!("No scenario running, start one with `scenario start <file>`");
+            return Ok(());
+        };
+        match action {
+            ScenarioAction::Start { .. } | ScenarioAction::Show => {}
+            ScenarioAction::Advance => {
+                if !run.advance() {
+                    log::warn!("Scenario {} is already at its last step", run.name());
+                }
+            }
+            ScenarioAction::Reset => run.reset(),
+            ScenarioAction::Stop => {
+                log::info!("Stopped scenario {}", run.name());
+                *scenario = None;
+                return Ok(());
+            }
+        }
+        log::info!("Scenario {} at step {}", run.name(), run.describe_step());
+        Ok(())
+    }
+
+    fn configure_tenants(&self, action: TenantAction) {
+        let mut tenancy = self.server_state.tenancy_mut();
+        match action {
+            TenantAction::Show => {
+                match &tenancy.source {
+                    Some(source) => log::info!("Tenants are identified by {}", source),
+                    None => log::info!("Tenants are not identified, see `tenant by`"),
+                }
+                for (tenant, endpoints) in tenancy.tenants() {
+                    let lines: Vec<String> = endpoints
+                        .entries(None)
+                        .into_iter()
+                        .flat_map(|(method, children)| {
+                            children.into_iter().map(move |(path, stub)| {
+                                format!(
+                                    "  {} {} -> {}",
+                                    method,
+                                    path,
+                                    String::from_utf8_lossy(&stub.body)
+                                )
+                            })
+                        })
+                        .collect();
+                    log::info!("Tenant {} overrides:\n{}", tenant, lines.join("\n"));
+                }
+            }
+            TenantAction::By { source } => {
+                log::info!("Identifying tenants by {}", source);
+                tenancy.source = Some(source);
+            }
+            TenantAction::Off => {
+                tenancy.source = None;
+                log::info!("Stopped identifying tenants");
+            }
+            TenantAction::Remove { tenant } => {
+                if tenancy.remove(&tenant) {
+                    log::info!("Removed the endpoints of tenant {}", tenant);
+                } else {
+                    log::warn!("Tenant {} has no endpoints", tenant);
+                }
+            }
         }
+    }
 
+    fn configure_virtual_hosts(&self, action: HostAction) {
+        let mut virtual_hosts = self.server_state.virtual_hosts_mut();
+        match action {
+            HostAction::List => {
+                if virtual_hosts.hosts().next().is_none() {
+                    log::info!("No host has endpoints of its own, see `endpoint add --host`");
+                }
+                for (host, endpoints) in virtual_hosts.hosts() {
+                    let lines: Vec<String> = endpoints
+                        .entries(None)
+                        .into_iter()
+                        .flat_map(|(method, children)| {
+                            children.into_iter().map(move |(path, stub)| {
+                                format!(
+                                    "  {} {} -> {}",
+                                    method,
+                                    path,
+                                    String::from_utf8_lossy(&stub.body)
+                                )
+                            })
+                        })
+                        .collect();
+                    log::info!("Host {} serves:\n{}", host, lines.join("\n"));
+                }
+            }
+            HostAction::Remove { host } => {
+                if virtual_hosts.remove(&host) {
+                    log::info!("Removed the endpoints of host {}", host);
+                } else {
+                    log::warn!("Host {} has no endpoints", host);
+                }
+            }
+        }
+    }
+
+    fn configure_metrics(&self, action: MetricsAction) {
+        let mut metrics = self.server_state.metrics();
+        match action {
+            MetricsAction::Show if metrics.counters().is_empty() => {
+                log::info!("No metrics counted, see `endpoint add --hook 'after metric <name>'`")
+            }
+            MetricsAction::Show => {
+                let width = metrics.counters().keys().map(String::len).max();
+                let lines: Vec<String> = metrics
+                    .counters()
+                    .iter()
+                    .map(|(metric, count)| {
+                        format!(
+                            "  {:<width$}  {}",
+                            metric,
+                            count,
+                            width = width.unwrap_or_default()
+                        )
+                    })
+                    .collect();
+                log::info!("Metrics:\n{}", lines.join("\n"));
+            }
+            MetricsAction::Reset => {
+                metrics.reset();
+                log::info!("Counting metrics from zero");
+            }
+        }
+    }
+
+    fn configure_coverage(&self, action: CoverageAction) {
+        let mut coverage = self.server_state.coverage();
+        let Some(coverage) = coverage.as_mut() else {
+            log::warn!("No OpenAPI document loaded, see `spec load`");
+            return;
+        };
+        match action {
+            CoverageAction::Report => {
+                let report = coverage.report();
+                let width = report.details.iter().map(|o| o.path.len()).max();
+                let lines: Vec<String> = report
+                    .details
+                    .iter()
+                    .map(|operation| {
+                        let hits = match operation.hits {
+                            0 => "never hit".to_owned(),
+                            1 => "1 hit".to_owned(),
+                            hits => format!("{} hits", hits),
+                        };
+                        format!(
+                            "  {:<7} {:<width$}  {}",
+                            operation.method,
+                            operation.path,
+                            hits,
+                            width = width.unwrap_or_default()
+                        )
+                    })
+                    .collect();
+                log::info!(
+                    "{} of {} operations of {} hit ({:.0}%):\n{}",
+                    report.covered,
+                    report.operations,
+                    report.spec.display(),
+                    report.percent,
+                    lines.join("\n")
+                );
+            }
+            CoverageAction::Reset => {
+                coverage.reset();
+                log::info!("Counting the hits of operations from zero");
+            }
+        }
+    }
+
+    fn configure_journal(&self, action: JournalAction) {
+        let mut journal = self.server_state.journal();
+        let mut retention = journal.retention().clone();
+        match action {
+            JournalAction::Show => {
+                let max_body = retention.max_body.map_or_else(
+                    || "kept whole".to_owned(),
+                    |size| format!("cut off at {}", format_size(size)),
+                );
+                log::info!(
+                    "Journal settings:\n  \
+                     capacity:  {} requests, {} held with {} of bodies\n  \
+                     bodies:    {}\n  \
+                     sampled:   {}% of requests\n  \
+                     redacted:  {} rules, see `redact`",
+                    retention.capacity,
+                    journal.len(),
+                    format_size(journal.body_bytes()),
+                    max_body,
+                    retention.sample,
+                    self.server_state.redactions().len()
+                );
+                return;
+            }
+            JournalAction::Capacity { size: None } => {
+                log::info!(
+                    "Keeping up to {} requests ({} held)",
+                    retention.capacity,
+                    journal.len()
+                );
+                return;
+            }
+            JournalAction::Capacity { size: Some(size) } => {
+                retention.capacity = size;
+                log::info!("Keeping up to {} requests", size);
+            }
+            JournalAction::MaxBody {
+                size: None,
+                off: false,
+            } => {
+                match retention.max_body {
+                    Some(size) => log::info!("Request bodies are cut off at {}", format_size(size)),
+                    None => log::info!("Request bodies are kept whole"),
+                }
+                return;
+            }
+            JournalAction::MaxBody { size, .. } => {
+                retention.max_body = size;
+                match size {
+                    Some(size) => log::info!("Cutting off request bodies at {}", format_size(size)),
+                    None => log::info!("Keeping request bodies whole"),
+                }
+            }
+            JournalAction::Sample { percent: None } => {
+                log::info!("Recording {}% of requests", retention.sample);
+                return;
+            }
+            JournalAction::Sample {
+                percent: Some(percent),
+            } => {
+                retention.sample = percent;
+                log::info!("Recording {}% of requests", percent);
+            }
+        }
+        journal.set_retention(retention);
+    }
+
+    fn configure_oauth(&self, action: OauthAction) {
+        let mut oauth = self.server_state.oauth_mut();
+        match action {
+            OauthAction::Show => match oauth.as_ref() {
+                Some(mock) => log::info!("{}", mock.describe()),
+                None => log::info!("No token endpoint, see `oauth mock`"),
+            },
+            OauthAction::Mock {
+                path,
+                expiry,
+                claims,
+                secret,
+            } => {
+                let mock = OAuthMock {
+                    path: format!("/{}", path.trim_matches('/')),
+                    secret,
+                    expiry,
+                    claims,
+                };
+                log::info!("{}", mock.describe());
+                *oauth = Some(mock);
+            }
+            OauthAction::Stop => match oauth.take() {
+                Some(mock) => log::info!("Stopped serving tokens at POST {}", mock.path),
+                None => log::info!("No token endpoint, see `oauth mock`"),
+            },
+        }
+    }
+
+    /// Send journaled requests to `target`, the inspected one unless `request` says otherwise
+    fn replay_requests(
+        &self,
+        request: Option<ReplaySelection>,
+        target: ReplayTarget,
+        filters: &[ReplayFilter],
+    ) -> InternalResult<()> {
+        let journal = self.server_state.journal();
+        let records: Vec<RequestRecord> =
+            match request.or(self.inspected.map(ReplaySelection::Request)) {
+                Some(ReplaySelection::Request(id)) => match journal.get(id) {
+                    Some(record) => vec![record.clone()],
+                    None => {
+                        log::warn!("Request #{} is not in the journal", id);
+                        return Ok(());
+                    }
+                },
+                Some(ReplaySelection::All) => journal
+                    .records()
+                    .filter(|record| filters.iter().all(|filter| filter.matches(record)))
+                    .cloned()
+                    .collect(),
+                None => {
+                    log::warn!("No request inspected, give the id of one or `all`");
+                    return Ok(());
+                }
+            };
+        drop(journal);
+        let truncated = records.iter().filter(|record| record.truncated()).count();
+        if truncated > 0 {
+            log::warn!(
+                "{} request(s) are replayed with their bodies cut off, see `journal max-body`",
+                truncated
+            );
+        }
+        if records.is_empty() {
+            log::info!("No journaled requests to replay");
+            return Ok(());
+        }
+        log::info!("Replaying {} request(s) against {}", records.len(), target);
+        replay(target, records)?;
+        Ok(())
+    }
+
+    fn configure_inbox(&mut self, action: InboxAction) {
+        let mut inbox = self.server_state.inbox();
+        match action {
+            InboxAction::List => {
+                if inbox.paths().is_empty() {
+                    log::info!("No inbox paths, see `inbox add`");
+                } else {
+                    log::info!("Capturing requests to {}", inbox.paths().join(", "));
+                }
+                for capture in inbox.captured() {
+                    let record = &capture.record;
+                    log::info!(
+                        "#{} {} {} {} ({})",
+                        capture.number,
+                        record.timestamp.format("%H:%M:%S"),
+                        record.method,
+                        record.path,
+                        format_size(record.body_size)
+                    );
+                }
+            }
+            InboxAction::Add { path } => {
+                if inbox.add(&path) {
+                    log::info!("Capturing requests to {}", path);
+                } else {
+                    log::info!("Already capturing requests to {}", path);
+                }
+            }
+            InboxAction::Show { number } => match inbox.get(number) {
+                Some(capture) => {
+                    let record = &capture.record;
+                    let uri = if record.query.is_empty() {
+                        record.path.clone()
+                    } else {
+                        format!("{}?{}", record.path, record.query)
+                    };
+                    let headers: String = record
+                        .headers
+                        .iter()
+                        .map(|(name, value)| format!("\n  {}: {}", name, value))
+                        .collect();
+                    let body = String::from_utf8_lossy(&record.body);
+                    let body = serde_json::from_str::<serde_json::Value>(&body)
+                        .and_then(|json| serde_json::to_string_pretty(&json))
+                        .unwrap_or_else(|_| body.into_owned());
+                    log::info!(
+                        "#{} {} {} received {}{}\n\n{}",
+                        number,
+                        record.method,
+                        uri,
+                        record.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                        headers,
+                        body
+                    );
+                    self.inbox_shown = Some(number);
+                    self.tab = Tab::Inbox;
+                }
+                None => log::warn!("No captured request #{}, see `inbox list`", number),
+            },
+            InboxAction::Delete { path } => {
+                if inbox.remove(&path) {
+                    log::info!("Stopped capturing requests to {}", path);
+                } else {
+                    log::warn!("Not capturing requests to {}", path);
+                }
+            }
+            InboxAction::Clear => {
+                let count = inbox.clear();
+                self.inbox_shown = None;
+                log::info!("Dropped {} captured request(s)", count);
+            }
+        }
+    }
+
+    fn configure_middleware(&mut self, action: MiddlewareAction) {
+        let chain = match action {
+            MiddlewareAction::List => return self.list_middleware(),
+            MiddlewareAction::Set { chain } => chain,
+            MiddlewareAction::Reset => MiddlewareChain::default(),
+        };
+        self.set_middleware(chain);
+    }
+
+    fn list_middleware(&self) {
+        let state = &self.server_state;
+        let count = |n: usize, what: &str| match n {
+            0 => format!("no {}s", what),
+            n => format!("{} {}(s)", n, what),
+        };
+        let chain = state.middleware_chain().clone();
+        let lines: Vec<String> = chain
+            .stages()
+            .iter()
+            .enumerate()
+            .map(|(i, stage)| {
+                let status = match stage {
+                    Stage::Cors if state.cors_config().is_enabled() => "on".to_owned(),
+                    Stage::Cors => "no origins allowed, see `cors`".to_owned(),
+                    Stage::Auth => count(state.auth_rules().len(), "rule"),
+                    Stage::RateLimit => state.rate_limiter().as_ref().map_or_else(
+                        || "no limit, see `rate-limit set`".to_owned(),
+                        |limiter| limiter.limit.to_string(),
+                    ),
+                    Stage::Chaos => count(state.chaos().rules().len(), "rule"),
+                    Stage::Redaction => count(state.redactions().len(), "rule"),
+                    Stage::Logging => "access log".to_owned(),
+                };
+                format!("  {}. {:<10} {}", i + 1, stage, status)
+            })
+            .collect();
+        let off: Vec<String> = MiddlewareChain::default()
+            .stages()
+            .iter()
+            .filter(|stage| !chain.contains(**stage))
+            .map(ToString::to_string)
+            .collect();
+        let off = if off.is_empty() {
+            String::new()
+        } else {
+            format!("\n  off: {}", off.join(", "))
+        };
+        log::info!("Middleware chain:\n{}{}", lines.join("\n"), off);
+    }
+
+    /// Replace the middleware chain, restarting the server if CORS is turned on or off
+    fn set_middleware(&mut self, chain: MiddlewareChain) {
+        let mut current = self.server_state.middleware_chain_mut();
+        if *current == chain {
+            return;
+        }
+        let restart = current.contains(Stage::Cors) != chain.contains(Stage::Cors)
+            && self.server_state.cors_config().is_enabled();
+        log::info!("Middleware chain: {}", chain);
+        *current = chain;
+        drop(current);
+        self.sync_log_redactions();
+        if restart {
+            self.supervisor.restart();
+        }
+    }
+
+    /// Send log entries to `target` from now on, at info level unless given
+    fn add_log_sink(&mut self, target: SinkTarget, level: Option<LogLevel>) {
+        // JSON lines would garble the TUI, which draws on stdout
+        if target == SinkTarget::StdoutJson && self.tui {
+            log::warn!("Logging to stdout only works in headless mode");
+            return;
+        }
+        let level = level.map_or(log::LevelFilter::Info, log::LevelFilter::from);
+        match LogSink::open(target.clone(), level) {
+            Ok(sink) => {
+                self.logger.add_sink(sink);
+                log::info!("Logging {} and above to {}", level, target);
+            }
+            Err(e) => report_error(&e),
+        }
+    }
+
+    fn configure_rate_limit(&self, action: RateLimitAction) {
+        let mut limiter = self.server_state.rate_limiter();
+        match action {
+            RateLimitAction::Show => match limiter.as_ref() {
+                Some(limiter) => log::info!("Allowing {} requests", limiter.limit),
+                None => log::info!("No rate limit, see `rate-limit set`"),
+            },
+            RateLimitAction::Set { limit } => {
+                log::info!("Allowing {} requests, answering others with 429", limit);
+                *limiter = Some(RateLimiter::new(limit));
+            }
+            RateLimitAction::Off => match limiter.take() {
+                Some(limiter) => log::info!("Stopped limiting requests to {}", limiter.limit),
+                None => log::info!("No rate limit, see `rate-limit set`"),
+            },
+        }
+    }
+
+    fn configure_fallback(&self, action: FallbackAction) {
+        let mut fallback = self.server_state.fallback_mut();
+        match action {
+            FallbackAction::Show => match fallback.as_ref() {
+                Some(upstream) => log::info!("Proxying unmatched requests to {}", upstream),
+                None => log::info!("Unmatched requests get 404, see `fallback set`"),
+            },
+            FallbackAction::Set { upstream } => {
+                log::info!("Proxying unmatched requests to {}", upstream);
+                *fallback = Some(upstream);
+            }
+            FallbackAction::Off => match fallback.take() {
+                Some(upstream) => log::info!("Stopped proxying unmatched requests to {}", upstream),
+                None => log::info!("Unmatched requests get 404, see `fallback set`"),
+            },
+        }
+    }
+
+    fn configure_connections(&self, action: ConnectionAction) {
+        let mut policy = self.server_state.connection_policy_mut();
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        match action {
+            ConnectionAction::Show => {
+                log::info!(
+                    "Connection settings:\n  \
+                     close:         {}\n  \
+                     max requests:  {}\n  \
+                     dropped:       {}% of responses",
+                    on_off(policy.close),
+                    policy
+                        .max_requests
+                        .map_or_else(|| "unlimited".to_owned(), |max| max.to_string()),
+                    policy.drop
+                );
+            }
+            ConnectionAction::Close { state } => {
+                if let Some(state) = state {
+                    policy.close = state.into();
+                }
+                log::info!("Closing every connection is {}", on_off(policy.close));
+            }
+            ConnectionAction::MaxRequests {
+                count: None,
+                off: false,
+            } => match policy.max_requests {
+                Some(max) => log::info!("Connections are closed after {} requests", max),
+                None => log::info!("Connections serve unlimited requests"),
+            },
+            ConnectionAction::MaxRequests { count, .. } => {
+                policy.max_requests = count;
+                match count {
+                    Some(max) => log::info!("Closing connections after {} requests", max),
+                    None => log::info!("Connections serve unlimited requests"),
+                }
+            }
+            ConnectionAction::Drop { percent } => {
+                if let Some(percent) = percent {
+                    policy.drop = percent;
+                }
+                log::info!(
+                    "Dropping the connection after {}% of responses",
+                    policy.drop
+                );
+            }
+        }
+    }
+
+    fn configure_redactions(&self, action: RedactAction) {
+        let mut redactions = self.server_state.redactions_mut();
+        match action {
+            RedactAction::List if redactions.is_empty() => log::info!("No redaction rules"),
+            RedactAction::List => {
+                let lines: Vec<String> = redactions
+                    .iter()
+                    .map(|rule| format!("  {}", rule))
+                    .collect();
+                log::info!("Redacting:\n{}", lines.join("\n"))
+            }
+            RedactAction::Add { rules } => {
+                for rule in rules {
+                    if redactions.contains(&rule) {
+                        log::warn!("Already redacting {}", rule);
+                    } else {
+                        log::info!("Redacting {}", rule);
+                        redactions.push(rule);
+                    }
+                }
+            }
+            RedactAction::Remove { rules } => {
+                for rule in rules {
+                    match redactions.iter().position(|r| *r == rule) {
+                        Some(index) => {
+                            redactions.remove(index);
+                            log::info!("Stopped redacting {}", rule);
+                        }
+                        None => log::warn!("No redaction rule {}", rule),
+                    }
+                }
+            }
+            RedactAction::Clear => {
+                redactions.clear();
+                log::info!("Removed all redaction rules");
+            }
+        }
+        drop(redactions);
+        self.sync_log_redactions();
+    }
+
+    fn configure_rewrites(&self, action: RewriteAction) {
+        let mut rewrites = self.server_state.rewrites_mut();
+        match action {
+            RewriteAction::List if rewrites.is_empty() => log::info!("No rewrite rules"),
+            RewriteAction::List => {
+                let lines: Vec<String> = rewrites
+                    .iter()
+                    .enumerate()
+                    .map(|(i, rule)| format!("  {}. {}", i + 1, rule.describe()))
+                    .collect();
+                log::info!("Request paths are rewritten by:\n{}", lines.join("\n"))
+            }
+            RewriteAction::Add {
+                pattern,
+                replacement,
+            } => {
+                let rule = RewriteRule {
+                    pattern,
+                    replacement,
+                };
+                log::info!("Rewriting request paths: {}", rule.describe());
+                rewrites.push(rule);
+            }
+            RewriteAction::Clear => {
+                rewrites.clear();
+                log::info!("Removed all rewrite rules");
+            }
+        }
+    }
+
+    fn configure_mutations(&self, action: MutateAction) {
+        let mut mutations = self.server_state.mutations();
+        let mode = match action {
+            MutateAction::Report => {
+                let outcomes = mutations.outcomes();
+                if outcomes.is_empty() {
+                    return log::info!("No responses mutated yet");
+                }
+                let rejected = outcomes.iter().filter(|o| o.rejected.is_some()).count();
+                let lines: Vec<String> = outcomes
+                    .iter()
+                    .map(|o| {
+                        let verdict = match &o.rejected {
+                            Some(reason) => format!("rejected ({})", reason),
+                            None => "tolerated".to_owned(),
+                        };
+                        format!(
+                            "  #{} {} {}: {} -> {}",
+                            o.request_id, o.method, o.endpoint, o.mutation, verdict
+                        )
+                    })
+                    .collect();
+                return log::info!(
+                    "Client tolerated {} of {} mutated response(s):\n{}",
+                    outcomes.len() - rejected,
+                    outcomes.len(),
+                    lines.join("\n")
+                );
+            }
+            MutateAction::Reject { request_id } => {
+                if mutations.reject(request_id) {
+                    return log::info!("Marked the response to #{} as rejected", request_id);
+                }
+                return log::warn!("The response to #{} was not mutated", request_id);
+            }
+            MutateAction::PerRequest => MutationMode::PerRequest,
+            MutateAction::PerSession => MutationMode::PerSession,
+            MutateAction::Off => MutationMode::Off,
+        };
+        mutations.set_mode(mode);
+        match mode {
+            MutationMode::Off => log::info!("Stopped mutating responses"),
+            MutationMode::PerRequest => log::warn!("Mutating every JSON response"),
+            MutationMode::PerSession => {
+                log::warn!("Mutating JSON responses, one mutation per endpoint this session")
+            }
+        }
+    }
+
+    fn configure_chaos(&self, action: ChaosAction) {
+        let mut chaos = self.server_state.chaos_mut();
+        let rule = match action {
+            ChaosAction::List => {
+                if chaos.rules().is_empty() {
+                    return log::info!("No chaos rules active");
+                }
+                let rules: Vec<String> = chaos
+                    .rules()
+                    .iter()
+                    .map(|rule| format!("  {}", rule.describe()))
+                    .collect();
+                return log::info!("Chaos rules:\n{}", rules.join("\n"));
+            }
+            ChaosAction::Clear { path } => {
+                let removed = chaos.clear(path.as_deref());
+                return log::info!("Removed {} chaos rule(s)", removed);
+            }
+            ChaosAction::Latency {
+                range: (min, max),
+                path,
+            } => ChaosRule {
+                scope: path,
+                fault: Fault::Latency { min, max },
+            },
+            ChaosAction::ErrorRate {
+                percent,
+                status,
+                path,
+            } => ChaosRule {
+                scope: path,
+                fault: Fault::Error { percent, status },
+            },
+            ChaosAction::AbortRate { percent, path } => ChaosRule {
+                scope: path,
+                fault: Fault::Abort { percent },
+            },
+            ChaosAction::MalformedRate {
+                percent,
+                malformation,
+                path,
+            } => ChaosRule {
+                scope: path,
+                fault: Fault::Malformed {
+                    percent,
+                    malformation,
+                },
+            },
+        };
+        chaos.set(rule.clone());
+        if chaos.rules().contains(&rule) {
+            log::warn!("Chaos: {}", rule.describe());
+        } else {
+            log::info!("Chaos: disabled {}", rule.describe());
+        }
+    }
+
+    fn configure_auth(&self, action: AuthAction) -> InternalResult<()> {
+        let mut rules = self.server_state.auth_rules_mut();
+        match action {
+            AuthAction::List if rules.is_empty() => log::info!("No credentials required"),
+            AuthAction::List => {
+                let lines: Vec<String> = rules
+                    .iter()
+                    .enumerate()
+                    .map(|(i, rule)| format!("  {}. {}", i + 1, rule.describe()))
+                    .collect();
+                log::info!("Credentials required:\n{}", lines.join("\n"))
+            }
+            AuthAction::Require {
+                scheme,
+                credentials,
+                scope,
+            } => {
+                let credentials = match scheme {
+                    AuthScheme::Bearer => Credentials::Bearer(credentials),
+                    AuthScheme::Basic => match credentials.split_once(':') {
+                        Some((user, password)) => Credentials::Basic {
+                            user: user.to_owned(),
+                            password: password.to_owned(),
+                        },
+                        None => {
+                            return Err(InternalError::InvalidArgument {
+                                argument: "credentials",
+                                reason: "basic auth takes `user:password`".to_owned(),
+                            });
+                        }
+                    },
+                };
+                let rule = AuthRule { scope, credentials };
+                log::info!("Requiring {}", rule.describe());
+                if !rules.contains(&rule) {
+                    rules.push(rule);
+                }
+            }
+            AuthAction::Remove { number } => {
+                if number.get() > rules.len() {
+                    log::warn!("No auth rule {}, see `auth list`", number);
+                } else {
+                    let rule = rules.remove(number.get() - 1);
+                    log::info!("No longer requiring {}", rule.describe());
+                }
+            }
+            AuthAction::Clear => {
+                rules.clear();
+                log::info!("Removed all auth rules");
+            }
+        }
+        Ok(())
+    }
+
+    /// Update the CORS settings, restarting the server to apply them
+    fn configure_cors(&mut self, action: CorsAction) {
+        let previous = self.server_state.cors_config();
+        let mut config = previous.clone();
+        match action {
+            CorsAction::Show => return log::info!("{}", config.describe()),
+            CorsAction::AllowOrigin { origins } => config.origins = origins,
+            CorsAction::AllowHeaders { headers } => config.headers = headers,
+            CorsAction::Off => config = CorsConfig::default(),
+        }
+        log::info!("{}", config.describe());
+        if config == previous {
+            return;
+        }
+        let restart = config.is_enabled() || previous.is_enabled();
+        self.server_state.set_cors_config(config);
+        if restart {
+            self.supervisor.restart();
+        } else {
+            log::info!("Allow an origin with `cors allow-origin` to enable CORS");
+        }
+    }
+
+    fn configure_grpc(&mut self, action: GrpcAction) -> InternalResult<()> {
+        match action {
+            GrpcAction::List => {
+                let methods = self.grpc.methods();
+                if methods.is_empty() {
+                    log::info!("No gRPC methods loaded, see `grpc load`");
+                }
+                for (path, method, reply, calls) in methods {
+                    let reply = match reply {
+                        Some(reply) => reply.to_string(),
+                        None => "empty message".to_owned(),
+                    };
+                    let streaming = if method.streaming { " (streaming)" } else { "" };
+                    log::info!(
+                        "{}{} -> {}: {} ({} calls)",
+                        path,
+                        streaming,
+                        method.output,
+                        reply,
+                        calls
+                    );
+                }
+                match &self.grpc_listener {
+                    Some(listener) => log::info!("Serving gRPC on port {}", listener.port),
+                    None => log::info!("Not serving gRPC, see `grpc start`"),
+                }
+            }
+            GrpcAction::Load { file } => {
+                let methods = self.grpc.load(&file)?;
+                log::info!("Loaded {} gRPC method(s) from {}", methods, file.display());
+            }
+            GrpcAction::Reply {
+                method,
+                json,
+                status,
+                message,
+            } => {
+                let json = json
+                    .map(|json| serde_json::from_str(&json))
+                    .transpose()
+                    .map_err(|e| InternalError::InvalidArgument {
+                        argument: "reply",
+                        reason: format!("not JSON: {}", e),
+                    })?;
+                let status = status.map(|code| (code, message.unwrap_or_default()));
+                self.grpc.reply(&method, json.as_ref(), status)?;
+                log::info!("Set the reply of {}", method);
+            }
+            GrpcAction::Delete { method } => match self.grpc.delete(&method) {
+                Some(_) => log::info!("Deleted the reply of {}", method),
+                None => log::info!("{} has no reply", method),
+            },
+            GrpcAction::Start { port } => {
+                // Stop first, the port may be the same
+                self.grpc_listener = None;
+                let host = self
+                    .supervisor
+                    .addr()
+                    .rsplit_once(':')
+                    .map_or("127.0.0.1", |(host, _)| host)
+                    .trim_matches(['[', ']']);
+                let listener = GrpcListener::start(self.grpc.clone(), host, port)?;
+                log::info!("Serving gRPC on port {}", listener.port);
+                self.grpc_listener = Some(listener);
+            }
+            GrpcAction::Stop => match self.grpc_listener.take() {
+                Some(listener) => log::info!("Stopped serving gRPC on port {}", listener.port),
+                None => log::info!("Not serving gRPC, see `grpc start`"),
+            },
+        }
+        Ok(())
+    }
+
+    fn configure_tls(&mut self, action: TlsAction) -> InternalResult<()> {
+        let mut listener = self.server_state.tls_listener_mut();
+        let previous = *listener;
+        match action {
+            TlsAction::Show => {
+                match previous {
+                    Some(tls) => log::info!("{}", tls.describe()),
+                    None => log::info!("Not serving TLS, see `tls start`"),
+                }
+                return Ok(());
+            }
+            TlsAction::Start { port, cert } => {
+                let tls = TlsListener {
+                    port,
+                    certificate: cert,
+                };
+                log::info!("{}", tls.describe());
+                *listener = Some(tls);
+            }
+            TlsAction::Ca { file } => {
+                drop(listener);
+                self.server_state.export_certificate_authority(&file)?;
+                log::info!("Exported the certificate authority to {}", file.display());
+                return Ok(());
+            }
+            TlsAction::Stop => match listener.take() {
+                Some(tls) => log::info!("Stopped serving TLS on port {}", tls.port),
+                None => log::info!("Not serving TLS, see `tls start`"),
+            },
+        }
+        let changed = *listener != previous;
+        drop(listener);
+        if changed {
+            self.supervisor.restart();
+        }
+        Ok(())
+    }
+
+    fn configure_watch(&mut self, file: Option<PathBuf>, stop: bool) -> InternalResult<()> {
+        let watched = file
+            .as_ref()
+            .and_then(|file| self.watches.iter().position(|watch| watch.path == *file));
+        match (file, watched) {
+            (None, _) if self.watches.is_empty() => log::info!("Not watching any file"),
+            (None, _) => {
+                let files: Vec<String> = self
+                    .watches
+                    .iter()
+                    .map(|watch| format!("  {}", watch.path.display()))
+                    .collect();
+                log::info!("Watching:\n{}", files.join("\n"));
+            }
+            (Some(file), Some(i)) if stop => {
+                self.watches.remove(i);
+                log::info!("Stopped watching {}, keeping its endpoints", file.display());
+            }
+            (Some(file), None) if stop => log::info!("Not watching {}", file.display()),
+            (Some(file), Some(_)) => log::info!("Already watching {}", file.display()),
+            (Some(file), None) => {
+                self.watches
+                    .push(FileWatch::start(&file, self.server_state.clone())?);
+                log::info!("Watching {} for changes", file.display());
+            }
+        }
+        Ok(())
+    }
+
+    fn import_file(
+        &mut self,
+        path: &Path,
+        on_conflict: OnConflict,
+        tenant: Option<&str>,
+    ) -> InternalResult<()> {
+        let mut file = EndpointFile::load(path)?;
+        if let Some(chain) = file.middleware.take() {
+            self.set_middleware(chain);
+        }
+        self.import_endpoints(path, file, on_conflict, tenant)
+    }
+
+    /// Merge files in order, so later files are merged into the result of earlier ones. All files
+    /// are loaded first so that an invalid file doesn't leave a partial merge behind.
+    fn merge_files(&self, paths: &[PathBuf], on_conflict: OnConflict) -> InternalResult<()> {
+        let files = paths
+            .iter()
+            .map(|path| EndpointFile::load(path))
+            .collect::<InternalResult<Vec<_>>>()?;
+        for (path, file) in paths.iter().zip(files) {
+            self.import_endpoints(path, file, on_conflict, None)?;
+        }
+        Ok(())
+    }
+
+    fn import_endpoints(
+        &self,
+        path: &Path,
+        file: EndpointFile,
+        on_conflict: OnConflict,
+        tenant: Option<&str>,
+    ) -> InternalResult<()> {
+        let report = self
+            .server_state
+            .import_endpoints(tenant, file.entries(), on_conflict);
+        let outcome = match on_conflict {
+            OnConflict::Ask => "",
+            OnConflict::Skip => ", keeping the existing one",
+            OnConflict::Overwrite => ", replacing it",
+            OnConflict::KeepBoth => ", keeping both",
+        };
+        for conflict in &report.conflicts {
+            log::warn!(
+                "{} {} already exists with a different {}{}",
+                conflict.method,
+                conflict.path,
+                conflict.differences.join(" and "),
+                outcome
+            );
+        }
+        if !report.applied {
+            return Err(InternalError::ImportConflicts {
+                path: path.to_owned(),
+                conflicts: report.conflicts.len(),
+            });
+        }
+        if tenant.is_none() {
+            self.record_definitions(path, &file, &report.conflicts, on_conflict);
+        }
+        let resolution = match on_conflict {
+            OnConflict::Ask | OnConflict::Skip => "skipped",
+            OnConflict::Overwrite => "overwritten",
+            OnConflict::KeepBoth => "kept as lower priority candidates",
+        };
+        log::info!(
+            "Imported {}: {} added, {} identical skipped, {} conflicting {}",
+            path.display(),
+            report.added,
+            report.duplicates,
+            report.conflicts.len(),
+            resolution
+        );
+        Ok(())
+    }
+
+    /// Remember where the endpoints of a file imported from `path` are defined, except for those
+    /// whose conflicts kept the existing endpoint
+    fn record_definitions(
+        &self,
+        path: &Path,
+        file: &EndpointFile,
+        conflicts: &[ImportConflict],
+        on_conflict: OnConflict,
+    ) {
+        // Editors open the file from wherever they run
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let mut definitions = self.server_state.definitions_mut();
+        for ((method, endpoint, _), line) in file.entries().into_iter().zip(&file.lines) {
+            let kept_existing = on_conflict == OnConflict::Skip
+                && conflicts
+                    .iter()
+                    .any(|conflict| conflict.method == method && conflict.path == endpoint);
+            if !kept_existing {
+                let definition = Definition {
+                    file: path.clone(),
+                    line: *line,
+                };
+                definitions.record(method, &endpoint, definition);
+            }
+        }
+    }
+
+    fn log_info(&self) {
+        let addrs = match self.supervisor.running() {
+            Some(running) => running
+                .addrs
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            None => format!("{} (not bound)", self.supervisor.addr()),
+        };
+        let workers = self
+            .supervisor
+            .running()
+            .map_or_else(|| "-".to_owned(), |running| running.workers.to_string());
+        let journal = self.server_state.journal();
+        log::info!(
+            "adaptable_rest v{} ({}, {} build)\n\
+             uptime:    {}\n\
+             server:    {:?}\n\
+             bound to:  {}\n\
+             workers:   {}\n\
+             base path: {}\n\
+             endpoints: {}\n\
+             requests:  {} total, {} in journal",
+            build::VERSION,
+            build::GIT_HASH,
+            build::BUILD_PROFILE,
+            build::format_duration(self.started.elapsed()),
+            self.health,
+            addrs,
+            workers,
+            match self.server_state.base_path().as_str() {
+                "" => "/".to_owned(),
+                base_path => base_path.to_owned(),
+            },
+            self.server_state.read_endpoints().len(),
+            journal.total(),
+            journal.len(),
+        );
+    }
+
+    fn push_message(&mut self, entry: LogEntry) {
+        self.messages.push_back(entry);
+        self.truncate_messages();
+    }
+
+    /// Hand the redaction rules to the logger, which applies them to every log message before it
+    /// is shown, printed or written to a sink
+    fn sync_log_redactions(&self) {
+        let rules = if self
+            .server_state
+            .middleware_chain()
+            .contains(Stage::Redaction)
+        {
+            self.server_state.redactions().clone()
+        } else {
+            Vec::new()
+        };
+        self.logger.set_redactions(rules);
+    }
+
+    /// Drop the oldest entries beyond the capacity, keeping the selection on the same entry
+    fn truncate_messages(&mut self) {
+        let excess = self.messages.len().saturating_sub(self.message_capacity);
+        if excess == 0 {
+            return;
+        }
+        self.messages.drain(..excess);
+        self.log_selected = self.log_selected.and_then(|i| i.checked_sub(excess));
+        // A group whose start was dropped now starts at the oldest entry
+        self.log_expanded = self
+            .log_expanded
+            .iter()
+            .map(|first| first.saturating_sub(excess))
+            .collect();
+    }
+
+    fn check_tutorial(&mut self) {
+        let Some(tutorial) = &mut self.tutorial else {
+            return;
+        };
+        if tutorial.advance(
+            &self.server_state.read_endpoints(),
+            &self.server_state.stats(),
+        ) {
+            self.tutorial = None;
+        }
+    }
+
+    fn configure_tutorial(&mut self, stop: bool) {
+        match (&self.tutorial, stop) {
+            (Some(_), true) => {
+                self.tutorial = None;
+                log::info!("Left the tutorial");
+            }
+            (None, true) => log::info!("No tutorial running"),
+            (Some(_), false) => log::info!("The tutorial is already running, see above the tabs"),
+            (None, false) => {
+                self.tutorial = Some(Tutorial::start(&self.server_state.read_endpoints()));
+                log::info!("Tutorial started, follow the steps shown above the tabs");
+            }
+        }
+    }
+
+    /// Poll the supervisor and log transitions between health states
+    fn check_health(&mut self) {
+        let health = self.supervisor.health();
+        if health == self.health {
+            return;
+        }
+        match &health {
+            ServerHealth::Up => log::info!("Server is up on {}", self.supervisor.addr()),
+            ServerHealth::Unresponsive => {
+                log::warn!("Server unresponsive, press R in normal mode to restart")
+            }
+            ServerHealth::Down(reason) => {
+                log::error!("Server down: {}, press R in normal mode to restart", reason)
+            }
+            ServerHealth::Starting => {}
+        }
+        self.health = health;
+    }
+
+    fn draw_requests(&self, frame: &mut Frame, area: Rect) {
+        let journal = self.server_state.journal();
+        let Some(record) = self.inspected.and_then(|id| journal.get(id)) else {
+            frame.render_widget(&RequestsPane { journal: &journal }, area);
+            return;
+        };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .split(area);
+        frame.render_widget(&RequestsPane { journal: &journal }, chunks[0]);
+        frame.render_widget(&RequestInspector { record }, chunks[1]);
+    }
+
+    fn draw_inbox(&self, frame: &mut Frame, area: Rect) {
+        let inbox = self.server_state.inbox();
+        let Some(capture) = self.inbox_shown.and_then(|number| inbox.get(number)) else {
+            frame.render_widget(&InboxPane { inbox: &inbox }, area);
+            return;
+        };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .split(area);
+        frame.render_widget(&InboxPane { inbox: &inbox }, chunks[0]);
+        frame.render_widget(
+            &RequestInspector {
+                record: &capture.record,
+            },
+            chunks[1],
+        );
+    }
+
+    /// Move the log selection to the next shown entry, in the given direction. A collapsed group
+    /// of repeated entries is one step.
+    /// Moving down past the newest entry clears the selection, so the pane follows new entries.
+    fn select_log(&mut self, forward: bool) {
+        let shown: Vec<usize> = shown_entries(&self.messages, &self.log_filter, &self.log_expanded)
+            .into_iter()
+            .filter_map(|shown| shown.last().copied())
+            .collect();
+        self.log_selected = match (self.log_selected, forward) {
+            (None, false) => shown.last().copied(),
+            (None, true) => None,
+            (Some(i), false) => shown.iter().rev().find(|&&j| j < i).copied().or(Some(i)),
+            (Some(i), true) => shown.iter().find(|&&j| j > i).copied(),
+        };
+    }
+
+    /// Copy a curl command reproducing the inspected request, or on the logs tab the request of
+    /// the selected access log entry, to the clipboard of the terminal
+    fn copy_curl_command(&self) {
+        let request_id = match self.tab {
+            Tab::Logs => self
+                .log_selected
+                .and_then(|i| self.messages.get(i))
+                .and_then(|entry| entry.request_id),
+            _ => self.inspected,
+        };
+        let Some(id) = request_id else {
+            return;
+        };
+        let Some(command) = self.server_state.journal().get(id).map(curl_command) else {
+            log::warn!("Request #{} is no longer in the journal", id);
+            return;
+        };
+        match copy_to_clipboard(&command) {
+            Ok(()) => log::info!("Copied the curl command of request #{}", id),
+            Err(e) => log::warn!("Copying the curl command of request #{} failed: {}", id, e),
+        }
+    }
+
+    /// Expand the group of repeated log entries the selection is in, or collapse it again
+    fn toggle_log_group(&mut self) {
+        let Some(selected) = self.log_selected else {
+            return;
+        };
+        let groups = log_groups(&self.messages, &self.log_filter);
+        let Some(group) = groups.iter().find(|group| group.contains(&selected)) else {
+            return;
+        };
+        if let [first, .., last] = group.as_slice() {
+            if self.log_expanded.remove(first) {
+                self.log_selected = Some(*last);
+            } else {
+                self.log_expanded.insert(*first);
+            }
+        }
+    }
+
+    /// Jump from the selected access log entry to its request in the inspector
+    fn inspect_selected(&mut self) {
+        let request_id = self
+            .log_selected
+            .and_then(|i| self.messages.get(i))
+            .and_then(|entry| entry.request_id);
+        match request_id {
+            Some(id) if self.server_state.journal().get(id).is_some() => {
+                self.inspected = Some(id);
+                self.tab = Tab::Requests;
+            }
+            Some(id) => log::warn!("Request #{} is no longer in the journal", id),
+            None => {}
+        }
+    }
+
+    fn draw_logs(&self, frame: &mut Frame, area: Rect) {
         let log_widget = LogPane {
             messages: &self.messages,
+            filter: &self.log_filter,
+            selected: self.log_selected,
+            guide: self.log_guide,
+            expanded: &self.log_expanded,
+        };
+        if !self.show_endpoints {
+            frame.render_widget(&log_widget, area);
+            return;
+        }
+        let body_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(area);
+        frame.render_widget(&log_widget, body_chunks[0]);
+        let store = self.server_state.read_endpoints();
+        let stats = self.server_state.stats();
+        let pane = EndpointsPane {
+            store: &store,
+            stats: &stats,
         };
-        frame.render_widget(&log_widget, chunks[1]);
+        frame.render_widget(&pane, body_chunks[1]);
     }
 
     fn exit(&mut self) {
@@ -109,39 +1919,113 @@ impl App {
         }
     }
 
+    /// Completion for the word before the cursor
+    fn completion(&self) -> Completion {
+        completion::complete(
+            self.input.before_cursor(),
+            &self.server_state.read_endpoints(),
+        )
+    }
+
+    /// Remainder of the currently offered completion candidate, rendered after the input
+    fn ghost_text(&self) -> String {
+        let completion = self.completion();
+        let prefix_len = completion.prefix(self.input.before_cursor()).len();
+        match completion.candidates.len() {
+            0 => String::new(),
+            n => completion.candidates[self.completion_index % n][prefix_len..].to_owned(),
+        }
+    }
+
+    /// Complete a unique candidate, otherwise extend to the common prefix of all candidates. If
+    /// that makes no progress, cycle the offered ghost text through the candidates.
+    fn complete_input(&mut self) {
+        let completion = self.completion();
+        let prefix_len = completion.prefix(self.input.before_cursor()).len();
+        match completion.candidates.as_slice() {
+            [] => {}
+            [candidate] => {
+                self.input
+                    .replace_before_cursor(completion.start, &format!("{} ", candidate));
+            }
+            _ => match completion.common_prefix() {
+                Some(common) if common.len() > prefix_len => {
+                    self.input.replace_before_cursor(completion.start, common);
+                }
+                _ => self.completion_index += 1,
+            },
+        }
+    }
+
     fn handle_key_event(&mut self, key_event: KeyEvent) -> InternalResult<()> {
+        if key_event.code != KeyCode::Tab {
+            self.completion_index = 0;
+        }
         match self.mode {
             InputMode::Normal => match key_event.code {
                 KeyCode::Char('q') => self.exit(),
                 KeyCode::Char('i') => self.mode = InputMode::Insert,
+                KeyCode::Char('r')
+                    if matches!(
+                        self.health,
+                        ServerHealth::Unresponsive | ServerHealth::Down(_)
+                    ) =>
+                {
+                    self.supervisor.restart();
+                    self.health = ServerHealth::Starting;
+                }
+                KeyCode::Char('f') => self.log_filter.cycle_level(),
+                KeyCode::Char('e') => self.show_endpoints = !self.show_endpoints,
+                KeyCode::Char('g') => self.log_guide = !self.log_guide,
+                KeyCode::Char(c) if c.is_ascii_digit() => {
+                    if let Some(tab) = c.to_digit(10).and_then(Tab::from_number) {
+                        self.tab = tab
+                    }
+                }
+                KeyCode::Up | KeyCode::Char('k') if self.tab == Tab::Logs => self.select_log(false),
+                KeyCode::Down | KeyCode::Char('j') if self.tab == Tab::Logs => {
+                    self.select_log(true)
+                }
+                KeyCode::Enter if self.tab == Tab::Logs => self.inspect_selected(),
+                KeyCode::Char(' ') if self.tab == Tab::Logs => self.toggle_log_group(),
+                KeyCode::Char('c') => self.copy_curl_command(),
+                KeyCode::Esc => {
+                    self.log_selected = None;
+                    self.inspected = None;
+                    self.inbox_shown = None;
+                }
+                KeyCode::Tab => self.tab = self.tab.next(),
+                KeyCode::BackTab => self.tab = self.tab.previous(),
                 _ => {}
             },
             InputMode::Insert => match key_event.code {
+                KeyCode::Tab => self.complete_input(),
                 KeyCode::Char('u') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.input.clear()
+                    self.input.delete_to_start()
                 }
                 KeyCode::Char('w') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
-                    if let Some(pos) = self.input.trim_end().rfind(' ') {
-                        self.input.truncate(pos + 1);
-                    } else {
-                        self.input.clear();
-                    }
+                    self.input.delete_word()
                 }
                 KeyCode::Esc => self.mode = InputMode::Normal,
                 KeyCode::Enter => {
                     self.execute_command()?;
                 }
-                KeyCode::Char(c) => self.input.push(c),
-                KeyCode::Backspace => {
-                    self.input.pop();
-                }
+                KeyCode::Char(c) => self.input.insert(c),
+                KeyCode::Backspace => self.input.backspace(),
+                KeyCode::Delete => self.input.delete(),
+                KeyCode::Left => self.input.move_left(),
+                KeyCode::Right => self.input.move_right(),
+                KeyCode::Home => self.input.move_home(),
+                KeyCode::End => self.input.move_end(),
                 KeyCode::Up => {
                     self.history_backward();
-                    self.input = self.history().to_string();
+                    let entry = self.history().to_owned();
+                    self.input.set(entry);
                 }
                 KeyCode::Down => {
                     self.history_forward();
-                    self.input = self.history().to_string();
+                    let entry = self.history().to_owned();
+                    self.input.set(entry);
                 }
                 _ => {}
             },
@@ -150,31 +2034,29 @@ impl App {
     }
 
     fn execute_command(&mut self) -> InternalResult<()> {
-        if self.input.trim().is_empty() {
+        if self.input.as_str().trim().is_empty() {
             return Ok(());
         }
-        log::debug!("> {}", self.input);
-        let args = shlex::split(&self.input).unwrap_or_default();
-        match Cli::try_parse_from(std::iter::once("").chain(args.iter().map(|s| s.as_str()))) {
-            Ok(cli) => match cli.command {
-                Command::Endpoint { action } => match action {
-                    EndpointAction::Add {
-                        method,
-                        path,
-                        response,
-                    } => {
-                        self.server_state
-                            .add_endpoint(method.into(), &path, response)?;
-                    }
-                    EndpointAction::List { method } => {
-                        let method = method.map(Into::into);
-                        self.server_state.list_endpoints(method.as_ref())?;
-                    }
-                    EndpointAction::Delete { method, path } => {
-                        self.server_state.delete_endpoint(&method.into(), &path)?;
-                    }
-                },
-            },
+        log::debug!("> {}", self.input.as_str());
+        if let Err(e) = self.run_command() {
+            report_error(&e);
+        }
+        self.history.push(self.input.as_str().to_owned());
+        self.history_index = None;
+        self.input.clear();
+        Ok(())
+    }
+
+    fn run_command(&mut self) -> InternalResult<()> {
+        let args = split_input(self.input.as_str())?;
+        match parse_command(&args) {
+            Ok(command) => {
+                self.usage.record(&args);
+                if let Some(tutorial) = &mut self.tutorial {
+                    tutorial.observe(&command);
+                }
+                self.dispatch(command)
+            }
             Err(e) => {
                 if e.kind() == clap::error::ErrorKind::DisplayHelp
                     || e.kind() == clap::error::ErrorKind::DisplayVersion
@@ -183,11 +2065,579 @@ impl App {
                 } else {
                     log::warn!("{}", e);
                 }
+                Ok(())
+            }
+        }
+    }
+
+    fn dispatch(&mut self, command: Command) -> InternalResult<()> {
+        match command {
+            Command::Endpoint { action } => match action {
+                EndpointAction::Add {
+                    method,
+                    path,
+                    response,
+                    json,
+                    xml,
+                    representations,
+                    description,
+                    status,
+                    response_headers,
+                    set_cookies,
+                    header_order,
+                    max_size,
+                    max_time,
+                    priority,
+                    query,
+                    headers,
+                    cookies,
+                    body_json,
+                    on_call,
+                    after_calls,
+                    client_ip,
+                    weight,
+                    flaky,
+                    scheduled,
+                    assertions,
+                    hooks,
+                    callback,
+                    callback_body,
+                    callback_delay,
+                    transforms,
+                    overlays,
+                    drip,
+                    compress,
+                    expect,
+                    close,
+                    no_response,
+                    close_connection,
+                    etag,
+                    ranges,
+                    framing,
+                    request_schema,
+                    raw,
+                    tenant,
+                    host,
+                    redirect,
+                } => {
+                    let redirect = match redirect.as_slice() {
+                        [status, location] => Some(
+                            format!("{} {}", status, location)
+                                .parse::<Redirect>()
+                                .map_err(|reason| InternalError::InvalidArgument {
+                                    argument: "redirect",
+                                    reason,
+                                })?,
+                        ),
+                        _ => None,
+                    };
+                    let callback = match callback.as_slice() {
+                        [method, url] => Some(Callback {
+                            body: callback_body.unwrap_or_default(),
+                            delay: callback_delay.unwrap_or_default(),
+                            ..format!("{} {}", method, url).parse::<Callback>().map_err(
+                                |reason| InternalError::InvalidArgument {
+                                    argument: "callback",
+                                    reason,
+                                },
+                            )?
+                        }),
+                        _ => None,
+                    };
+                    let stub = Stub {
+                        body: response.unwrap_or_default().into(),
+                        description,
+                        status,
+                        headers: response_headers.into_iter().chain(set_cookies).collect(),
+                        header_order,
+                        budget: Budget { max_size, max_time },
+                        priority,
+                        matchers: query
+                            .into_iter()
+                            .chain(headers)
+                            .chain(cookies)
+                            .chain(body_json)
+                            .chain(on_call)
+                            .chain(after_calls)
+                            .chain(client_ip)
+                            .collect(),
+                        weight,
+                        flaky,
+                        scheduled,
+                        assertions,
+                        events: None,
+                        drip,
+                        redirect,
+                        compress: compress.map(bool::from),
+                        expect,
+                        close,
+                        framing,
+                        request_schema,
+                        raw,
+                        etag,
+                        ranges,
+                        paging: None,
+                        hooks,
+                        callback,
+                        transforms,
+                        overlays,
+                        representations: json
+                            .map(Representation::json)
+                            .into_iter()
+                            .chain(xml.map(Representation::xml))
+                            .chain(representations)
+                            .collect(),
+                        hangup: match (no_response, close_connection) {
+                            (true, _) => Some(Hangup::NoResponse),
+                            (_, true) => Some(Hangup::Close),
+                            _ => None,
+                        },
+                    };
+                    let scope = match (&tenant, &host) {
+                        (Some(tenant), _) => EndpointScope::Tenant(tenant),
+                        (_, Some(host)) => EndpointScope::Host(host),
+                        _ => EndpointScope::All,
+                    };
+                    self.server_state
+                        .add_scoped_endpoint(scope, method.into(), &path, stub)?;
+                }
+                EndpointAction::AddSse {
+                    method,
+                    path,
+                    message,
+                    interval,
+                    count,
+                } => {
+                    let stub = Stub {
+                        events: Some(EventStream { interval, count }),
+                        ..Stub::from(message)
+                    };
+                    self.server_state.add_endpoint(method.into(), &path, stub)?;
+                }
+                EndpointAction::AddPaged {
+                    method,
+                    path,
+                    source,
+                    page_size,
+                    style,
+                } => {
+                    let content =
+                        fs::read_to_string(&source).map_err(|e| InternalError::FileIo {
+                            path: source.clone(),
+                            source: e,
+                        })?;
+                    let items = match serde_json::from_str(&content) {
+                        Ok(serde_json::Value::Array(items)) => items,
+                        _ => {
+                            return Err(InternalError::InvalidArgument {
+                                argument: "source",
+                                reason: format!("{} holds no JSON array", source.display()),
+                            });
+                        }
+                    };
+                    let stub = Stub {
+                        paging: Some(Pagination {
+                            style: style.into(),
+                            page_size,
+                        }),
+                        ..Stub::from(content)
+                    };
+                    self.server_state.add_endpoint(method.into(), &path, stub)?;
+                    log::info!("Serving {} items in pages of {}", items.len(), page_size);
+                }
+                EndpointAction::Inspect { path, method } => {
+                    let method = method.map(Into::into);
+                    self.server_state.inspect_endpoint(method.as_ref(), &path)?;
+                }
+                EndpointAction::Curl { method, path } => {
+                    let origin = match self.supervisor.running().and_then(|r| r.addrs.first()) {
+                        // A server bound to all interfaces is reached on the loopback one
+                        Some(addr) if addr.ip().is_unspecified() => {
+                            format!("http://localhost:{}", addr.port())
+                        }
+                        Some(addr) => format!("http://{}", addr),
+                        None => format!("http://{}", self.supervisor.addr()),
+                    };
+                    let command =
+                        self.server_state
+                            .endpoint_curl(&method.into(), &path, &origin)?;
+                    log::info!("{}", command);
+                    if self.tui {
+                        match copy_to_clipboard(&command) {
+                            Ok(()) => log::info!("Copied the curl command"),
+                            Err(e) => log::warn!("Copying the curl command failed: {}", e),
+                        }
+                    }
+                }
+                EndpointAction::Infer { method, path, add } => {
+                    let method = method.into();
+                    let template = self.server_state.infer_template(&method, &path)?;
+                    log::info!("Inferred body of {} {}:\n{}", method, path, template);
+                    if add {
+                        let stub = Stub {
+                            transforms: vec![Transform::Template],
+                            ..Stub::from(template.as_str())
+                        };
+                        self.server_state.add_endpoint(method, &path, stub)?;
+                    } else {
+                        log::info!(
+                            "Serve it with `endpoint infer {} {} --add`",
+                            method.as_str().to_lowercase(),
+                            path
+                        );
+                    }
+                }
+                EndpointAction::List { method } => {
+                    let method = method.map(Into::into);
+                    self.server_state.list_endpoints(method.as_ref())?;
+                }
+                EndpointAction::Stats {
+                    path,
+                    method,
+                    reset,
+                } => {
+                    let method = method.map(Into::into);
+                    if reset {
+                        self.server_state
+                            .reset_endpoint_stats(method.as_ref(), &path)?;
+                    } else {
+                        self.server_state.endpoint_stats(method.as_ref(), &path)?;
+                    }
+                }
+                EndpointAction::Delete { method, path } => {
+                    self.server_state.delete_endpoint(&method.into(), &path)?;
+                }
+            },
+            Command::Suggest { action } => match action.unwrap_or(SuggestAction::List) {
+                SuggestAction::List => self.list_suggestions(),
+                SuggestAction::Accept { numbers, all, body } => {
+                    self.accept_suggestions(&numbers, all, &body)?
+                }
+            },
+            Command::Learn { state } => {
+                let learning = &self.server_state.learning;
+                if let Some(state) = state {
+                    learning.store(state.into(), Ordering::Relaxed);
+                }
+                log::info!(
+                    "Learning mode is {}",
+                    if learning.load(Ordering::Relaxed) {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                );
+            }
+            Command::Compress { state } => {
+                let compress = &self.server_state.compress;
+                if let Some(state) = state {
+                    compress.store(state.into(), Ordering::Relaxed);
+                }
+                log::info!(
+                    "Compression is {}",
+                    if compress.load(Ordering::Relaxed) {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                );
+            }
+            Command::Info => self.log_info(),
+            Command::Server { action } => match action {
+                ServerAction::BasePath { prefix: None } => {
+                    match self.server_state.base_path().as_str() {
+                        "" => log::info!("No base path, endpoints are served at their paths"),
+                        base_path => log::info!("Endpoints are served below {}", base_path),
+                    }
+                }
+                ServerAction::BasePath {
+                    prefix: Some(prefix),
+                } => {
+                    self.server_state.set_base_path(&prefix);
+                    match self.server_state.base_path().as_str() {
+                        "" => log::info!("Removed the base path"),
+                        base_path => log::info!("Endpoints are now served below {}", base_path),
+                    }
+                }
+                ServerAction::Rewrite { action } => self.configure_rewrites(action),
+            },
+            Command::Log { action } => match action {
+                LogAction::Level { level: Some(level) } => {
+                    self.logger.set_level(level.into());
+                    log::info!("Log level set to {}", self.logger.level());
+                }
+                LogAction::Level { level: None } => {
+                    log::info!("Log level is {}", self.logger.level())
+                }
+                LogAction::Sinks => {
+                    let lines: Vec<String> = self
+                        .logger
+                        .sinks()
+                        .iter()
+                        .map(|(target, level)| format!("  {:<5} {}", level, target))
+                        .collect();
+                    log::info!(
+                        "Log sinks:\n  {:<5} TUI\n{}",
+                        self.logger.level(),
+                        lines.join("\n")
+                    );
+                }
+                LogAction::Sink {
+                    target,
+                    remove: true,
+                    ..
+                } => match self.logger.remove_sink(&target) {
+                    true => log::info!("Stopped logging to {}", target),
+                    false => log::warn!("Not logging to {}, see `log sinks`", target),
+                },
+                LogAction::Sink { target, level, .. } => self.add_log_sink(target, level),
+                LogAction::Capacity { size: Some(size) } => {
+                    self.message_capacity = size.get();
+                    self.truncate_messages();
+                    log::info!("Keeping up to {} log entries", size);
+                }
+                LogAction::Capacity { size: None } => log::info!(
+                    "Keeping up to {} log entries ({} stored)",
+                    self.message_capacity,
+                    self.messages.len()
+                ),
+            },
+            Command::Filter { action } => match action {
+                FilterAction::Level { level } => self.log_filter.level = level.into(),
+                FilterAction::Text { text } => self.log_filter.text = Some(text),
+                FilterAction::Clear => self.log_filter = LogFilter::default(),
+            },
+            Command::Replay {
+                request,
+                target,
+                filters,
+            } => self.replay_requests(request, target, &filters)?,
+            Command::Verify {
+                action: Some(VerifyAction::Report),
+                ..
+            } => self.server_state.verify_report(),
+            Command::Verify {
+                action: None,
+                method,
+                path,
+                times,
+                never,
+            } => {
+                let expected = Expectation::from_flags(times, never);
+                // clap requires both unless a subcommand is given
+                if let (Some(method), Some(path)) = (method, path) {
+                    let verification = self.server_state.verify(&method.into(), &path, expected);
+                    if !verification.passed {
+                        return Err(InternalError::VerificationFailed { verification });
+                    }
+                    log::info!("{}", verification.describe());
+                }
+            }
+            Command::Export {
+                format: Some(ExportFormat::Markdown { file }),
+                ..
+            } => {
+                let exporter = formats::registry().exporter("markdown")?;
+                self.server_state.export_endpoints(exporter, &file)?;
+                log::info!("Documented the endpoints in {}", file.display());
+            }
+            Command::Export {
+                format: Some(ExportFormat::Endpoints { format, file }),
+                ..
+            } => {
+                let exporter = formats::registry().exporter(&format)?;
+                self.server_state.export_endpoints(exporter, &file)?;
+                log::info!("Exported the endpoints as {} to {}", format, file.display());
+            }
+            Command::Export {
+                format: Some(ExportFormat::Curl { file, failed }),
+                ..
+            } => {
+                let count = self.server_state.export_curl(&file, failed)?;
+                log::info!(
+                    "Wrote curl commands for {} requests to {}",
+                    count,
+                    file.display()
+                );
+            }
+            Command::Export {
+                format: Some(ExportFormat::Har { file, failed }),
+                ..
+            } => {
+                let count = self.server_state.export_har(&file, failed)?;
+                log::info!("Wrote {} requests to {}", count, file.display());
+            }
+            Command::Export {
+                format: None,
+                file: Some(file),
+            } => {
+                self.server_state.export_report(&file)?;
+                log::info!("Exported the report to {}", file.display());
+            }
+            // Clap requires a file without a format
+            Command::Export {
+                format: None,
+                file: None,
+            } => {}
+            Command::Spec {
+                action: SpecAction::Export { file },
+            } => {
+                let exporter = formats::registry().exporter("openapi")?;
+                self.server_state.export_endpoints(exporter, &file)?;
+                log::info!("Exported an OpenAPI document to {}", file.display());
+            }
+            Command::Spec {
+                action: SpecAction::Load { file },
+            } => {
+                let operations = self.server_state.load_spec(&file)?;
+                log::info!(
+                    "Tracking the coverage of {} operations of {}, see `coverage`",
+                    operations,
+                    file.display()
+                );
+            }
+            Command::Coverage { action } => {
+                self.configure_coverage(action.unwrap_or(CoverageAction::Report))
+            }
+            Command::Metrics { action } => {
+                self.configure_metrics(action.unwrap_or(MetricsAction::Show))
+            }
+            Command::Normalize {
+                uri,
+                method,
+                headers,
+            } => self
+                .server_state
+                .normalize(&method.into(), &uri, headers.into_iter().collect()),
+            Command::Scenario { action } => {
+                self.configure_scenario(action.unwrap_or(ScenarioAction::Show))?
+            }
+            Command::Redact { action } => {
+                self.configure_redactions(action.unwrap_or(RedactAction::List))
+            }
+            Command::Oauth { action } => self.configure_oauth(action.unwrap_or(OauthAction::Show)),
+            Command::Inbox { action } => self.configure_inbox(action.unwrap_or(InboxAction::List)),
+            Command::Connection { action } => {
+                self.configure_connections(action.unwrap_or(ConnectionAction::Show))
+            }
+            Command::Journal { action } => {
+                self.configure_journal(action.unwrap_or(JournalAction::Show))
+            }
+            Command::Tenant { action } => {
+                self.configure_tenants(action.unwrap_or(TenantAction::Show))
+            }
+            Command::Host { action } => {
+                self.configure_virtual_hosts(action.unwrap_or(HostAction::List))
+            }
+            Command::Mutate { action } => {
+                self.configure_mutations(action.unwrap_or(MutateAction::Report))
+            }
+            Command::Chaos { action } => self.configure_chaos(action.unwrap_or(ChaosAction::List)),
+            Command::Auth { action } => self.configure_auth(action.unwrap_or(AuthAction::List))?,
+            Command::Cors { action } => self.configure_cors(action.unwrap_or(CorsAction::Show)),
+            Command::Tls { action } => self.configure_tls(action.unwrap_or(TlsAction::Show))?,
+            Command::Grpc { action } => self.configure_grpc(action.unwrap_or(GrpcAction::List))?,
+            Command::Fallback { action } => {
+                self.configure_fallback(action.unwrap_or(FallbackAction::Show))
+            }
+            Command::Preset { action } => match action.unwrap_or(PresetAction::List) {
+                PresetAction::List => list_presets(),
+                PresetAction::Apply {
+                    name,
+                    base,
+                    on_conflict,
+                    tenant,
+                } => {
+                    let file = preset::expand(&name, &base, preset::preset_dir().as_deref())?;
+                    let label = PathBuf::from(format!("preset {} at {}", name, base));
+                    self.import_endpoints(&label, file, on_conflict.into(), tenant.as_deref())?;
+                }
+            },
+            Command::Config { action } => match action {
+                ConfigAction::Import {
+                    file,
+                    on_conflict,
+                    tenant,
+                } => self.import_file(&file, on_conflict.into(), tenant.as_deref())?,
+                ConfigAction::Merge { files, strategy } => {
+                    self.merge_files(&files, strategy.into())?
+                }
+                ConfigAction::Save { dir } => {
+                    let files = config::save_dir(&self.server_state.read_endpoints(), &dir)?;
+                    log::info!("Saved {} endpoint file(s) to {}", files, dir.display());
+                }
+            },
+            Command::Middleware { action } => {
+                self.configure_middleware(action.unwrap_or(MiddlewareAction::List))
+            }
+            Command::RateLimit { action } => {
+                self.configure_rate_limit(action.unwrap_or(RateLimitAction::Show))
+            }
+            Command::Watch { file, stop } => self.configure_watch(file, stop)?,
+            Command::Tutorial { stop } => self.configure_tutorial(stop),
+            Command::Import {
+                format,
+                file,
+                on_conflict,
+                tenant,
+            } => {
+                let endpoints = formats::registry().load(&format, &file)?;
+                self.import_endpoints(&file, endpoints, on_conflict.into(), tenant.as_deref())?
             }
         }
-        self.history.push(self.input.clone());
-        self.history_index = None;
-        self.input.clear();
         Ok(())
     }
 }
+
+fn parse_command(args: &[String]) -> Result<Command, clap::Error> {
+    Cli::try_parse_from(std::iter::once("").chain(args.iter().map(|s| s.as_str())))
+        .map(|cli| cli.command)
+}
+
+fn list_presets() {
+    let dir = preset::preset_dir();
+    let presets: Vec<String> = preset::presets(dir.as_deref())
+        .into_iter()
+        .map(|(name, source)| match source {
+            PresetSource::Builtin(_) => format!("  {} (builtin)", name),
+            PresetSource::File(path) => format!("  {} ({})", name, path.display()),
+        })
+        .collect();
+    log::info!("Presets:\n{}", presets.join("\n"));
+    if let Some(dir) = dir {
+        log::info!(
+            "Add your own presets as endpoint files in {}",
+            dir.display()
+        );
+    }
+}
+
+/// Lines read from stdin on a thread of its own, the channel disconnects once stdin is closed
+fn read_stdin_lines() -> Receiver<String> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Copy `text` to the clipboard of the terminal with OSC 52, which most terminals, also over
+/// SSH, pass on to the system clipboard
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
+
+/// Log a failed command along with a hint on how to fix it, if there is one
+fn report_error(err: &InternalError) {
+    match err.help() {
+        Some(help) => log::error!("{}\nhelp: {}", err, help),
+        None => log::error!("{}", err),
+    }
+}
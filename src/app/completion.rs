@@ -0,0 +1,161 @@
+use clap::{Arg, CommandFactory, ValueEnum};
+
+use crate::{
+    command::{Cli, HttpMethod},
+    server::EndpointStore,
+};
+
+/// Completion candidates for the word under the cursor (always the last word of the input)
+#[derive(Debug, Default, PartialEq)]
+pub struct Completion {
+    /// Byte offset in the input where the word being completed starts
+    pub start: usize,
+    pub candidates: Vec<String>,
+}
+
+impl Completion {
+    /// The part of the word being completed that has already been typed
+    pub fn prefix<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.start..]
+    }
+
+    /// Longest prefix shared by all candidates
+    pub fn common_prefix(&self) -> Option<&str> {
+        let first = self.candidates.first()?;
+        let mut len = first.len();
+        for candidate in &self.candidates[1..] {
+            len = first[..len]
+                .char_indices()
+                .zip(candidate.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(len.min(candidate.len()), |((i, _), _)| i);
+        }
+        Some(&first[..len])
+    }
+}
+
+/// Complete the last word of `input` by walking the clap command tree. Positional arguments are
+/// completed from their possible values, paths from the endpoints currently registered in `store`.
+pub fn complete(input: &str, store: &EndpointStore) -> Completion {
+    let start = input
+        .char_indices()
+        .rfind(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let prefix = &input[start..];
+    let words: Vec<&str> = input[..start].split_whitespace().collect();
+
+    let mut command = Cli::command();
+    let mut positionals: Vec<&str> = Vec::new();
+    for word in &words {
+        if word.starts_with('-') {
+            continue;
+        }
+        let sub = command
+            .get_subcommands()
+            .find(|sub| {
+                positionals.is_empty()
+                    && sub
+                        .get_all_aliases()
+                        .chain([sub.get_name()])
+                        .any(|n| n == *word)
+            })
+            .cloned();
+        match sub {
+            Some(sub) => command = sub,
+            None => positionals.push(word),
+        }
+    }
+
+    let candidates: Vec<String> = if prefix.starts_with('-') {
+        command
+            .get_arguments()
+            .filter_map(Arg::get_long)
+            .map(|long| format!("--{}", long))
+            .collect()
+    } else if command.has_subcommands() && positionals.is_empty() {
+        command
+            .get_subcommands()
+            .map(|sub| sub.get_name().to_owned())
+            .filter(|name| name != "help")
+            .collect()
+    } else {
+        let args: Vec<&Arg> = command.get_positionals().collect();
+        match args.get(positionals.len()) {
+            Some(arg) if arg.get_id() == "path" => {
+                let method = args
+                    .iter()
+                    .zip(&positionals)
+                    .find(|(arg, _)| arg.get_id() == "method")
+                    .and_then(|(_, value)| HttpMethod::from_str(value, true).ok())
+                    .map(actix_web::http::Method::from);
+                store
+                    .entries(method.as_ref())
+                    .into_iter()
+                    .flat_map(|(_, children)| children.into_iter().map(|(path, _)| path))
+                    .collect()
+            }
+            Some(arg) => arg
+                .get_possible_values()
+                .iter()
+                .map(|value| value.get_name().to_owned())
+                .collect(),
+            None => Vec::new(),
+        }
+    };
+
+    let mut candidates: Vec<String> = candidates
+        .into_iter()
+        .filter(|c| c.starts_with(prefix) && c != prefix)
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    Completion { start, candidates }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::{http::Method, web::Bytes};
+
+    use super::*;
+
+    fn store() -> EndpointStore {
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/users", Bytes::from("[]"));
+        store.add(Method::GET, "/users/1", Bytes::from("{}"));
+        store.add(Method::POST, "/orders", Bytes::from("{}"));
+        store
+    }
+
+    #[test]
+    fn test_complete_subcommands() {
+        let completion = complete("endpoint de", &store());
+        assert_eq!(completion.start, 9);
+        assert_eq!(completion.candidates, ["delete"]);
+
+        let completion = complete("e", &store());
+        assert_eq!(completion.candidates, ["endpoint", "export"]);
+    }
+
+    #[test]
+    fn test_complete_methods_after_alias() {
+        let completion = complete("ep add p", &store());
+        assert_eq!(completion.candidates, ["patch", "post", "put"]);
+    }
+
+    #[test]
+    fn test_complete_paths_filtered_by_method() {
+        let completion = complete("endpoint delete get /u", &store());
+        assert_eq!(completion.candidates, ["/users", "/users/1"]);
+        assert_eq!(completion.common_prefix(), Some("/users"));
+
+        let completion = complete("endpoint delete post ", &store());
+        assert_eq!(completion.candidates, ["/orders"]);
+    }
+
+    #[test]
+    fn test_complete_nothing_after_last_positional() {
+        let completion = complete("endpoint delete get /users ", &store());
+        assert!(completion.candidates.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, Sender},
+};
+
+use crate::util::result::InternalResult;
+
+/// A command line received on the control socket, answered through `reply`
+#[derive(Debug)]
+pub struct ControlCommand {
+    pub line: String,
+    pub reply: Sender<String>,
+}
+
+/// A local socket accepting the commands of the TUI, one per line, each answered with a JSON
+/// line like on stdin in headless mode. Lets editor plugins and scripts on the same machine
+/// drive a running session without going over the network. The socket file is removed once
+/// dropped.
+#[derive(Debug)]
+pub struct ControlSocket {
+    path: PathBuf,
+    commands: Receiver<ControlCommand>,
+}
+
+impl ControlSocket {
+    /// Listen on a Unix socket at `path`, replacing a stale one left behind by a crashed instance
+    #[cfg(unix)]
+    pub fn bind(path: &Path) -> InternalResult<Self> {
+        use std::{
+            fs::{self, DirBuilder, Permissions},
+            os::unix::{
+                fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+                net::{UnixListener, UnixStream},
+            },
+            process,
+            sync::mpsc,
+            thread,
+        };
+
+        use crate::util::error::InternalError;
+
+        let io_error = |source| InternalError::FileIo {
+            path: path.to_owned(),
+            source,
+        };
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            let reason = if !metadata.file_type().is_socket() {
+                Some("it exists and is not a socket")
+            } else if UnixStream::connect(path).is_ok() {
+                Some("another instance is listening on it")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(InternalError::InvalidArgument {
+                    argument: "control-socket",
+                    reason: format!("can't listen on `{}`, {}", path.display(), reason),
+                });
+            }
+            fs::remove_file(path).map_err(io_error)?;
+        }
+        // Commands change the running session, so only the owner may send them. The socket is
+        // bound in a directory only the owner can enter, where nobody can connect before its
+        // permissions are tightened, then moved into place.
+        let file_name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+        let private_dir = path.with_file_name(format!(".{}.{}", file_name, process::id()));
+        DirBuilder::new()
+            .mode(0o700)
+            .create(&private_dir)
+            .map_err(|source| InternalError::FileIo {
+                path: private_dir.clone(),
+                source,
+            })?;
+        let private_path = private_dir.join("socket");
+        let bound = UnixListener::bind(&private_path).and_then(|listener| {
+            fs::set_permissions(&private_path, Permissions::from_mode(0o600))?;
+            fs::rename(&private_path, path)?;
+            Ok(listener)
+        });
+        let _ = fs::remove_file(&private_path);
+        let _ = fs::remove_dir(&private_dir);
+        let listener = bound.map_err(io_error)?;
+        let (sender, commands) = mpsc::channel();
+        thread::Builder::new()
+            .name("control-socket".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let sender = sender.clone();
+                    thread::spawn(move || serve(stream, sender));
+                }
+            })?;
+        Ok(Self {
+            path: path.to_owned(),
+            commands,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind(path: &Path) -> InternalResult<Self> {
+        Err(crate::util::error::InternalError::InvalidArgument {
+            argument: "control-socket",
+            reason: format!(
+                "can't listen on `{}`, control sockets are only supported on Unix",
+                path.display()
+            ),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The next command received, if any, without waiting
+    pub fn try_recv(&self) -> Option<ControlCommand> {
+        self.commands.try_recv().ok()
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Pass the lines of a connection on as commands and write back their answers, until the client
+/// disconnects or the session ends
+#[cfg(unix)]
+fn serve(stream: std::os::unix::net::UnixStream, commands: Sender<ControlCommand>) {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        sync::mpsc,
+    };
+
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        let (reply, answer) = mpsc::channel();
+        if commands.send(ControlCommand { line, reply }).is_err() {
+            break;
+        }
+        // Blank lines are dropped without an answer
+        let Ok(answer) = answer.recv() else { continue };
+        if writeln!(writer, "{}", answer).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        os::unix::{fs::PermissionsExt, net::UnixStream},
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_answer_over_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("control.sock");
+        let socket = ControlSocket::bind(&path).unwrap();
+        assert!(ControlSocket::bind(&path).is_err());
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"endpoint list\n").unwrap();
+        let command = loop {
+            match socket.try_recv() {
+                Some(command) => break command,
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        assert_eq!(command.line, "endpoint list");
+        command.reply.send(r#"{"ok":true}"#.to_owned()).unwrap();
+        let mut answer = String::new();
+        BufReader::new(client).read_line(&mut answer).unwrap();
+        assert_eq!(answer, "{\"ok\":true}\n");
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,142 @@
+/// Single line text input with a cursor
+#[derive(Debug, Default)]
+pub struct InputLine {
+    text: String,
+    /// Byte offset of the cursor, always on a char boundary
+    cursor: usize,
+}
+
+impl InputLine {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn before_cursor(&self) -> &str {
+        &self.text[..self.cursor]
+    }
+
+    /// Cursor position counted in chars, for rendering
+    pub fn cursor_column(&self) -> usize {
+        self.text[..self.cursor].chars().count()
+    }
+
+    pub fn is_cursor_at_end(&self) -> bool {
+        self.cursor == self.text.len()
+    }
+
+    /// Replace the whole text, placing the cursor at the end
+    pub fn set(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.text.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.set(String::new());
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Replace the text from byte offset `start` up to the cursor
+    pub fn replace_before_cursor(&mut self, start: usize, s: &str) {
+        self.text.replace_range(start..self.cursor, s);
+        self.cursor = start + s.len();
+    }
+
+    /// Delete the char before the cursor
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    /// Delete the char under the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Delete everything before the cursor
+    pub fn delete_to_start(&mut self) {
+        self.text.drain(..self.cursor);
+        self.cursor = 0;
+    }
+
+    /// Delete the word before the cursor, including trailing whitespace
+    pub fn delete_word(&mut self) {
+        let before = self.text[..self.cursor].trim_end();
+        let start = before.rfind(' ').map_or(0, |pos| pos + 1);
+        self.text.drain(start..self.cursor);
+        self.cursor = start;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(text: &str, cursor: usize) -> InputLine {
+        InputLine {
+            text: text.to_owned(),
+            cursor,
+        }
+    }
+
+    #[test]
+    fn test_insert_and_delete_at_cursor() {
+        let mut line = input("endpoint ad get", 11);
+        line.insert('d');
+        assert_eq!(line.as_str(), "endpoint add get");
+        line.move_home();
+        line.delete();
+        line.insert('E');
+        assert_eq!(line.as_str(), "Endpoint add get");
+        line.move_end();
+        line.backspace();
+        assert_eq!(line.as_str(), "Endpoint add ge");
+    }
+
+    #[test]
+    fn test_cursor_moves_over_multibyte_chars() {
+        let mut line = input("aé", 3);
+        line.move_left();
+        assert_eq!(line.cursor_column(), 1);
+        line.backspace();
+        assert_eq!(line.as_str(), "é");
+        line.move_right();
+        assert!(line.is_cursor_at_end());
+    }
+
+    #[test]
+    fn test_delete_word_before_cursor() {
+        let mut line = input("endpoint add get /users", 17);
+        line.delete_word();
+        assert_eq!(line.as_str(), "endpoint add /users");
+        line.delete_to_start();
+        assert_eq!(line.as_str(), "/users");
+    }
+}
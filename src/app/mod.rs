@@ -1,4 +1,10 @@
 mod app_state;
+mod completion;
+mod control;
+mod input;
+mod tutorial;
 mod ui;
+mod usage;
 
-pub use app_state::App;
+pub use app_state::{App, ExitStatus};
+pub use ui::Tab;
@@ -0,0 +1,222 @@
+use actix_web::http::Method;
+
+use crate::{
+    command::{Command, EndpointAction},
+    server::{EndpointStatsTable, EndpointStore},
+};
+
+/// Steps of the tutorial, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Create,
+    List,
+    Call,
+    Delete,
+}
+
+impl Step {
+    const ALL: [Step; 4] = [Step::Create, Step::List, Step::Call, Step::Delete];
+
+    fn title(self) -> &'static str {
+        match self {
+            Step::Create => "Create an endpoint",
+            Step::List => "List the endpoints",
+            Step::Call => "Call the endpoint",
+            Step::Delete => "Delete the endpoint",
+        }
+    }
+}
+
+/// Walks a new user through creating, listing, calling and deleting an endpoint. Each step is
+/// checked against the server state, so it also counts when done some other way, e.g. by
+/// importing a file.
+#[derive(Debug)]
+pub struct Tutorial {
+    step: Step,
+    /// Endpoints present when the tutorial started, so the one created in it can be told apart
+    existing: Vec<(Method, String)>,
+    /// Endpoint created in the first step
+    endpoint: Option<(Method, String)>,
+    /// Whether `endpoint list` ran since the endpoint was created
+    listed: bool,
+    /// Hits of the endpoint when the call step started
+    hits: u64,
+}
+
+impl Tutorial {
+    pub fn start(endpoints: &EndpointStore) -> Self {
+        Self {
+            step: Step::Create,
+            existing: registered(endpoints),
+            endpoint: None,
+            listed: false,
+            hits: 0,
+        }
+    }
+
+    /// Note commands the server state doesn't reflect, i.e. listing the endpoints
+    pub fn observe(&mut self, command: &Command) {
+        if let Command::Endpoint {
+            action: EndpointAction::List { .. },
+        } = command
+        {
+            self.listed = true;
+        }
+    }
+
+    /// e.g. `2/4 List the endpoints`
+    pub fn title(&self) -> String {
+        let number = Step::ALL
+            .iter()
+            .position(|step| *step == self.step)
+            .unwrap_or_default()
+            + 1;
+        format!("{}/{} {}", number, Step::ALL.len(), self.step.title())
+    }
+
+    /// What to do in the current step. `url` is where the server is reached, e.g.
+    /// `http://127.0.0.1:3000`.
+    pub fn instruction(&self, url: &str) -> String {
+        match (self.step, &self.endpoint) {
+            (Step::Create, _) => {
+                "Press i, then type `endpoint add get /hello 'Hello!'` and Enter. \
+                 The method, path and response can be anything you like."
+                    .to_owned()
+            }
+            (Step::List, _) => "Type `endpoint list` to see all endpoints with their responses \
+                 and how often they were hit."
+                .to_owned(),
+            (Step::Call, Some((method, path))) => {
+                let method = match *method {
+                    Method::GET => String::new(),
+                    ref method => format!("-X {} ", method),
+                };
+                format!(
+                    "Call it from another terminal, e.g. `curl -i {}{}{}`, and watch the logs tab.",
+                    method, url, path
+                )
+            }
+            (Step::Delete, Some((method, path))) => format!(
+                "Type `endpoint delete {} {}` to remove it again.",
+                method.as_str().to_ascii_lowercase(),
+                path
+            ),
+            (_, None) => String::new(),
+        }
+    }
+
+    /// Move on once the current step is done, logging the progress. Returns whether the tutorial
+    /// is complete.
+    pub fn advance(&mut self, endpoints: &EndpointStore, stats: &EndpointStatsTable) -> bool {
+        let hits = |(method, path): &(Method, String)| {
+            stats.get(method, path).map_or(0, |stats| stats.hits)
+        };
+        // Without the endpoint the later steps can't be done
+        if let Some((method, path)) = &self.endpoint
+            && self.step != Step::Delete
+            && endpoints.candidates(method, path).is_empty()
+        {
+            log::info!("Tutorial: {} {} is gone, create it again", method, path);
+            self.step = Step::Create;
+            self.endpoint = None;
+        }
+        let done = match (self.step, &self.endpoint) {
+            (Step::Create, _) => {
+                self.endpoint = registered(endpoints)
+                    .into_iter()
+                    .find(|endpoint| !self.existing.contains(endpoint));
+                self.listed = false;
+                self.endpoint.is_some()
+            }
+            (Step::List, _) => self.listed,
+            (Step::Call, Some(endpoint)) => hits(endpoint) > self.hits,
+            (Step::Delete, Some((method, path))) => endpoints.candidates(method, path).is_empty(),
+            (_, None) => false,
+        };
+        if !done {
+            return false;
+        }
+        log::info!("Tutorial: {} done", self.step.title().to_lowercase());
+        self.step = match self.step {
+            Step::Create => Step::List,
+            Step::List => {
+                self.hits = self.endpoint.as_ref().map_or(0, hits);
+                Step::Call
+            }
+            Step::Call => Step::Delete,
+            Step::Delete => {
+                log::info!(
+                    "Tutorial complete. See the help tab for all commands, e.g. `endpoint add \
+                     --help` for matchers, faults and more."
+                );
+                return true;
+            }
+        };
+        false
+    }
+}
+
+/// Method and path of every endpoint, once per endpoint
+fn registered(endpoints: &EndpointStore) -> Vec<(Method, String)> {
+    let mut registered: Vec<(Method, String)> = endpoints
+        .entries(None)
+        .into_iter()
+        .flat_map(|(method, entries)| {
+            entries
+                .into_iter()
+                .map(move |(path, _)| (method.clone(), path))
+        })
+        .collect();
+    registered.dedup();
+    registered
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn test_walk_through_steps() {
+        let mut endpoints = EndpointStore::default();
+        endpoints.add(Method::GET, "/health", "ok");
+        let mut stats = EndpointStatsTable::default();
+        let mut tutorial = Tutorial::start(&endpoints);
+        assert!(!tutorial.advance(&endpoints, &stats));
+        assert_eq!(tutorial.title(), "1/4 Create an endpoint");
+
+        endpoints.add(Method::POST, "/hello", "Hello!");
+        assert!(!tutorial.advance(&endpoints, &stats));
+        assert_eq!(tutorial.title(), "2/4 List the endpoints");
+        tutorial.observe(&Command::Endpoint {
+            action: EndpointAction::List { method: None },
+        });
+        assert!(!tutorial.advance(&endpoints, &stats));
+        assert_eq!(
+            tutorial.instruction("http://127.0.0.1:3000"),
+            "Call it from another terminal, e.g. `curl -i -X POST http://127.0.0.1:3000/hello`, \
+             and watch the logs tab."
+        );
+
+        stats.record(&Method::POST, "/hello", StatusCode::OK);
+        assert!(!tutorial.advance(&endpoints, &stats));
+        assert_eq!(tutorial.title(), "4/4 Delete the endpoint");
+        endpoints.delete(&Method::POST, "/hello");
+        assert!(tutorial.advance(&endpoints, &stats));
+    }
+
+    #[test]
+    fn test_recreate_deleted_endpoint() {
+        let mut endpoints = EndpointStore::default();
+        let stats = EndpointStatsTable::default();
+        let mut tutorial = Tutorial::start(&endpoints);
+        endpoints.add(Method::GET, "/hello", "Hello!");
+        tutorial.advance(&endpoints, &stats);
+        endpoints.delete(&Method::GET, "/hello");
+
+        assert!(!tutorial.advance(&endpoints, &stats));
+        assert_eq!(tutorial.title(), "1/4 Create an endpoint");
+    }
+}
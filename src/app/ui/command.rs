@@ -5,16 +5,15 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Widget},
 };
 
-#[derive(Debug, Default)]
-pub enum InputMode {
-    #[default]
-    Normal,
-    Insert,
-}
+use super::InputMode;
 
 pub struct CommandPane<'a> {
     pub input: &'a str,
+    /// Completion suggestion shown dimmed after the input
+    pub ghost: &'a str,
     pub mode: &'a InputMode,
+    /// Number of columns scrolled off to the left
+    pub scroll: u16,
 }
 
 impl<'a> Widget for &CommandPane<'a> {
@@ -27,6 +26,10 @@ impl<'a> Widget for &CommandPane<'a> {
             Span::styled(" for insert mode ", Style::default().fg(Color::Red)),
             Span::styled("ESC", tips_style),
             Span::styled(" for normal mode ", Style::default().fg(Color::Red)),
+            Span::styled("E", tips_style),
+            Span::styled(" to toggle endpoints ", Style::default().fg(Color::Red)),
+            Span::styled("1-6/TAB", tips_style),
+            Span::styled(" to switch tabs ", Style::default().fg(Color::Red)),
         ]);
 
         let title = Line::from(
@@ -41,34 +44,13 @@ impl<'a> Widget for &CommandPane<'a> {
             .title_bottom(instruction)
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::Red));
-        Paragraph::new(self.input).block(block).render(area, buf);
-    }
-}
-
-pub struct LogPane<'a> {
-    pub messages: &'a [String],
-}
-
-impl<'a> Widget for &LogPane<'a> {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let block = Block::default()
-            .title("Server Logs")
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Red));
-        let text: Vec<Line> = self
-            .messages
-            .iter()
-            .flat_map(|m| {
-                m.lines().enumerate().map(|(i, line)| {
-                    if i == 0 {
-                        Line::from(line)
-                    } else {
-                        // Indent continuation lines to align with message content after "[LEVEL] "
-                        Line::from(format!("        {}", line))
-                    }
-                })
-            })
-            .collect();
-        Paragraph::new(text).block(block).render(area, buf);
+        let input = Line::from(vec![
+            Span::raw(self.input),
+            Span::styled(self.ghost, Style::default().fg(Color::DarkGray)),
+        ]);
+        Paragraph::new(input)
+            .scroll((0, self.scroll))
+            .block(block)
+            .render(area, buf);
     }
 }
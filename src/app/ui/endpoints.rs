@@ -0,0 +1,79 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::server::{EndpointStatsTable, EndpointStore};
+
+/// Maximum number of characters of a response body shown in the endpoints pane
+const BODY_PREVIEW_LEN: usize = 40;
+
+pub struct EndpointsPane<'a> {
+    pub store: &'a EndpointStore,
+    pub stats: &'a EndpointStatsTable,
+}
+
+impl<'a> Widget for &EndpointsPane<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .title("Endpoints")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red));
+        if self.store.is_empty() {
+            Paragraph::new("No endpoints defined".italic())
+                .block(block)
+                .render(area, buf);
+            return;
+        }
+        let mut text = Vec::new();
+        for (method, children) in self.store.entries(None) {
+            text.push(Line::from(method.as_str().bold()));
+            let last = children.len().saturating_sub(1);
+            for (i, (path, stub)) in children.iter().enumerate() {
+                let branch = if i == last { "└─ " } else { "├─ " };
+                let (hits, failures) = self
+                    .stats
+                    .get(method, path)
+                    .map_or((0, 0), |s| (s.hits, s.failures));
+                let mut line = Line::from(vec![
+                    Span::raw(branch),
+                    Span::styled(path.clone(), Style::default().fg(Color::Cyan)),
+                    Span::styled(
+                        format!(" {}× ", hits),
+                        Style::default().fg(if hits == 0 {
+                            Color::DarkGray
+                        } else {
+                            Color::Green
+                        }),
+                    ),
+                ]);
+                if failures > 0 {
+                    line.push_span(Span::styled(
+                        format!("{}✗ ", failures),
+                        Style::default().fg(Color::Red).bold(),
+                    ));
+                }
+                line.push_span(Span::styled(
+                    body_preview(&stub.body),
+                    Style::default().fg(Color::DarkGray),
+                ));
+                text.push(line);
+            }
+        }
+        Paragraph::new(text).block(block).render(area, buf);
+    }
+}
+
+/// Single-line, truncated rendering of a response body
+fn body_preview(body: &[u8]) -> String {
+    let body = String::from_utf8_lossy(body);
+    let flat = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() > BODY_PREVIEW_LEN {
+        let truncated: String = flat.chars().take(BODY_PREVIEW_LEN).collect();
+        format!("{}…", truncated)
+    } else {
+        flat
+    }
+}
@@ -0,0 +1,70 @@
+use clap::CommandFactory;
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+};
+
+use crate::command::Cli;
+
+const KEY_BINDINGS: &[(&str, &str)] = &[
+    ("q", "quit (normal mode)"),
+    ("i", "enter insert mode"),
+    ("Esc", "return to normal mode, then clear log selection"),
+    ("1-6", "switch to tab"),
+    ("Tab / Shift-Tab", "next / previous tab"),
+    ("r", "restart the server if it is down or unresponsive"),
+    ("f", "cycle the minimum level of shown log entries"),
+    ("Up / Down / j / k", "select log entry (logs tab)"),
+    (
+        "Enter",
+        "inspect the request of the selected access log entry",
+    ),
+    (
+        "Space",
+        "expand / collapse the selected group of repeated log entries",
+    ),
+    (
+        "c",
+        "copy a curl command reproducing the inspected or selected request",
+    ),
+    ("e", "toggle the endpoints pane next to the logs"),
+    ("g", "toggle the guide next to multi-line log entries"),
+    ("Tab", "complete command, method or path (insert mode)"),
+    ("Up / Down", "browse command history (insert mode)"),
+    ("Left / Right", "move the cursor (insert mode)"),
+    (
+        "Home / End",
+        "jump to start / end of the input (insert mode)",
+    ),
+    (
+        "Ctrl-U / Ctrl-W",
+        "delete to start / delete word before the cursor (insert mode)",
+    ),
+];
+
+pub struct HelpPane;
+
+impl Widget for &HelpPane {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .title("Help")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red));
+        let mut text = vec![Line::from("Key bindings".bold())];
+        text.extend(
+            KEY_BINDINGS
+                .iter()
+                .map(|(key, action)| Line::from(format!("  {:<18}{}", key, action))),
+        );
+        text.push(Line::from(""));
+        text.push(Line::from("Commands".bold()));
+        let usage = Cli::command().render_long_help().to_string();
+        text.extend(usage.lines().map(|l| Line::from(format!("  {}", l))));
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .block(block)
+            .render(area, buf);
+    }
+}
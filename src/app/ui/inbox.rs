@@ -0,0 +1,64 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::{server::Inbox, util::units::format_size};
+
+/// Requests captured by the inbox paths, see `inbox show` for one in full
+pub struct InboxPane<'a> {
+    pub inbox: &'a Inbox,
+}
+
+impl<'a> Widget for &InboxPane<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .title(format!("Inbox ({})", self.inbox.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red));
+        if self.inbox.is_empty() {
+            let message = if self.inbox.paths().is_empty() {
+                "No inbox paths, add one with `inbox add /hooks/name`".to_owned()
+            } else {
+                format!("Nothing sent to {} yet", self.inbox.paths().join(", "))
+            };
+            Paragraph::new(message.italic())
+                .block(block)
+                .render(area, buf);
+            return;
+        }
+        // Newest first, only as many as fit into the pane
+        let visible = area.height.saturating_sub(2) as usize;
+        let text: Vec<Line> = self
+            .inbox
+            .captured()
+            .rev()
+            .take(visible)
+            .map(|capture| {
+                let record = &capture.record;
+                Line::from(vec![
+                    Span::styled(
+                        format!("#{:<4}", capture.number),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::styled(
+                        record.timestamp.format("%H:%M:%S ").to_string(),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        format!("{:<7}", record.method.as_str()),
+                        Style::default().bold(),
+                    ),
+                    Span::raw(record.path.clone()),
+                    Span::styled(
+                        format!(" {}", format_size(record.body_size)),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ])
+            })
+            .collect();
+        Paragraph::new(text).block(block).render(area, buf);
+    }
+}
@@ -0,0 +1,117 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+};
+
+use crate::{
+    server::{RequestRecord, curl_command},
+    util::units::format_size,
+};
+
+/// Full details of a single journaled request
+pub struct RequestInspector<'a> {
+    pub record: &'a RequestRecord,
+}
+
+impl<'a> Widget for &RequestInspector<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let record = self.record;
+        let block = Block::default()
+            .title(format!("Request #{}", record.id))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red));
+        let uri = if record.query.is_empty() {
+            record.path.clone()
+        } else {
+            format!("{}?{}", record.path, record.query)
+        };
+        let mut text = vec![
+            Line::from(vec![
+                Span::styled(format!("{} ", record.method), Style::default().bold()),
+                Span::raw(uri),
+                Span::raw(format!(" -> {}", record.status)),
+            ]),
+            Line::from(
+                record
+                    .timestamp
+                    .format("%Y-%m-%d %H:%M:%S%.3f")
+                    .to_string()
+                    .dark_gray(),
+            ),
+        ];
+        if let Some(fault) = &record.fault {
+            text.push(Line::from(vec![
+                Span::styled("Injected fault: ", Style::default().fg(Color::Red).bold()),
+                Span::raw(fault.clone()),
+            ]));
+        }
+        text.extend(record.failures.iter().map(|failure| {
+            Line::from(vec![
+                Span::styled("Assertion failed: ", Style::default().fg(Color::Red).bold()),
+                Span::raw(failure.clone()),
+            ])
+        }));
+        text.extend(record.violations.iter().map(|violation| {
+            Line::from(vec![
+                Span::styled("Schema violation: ", Style::default().fg(Color::Red).bold()),
+                Span::raw(violation.clone()),
+            ])
+        }));
+        text.push(Line::from(""));
+        text.push(Line::from("Headers".bold()));
+        text.extend(record.headers.iter().map(|(name, value)| {
+            Line::from(vec![
+                Span::styled(format!("  {}: ", name), Style::default().fg(Color::Cyan)),
+                Span::raw(value.clone()),
+            ])
+        }));
+        text.push(Line::from(""));
+        let mut notes = Vec::new();
+        if let Some(size) = record.encoded_size {
+            notes.push(format!("decoded, {} compressed", format_size(size)));
+        }
+        if record.truncated() {
+            notes.push(format!(
+                "cut off, {} received",
+                format_size(record.body_size)
+            ));
+        }
+        if notes.is_empty() {
+            text.push(Line::from("Body".bold()));
+        } else {
+            text.push(Line::from(vec![
+                "Body".bold(),
+                format!(" ({})", notes.join(", ")).dark_gray(),
+            ]));
+        }
+        text.extend(body_lines(&record.body, "(empty)"));
+        text.push(Line::from(""));
+        text.push(Line::from("Matched endpoint".bold()));
+        match &record.stub {
+            Some(stub) => text.extend(body_lines(stub, "(empty response)")),
+            None => text.push(Line::from("  none, answered with 404".italic())),
+        }
+        text.push(Line::from(""));
+        text.push(Line::from(vec![
+            "Reproduce".bold(),
+            " (c to copy)".dark_gray(),
+        ]));
+        text.push(Line::from(format!("  {}", curl_command(record))));
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .block(block)
+            .render(area, buf);
+    }
+}
+
+fn body_lines(body: &[u8], placeholder: &'static str) -> Vec<Line<'static>> {
+    if body.is_empty() {
+        return vec![Line::from(format!("  {}", placeholder).italic())];
+    }
+    String::from_utf8_lossy(body)
+        .lines()
+        .map(|line| Line::from(format!("  {}", line)))
+        .collect()
+}
@@ -0,0 +1,327 @@
+use std::collections::{HashSet, VecDeque};
+
+use log::{Level, LevelFilter};
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::logger::LogEntry;
+
+/// Runtime filter for the log pane, hiding entries below a level or not containing a text
+#[derive(Debug)]
+pub struct LogFilter {
+    pub level: LevelFilter,
+    /// Case-insensitive substring the message or target of an entry must contain
+    pub text: Option<String>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::Trace,
+            text: None,
+        }
+    }
+}
+
+impl LogFilter {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        entry.level <= self.level
+            && self
+                .text
+                .as_ref()
+                .is_none_or(|text| entry.message.to_lowercase().contains(&text.to_lowercase()))
+    }
+
+    /// Step the minimum level up, wrapping around to showing everything
+    pub fn cycle_level(&mut self) {
+        self.level = match self.level {
+            LevelFilter::Trace | LevelFilter::Off => LevelFilter::Debug,
+            LevelFilter::Debug => LevelFilter::Info,
+            LevelFilter::Info => LevelFilter::Warn,
+            LevelFilter::Warn => LevelFilter::Error,
+            LevelFilter::Error => LevelFilter::Trace,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.level < LevelFilter::Trace || self.text.is_some()
+    }
+
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.level < LevelFilter::Trace {
+            parts.push(format!(">= {}", self.level));
+        }
+        if let Some(text) = &self.text {
+            parts.push(format!("'{}'", text));
+        }
+        parts.join(", ")
+    }
+}
+
+fn level_color(level: Level) -> Color {
+    match level {
+        Level::Error => Color::Red,
+        Level::Warn => Color::Yellow,
+        Level::Info => Color::Green,
+        Level::Debug => Color::Blue,
+        Level::Trace => Color::Magenta,
+    }
+}
+
+/// Fixed width, colored label for a log level
+fn level_badge(level: Level) -> Span<'static> {
+    Span::styled(
+        format!(" {:<5} ", level),
+        Style::default()
+            .fg(Color::Black)
+            .bg(level_color(level))
+            .bold(),
+    )
+}
+
+/// Lines of an entry, the first after its time and level. The following lines hang below the
+/// start of the message, by the width the prefix actually takes, so pretty-printed JSON keeps
+/// its shape. With `guide`, a line in the color of the level marks them as part of the entry.
+fn entry_lines(entry: &LogEntry, style: Style, guide: bool) -> Vec<Line<'_>> {
+    let prefix = vec![
+        Span::styled(
+            entry.timestamp.format("%H:%M:%S ").to_string(),
+            Style::default().fg(Color::DarkGray),
+        ),
+        level_badge(entry.level),
+        Span::raw(" "),
+    ];
+    let width: usize = prefix.iter().map(Span::width).sum();
+    let mut lines = entry.message.lines();
+    let mut first = prefix;
+    first.push(Span::raw(lines.next().unwrap_or_default()));
+    let mut text = vec![Line::from(first).style(style)];
+    text.extend(lines.map(|line| {
+        let indent = if guide {
+            vec![
+                Span::raw(" ".repeat(width.saturating_sub(2))),
+                Span::styled("│ ", Style::default().fg(level_color(entry.level))),
+            ]
+        } else {
+            vec![Span::raw(" ".repeat(width))]
+        };
+        Line::from([indent, vec![Span::raw(line)]].concat()).style(style)
+    }));
+    text
+}
+
+/// Whether `next` repeats `entry`. Timings, i.e. words that are decimal numbers like `0.000154`,
+/// may differ, so that the access log lines of a polling client repeat.
+fn repeats(entry: &LogEntry, next: &LogEntry) -> bool {
+    entry.level == next.level
+        && entry.target == next.target
+        && compared_words(&entry.message).eq(compared_words(&next.message))
+}
+
+/// Words of a message with timings blanked
+fn compared_words(message: &str) -> impl Iterator<Item = &str> {
+    message.split(' ').map(|word| {
+        if word.contains('.') && word.parse::<f64>().is_ok() {
+            ""
+        } else {
+            word
+        }
+    })
+}
+
+/// Indices into `messages` of the shown entries, split into runs repeating the same message
+pub fn log_groups(messages: &VecDeque<LogEntry>, filter: &LogFilter) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (index, entry) in messages.iter().enumerate() {
+        if !filter.matches(entry) {
+            continue;
+        }
+        match groups.last_mut() {
+            Some(group)
+                if group
+                    .last()
+                    .is_some_and(|&last| repeats(&messages[last], entry)) =>
+            {
+                group.push(index)
+            }
+            _ => groups.push(vec![index]),
+        }
+    }
+    groups
+}
+
+/// Indices of the entries shown by each entry line of the pane, of which the last is shown. A
+/// collapsed group shares one line, the entries of groups in `expanded`, keyed by their first
+/// index, get a line each.
+pub fn shown_entries(
+    messages: &VecDeque<LogEntry>,
+    filter: &LogFilter,
+    expanded: &HashSet<usize>,
+) -> Vec<Vec<usize>> {
+    log_groups(messages, filter)
+        .into_iter()
+        .flat_map(|group| match group.first() {
+            Some(first) if !expanded.contains(first) => vec![group],
+            _ => group.into_iter().map(|index| vec![index]).collect(),
+        })
+        .collect()
+}
+
+pub struct LogPane<'a> {
+    pub messages: &'a VecDeque<LogEntry>,
+    pub filter: &'a LogFilter,
+    /// Index into `messages` of the highlighted entry. Without a selection the pane follows the
+    /// newest entries.
+    pub selected: Option<usize>,
+    /// Mark the continuation lines of multi-line entries with a vertical guide
+    pub guide: bool,
+    /// Groups of repeated entries shown one by one, see [`shown_entries`]
+    pub expanded: &'a HashSet<usize>,
+}
+
+impl<'a> Widget for &LogPane<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let title = if self.filter.is_active() {
+            format!("Server Logs [{}]", self.filter.describe())
+        } else {
+            "Server Logs".to_owned()
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red));
+        let mut text: Vec<Line> = Vec::new();
+        let mut selected_line = None;
+        for shown in shown_entries(self.messages, self.filter, self.expanded) {
+            let Some(&index) = shown.last() else {
+                continue;
+            };
+            let entry = &self.messages[index];
+            let is_selected = self
+                .selected
+                .is_some_and(|selected| shown.contains(&selected));
+            if is_selected {
+                selected_line = Some(text.len());
+            }
+            let style = if is_selected {
+                Style::default().reversed()
+            } else {
+                Style::default()
+            };
+            let mut lines = entry_lines(entry, style, self.guide);
+            if shown.len() > 1 {
+                lines[0].push_span(Span::styled(
+                    format!(" ×{}", shown.len()),
+                    Style::default().fg(Color::Cyan).bold(),
+                ));
+            }
+            text.extend(lines);
+        }
+        // Follow the tail, unless that would scroll the selection out of view
+        let height = area.height.saturating_sub(2) as usize;
+        let tail_offset = text.len().saturating_sub(height);
+        let offset = selected_line.map_or(tail_offset, |line| line.min(tail_offset));
+        Paragraph::new(text)
+            .block(block)
+            .scroll((u16::try_from(offset).unwrap_or(u16::MAX), 0))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+
+    use super::*;
+
+    fn entry(level: Level, message: &str) -> LogEntry {
+        LogEntry {
+            level,
+            timestamp: Local::now(),
+            target: "test".to_owned(),
+            message: message.to_owned(),
+            request_id: None,
+        }
+    }
+
+    #[test]
+    fn test_filter_by_level() {
+        let filter = LogFilter {
+            level: LevelFilter::Warn,
+            text: None,
+        };
+        assert!(filter.matches(&entry(Level::Error, "boom")));
+        assert!(filter.matches(&entry(Level::Warn, "careful")));
+        assert!(!filter.matches(&entry(Level::Info, "hello")));
+    }
+
+    #[test]
+    fn test_continuation_lines_hang_below_message() {
+        let json = entry(Level::Debug, "Body:\n{\n  \"id\": 1\n}");
+        let column = |line: &Line| {
+            let text = line.to_string();
+            text.len() - text.trim_start_matches([' ', '│']).len()
+        };
+
+        let lines = entry_lines(&json, Style::default(), false);
+        assert_eq!(lines.len(), 4);
+        let message_column = lines[0].width() - "Body:".len();
+        assert_eq!(column(&lines[1]), message_column);
+        assert_eq!(lines[2].to_string().trim_start(), "\"id\": 1");
+
+        let guided = entry_lines(&json, Style::default(), true);
+        assert_eq!(guided[1].width(), lines[1].width());
+        assert!(guided[1].to_string().contains("│ {"));
+        assert_eq!(
+            entry_lines(&entry(Level::Info, ""), Style::default(), true).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_collapse_repeated_entries() {
+        let messages: VecDeque<LogEntry> = [
+            "127.0.0.1 \"GET /health HTTP/1.1\" 200 0.000154",
+            "127.0.0.1 \"GET /health HTTP/1.1\" 200 0.000098",
+            "127.0.0.1 \"GET /health HTTP/1.1\" 200 0.000120",
+            "127.0.0.1 \"GET /users HTTP/1.1\" 200 0.000301",
+            "127.0.0.1 \"GET /health HTTP/1.1\" 200 0.000101",
+        ]
+        .into_iter()
+        .map(|message| entry(Level::Info, message))
+        .collect();
+        let filter = LogFilter::default();
+
+        assert_eq!(
+            log_groups(&messages, &filter),
+            vec![vec![0, 1, 2], vec![3], vec![4]]
+        );
+        assert_eq!(
+            shown_entries(&messages, &filter, &HashSet::new()),
+            vec![vec![0, 1, 2], vec![3], vec![4]]
+        );
+        assert_eq!(
+            shown_entries(&messages, &filter, &HashSet::from([0])),
+            vec![vec![0], vec![1], vec![2], vec![3], vec![4]]
+        );
+        assert!(!repeats(
+            &entry(Level::Info, "GET /users 200"),
+            &entry(Level::Warn, "GET /users 200")
+        ));
+    }
+
+    #[test]
+    fn test_filter_by_text_ignores_case() {
+        let filter = LogFilter {
+            level: LevelFilter::Trace,
+            text: Some("users".to_owned()),
+        };
+        assert!(filter.matches(&entry(Level::Info, "GET /Users 200")));
+        assert!(!filter.matches(&entry(Level::Info, "GET /orders 200")));
+    }
+}
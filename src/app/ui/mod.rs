@@ -0,0 +1,151 @@
+mod command;
+mod endpoints;
+mod help;
+mod inbox;
+mod inspector;
+mod logs;
+mod requests;
+mod status;
+mod tutorial;
+mod usage;
+
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style},
+    text::{Line, Span, Text},
+    widgets::{Paragraph, Tabs, Widget},
+};
+
+use crate::server::Profile;
+
+pub use command::CommandPane;
+pub use endpoints::EndpointsPane;
+pub use help::HelpPane;
+pub use inbox::InboxPane;
+pub use inspector::RequestInspector;
+pub use logs::{LogFilter, LogPane, log_groups, shown_entries};
+pub use requests::RequestsPane;
+pub use status::StatusBar;
+pub use tutorial::TutorialPane;
+pub use usage::UsagePane;
+
+#[derive(Debug, Default)]
+pub enum InputMode {
+    #[default]
+    Normal,
+    Insert,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    #[default]
+    Logs,
+    Endpoints,
+    Requests,
+    Inbox,
+    Usage,
+    Help,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 6] = [
+        Tab::Logs,
+        Tab::Endpoints,
+        Tab::Requests,
+        Tab::Inbox,
+        Tab::Usage,
+        Tab::Help,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Tab::Logs => "Logs",
+            Tab::Endpoints => "Endpoints",
+            Tab::Requests => "Requests",
+            Tab::Inbox => "Inbox",
+            Tab::Usage => "Usage",
+            Tab::Help => "Help",
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|t| *t == self)
+            .unwrap_or_default()
+    }
+
+    /// Tab by its title, ignoring case, e.g. `requests`
+    pub fn from_title(title: &str) -> Result<Tab, String> {
+        Self::ALL
+            .into_iter()
+            .find(|tab| tab.title().eq_ignore_ascii_case(title))
+            .ok_or_else(|| {
+                format!(
+                    "unknown tab `{}`, use logs, endpoints, requests, inbox, usage or help",
+                    title
+                )
+            })
+    }
+
+    /// Tab for a 1-based number key
+    pub fn from_number(n: u32) -> Option<Tab> {
+        Self::ALL.get((n as usize).checked_sub(1)?).copied()
+    }
+
+    pub fn next(self) -> Tab {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> Tab {
+        Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+pub struct TabBar {
+    pub selected: Tab,
+}
+
+impl Widget for &TabBar {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let titles = Tab::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| Line::from(format!(" {} {} ", i + 1, tab.title())));
+        Tabs::new(titles)
+            .select(self.selected.index())
+            .style(Style::default().fg(Color::Red))
+            .highlight_style(Style::default().bold().reversed())
+            .divider("|")
+            .render(area, buf);
+    }
+}
+
+/// Banner of the active profile, one row per line
+pub struct ProfileBanner<'a> {
+    pub profile: &'a Profile,
+}
+
+impl ProfileBanner<'_> {
+    pub fn height(&self) -> u16 {
+        let lines = self
+            .profile
+            .banner
+            .as_deref()
+            .map_or(0, |b| b.lines().count());
+        u16::try_from(lines).unwrap_or(u16::MAX)
+    }
+}
+
+impl Widget for &ProfileBanner<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = Style::default().fg(Color::Yellow);
+        let mut lines = self.profile.banner.as_deref().unwrap_or_default().lines();
+        let first = Line::from(vec![
+            Span::styled(format!(" {} ", self.profile.name), style.bold().reversed()),
+            Span::styled(format!(" {}", lines.next().unwrap_or_default()), style),
+        ]);
+        let rest = lines.map(|line| Line::styled(format!(" {}", line), style));
+        Paragraph::new(Text::from_iter(std::iter::once(first).chain(rest))).render(area, buf);
+    }
+}
@@ -0,0 +1,58 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::server::RequestJournal;
+
+pub struct RequestsPane<'a> {
+    pub journal: &'a RequestJournal,
+}
+
+impl<'a> Widget for &RequestsPane<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .title(format!("Requests ({})", self.journal.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red));
+        if self.journal.is_empty() {
+            Paragraph::new("No requests received yet".italic())
+                .block(block)
+                .render(area, buf);
+            return;
+        }
+        // Newest first, only as many as fit into the pane
+        let visible = area.height.saturating_sub(2) as usize;
+        let text: Vec<Line> = self
+            .journal
+            .records()
+            .rev()
+            .take(visible)
+            .map(|record| {
+                let status_color = if record.matched() {
+                    Color::Green
+                } else {
+                    Color::Yellow
+                };
+                Line::from(vec![
+                    Span::styled(
+                        record.timestamp.format("%H:%M:%S ").to_string(),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        format!("{} ", record.status.as_u16()),
+                        Style::default().fg(status_color),
+                    ),
+                    Span::styled(
+                        format!("{:<7}", record.method.as_str()),
+                        Style::default().bold(),
+                    ),
+                    Span::raw(record.path.clone()),
+                ])
+            })
+            .collect();
+        Paragraph::new(text).block(block).render(area, buf);
+    }
+}
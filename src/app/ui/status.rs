@@ -0,0 +1,56 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Widget,
+};
+
+use crate::{server::ServerHealth, util::build};
+
+pub struct StatusBar<'a> {
+    pub health: &'a ServerHealth,
+    pub addr: &'a str,
+    pub uptime: &'a str,
+    pub endpoints: usize,
+    pub requests: u64,
+    /// Project loaded from the working directory on startup
+    pub project: Option<&'a str>,
+}
+
+impl<'a> Widget for &StatusBar<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (label, color) = match self.health {
+            ServerHealth::Starting => ("starting".to_owned(), Color::Yellow),
+            ServerHealth::Up => ("up".to_owned(), Color::Green),
+            ServerHealth::Unresponsive => ("unresponsive".to_owned(), Color::Red),
+            ServerHealth::Down(reason) => (format!("down ({})", reason), Color::Red),
+        };
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut spans = vec![
+            Span::styled(format!(" v{} ({}) |", build::VERSION, build::GIT_HASH), dim),
+            Span::raw(" server "),
+            Span::styled(label, Style::default().fg(color).bold()),
+            Span::raw(format!(" on {} ", self.addr)),
+            Span::styled(
+                format!(
+                    "| up {} | {} endpoints | {} requests ",
+                    self.uptime, self.endpoints, self.requests
+                ),
+                dim,
+            ),
+        ];
+        if let Some(project) = self.project {
+            spans.push(Span::styled("| project ", dim));
+            spans.push(Span::styled(project, Style::default().fg(Color::Cyan)));
+            spans.push(Span::raw(" "));
+        }
+        if matches!(
+            self.health,
+            ServerHealth::Unresponsive | ServerHealth::Down(_)
+        ) {
+            spans.push(Span::styled("R", Style::default().fg(Color::Red).bold()));
+            spans.push(Span::styled(" to restart", Style::default().fg(Color::Red)));
+        }
+        Line::from(spans).render(area, buf);
+    }
+}
@@ -0,0 +1,37 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+};
+
+/// The current step of the tutorial, shown above the tabs
+pub struct TutorialPane<'a> {
+    /// e.g. `2/4 List the endpoints`
+    pub title: &'a str,
+    pub instruction: &'a str,
+}
+
+impl TutorialPane<'_> {
+    pub const HEIGHT: u16 = 4;
+}
+
+impl Widget for &TutorialPane<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .title(format!("Tutorial {}", self.title))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow));
+        let text = vec![
+            Line::from(self.instruction),
+            Line::styled(
+                "`tutorial --stop` to leave it",
+                Style::default().fg(Color::DarkGray),
+            ),
+        ];
+        Paragraph::new(text)
+            .wrap(Wrap { trim: true })
+            .block(block)
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,90 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::{
+    app::usage::Usage,
+    server::{EndpointStatsTable, EndpointStore},
+};
+
+/// Number of entries shown per list
+const TOP: usize = 10;
+
+/// Local usage of the instance: commands run, the most hit endpoints and the busiest hour, along
+/// with endpoints never hit, which may be stale
+pub struct UsagePane<'a> {
+    pub usage: &'a Usage,
+    pub store: &'a EndpointStore,
+    pub stats: &'a EndpointStatsTable,
+}
+
+impl<'a> Widget for &UsagePane<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .title("Usage (kept locally)")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red));
+        let count = |n: u64| Span::styled(format!("{:>6}  ", n), Style::default().fg(Color::Green));
+        let none = || Line::from("  none yet".italic());
+
+        let mut text = vec![Line::from(
+            format!("Commands ({} run)", self.usage.total()).bold(),
+        )];
+        let commands = self.usage.most_run(TOP);
+        if commands.is_empty() {
+            text.push(none());
+        }
+        text.extend(
+            commands
+                .into_iter()
+                .map(|(name, runs)| Line::from(vec![count(runs), Span::raw(name.to_owned())])),
+        );
+
+        text.push(Line::from(""));
+        text.push(Line::from("Most hit endpoints".bold()));
+        let endpoints = self.stats.most_hit(TOP);
+        if endpoints.is_empty() {
+            text.push(none());
+        }
+        text.extend(endpoints.into_iter().map(|(method, path, hits)| {
+            Line::from(vec![
+                count(hits),
+                Span::styled(format!("{:<7}", method.as_str()), Style::default().bold()),
+                Span::styled(path.to_owned(), Style::default().fg(Color::Cyan)),
+            ])
+        }));
+
+        text.push(Line::from(""));
+        text.push(match self.stats.busiest_hour() {
+            Some((hour, hits)) => Line::from(vec![
+                "Busiest hour ".bold(),
+                Span::raw(format!("{:02}:00-{:02}:00, {} hits", hour, hour + 1, hits)),
+            ]),
+            None => Line::from(vec!["Busiest hour ".bold(), "none yet".italic()]),
+        });
+
+        let mut stale: Vec<String> = self
+            .store
+            .entries(None)
+            .into_iter()
+            .flat_map(|(method, entries)| entries.into_iter().map(move |(path, _)| (method, path)))
+            .filter(|(method, path)| self.stats.get(method, path).is_none_or(|s| s.hits == 0))
+            .map(|(method, path)| format!("{:<7}{}", method.as_str(), path))
+            .collect();
+        stale.dedup();
+        text.push(Line::from(""));
+        text.push(Line::from(
+            format!("Never hit ({}), candidates for pruning", stale.len()).bold(),
+        ));
+        text.extend(stale.into_iter().map(|endpoint| {
+            Line::styled(
+                format!("  {}", endpoint),
+                Style::default().fg(Color::DarkGray),
+            )
+        }));
+        Paragraph::new(text).block(block).render(area, buf);
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use clap::CommandFactory;
+
+use crate::command::Cli;
+
+/// Commands run in this session, only kept in memory and never sent anywhere
+#[derive(Debug, Default)]
+pub struct Usage {
+    /// Runs by command name, e.g. `endpoint add`
+    commands: HashMap<String, u64>,
+}
+
+impl Usage {
+    /// Count a parsed command, by the words of `args` naming it
+    pub fn record(&mut self, args: &[String]) {
+        let name = command_name(args);
+        if !name.is_empty() {
+            *self.commands.entry(name).or_default() += 1;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.commands.values().sum()
+    }
+
+    /// Commands by runs, most run first
+    pub fn most_run(&self, limit: usize) -> Vec<(&str, u64)> {
+        let mut run: Vec<(&str, u64)> = self
+            .commands
+            .iter()
+            .map(|(name, runs)| (name.as_str(), *runs))
+            .collect();
+        run.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        run.truncate(limit);
+        run
+    }
+}
+
+/// Name of a command with its subcommands, without arguments and with aliases resolved, e.g.
+/// `endpoint add` for `endpoint add get /users '[]'`
+fn command_name(args: &[String]) -> String {
+    let mut command = Cli::command();
+    let mut names = Vec::new();
+    for arg in args {
+        let Some(subcommand) = command.find_subcommand(arg).cloned() else {
+            break;
+        };
+        names.push(subcommand.get_name().to_owned());
+        command = subcommand;
+    }
+    names.join(" ")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn args(input: &str) -> Vec<String> {
+        input.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn test_most_run() {
+        let mut usage = Usage::default();
+        usage.record(&args("endpoint add get /users []"));
+        usage.record(&args("endpoint list"));
+        usage.record(&args("ep add post /users {}"));
+        usage.record(&args("tutorial"));
+
+        assert_eq!(usage.total(), 4);
+        assert_eq!(
+            usage.most_run(2),
+            vec![("endpoint add", 2), ("endpoint list", 1)]
+        );
+    }
+}
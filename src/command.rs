@@ -1,4 +1,40 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use std::{
+    num::{NonZeroU32, NonZeroU64, NonZeroUsize},
+    path::PathBuf,
+    time::Duration,
+};
+
+use actix_web::http::{
+    StatusCode, Uri,
+    header::{HeaderName, HeaderValue},
+};
+use clap::{
+    CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+    builder::{PossibleValue, PossibleValuesParser},
+};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    config::PROJECT_DIR,
+    formats,
+    grpc::GrpcCode,
+    logger::{LogFallback, SinkTarget},
+    server::{
+        Assertion, CertificateKind, Claim, ContinueBehavior, Drip, ETag, Fallback, Flaky, Framing,
+        HeaderOrder, Hook, Malformation, Matcher, MiddlewareChain, OnConflict, Overlay, PageStyle,
+        RateLimit, RawResponse, Redaction, ReplayFilter, ReplaySelection, ReplayTarget,
+        Representation, RequestSchema, ResponseHeader, ScheduledFault, TenantSource, Transform,
+        parse_after_calls, parse_body_json, parse_call_range, parse_cookie_pair, parse_header,
+        parse_header_line, parse_header_pair, parse_host, parse_ip_range, parse_origin,
+        parse_pattern, parse_percent, parse_query_pair, parse_set_cookie, parse_status,
+    },
+    util::{
+        error::InternalError,
+        result::InternalResult,
+        units::{parse_duration, parse_duration_range, parse_size},
+    },
+};
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum HttpMethod {
@@ -21,6 +57,73 @@ impl From<HttpMethod> for actix_web::http::Method {
     }
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Switch {
+    On,
+    Off,
+}
+
+impl From<Switch> for bool {
+    fn from(s: Switch) -> Self {
+        matches!(s, Switch::On)
+    }
+}
+
+/// Arguments of the binary itself, as opposed to [`Cli`] parsing commands typed in the TUI
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Args {
+    /// Serve without the TUI until interrupted, logging to stderr
+    #[arg(long)]
+    pub headless: bool,
+    /// Read commands from stdin in headless mode, one per line as typed in the TUI, and answer
+    /// each with a JSON line on stdout. Closing stdin ends the run like an interrupt.
+    #[arg(long, requires = "headless")]
+    pub stdin: bool,
+    /// Startup configuration, `adaptive_rest.toml` in the working directory if it exists
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Address to serve on, e.g. `0.0.0.0:8080`, overriding the configuration
+    #[arg(long, value_name = "ADDR")]
+    pub bind: Option<String>,
+    /// Log level, overriding the configuration
+    #[arg(long, value_enum, ignore_case = true)]
+    pub log_level: Option<LogLevel>,
+    /// Where log entries go if the TUI stops receiving them while the server keeps serving,
+    /// `stderr` or a file appended to, overriding the configuration. Defaults to stderr.
+    #[arg(long, value_name = "stderr|FILE")]
+    pub log_fallback: Option<LogFallback>,
+    /// Accept commands on a Unix socket at this path, one per line as typed in the TUI, each
+    /// answered with a JSON line, e.g. for editor plugins adding endpoints to a running session.
+    /// Only the current user may connect. Overrides the configuration.
+    #[arg(long, value_name = "PATH")]
+    pub control_socket: Option<PathBuf>,
+    /// Run a command before exiting in headless mode, e.g. `--on-exit export report.json`.
+    /// Repeat for several commands, quote commands with options, e.g.
+    /// `--on-exit 'verify get /users --times 3'`. Exits with 1 if a verification failed, 2 if a
+    /// command was invalid or failed, 3 if the server went down.
+    #[arg(long = "on-exit", num_args = 1.., value_name = "COMMAND", requires = "headless")]
+    on_exit_args: Vec<String>,
+    /// The arguments of each `--on-exit`
+    #[arg(skip)]
+    pub on_exit: Vec<Vec<String>>,
+}
+
+impl Args {
+    /// Parse the process arguments, exiting with a usage message if they are invalid
+    pub fn load() -> Self {
+        let matches = Self::command().get_matches();
+        let mut args = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        args.on_exit = matches
+            .get_occurrences::<String>("on_exit_args")
+            .into_iter()
+            .flatten()
+            .map(|occurrence| occurrence.cloned().collect())
+            .collect();
+        args
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "")]
 pub struct Cli {
@@ -29,6 +132,8 @@ pub struct Cli {
 }
 
 #[derive(Subcommand, Debug)]
+// Parsed once per command line, like `EndpointAction`
+#[allow(clippy::large_enum_variant)]
 pub enum Command {
     /// Manage endpoints
     #[command(alias = "ep")]
@@ -36,11 +141,887 @@ pub enum Command {
         #[command(subcommand)]
         action: EndpointAction,
     },
+    /// Propose endpoints for requests that were answered with 404
+    Suggest {
+        #[command(subcommand)]
+        action: Option<SuggestAction>,
+    },
+    /// Automatically add endpoints for unmatched requests, with ids collapsed into parameters
+    Learn {
+        #[arg(ignore_case = true)]
+        state: Option<Switch>,
+    },
+    /// Compress responses for clients sending `Accept-Encoding`, endpoints may override it with
+    /// `--compress`
+    Compress {
+        #[arg(ignore_case = true)]
+        state: Option<Switch>,
+    },
+    /// Show version, uptime and server details
+    Info,
+    /// Configure how the server routes requests
+    Server {
+        #[command(subcommand)]
+        action: ServerAction,
+    },
+    /// Configure logging
+    Log {
+        #[command(subcommand)]
+        action: LogAction,
+    },
+    /// Close connections instead of keeping them alive, to exercise client connection pools
+    #[command(alias = "conn")]
+    Connection {
+        #[command(subcommand)]
+        action: Option<ConnectionAction>,
+    },
+    /// Configure how much of the traffic the request journal keeps
+    #[command(alias = "j")]
+    Journal {
+        #[command(subcommand)]
+        action: Option<JournalAction>,
+    },
+    /// Keep secrets out of the logs, the journal and exports
+    Redact {
+        #[command(subcommand)]
+        action: Option<RedactAction>,
+    },
+    /// Filter the log pane
+    #[command(alias = "f")]
+    Filter {
+        #[command(subcommand)]
+        action: FilterAction,
+    },
+    /// Send journaled requests again to another server, e.g. a new implementation of the API,
+    /// and log how its responses differ from the mock's, e.g. `replay 17 --target
+    /// http://localhost:8081` or `replay all --filter method=post --target http://localhost:8081`
+    Replay {
+        /// Journal id of a request or `all`, the inspected request if left out
+        request: Option<ReplaySelection>,
+        #[arg(long)]
+        target: ReplayTarget,
+        /// Only replay requests matching `method=<method>`, `path=</prefix>`, `status=<code>`,
+        /// `matched` or `unmatched`. Repeat to require several.
+        #[arg(long = "filter")]
+        filters: Vec<ReplayFilter>,
+    },
+    /// Check how often an endpoint was requested, e.g. `verify get /users --times 3`, or list
+    /// assertion failures with `verify report`
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Verify {
+        #[command(subcommand)]
+        action: Option<VerifyAction>,
+        #[arg(ignore_case = true, required = true)]
+        method: Option<HttpMethod>,
+        /// May contain parameters, e.g. `/users/{id}`
+        #[arg(required = true)]
+        path: Option<String>,
+        /// Expect exactly this many requests instead of at least one
+        #[arg(long, conflicts_with = "never")]
+        times: Option<u64>,
+        /// Expect no requests at all
+        #[arg(long)]
+        never: bool,
+    },
+    /// Write a JSON report of the endpoints, hits, verifications, unmatched requests and
+    /// latencies, e.g. for CI jobs to archive, or document the endpoints, e.g. `export markdown
+    /// endpoints.md`
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Export {
+        #[command(subcommand)]
+        format: Option<ExportFormat>,
+        #[arg(required = true)]
+        file: Option<PathBuf>,
+    },
+    /// Describe the endpoints as an API specification
+    Spec {
+        #[command(subcommand)]
+        action: SpecAction,
+    },
+    /// Show which operations of the OpenAPI document loaded with `spec load` requests hit, also
+    /// at `/__admin/coverage`
+    Coverage {
+        #[command(subcommand)]
+        action: Option<CoverageAction>,
+    },
+    /// Show the counters of the `metric` hooks of endpoints, see `endpoint add --hook`
+    Metrics {
+        #[command(subcommand)]
+        action: Option<MetricsAction>,
+    },
+    /// Show how a request path and query would be normalized and matched, without sending it,
+    /// e.g. `normalize '/tenants/acme/users/?page=2' --header 'X-Tenant: acme'`
+    #[command(alias = "n")]
+    Normalize {
+        uri: Uri,
+        #[arg(long, ignore_case = true, default_value = "get")]
+        method: HttpMethod,
+        /// Send a header, e.g. `'Authorization: Bearer test'`. Repeat for several headers.
+        #[arg(long = "header", value_parser = parse_header_line)]
+        headers: Vec<(HeaderName, HeaderValue)>,
+    },
+    /// Coordinate several endpoints through the steps of a scenario file
+    #[command(alias = "sc")]
+    Scenario {
+        #[command(subcommand)]
+        action: Option<ScenarioAction>,
+    },
+    /// Serve tenants their own endpoints, falling back to the regular ones
+    #[command(alias = "t")]
+    Tenant {
+        #[command(subcommand)]
+        action: Option<TenantAction>,
+    },
+    /// Impersonate several services on one port by answering the Host header of requests with
+    /// endpoints of their own, see `endpoint add --host`
+    Host {
+        #[command(subcommand)]
+        action: Option<HostAction>,
+    },
+    /// Mutate JSON responses to test how robust clients deserialize them
+    Mutate {
+        #[command(subcommand)]
+        action: Option<MutateAction>,
+    },
+    /// Inject latency, errors and dropped connections, globally or below a path
+    Chaos {
+        #[command(subcommand)]
+        action: Option<ChaosAction>,
+    },
+    /// Require credentials below a path, answering requests without them with 401
+    Auth {
+        #[command(subcommand)]
+        action: Option<AuthAction>,
+    },
+    /// Serve a mock OAuth2 token endpoint issuing signed JWTs
+    Oauth {
+        #[command(subcommand)]
+        action: Option<OauthAction>,
+    },
+    /// Capture whatever is sent to a path, answering with 200, so webhooks of third party
+    /// sandboxes can be pointed at the server and inspected
+    Inbox {
+        #[command(subcommand)]
+        action: Option<InboxAction>,
+    },
+    /// Configure cross-origin requests, so browser frontends can call the server
+    Cors {
+        #[command(subcommand)]
+        action: Option<CorsAction>,
+    },
+    /// Serve the endpoints over TLS on a second port with a broken certificate, so client
+    /// certificate validation and pinning failures can be exercised locally
+    Tls {
+        #[command(subcommand)]
+        action: Option<TlsAction>,
+    },
+    /// Answer gRPC calls on a port of their own with canned replies per method, typed by a
+    /// descriptor set written with `protoc --include_imports --descriptor_set_out`
+    Grpc {
+        #[command(subcommand)]
+        action: Option<GrpcAction>,
+    },
+    /// Proxy requests no endpoint matches to the real API instead of answering 404, so only a few
+    /// of its routes need to be mocked
+    Fallback {
+        #[command(subcommand)]
+        action: Option<FallbackAction>,
+    },
+    /// Add sets of related endpoints from templates
+    Preset {
+        #[command(subcommand)]
+        action: Option<PresetAction>,
+    },
+    /// Load and save endpoints in files
+    #[command(alias = "c")]
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Add endpoints from traffic or collections saved by other tools, e.g. `import har
+    /// session.har`
+    Import {
+        #[arg(value_parser = importer_formats())]
+        format: String,
+        file: PathBuf,
+        /// What to do with endpoints that would replace a different existing one
+        #[arg(long, value_enum, ignore_case = true, default_value_t = ConflictStrategy::Ask)]
+        on_conflict: ConflictStrategy,
+        /// Add the endpoints for one tenant only, overriding the regular ones
+        #[arg(long)]
+        tenant: Option<String>,
+    },
+    /// Show or reorder the cross-cutting stages requests pass through, e.g. whether auth or chaos
+    /// answers first
+    #[command(alias = "mw")]
+    Middleware {
+        #[command(subcommand)]
+        action: Option<MiddlewareAction>,
+    },
+    /// Answer requests beyond a rate across all endpoints with 429
+    RateLimit {
+        #[command(subcommand)]
+        action: Option<RateLimitAction>,
+    },
+    /// Walk through creating, listing, calling and deleting an endpoint, with the current step
+    /// shown above the tabs
+    Tutorial {
+        /// Leave the tutorial
+        #[arg(long)]
+        stop: bool,
+    },
+    /// Apply the endpoints of a YAML or JSON file and re-apply them whenever it changes, e.g.
+    /// `watch ./mocks.yaml`. Lists the watched files without one.
+    #[command(alias = "w")]
+    Watch {
+        file: Option<PathBuf>,
+        /// Stop watching the file, keeping its endpoints
+        #[arg(long, requires = "file")]
+        stop: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(l: LogLevel) -> Self {
+        match l {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SuggestAction {
+    /// Analyze unmatched requests and list proposed endpoints
+    #[command(alias = "l")]
+    List,
+    /// Add proposed endpoints by their number in the last listing
+    #[command(alias = "a")]
+    Accept {
+        #[arg(required_unless_present = "all")]
+        numbers: Vec<usize>,
+        /// Accept all proposed endpoints
+        #[arg(long, conflicts_with = "numbers")]
+        all: bool,
+        /// Response body of the added endpoints
+        #[arg(long, default_value = "{}")]
+        body: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LogAction {
+    /// Show or change the level of log messages shown in the TUI
+    #[command(alias = "l")]
+    Level {
+        #[arg(ignore_case = true)]
+        level: Option<LogLevel>,
+    },
+    /// List the sinks log messages go to besides the TUI, each with its own level
+    Sinks,
+    /// Send log messages to a sink, or change its level: a file path, `stdout` for JSON lines in
+    /// headless mode, or `otlp:` followed by the URL of an OpenTelemetry collector, e.g.
+    /// `otlp:http://localhost:4318`
+    Sink {
+        #[arg(value_name = "FILE|stdout|otlp:URL")]
+        target: SinkTarget,
+        /// Defaults to info
+        #[arg(ignore_case = true)]
+        level: Option<LogLevel>,
+        /// Stop sending to the sink
+        #[arg(long, conflicts_with = "level")]
+        remove: bool,
+    },
+    /// Show or change how many log entries are kept, oldest are dropped first
+    #[command(alias = "c")]
+    Capacity { size: Option<NonZeroUsize> },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum JournalAction {
+    /// Show the retention settings and how much the journal holds
+    Show,
+    /// Show or change how many requests are kept, oldest are dropped first
+    #[command(alias = "c")]
+    Capacity { size: Option<NonZeroUsize> },
+    /// Show or change the size request bodies are cut off at, e.g. `4kb`
+    MaxBody {
+        #[arg(value_parser = parse_size)]
+        size: Option<usize>,
+        /// Keep bodies whole
+        #[arg(long, conflicts_with = "size")]
+        off: bool,
+    },
+    /// Show or change the percentage of requests recorded, e.g. `10%`. Verifications and
+    /// reports only see recorded requests.
+    Sample {
+        #[arg(value_parser = parse_percent)]
+        percent: Option<f64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConnectionAction {
+    /// Show when connections are closed
+    Show,
+    /// Show or change whether every response carries `Connection: close`, endpoints may force
+    /// it with `--close`
+    Close {
+        #[arg(ignore_case = true)]
+        state: Option<Switch>,
+    },
+    /// Show or change after how many requests connections are closed
+    MaxRequests {
+        count: Option<NonZeroU64>,
+        /// Keep connections alive regardless of their requests
+        #[arg(long, conflicts_with = "count")]
+        off: bool,
+    },
+    /// Show or change the percentage of responses after which the connection is closed shortly,
+    /// without `Connection: close`, e.g. `10%`
+    Drop {
+        #[arg(value_parser = parse_percent)]
+        percent: Option<f64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RedactAction {
+    /// List the redaction rules
+    #[command(alias = "l")]
+    List,
+    /// Redact the values of a header, e.g. `header:Authorization`, fields of JSON bodies, e.g.
+    /// `json:$.user.password`, or matches of a regex, e.g. `'regex:Bearer \S+'`. Requests
+    /// journaled before are not redacted.
+    #[command(alias = "a")]
+    Add {
+        #[arg(required = true)]
+        rules: Vec<Redaction>,
+    },
+    /// Remove redaction rules, given as listed
+    #[command(aliases = ["rm", "d"])]
+    Remove {
+        #[arg(required = true)]
+        rules: Vec<Redaction>,
+    },
+    /// Remove all redaction rules
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PresetAction {
+    /// List builtin presets and those in the user preset directory
+    #[command(alias = "l")]
+    List,
+    /// Add the endpoints of a preset below a base path, e.g. `preset apply rest-crud /widgets`
+    #[command(alias = "a")]
+    Apply {
+        name: String,
+        base: String,
+        /// What to do with endpoints that would replace a different existing one
+        #[arg(long, value_enum, ignore_case = true, default_value_t = ConflictStrategy::Ask)]
+        on_conflict: ConflictStrategy,
+        /// Add the endpoints for one tenant only, overriding the regular ones
+        #[arg(long)]
+        tenant: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ServerAction {
+    /// Serve all endpoints below a prefix, e.g. `/api/v2`, without changing their paths. Shows
+    /// the current prefix without an argument, `/` removes it.
+    BasePath { prefix: Option<String> },
+    /// Rewrite request paths before matching them, e.g. to strip a tenant segment
+    Rewrite {
+        #[command(subcommand)]
+        action: RewriteAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RewriteAction {
+    /// List the rewrite rules in the order they apply
+    #[command(alias = "l")]
+    List,
+    /// Replace matches of a regex in request paths, e.g. `'^/tenants/[^/]+' ''`. The replacement
+    /// may refer to capture groups, e.g. `'/$1'`.
+    #[command(alias = "a")]
+    Add {
+        #[arg(value_parser = parse_pattern)]
+        pattern: Regex,
+        replacement: String,
+    },
+    /// Remove all rewrite rules
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum VerifyAction {
+    /// List the requests that violated assertions of their endpoint
+    #[command(alias = "r")]
+    Report,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ScenarioAction {
+    /// Show the running scenario and its current step
+    Show,
+    /// Load a scenario file and start at its first step, replacing any running scenario
+    Start { file: PathBuf },
+    /// Move to the next step
+    #[command(alias = "a")]
+    Advance,
+    /// Go back to the first step
+    Reset,
+    /// Stop the scenario, leaving only the regular endpoints
+    Stop,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TenantAction {
+    /// Show how tenants are identified and the endpoints they override
+    Show,
+    /// Identify the tenant of requests by a header, e.g. `header:X-Tenant-Id`, or a path
+    /// segment, e.g. `segment:2` for `acme` in `/tenants/acme/users`, matched as `/users`
+    By { source: TenantSource },
+    /// Stop identifying tenants, keeping their endpoints for later
+    Off,
+    /// Remove the endpoints of a tenant
+    #[command(aliases = ["rm", "d"])]
+    Remove { tenant: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HostAction {
+    /// List the hosts with endpoints of their own
+    #[command(alias = "l")]
+    List,
+    /// Remove the endpoints of a host
+    #[command(aliases = ["rm", "d"])]
+    Remove {
+        #[arg(value_parser = parse_host)]
+        host: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MutateAction {
+    /// List the mutated responses and whether the client tolerated them
+    #[command(alias = "r")]
+    Report,
+    /// Apply the next mutation to every response of an endpoint
+    PerRequest,
+    /// Apply one mutation per endpoint, a different one each session
+    PerSession,
+    /// Stop mutating responses
+    Off,
+    /// Mark the mutated response of a request as rejected by the client
+    Reject { request_id: u64 },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ChaosAction {
+    /// List the active chaos rules
+    #[command(alias = "l")]
+    List,
+    /// Delay responses by a fixed or random duration, e.g. `200ms..2s`, `0` to disable
+    Latency {
+        #[arg(value_parser = parse_duration_range)]
+        range: (Duration, Duration),
+        /// Only affect requests below this path
+        #[arg(long, default_value = "/")]
+        path: String,
+    },
+    /// Answer a percentage of requests with an error status instead of the stub
+    ErrorRate {
+        #[arg(value_parser = parse_percent)]
+        percent: f64,
+        #[arg(long, default_value = "503", value_parser = parse_status)]
+        status: StatusCode,
+        /// Only affect requests below this path
+        #[arg(long, default_value = "/")]
+        path: String,
+    },
+    /// Drop the connection in the middle of a percentage of responses
+    AbortRate {
+        #[arg(value_parser = parse_percent)]
+        percent: f64,
+        /// Only affect requests below this path
+        #[arg(long, default_value = "/")]
+        path: String,
+    },
+    /// Answer a percentage of requests with deliberately invalid HTTP: a `Content-Length` larger
+    /// than the body (`bad-length`), a header with control characters (`bad-header`), a chunked
+    /// body missing its final chunk (`truncated-chunks`) or a garbled status line (`bad-status`)
+    MalformedRate {
+        #[arg(value_parser = parse_percent)]
+        percent: f64,
+        #[arg(long = "with", default_value = "bad-length")]
+        malformation: Malformation,
+        /// Only affect requests below this path
+        #[arg(long, default_value = "/")]
+        path: String,
+    },
+    /// Remove all chaos rules, or those of one path
+    #[command(alias = "c")]
+    Clear {
+        #[arg(long)]
+        path: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum AuthScheme {
+    Basic,
+    Bearer,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AuthAction {
+    /// List the auth rules
+    #[command(alias = "l")]
+    List,
+    /// Require a bearer token, e.g. `require bearer my-token --scope /api/private`, or basic
+    /// credentials, e.g. `require basic user:password`. Requests presenting the credentials of
+    /// any rule covering their path are matched as usual.
+    Require {
+        #[arg(ignore_case = true)]
+        scheme: AuthScheme,
+        /// The token, or `user:password` for basic auth
+        credentials: String,
+        /// Only require it for requests below this path
+        #[arg(long, default_value = "/")]
+        scope: String,
+    },
+    /// Remove an auth rule by its number in the listing
+    #[command(aliases = ["rm", "d"])]
+    Remove { number: NonZeroUsize },
+    /// Remove all auth rules
+    #[command(alias = "c")]
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SpecAction {
+    /// Write an OpenAPI 3 document of the endpoints with their paths, methods, statuses and
+    /// bodies as examples, e.g. `spec export openapi.json`. Files ending in `.yaml` get YAML.
+    Export { file: PathBuf },
+    /// Track which operations of an OpenAPI document, JSON or YAML, requests hit, e.g. `spec
+    /// load openapi.yaml`. See `coverage`.
+    Load { file: PathBuf },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CoverageAction {
+    /// Show how many operations of the loaded OpenAPI document requests hit, and which not
+    #[command(alias = "r")]
+    Report,
+    /// Count hits from zero again, e.g. before another test run
+    Reset,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MetricsAction {
+    /// Show every counter
+    Show,
+    /// Count from zero again, e.g. before another test run
+    Reset,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OauthAction {
+    /// Show the token endpoint
+    Show,
+    /// Answer client credentials and password grants posted to a path with an HS256 JWT, e.g.
+    /// `mock /oauth/token --expiry 5m --claim role=admin`. Any client and user is granted a
+    /// token, auth rules don't apply to the path.
+    Mock {
+        path: String,
+        /// Time until tokens expire
+        #[arg(long, value_parser = parse_duration, default_value = "1h")]
+        expiry: Duration,
+        /// Add a claim to every token, e.g. `role=admin` or `tier=3`. Repeat for several claims.
+        #[arg(long = "claim")]
+        claims: Vec<Claim>,
+        /// Key the tokens are signed with
+        #[arg(long, default_value = "adaptive_rest")]
+        secret: String,
+    },
+    /// Stop serving the token endpoint
+    #[command(alias = "off")]
+    Stop,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum InboxAction {
+    /// Show the inbox paths and the requests they captured, newest last
+    #[command(alias = "l")]
+    List,
+    /// Capture requests of any method to a path, e.g. `add /hooks/stripe`. Endpoints, auth rules
+    /// and the fallback don't apply to it.
+    Add { path: String },
+    /// Show a captured request in full, by its number in `list`
+    Show { number: usize },
+    /// Stop capturing requests to a path, keeping what it captured
+    #[command(alias = "d")]
+    Delete { path: String },
+    /// Drop the captured requests
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CorsAction {
+    /// Show the current CORS settings
+    Show,
+    /// Allow requests from these origins, `*` for any, and enable CORS
+    AllowOrigin {
+        #[arg(required = true, value_parser = parse_origin)]
+        origins: Vec<String>,
+    },
+    /// Allow these request headers, `*` for any (the default)
+    AllowHeaders {
+        #[arg(required = true, value_parser = parse_header)]
+        headers: Vec<String>,
+    },
+    /// Disable CORS
+    Off,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TlsAction {
+    /// Show the TLS listener
+    Show,
+    /// Listen for TLS on a port, presenting a certificate that is `expired`, `self-signed`,
+    /// issued for the `wrong-host` or `valid`, e.g. `start 3443 --cert expired`. Restarts the
+    /// server.
+    Start {
+        #[arg(default_value_t = 3443)]
+        port: u16,
+        #[arg(long, default_value = "self-signed")]
+        cert: CertificateKind,
+    },
+    /// Write the certificate of the authority issuing all but the self-signed certificate, e.g.
+    /// `ca ca.pem`. Clients trusting it fail on the flaw of the certificate alone.
+    Ca { file: PathBuf },
+    /// Stop listening for TLS, restarting the server
+    #[command(alias = "off")]
+    Stop,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GrpcAction {
+    /// Show the loaded methods with their reply and number of calls
+    #[command(alias = "l")]
+    List,
+    /// Load a descriptor set, replacing the methods loaded before, e.g. `load greeter.pb`
+    Load { file: PathBuf },
+    /// Answer a method with a message given as JSON, fields named as in the `.proto` file, or
+    /// with a status, e.g. `reply helloworld.Greeter/SayHello '{"message": "hi"}'` or
+    /// `reply helloworld.Greeter/SayHello --status not-found --message 'no such user'`. Loaded
+    /// methods without a reply answer with an empty message.
+    Reply {
+        method: String,
+        json: Option<String>,
+        /// A status by name or number, e.g. `unavailable` or `14`
+        #[arg(long)]
+        status: Option<GrpcCode>,
+        /// Message sent along with the status
+        #[arg(long, requires = "status")]
+        message: Option<String>,
+    },
+    /// Drop the reply of a method, answering it with an empty message again
+    #[command(alias = "d")]
+    Delete { method: String },
+    /// Listen for gRPC calls on a port, on the host the server is bound to
+    Start {
+        #[arg(default_value_t = 50051)]
+        port: u16,
+    },
+    /// Stop listening for gRPC calls
+    #[command(alias = "off")]
+    Stop,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MiddlewareAction {
+    /// Show the stages in the order requests pass through them
+    #[command(alias = "l")]
+    List,
+    /// Set the stages and their order, leaving out those to turn off, e.g.
+    /// `set cors,chaos,auth,logging`. Of auth, rate-limit and chaos the first to answer wins,
+    /// redaction and logging are only turned on or off and come last.
+    Set { chain: MiddlewareChain },
+    /// Restore all stages in their default order
+    Reset,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RateLimitAction {
+    /// Show the rate limit
+    Show,
+    /// Allow this many requests per window, e.g. `set 10/s` or `set 100/1m`
+    Set { limit: RateLimit },
+    /// Stop limiting requests
+    Off,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FallbackAction {
+    /// Show the upstream unmatched requests are proxied to
+    Show,
+    /// Proxy unmatched requests to an upstream, keeping their path and query, e.g.
+    /// `set https://api.example.com`. A path of the upstream is prepended to theirs.
+    Set { upstream: Fallback },
+    /// Answer unmatched requests with 404 again
+    #[command(alias = "clear")]
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum PagingStyle {
+    Offset,
+    Cursor,
+}
+
+impl From<PagingStyle> for PageStyle {
+    fn from(style: PagingStyle) -> Self {
+        match style {
+            PagingStyle::Offset => PageStyle::Offset,
+            PagingStyle::Cursor => PageStyle::Cursor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ConflictStrategy {
+    Ask,
+    Skip,
+    Overwrite,
+    KeepBoth,
+}
+
+impl From<ConflictStrategy> for OnConflict {
+    fn from(s: ConflictStrategy) -> Self {
+        match s {
+            ConflictStrategy::Ask => OnConflict::Ask,
+            ConflictStrategy::Skip => OnConflict::Skip,
+            ConflictStrategy::Overwrite => OnConflict::Overwrite,
+            ConflictStrategy::KeepBoth => OnConflict::KeepBoth,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum MergeStrategy {
+    PreferExisting,
+    PreferIncoming,
+}
+
+impl From<MergeStrategy> for OnConflict {
+    fn from(s: MergeStrategy) -> Self {
+        match s {
+            MergeStrategy::PreferExisting => OnConflict::Skip,
+            MergeStrategy::PreferIncoming => OnConflict::Overwrite,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportFormat {
+    /// Write a Markdown document of the endpoints with their descriptions and an example request
+    /// and response each, e.g. to paste into a wiki
+    #[command(alias = "md")]
+    Markdown { file: PathBuf },
+    /// Write the endpoints in a format of another tool, e.g. `export endpoints openapi api.yaml`
+    Endpoints {
+        #[arg(value_parser = exporter_formats())]
+        format: String,
+        file: PathBuf,
+    },
+    /// Write a shell script of curl commands reproducing the journaled requests verbatim, e.g.
+    /// to hand failing requests to the developers of a backend
+    Curl {
+        file: PathBuf,
+        /// Only requests answered with 4xx or 5xx, hit by a fault or failing an assertion
+        #[arg(long)]
+        failed: bool,
+    },
+    /// Write the journaled requests as a HAR file, e.g. `export har capture.har` to open in
+    /// browser devtools. Responses carry the status and the body of the endpoint that answered.
+    Har {
+        file: PathBuf,
+        /// Only requests answered with 4xx or 5xx, hit by a fault or failing an assertion
+        #[arg(long)]
+        failed: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Add the endpoints defined in a YAML or JSON file
+    #[command(alias = "i")]
+    Import {
+        file: PathBuf,
+        /// What to do with endpoints that would replace a different existing one
+        #[arg(long, value_enum, ignore_case = true, default_value_t = ConflictStrategy::Ask)]
+        on_conflict: ConflictStrategy,
+        /// Add the endpoints for one tenant only, overriding the regular ones
+        #[arg(long)]
+        tenant: Option<String>,
+    },
+    /// Combine the endpoints of one or more files with the running ones, reporting conflicts
+    #[command(alias = "m")]
+    Merge {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Which side wins when both define a different stub for the same endpoint
+        #[arg(long, value_enum, ignore_case = true, default_value_t = MergeStrategy::PreferExisting)]
+        strategy: MergeStrategy,
+    },
+    /// Save all endpoints below a directory, one file per endpoint, so changes diff cleanly
+    #[command(alias = "s")]
+    Save {
+        /// Defaults to the project directory, which is loaded on startup
+        #[arg(default_value = PROJECT_DIR)]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FilterAction {
+    /// Only show log entries at or above a level
+    #[command(alias = "l")]
+    Level {
+        #[arg(ignore_case = true)]
+        level: LogLevel,
+    },
+    /// Only show log entries whose message or target contains a text (case-insensitive)
+    #[command(alias = "t")]
+    Text { text: String },
+    /// Show all log entries again
+    #[command(alias = "c")]
+    Clear,
 }
 
 // TODO: later: add endpoints from json files, handle different methods and formats
 
 #[derive(Subcommand, Debug)]
+// Parsed once per command, so the size of `Add` doesn't matter
+#[allow(clippy::large_enum_variant)]
 pub enum EndpointAction {
     /// Add a new endpoint
     #[command(aliases = ["a", "ad", "update", "u", "up"])]
@@ -48,15 +1029,328 @@ pub enum EndpointAction {
         #[arg(ignore_case = true)]
         method: HttpMethod,
         path: String,
-        response: String,
+        #[arg(required_unless_present_any = ["redirect", "raw", "json", "xml", "representations"])]
+        response: Option<String>,
+        /// Send this body to clients accepting JSON. With `--xml` or `--representation`, the
+        /// `Accept` header of requests picks the body, and requests accepting none are answered
+        /// with 406. Equally acceptable bodies are preferred in the order JSON, XML, then
+        /// representations as given.
+        #[arg(long, conflicts_with_all = ["response", "redirect", "raw"])]
+        json: Option<String>,
+        /// Send this body to clients accepting XML, see `--json`
+        #[arg(long, conflicts_with_all = ["response", "redirect", "raw"])]
+        xml: Option<String>,
+        /// Send a body to clients accepting its content type, e.g. `'text/csv:id,name'`. Repeat
+        /// for several types, see `--json`.
+        #[arg(long = "representation", conflicts_with_all = ["response", "redirect", "raw"])]
+        representations: Vec<Representation>,
+        /// What the endpoint is for, shown by `endpoint inspect` and `export markdown`
+        #[arg(long)]
+        description: Option<String>,
+        /// Answer with this status instead of 200, e.g. `404`
+        #[arg(long, value_parser = parse_status, conflicts_with_all = ["redirect", "raw"])]
+        status: Option<StatusCode>,
+        /// Add a header to the response, e.g. `'Cache-Control: no-store'`. Repeat for several
+        /// headers, repeating a name sends it several times, e.g. for `Set-Cookie` or `Link`.
+        #[arg(long = "header")]
+        response_headers: Vec<ResponseHeader>,
+        /// Set a cookie in the response, e.g. `'session=abc; HttpOnly; Path=/'`. Repeat for
+        /// several cookies.
+        #[arg(long = "set-cookie", value_parser = parse_set_cookie)]
+        set_cookies: Vec<ResponseHeader>,
+        /// Send these response headers first, in this order and spelling, e.g.
+        /// `Content-Type,X-Legacy-ID,Content-Length`. Others follow lowercase.
+        #[arg(long, conflicts_with = "raw")]
+        header_order: Option<HeaderOrder>,
+        /// Answer with a redirect status and a `Location` header, e.g. `--redirect 302 /new-path`
+        #[arg(long, num_args = 2, value_names = ["STATUS", "LOCATION"])]
+        redirect: Vec<String>,
+        /// Warn when the response body exceeds this size, e.g. `100kb`
+        #[arg(long, value_parser = parse_size)]
+        max_size: Option<usize>,
+        /// Warn when producing the response takes longer than this, e.g. `200ms`
+        #[arg(long, value_parser = parse_duration)]
+        max_time: Option<Duration>,
+        /// Candidates of the same endpoint are tried from the highest priority down
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        priority: i32,
+        /// Only answer requests with this query parameter, e.g. `q=rust`. A query in the path,
+        /// e.g. `'/search?q=rust'`, works the same. Repeat for several parameters.
+        #[arg(long = "query", value_parser = parse_query_pair)]
+        query: Vec<Matcher>,
+        /// Only answer requests with this header, e.g. `'Authorization: Bearer test'`. Repeat
+        /// for several headers.
+        #[arg(long = "match-header", value_parser = parse_header_pair)]
+        headers: Vec<Matcher>,
+        /// Only answer requests sending this cookie, e.g. `session=abc` for a session set by a
+        /// login endpoint with `--set-cookie`. Repeat for several cookies.
+        #[arg(long = "match-cookie", value_parser = parse_cookie_pair)]
+        cookies: Vec<Matcher>,
+        /// Only answer requests with a JSON body meeting a JSONPath condition, e.g.
+        /// `'$.type == "refund"'`, or `'$.trace_id'` for a field being present
+        #[arg(long = "match-body-json", value_parser = parse_body_json)]
+        body_json: Vec<Matcher>,
+        /// Only answer some calls to the endpoint, counting from 1, e.g. `1..2` to fail the
+        /// first two requests while a candidate without it answers the rest, `3` or `6..`. See
+        /// `endpoint stats --reset` to count from 1 again.
+        #[arg(long, value_parser = parse_call_range)]
+        on_call: Option<Matcher>,
+        /// Only answer calls to the endpoint after the first this many, e.g. `5`
+        #[arg(long, value_parser = parse_after_calls, conflicts_with = "on_call")]
+        after_calls: Option<Matcher>,
+        /// Only answer clients connecting from an address in a range, e.g. `10.0.0.0/8` or
+        /// `192.168.1.20`, to serve devices of a lab different responses
+        #[arg(long = "match-ip", value_parser = parse_ip_range)]
+        client_ip: Option<Matcher>,
+        /// Add the response as a variant picked at random in proportion to its weight, e.g.
+        /// `--weight 90` for a success and `--weight 10` for an error with the same matchers
+        #[arg(long)]
+        weight: Option<NonZeroU32>,
+        /// Make a share of requests hit a fault, e.g. `0.1:timeout`. Faults are timeout, slow,
+        /// abort, error, unavailable, a malformation like `bad-length` (see `chaos
+        /// malformed-rate`) or a status code. Repeat for several faults.
+        #[arg(long)]
+        flaky: Vec<Flaky>,
+        /// Hit a fault on a schedule, e.g. `'during 00:00..00:05=503'` or `'every 10=slow'`.
+        /// Times are local. Repeat for several rules.
+        #[arg(long = "rule")]
+        scheduled: Vec<ScheduledFault>,
+        /// Record requests violating an expectation as failures, e.g. `header:Authorization`,
+        /// `field:trace_id` or `contains:text`. Repeat for several assertions.
+        #[arg(long = "assert")]
+        assertions: Vec<Assertion>,
+        /// Run an action before or after every response, e.g. `'after header X-Trace: abc'`,
+        /// `'after remove-header Server'`, `'after metric checkouts'` counted by `metrics`, or
+        /// `'before append ./orders.jsonl'` adding the request as a JSON line, relative to the
+        /// project directory if there is one. Repeat for several hooks, run in order.
+        #[arg(long = "hook")]
+        hooks: Vec<Hook>,
+        /// Send a request after every request the endpoint answers, like the webhook of an async
+        /// API, e.g. `--callback POST http://localhost:8080/notify`
+        #[arg(long, num_args = 2, value_names = ["METHOD", "URL"])]
+        callback: Vec<String>,
+        /// Body of the callback, sent as JSON if it is valid JSON
+        #[arg(long, requires = "callback")]
+        callback_body: Option<String>,
+        /// Time between the request and the callback, e.g. `2s`
+        #[arg(long, value_parser = parse_duration, requires = "callback")]
+        callback_delay: Option<Duration>,
+        /// Pass the body through a step when serving it: `template` filling in placeholders
+        /// like `{{param.id}}`, `{{query.page}}`, `{{header.x-trace}}` or `{{body.user.id}}`,
+        /// `patch:<file>` applying a JSON Patch, `merge:<file>` a JSON Merge Patch, or `gzip`.
+        /// Repeat for a pipeline run in order, e.g. `--transform template --transform
+        /// patch:./overlays/staging.json --transform gzip`.
+        #[arg(long = "transform")]
+        transforms: Vec<Transform>,
+        /// Patch the body while a profile is active, before the transformations, e.g.
+        /// `--overlay staging=merge:./overlays/staging.json`. Repeat for several profiles.
+        #[arg(long = "overlay")]
+        overlays: Vec<Overlay>,
+        /// Stream the body in chunks with a delay between them, e.g. `1kb/500ms`
+        #[arg(long)]
+        drip: Option<Drip>,
+        /// Compress the response for clients accepting it, or never, regardless of `compress`
+        #[arg(long, ignore_case = true)]
+        compress: Option<Switch>,
+        /// Answer `Expect: 100-continue` late, e.g. `delay:2s`, or refuse it with a status,
+        /// e.g. `refuse` for 417 or `refuse:413`, so the body is never sent
+        #[arg(long)]
+        expect: Option<ContinueBehavior>,
+        /// Answer with `Connection: close`, so clients can't reuse the connection
+        #[arg(long)]
+        close: bool,
+        /// Never respond and hold the connection open until the client gives up, to test its
+        /// timeouts. The request is journaled with status 444.
+        #[arg(long, conflicts_with_all = ["close_connection", "raw"])]
+        no_response: bool,
+        /// Drop the connection without responding, to test the retries of clients. The request
+        /// is journaled with status 444.
+        #[arg(long, conflicts_with = "raw")]
+        close_connection: bool,
+        /// Send an ETag with the response, `auto` for one derived from the body or a fixed tag
+        /// like `v1`. Requests listing it in `If-None-Match` are answered with 304 and no body,
+        /// to test the HTTP caching of clients.
+        #[arg(long, conflicts_with = "raw")]
+        etag: Option<ETag>,
+        /// Answer GET requests with a `Range` header, e.g. `bytes=0-1023`, with 206 and that
+        /// part of the body, to test resuming downloads and media players. The body is never
+        /// compressed then.
+        #[arg(long, conflicts_with_all = ["raw", "redirect"])]
+        ranges: bool,
+        /// Send the body with `Content-Length` or `chunked`, even if actix would pick the other.
+        /// `short-length` and `long-length` claim a wrong `Content-Length` instead.
+        #[arg(long, conflicts_with = "raw")]
+        framing: Option<Framing>,
+        /// Answer requests with 400 if their body violates a JSON Schema, e.g.
+        /// `./user.schema.json`
+        #[arg(long)]
+        request_schema: Option<RequestSchema>,
+        /// Write these bytes to the socket instead of a response and close the connection, e.g.
+        /// `'HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\nshort'`. Escapes are `\r`, `\n`,
+        /// `\t`, `\\` and `\xHH`.
+        #[arg(long, conflicts_with = "redirect")]
+        raw: Option<RawResponse>,
+        /// Override the endpoint for one tenant only, see `tenant by`
+        #[arg(long)]
+        tenant: Option<String>,
+        /// Answer requests sent to this host only, e.g. `api.service-a.local`, matched against
+        /// the Host header without its port. Regular endpoints answer every host.
+        #[arg(long, value_parser = parse_host, conflicts_with = "tenant")]
+        host: Option<String>,
+    },
+    /// Add an endpoint answering with Server-Sent Events, e.g.
+    /// `endpoint add-sse get /events --message '{"tick":1}' --interval 1s --count 10`
+    AddSse {
+        #[arg(ignore_case = true)]
+        method: HttpMethod,
+        path: String,
+        /// Data of every event
+        #[arg(long)]
+        message: String,
+        /// Time between events, the first one is sent right away
+        #[arg(long, value_parser = parse_duration, default_value = "1s")]
+        interval: Duration,
+        /// Number of events before the stream ends, events are sent until the client leaves
+        /// without it
+        #[arg(long)]
+        count: Option<NonZeroU64>,
+    },
+    /// Add an endpoint serving a JSON array page by page, e.g.
+    /// `endpoint add-paged get /items --source ./items.json --page-size 20 --style cursor`
+    AddPaged {
+        #[arg(ignore_case = true)]
+        method: HttpMethod,
+        path: String,
+        /// JSON file holding the array of items
+        #[arg(long)]
+        source: PathBuf,
+        /// Items per page, and the most the `limit` query parameter may ask for
+        #[arg(long, default_value = "20")]
+        page_size: NonZeroUsize,
+        /// `offset` pages by `?offset=&limit=` with the total and first, prev, next and last
+        /// links. `cursor` pages by `?cursor=&limit=` with a link to the next page only.
+        #[arg(long, ignore_case = true, default_value = "offset")]
+        style: PagingStyle,
     },
     /// Delete endpoint
     #[command(aliases = ["d", "del"])]
     Delete { method: HttpMethod, path: String },
+    /// Show the candidates of the endpoints at a path with their description, options and an
+    /// example request and response. `path` may also be a concrete request path, e.g.
+    /// `/users/42` for `/users/{id}`.
+    Inspect {
+        path: String,
+        #[arg(long, ignore_case = true)]
+        method: Option<HttpMethod>,
+    },
+    /// Show a curl command sending a request the endpoint answers to the running server, with
+    /// the query, headers and credentials it needs, e.g. `curl get /users/1`. In the TUI it is
+    /// copied to the clipboard as well.
+    Curl {
+        #[arg(ignore_case = true)]
+        method: HttpMethod,
+        path: String,
+    },
+    /// Infer a body template from the JSON responses the fallback upstream answered requests for
+    /// a path with, e.g. `endpoint infer get /users/{id}` after a recording session. Fields equal
+    /// in all responses are kept, varying ones echo the request or become fake values.
+    Infer {
+        #[arg(ignore_case = true)]
+        method: HttpMethod,
+        path: String,
+        /// Add the endpoint with the template as its body and the `template` transformation
+        #[arg(long)]
+        add: bool,
+    },
     /// List all endpoints
     #[command(alias = "l")]
     List {
         #[arg(ignore_case = true)]
         method: Option<HttpMethod>,
     },
+    /// Show how often the endpoints at a path were hit and with which status
+    #[command(alias = "s")]
+    Stats {
+        path: String,
+        #[arg(long, ignore_case = true)]
+        method: Option<HttpMethod>,
+        /// Forget the usage instead, so `--on-call` and `--after-calls` count from 1 again
+        #[arg(long)]
+        reset: bool,
+    },
+}
+
+/// Names of the registered importers, completed and listed in the help of `import`
+fn importer_formats() -> PossibleValuesParser {
+    formats::registry()
+        .importers()
+        .map(|importer| PossibleValue::new(importer.name()).help(importer.description()))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Names of the registered exporters, like [`importer_formats`]
+fn exporter_formats() -> PossibleValuesParser {
+    formats::registry()
+        .exporters()
+        .map(|exporter| PossibleValue::new(exporter.name()).help(exporter.description()))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Split a line of TUI input into shell-style arguments. On failure the error carries the span of
+/// the offending quote or escape.
+pub fn split_input(input: &str) -> InternalResult<Vec<String>> {
+    if let Some(args) = shlex::split(input) {
+        return Ok(args);
+    }
+    let mut open_quote: Option<(usize, char)> = None;
+    let mut escaped_at = None;
+    for (i, c) in input.char_indices() {
+        if escaped_at.take().is_some() {
+            continue;
+        }
+        match (open_quote, c) {
+            (Some((_, '\'')), '\'') => open_quote = None,
+            (Some((_, '\'')), _) => {}
+            (_, '\\') => escaped_at = Some(i),
+            (Some((_, q)), c) if c == q => open_quote = None,
+            (None, '"' | '\'') => open_quote = Some((i, c)),
+            _ => {}
+        }
+    }
+    let (span, reason) = match (open_quote, escaped_at) {
+        (Some((start, q)), _) => (start..input.len(), format!("unterminated {} quote", q)),
+        (None, Some(start)) => (start..input.len(), "trailing escape character".to_owned()),
+        (None, None) => (0..input.len(), "invalid input".to_owned()),
+    };
+    Err(InternalError::Parse {
+        input: input.to_owned(),
+        span,
+        reason,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_input() {
+        let args = split_input("endpoint add get /x '{\"a\": 1}'").unwrap();
+        assert_eq!(args, ["endpoint", "add", "get", "/x", "{\"a\": 1}"]);
+    }
+
+    #[test]
+    fn test_split_input_unterminated_quote_span() {
+        let input = "endpoint add get /x '{\"a\": 1}";
+        match split_input(input) {
+            Err(InternalError::Parse { span, reason, .. }) => {
+                assert_eq!(span, 20..input.len());
+                assert_eq!(reason, "unterminated ' quote");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
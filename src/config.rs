@@ -0,0 +1,1167 @@
+use std::{
+    fmt::Display,
+    fs,
+    num::{NonZeroU32, NonZeroUsize},
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use actix_web::http::{Method, StatusCode};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+use crate::{
+    app::Tab,
+    command::LogLevel,
+    logger::{LogFallback, SinkTarget},
+    server::{
+        Assertion, Budget, Callback, CertificateKind, ContinueBehavior, Drip, ETag, EndpointStore,
+        EventStream, Flaky, Framing, Hangup, HeaderOrder, Hook, Matcher, MiddlewareChain, Overlay,
+        Pagination, Profile, RawResponse, Redirect, Representation, RequestSchema, ResponseHeader,
+        Scenario, ScenarioStep, ScheduledFault, Stub, TlsListener, Transform,
+    },
+    util::{
+        error::InternalError,
+        result::InternalResult,
+        units::{format_size, format_time, parse_duration, parse_size},
+    },
+};
+
+/// Startup configuration picked up from the working directory
+pub const STARTUP_FILE: &str = "adaptive_rest.toml";
+/// Endpoint file picked up from the working directory on startup
+pub const PROJECT_FILE: &str = "adaptive_rest.yaml";
+/// Directory picked up from the working directory on startup, all endpoint files below it are loaded
+pub const PROJECT_DIR: &str = ".adaptive_rest";
+/// Files written by [`save_dir`], relative to its directory, one per line
+const SAVED_FILES: &str = ".saved";
+
+/// A set of endpoints as stored in a YAML (or JSON) file
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointFile {
+    /// Order of the middleware stages, e.g. `cors,auth,chaos,logging`, applied when loaded
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub middleware: Option<MiddlewareChain>,
+    #[serde(default)]
+    pub endpoints: Vec<EndpointDef>,
+    /// Line of each endpoint definition in the file it was parsed from, counting from 1
+    #[serde(skip)]
+    pub lines: Vec<usize>,
+}
+
+/// Field order is the order in saved files, optional fields are omitted when unset
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointDef {
+    #[serde(deserialize_with = "method", serialize_with = "serialize_method")]
+    pub method: Method,
+    pub path: String,
+    /// What the endpoint is for, e.g. for `export markdown`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub body: String,
+    /// e.g. `404`, 200 when omitted
+    #[serde(
+        default,
+        deserialize_with = "status",
+        serialize_with = "serialize_status",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub status: Option<StatusCode>,
+    /// e.g. `["Cache-Control: no-store"]`, repeat a name to send it several times
+    #[serde(
+        default,
+        deserialize_with = "parsed_list",
+        serialize_with = "serialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub headers: Vec<ResponseHeader>,
+    /// Header names in the order and spelling they are sent in, e.g.
+    /// `Content-Type,X-Legacy-ID,Content-Length`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub header_order: Option<HeaderOrder>,
+    /// e.g. `302 /new-path`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub redirect: Option<Redirect>,
+    /// e.g. `100kb`
+    #[serde(
+        default,
+        deserialize_with = "size",
+        serialize_with = "serialize_size",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_size: Option<usize>,
+    /// e.g. `200ms`
+    #[serde(
+        default,
+        deserialize_with = "duration",
+        serialize_with = "serialize_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_time: Option<Duration>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    /// e.g. `["query:q=rust", "header:authorization: Bearer test"]`
+    #[serde(
+        default,
+        rename = "match",
+        deserialize_with = "parsed_list",
+        serialize_with = "serialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub matchers: Vec<Matcher>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<NonZeroU32>,
+    /// e.g. `["0.1:timeout"]`
+    #[serde(
+        default,
+        deserialize_with = "parsed_list",
+        serialize_with = "serialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub flaky: Vec<Flaky>,
+    /// e.g. `["every 10=slow"]`
+    #[serde(
+        default,
+        rename = "rules",
+        deserialize_with = "parsed_list",
+        serialize_with = "serialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub scheduled: Vec<ScheduledFault>,
+    /// e.g. `["header:Authorization"]`
+    #[serde(
+        default,
+        rename = "assert",
+        deserialize_with = "parsed_list",
+        serialize_with = "serialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub assertions: Vec<Assertion>,
+    /// Server-Sent Events of the body, e.g. `1s x10`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sse: Option<EventStream>,
+    /// Chunks the body is streamed in, e.g. `1kb/500ms`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub drip: Option<Drip>,
+    /// Overrides whether the server compresses responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress: Option<bool>,
+    /// Answer to `Expect: 100-continue`, e.g. `delay:2s` or `refuse:417`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expect: Option<ContinueBehavior>,
+    /// Answer with `Connection: close`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub close: bool,
+    /// Leave requests unanswered, `no-response` or `close-connection`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub hangup: Option<Hangup>,
+    /// Bodies by content type picked by the `Accept` header, e.g.
+    /// `['application/json:{"a":1}', 'application/xml:<a>1</a>']`
+    #[serde(
+        default,
+        deserialize_with = "parsed_list",
+        serialize_with = "serialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub representations: Vec<Representation>,
+    /// `auto` for a tag derived from the body, or a fixed tag like `v1`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub etag: Option<ETag>,
+    /// Serve parts of the body to requests with a `Range` header
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub ranges: bool,
+    /// Pages of the body, a JSON array, e.g. `offset:20` or `cursor:20`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub paging: Option<Pagination>,
+    /// `length`, `chunked`, or a lying `short-length` or `long-length`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub framing: Option<Framing>,
+    /// Path of a JSON Schema file request bodies have to satisfy, relative to the working
+    /// directory
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub request_schema: Option<RequestSchema>,
+    /// Bytes written to the socket instead of a response, with escapes like `\r\n`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub raw: Option<RawResponse>,
+    /// Run before or after every response, e.g. `["after header X-Trace: abc", "after metric
+    /// checkouts", "before append ./orders.jsonl"]`
+    #[serde(
+        default,
+        deserialize_with = "parsed_list",
+        serialize_with = "serialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub hooks: Vec<Hook>,
+    /// Request sent after every request, e.g. `POST http://localhost:8080/notify`
+    #[serde(
+        default,
+        deserialize_with = "parsed",
+        serialize_with = "serialize_parsed",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub callback: Option<Callback>,
+    /// Body of the callback, sent as JSON if it is valid JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback_body: Option<String>,
+    /// Time between the request and the callback, e.g. `2s`
+    #[serde(
+        default,
+        deserialize_with = "duration",
+        serialize_with = "serialize_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub callback_delay: Option<Duration>,
+    /// Steps the body passes through when served, e.g. `["template", "patch:./staging.json",
+    /// "gzip"]`
+    #[serde(
+        default,
+        deserialize_with = "parsed_list",
+        serialize_with = "serialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub transforms: Vec<Transform>,
+    /// Patches applied while a profile is active, before the transformations, e.g.
+    /// `["staging=merge:./overlays/staging.json"]`
+    #[serde(
+        default,
+        deserialize_with = "parsed_list",
+        serialize_with = "serialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub overlays: Vec<Overlay>,
+}
+
+impl EndpointDef {
+    pub fn new(method: Method, path: String, stub: &Stub) -> Self {
+        Self {
+            method,
+            path,
+            description: stub.description.clone(),
+            body: String::from_utf8_lossy(&stub.body).into_owned(),
+            status: stub.status,
+            headers: stub.headers.clone(),
+            header_order: stub.header_order.clone(),
+            redirect: stub.redirect.clone(),
+            max_size: stub.budget.max_size,
+            max_time: stub.budget.max_time,
+            priority: stub.priority,
+            matchers: stub.matchers.clone(),
+            weight: stub.weight,
+            flaky: stub.flaky.clone(),
+            scheduled: stub.scheduled.clone(),
+            assertions: stub.assertions.clone(),
+            sse: stub.events,
+            drip: stub.drip,
+            compress: stub.compress,
+            expect: stub.expect,
+            close: stub.close,
+            hangup: stub.hangup,
+            representations: stub.representations.clone(),
+            etag: stub.etag.clone(),
+            ranges: stub.ranges,
+            paging: stub.paging,
+            framing: stub.framing,
+            request_schema: stub.request_schema.clone(),
+            raw: stub.raw.clone(),
+            hooks: stub.hooks.clone(),
+            callback: stub.callback.clone(),
+            callback_body: stub
+                .callback
+                .as_ref()
+                .map(|callback| callback.body.clone())
+                .filter(|body| !body.is_empty()),
+            callback_delay: stub
+                .callback
+                .as_ref()
+                .map(|callback| callback.delay)
+                .filter(|delay| !delay.is_zero()),
+            transforms: stub.transforms.clone(),
+            overlays: stub.overlays.clone(),
+        }
+    }
+
+    pub fn stub(&self) -> Stub {
+        Stub {
+            body: self.body.clone().into(),
+            description: self.description.clone(),
+            status: self.status,
+            headers: self.headers.clone(),
+            header_order: self.header_order.clone(),
+            budget: Budget {
+                max_size: self.max_size,
+                max_time: self.max_time,
+            },
+            priority: self.priority,
+            matchers: self.matchers.clone(),
+            weight: self.weight,
+            flaky: self.flaky.clone(),
+            scheduled: self.scheduled.clone(),
+            assertions: self.assertions.clone(),
+            events: self.sse,
+            drip: self.drip,
+            compress: self.compress,
+            expect: self.expect,
+            close: self.close,
+            hangup: self.hangup,
+            representations: self.representations.clone(),
+            etag: self.etag.clone(),
+            ranges: self.ranges,
+            paging: self.paging,
+            framing: self.framing,
+            request_schema: self.request_schema.clone(),
+            raw: self.raw.clone(),
+            redirect: self.redirect.clone(),
+            hooks: self.hooks.clone(),
+            callback: self.callback.clone().map(|callback| Callback {
+                body: self.callback_body.clone().unwrap_or_default(),
+                delay: self.callback_delay.unwrap_or_default(),
+                ..callback
+            }),
+            transforms: self.transforms.clone(),
+            overlays: self.overlays.clone(),
+        }
+    }
+}
+
+impl EndpointFile {
+    /// Read and parse an endpoint file. Parse errors carry the offending line.
+    pub fn load(path: &Path) -> InternalResult<Self> {
+        let content = fs::read_to_string(path).map_err(|source| InternalError::FileIo {
+            path: path.to_owned(),
+            source,
+        })?;
+        Self::parse_named(&content, path)
+    }
+
+    /// Parse endpoints read from `path`, which is only used for error messages
+    pub fn parse_named(content: &str, path: &Path) -> InternalResult<Self> {
+        let mut file = Self::parse(content).map_err(|e| InternalError::Import {
+            path: path.to_owned(),
+            line: e.location().map_or(1, |l| l.line()),
+            reason: e.to_string(),
+        })?;
+        file.lines = definition_lines(content, &file.endpoints);
+        Ok(file)
+    }
+
+    fn parse(content: &str) -> Result<Self, serde_yaml::Error> {
+        // An empty file is a valid, empty endpoint set
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_yaml::from_str(content)
+    }
+
+    /// Endpoints as `(method, path, stub)`, paths with a leading slash
+    pub fn entries(&self) -> Vec<(Method, String, Stub)> {
+        self.endpoints
+            .iter()
+            .map(|def| {
+                let path = format!("/{}", def.path.trim_start_matches('/'));
+                (def.method.clone(), path, def.stub())
+            })
+            .collect()
+    }
+}
+
+/// Line each definition starts at, i.e. the list item holding the first line after the previous
+/// definition that mentions its path. serde_yaml keeps no locations of parsed values, so they are
+/// looked up in the text. Definitions whose path isn't found share the line of the previous one.
+fn definition_lines(content: &str, endpoints: &[EndpointDef]) -> Vec<usize> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut start = 0;
+    let mut found = 0;
+    endpoints
+        .iter()
+        .map(|def| {
+            if let Some(offset) = lines[start..]
+                .iter()
+                .position(|line| line.contains("path") && line.contains(def.path.as_str()))
+            {
+                let path_line = start + offset;
+                found = (start..=path_line)
+                    .rev()
+                    .find(|i| lines[*i].trim_start().starts_with('-'))
+                    .unwrap_or(path_line);
+                start = path_line + 1;
+            }
+            found + 1
+        })
+        .collect()
+}
+
+/// Settings applied on startup, from `adaptive_rest.toml` or the file given with `--config`.
+/// Flags of the binary override them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StartupConfig {
+    /// Address to serve on, e.g. `0.0.0.0:8080`
+    pub bind: Option<String>,
+    pub log_level: Option<LogLevel>,
+    /// Where log entries go once the TUI stops receiving them, `stderr` or a file path
+    #[serde(default, deserialize_with = "parsed")]
+    pub log_fallback: Option<LogFallback>,
+    /// Unix socket accepting commands, see `--control-socket`
+    pub control_socket: Option<PathBuf>,
+    /// Where log entries go besides the TUI, as `[[log_sinks]]` tables
+    #[serde(default)]
+    pub log_sinks: Vec<LogSinkDef>,
+    pub tls: Option<TlsDef>,
+    /// Added to every response that doesn't set them itself, e.g. `["X-Environment: mock"]`
+    #[serde(default, deserialize_with = "parsed_list")]
+    pub default_headers: Vec<ResponseHeader>,
+    /// Served at `/__about`, with a banner shown in the TUI
+    pub profile: Option<Profile>,
+    #[serde(default)]
+    pub tui: TuiPreferences,
+    /// Same fields as in endpoint files, as `[[endpoints]]` tables
+    #[serde(default)]
+    pub endpoints: Vec<EndpointDef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogSinkDef {
+    /// A file path, `stdout` or `otlp:` followed by the URL of a collector
+    #[serde(deserialize_with = "sink_target")]
+    pub target: SinkTarget,
+    /// Defaults to info
+    pub level: Option<LogLevel>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsDef {
+    #[serde(default = "default_tls_port")]
+    pub port: u16,
+    /// e.g. `expired`, self-signed when omitted
+    #[serde(default, deserialize_with = "parsed")]
+    pub cert: Option<CertificateKind>,
+}
+
+impl From<&TlsDef> for TlsListener {
+    fn from(def: &TlsDef) -> Self {
+        Self {
+            port: def.port,
+            certificate: def.cert.unwrap_or(CertificateKind::SelfSigned),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TuiPreferences {
+    /// Tab shown first, e.g. `requests`
+    #[serde(default, deserialize_with = "tab")]
+    pub tab: Option<Tab>,
+    /// Show the endpoints next to the logs
+    #[serde(default)]
+    pub show_endpoints: bool,
+    /// Log entries kept before the oldest are dropped
+    pub log_capacity: Option<NonZeroUsize>,
+    /// Mark the continuation lines of multi-line log entries with a vertical guide
+    #[serde(default)]
+    pub log_guide: bool,
+}
+
+impl StartupConfig {
+    /// Read `path`, or `adaptive_rest.toml` in the working directory if it exists. `None` if
+    /// neither is given.
+    pub fn load(path: Option<&Path>) -> InternalResult<Option<(PathBuf, Self)>> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None if Path::new(STARTUP_FILE).is_file() => PathBuf::from(STARTUP_FILE),
+            None => return Ok(None),
+        };
+        let content = fs::read_to_string(&path).map_err(|source| InternalError::FileIo {
+            path: path.clone(),
+            source,
+        })?;
+        let config = toml::from_str(&content).map_err(|e| InternalError::Import {
+            path: path.clone(),
+            line: e.span().map_or(1, |span| {
+                content.as_bytes()[..span.start.min(content.len())]
+                    .iter()
+                    .filter(|b| **b == b'\n')
+                    .count()
+                    + 1
+            }),
+            reason: e.message().to_owned(),
+        })?;
+        Ok(Some((path, config)))
+    }
+}
+
+fn default_tls_port() -> u16 {
+    3443
+}
+
+/// Steps of a scenario as stored in a YAML (or JSON) file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioFile {
+    /// Defaults to the file name
+    #[serde(default)]
+    pub name: Option<String>,
+    pub steps: Vec<StepDef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StepDef {
+    pub name: String,
+    #[serde(default)]
+    pub endpoints: Vec<EndpointDef>,
+    /// Requests advancing to the next step, e.g. `["POST /login"]`
+    #[serde(default, deserialize_with = "requests")]
+    pub advance_on: Vec<(Method, String)>,
+}
+
+impl ScenarioFile {
+    /// Read and parse a scenario file. Parse errors carry the offending line.
+    pub fn load(path: &Path) -> InternalResult<Scenario> {
+        let content = fs::read_to_string(path).map_err(|source| InternalError::FileIo {
+            path: path.to_owned(),
+            source,
+        })?;
+        let file: Self = serde_yaml::from_str(&content).map_err(|e| InternalError::Import {
+            path: path.to_owned(),
+            line: e.location().map_or(1, |l| l.line()),
+            reason: e.to_string(),
+        })?;
+        let name = file.name.unwrap_or_else(|| {
+            path.file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+        });
+        let steps = file
+            .steps
+            .into_iter()
+            .map(|def| {
+                let mut step = ScenarioStep {
+                    name: def.name,
+                    ..Default::default()
+                };
+                let endpoints = EndpointFile {
+                    endpoints: def.endpoints,
+                    ..Default::default()
+                };
+                for (method, path, stub) in endpoints.entries() {
+                    step.endpoints.add(method, &path, stub);
+                }
+                for (method, path) in def.advance_on {
+                    step.triggers.add(method, &path, Stub::default());
+                }
+                step
+            })
+            .collect();
+        Ok(Scenario { name, steps })
+    }
+}
+
+/// Endpoint files of a project in `dir`: the project file followed by every YAML or JSON file below
+/// the project directory, in path order
+pub fn project_files(dir: &Path) -> InternalResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let file = dir.join(PROJECT_FILE);
+    if file.is_file() {
+        files.push(file);
+    }
+    let project_dir = dir.join(PROJECT_DIR);
+    if project_dir.is_dir() {
+        collect_files(&project_dir, &mut files)?;
+    }
+    Ok(files)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> InternalResult<()> {
+    let read_dir = fs::read_dir(dir).map_err(|source| InternalError::FileIo {
+        path: dir.to_owned(),
+        source,
+    })?;
+    let mut paths: Vec<PathBuf> = read_dir
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml" || ext == "json")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Save `store` below `dir` with one file per endpoint, at `<path segments>/<METHOD>.yaml`, holding
+/// all its candidates. Output only depends on the endpoints, so saving unchanged endpoints leaves
+/// the files untouched and a change only touches the files of affected endpoints. Files written by
+/// an earlier save for endpoints that no longer exist are removed, other files are left alone.
+/// Returns the number of written files.
+pub fn save_dir(store: &EndpointStore, dir: &Path) -> InternalResult<usize> {
+    let mut files: Vec<(PathBuf, EndpointFile)> = Vec::new();
+    for (method, entries) in store.entries(None) {
+        for (path, stub) in entries {
+            // Learned paths are taken as requested, e.g. `/../../x`, so they are kept below `dir`
+            let relative = path
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .map(file_segment)
+                .fold(PathBuf::new(), |dir, segment| dir.join(segment))
+                .join(format!("{}.yaml", method));
+            if !is_below(&relative) {
+                return Err(InternalError::InvalidArgument {
+                    argument: "endpoint path",
+                    reason: format!("{} can't be saved below {}", path, dir.display()),
+                });
+            }
+            let file_path = dir.join(relative);
+            let def = EndpointDef::new(method.clone(), path, stub);
+            match files.last_mut() {
+                Some((last, file)) if *last == file_path => file.endpoints.push(def),
+                _ => files.push((
+                    file_path,
+                    EndpointFile {
+                        endpoints: vec![def],
+                        ..Default::default()
+                    },
+                )),
+            }
+        }
+    }
+
+    let io_error = |path: &Path| {
+        let path = path.to_owned();
+        move |source| InternalError::FileIo { path, source }
+    };
+    let saved_files = dir.join(SAVED_FILES);
+    let previous = fs::read_to_string(&saved_files).unwrap_or_default();
+    let stale = previous
+        .lines()
+        .map(Path::new)
+        .filter(|relative| is_below(relative))
+        .map(|relative| dir.join(relative))
+        .filter(|path| is_endpoint_file(path) && !files.iter().any(|(p, _)| p == path));
+    for path in stale {
+        match fs::remove_file(&path) {
+            Ok(()) => remove_empty_parents(&path, dir),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(source) => return Err(InternalError::FileIo { path, source }),
+        }
+    }
+    for (path, file) in &files {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error(parent))?;
+        }
+        let content = serde_yaml::to_string(file).map_err(|e| InternalError::FileIo {
+            path: path.clone(),
+            source: std::io::Error::other(e),
+        })?;
+        if fs::read_to_string(path).ok().as_deref() != Some(content.as_str()) {
+            fs::write(path, content).map_err(io_error(path))?;
+        }
+    }
+    let written: Vec<String> = files
+        .iter()
+        .filter_map(|(path, _)| path.strip_prefix(dir).ok())
+        .map(|relative| {
+            let segments: Vec<_> = relative.iter().map(|s| s.to_string_lossy()).collect();
+            segments.join("/")
+        })
+        .collect();
+    if written.is_empty() {
+        if saved_files.exists() {
+            fs::remove_file(&saved_files).map_err(io_error(&saved_files))?;
+        }
+    } else {
+        fs::create_dir_all(dir).map_err(io_error(dir))?;
+        let content = written.join("\n") + "\n";
+        if previous != content {
+            fs::write(&saved_files, content).map_err(io_error(&saved_files))?;
+        }
+    }
+    Ok(files.len())
+}
+
+/// A path segment as directory name, with `.`, `..` and separators percent-encoded
+fn file_segment(segment: &str) -> String {
+    if segment.chars().all(|c| c == '.') {
+        return segment.replace('.', "%2E");
+    }
+    segment.replace('\\', "%5C")
+}
+
+/// Whether `relative` only descends, so joined to a directory it stays below it
+fn is_below(relative: &Path) -> bool {
+    relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Whether `path` is named like a file written by [`save_dir`]
+fn is_endpoint_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "yaml")
+        && path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| !stem.is_empty() && stem.chars().all(|c| c.is_ascii_uppercase()))
+}
+
+/// Remove the directories between `path` and `root` that became empty
+fn remove_empty_parents(path: &Path, root: &Path) {
+    for parent in path.ancestors().skip(1) {
+        if parent == root || fs::remove_dir(parent).is_err() {
+            break;
+        }
+    }
+}
+
+fn is_zero(n: &i32) -> bool {
+    *n == 0
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+fn serialize_method<S: Serializer>(method: &Method, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(method.as_str())
+}
+
+fn serialize_size<S: Serializer>(size: &Option<usize>, serializer: S) -> Result<S::Ok, S::Error> {
+    match size {
+        Some(size) => serializer.serialize_str(&format_size(*size)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn serialize_duration<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serializer.serialize_str(&format_time(*duration)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn serialize_status<S: Serializer>(
+    status: &Option<StatusCode>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match status {
+        Some(status) => serializer.serialize_u16(status.as_u16()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn serialize_list<T: Display, S: Serializer>(
+    items: &[T],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(items.iter().map(ToString::to_string))
+}
+
+fn serialize_parsed<T: Display, S: Serializer>(
+    item: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match item {
+        Some(item) => serializer.serialize_str(&item.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn method<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Method, D::Error> {
+    let method = String::deserialize(deserializer)?;
+    Method::from_bytes(method.to_ascii_uppercase().as_bytes()).map_err(D::Error::custom)
+}
+
+fn sink_target<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SinkTarget, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(D::Error::custom)
+}
+
+fn parsed_list<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    T: FromStr<Err = String>,
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|item| item.parse().map_err(D::Error::custom))
+        .collect()
+}
+
+fn tab<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Tab>, D::Error> {
+    Tab::from_title(&String::deserialize(deserializer)?)
+        .map(Some)
+        .map_err(D::Error::custom)
+}
+
+fn parsed<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: FromStr<Err = String>,
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map(Some)
+        .map_err(D::Error::custom)
+}
+
+/// Requests written as e.g. `POST /login`
+fn requests<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(Method, String)>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|request| {
+            let (method, path) = request.trim().split_once(' ').ok_or_else(|| {
+                D::Error::custom(format!("`{}` is not like `POST /login`", request))
+            })?;
+            let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                .map_err(D::Error::custom)?;
+            Ok((method, format!("/{}", path.trim().trim_start_matches('/'))))
+        })
+        .collect()
+}
+
+fn size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usize>, D::Error> {
+    let size = String::deserialize(deserializer)?;
+    parse_size(&size).map(Some).map_err(D::Error::custom)
+}
+
+fn status<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<StatusCode>, D::Error> {
+    let status = u16::deserialize(deserializer)?;
+    StatusCode::from_u16(status)
+        .map(Some)
+        .map_err(D::Error::custom)
+}
+
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let duration = String::deserialize(deserializer)?;
+    parse_duration(&duration)
+        .map(Some)
+        .map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::{HttpResponse, http::header::SET_COOKIE};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_endpoint_file() {
+        let file = EndpointFile::parse(
+            "endpoints:\n\
+             - method: get\n\
+             \x20 path: users\n\
+             \x20 body: '[]'\n\
+             \x20 max_time: 200ms\n\
+             \x20 rules: ['every 10=slow']\n\
+             - method: POST\n\
+             \x20 path: /users\n",
+        )
+        .unwrap();
+
+        let entries = file.entries();
+        assert_eq!(entries[0].0, Method::GET);
+        assert_eq!(entries[0].1, "/users");
+        assert_eq!(entries[0].2.body.as_ref(), b"[]");
+        assert_eq!(
+            entries[0].2.budget.max_time,
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(entries[0].2.scheduled[0].to_string(), "every 10=slow");
+        assert!(entries[1].2.body.is_empty());
+        assert!(EndpointFile::parse("").unwrap().endpoints.is_empty());
+    }
+
+    #[test]
+    fn test_definition_lines() {
+        let content = "# Users\n\
+                       endpoints:\n\
+                       - method: GET\n\
+                       \x20 path: /users\n\
+                       - path: /users\n\
+                       \x20 method: POST\n\
+                       \x20 body: '{}'\n\
+                       - {method: GET, path: /health}\n";
+        let file = EndpointFile::parse_named(content, Path::new("users.yaml")).unwrap();
+        assert_eq!(file.lines, [3, 5, 8]);
+    }
+
+    #[test]
+    fn test_repeated_response_headers() {
+        let file = EndpointFile::parse(
+            "endpoints:\n\
+             - method: POST\n\
+             \x20 path: /login\n\
+             \x20 headers: ['Set-Cookie: session=abc', 'Set-Cookie: theme=dark']\n",
+        )
+        .unwrap();
+
+        let (_, _, stub) = &file.entries()[0];
+        let mut response = HttpResponse::Ok()
+            .insert_header((SET_COOKIE, "stale=1"))
+            .finish();
+        stub.add_headers(&mut response);
+        let cookies: Vec<_> = response.headers().get_all(SET_COOKIE).collect();
+        assert_eq!(cookies, ["session=abc", "theme=dark"]);
+        let saved = serde_yaml::to_string(&file).unwrap();
+        assert!(saved.contains("- 'set-cookie: session=abc'\n  - 'set-cookie: theme=dark'"));
+    }
+
+    #[test]
+    fn test_parse_error_has_line() {
+        let err = EndpointFile::parse(
+            "endpoints:\n\
+             - method: GET\n\
+             \x20 path: /users\n\
+             \x20 max_size: lots\n",
+        )
+        .unwrap_err();
+
+        // Invalid values are reported at the start of their endpoint
+        assert_eq!(err.location().unwrap().line(), 2);
+        assert!(err.to_string().contains("`lots`"));
+    }
+
+    #[test]
+    fn test_load_scenario() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("login.yaml");
+        fs::write(
+            &path,
+            "steps:\n\
+             - name: logged-out\n\
+             \x20 advance_on: ['post login']\n\
+             \x20 endpoints:\n\
+             \x20 - method: GET\n\
+             \x20   path: /profile\n\
+             \x20   body: unauthorized\n\
+             - name: logged-in\n",
+        )
+        .unwrap();
+
+        let scenario = ScenarioFile::load(&path).unwrap();
+        assert_eq!(scenario.name, "login");
+        assert_eq!(scenario.steps.len(), 2);
+        let step = &scenario.steps[0];
+        assert!(step.endpoints.get(&Method::GET, "/profile").is_some());
+        assert!(step.triggers.get(&Method::POST, "/login").is_some());
+    }
+
+    #[test]
+    fn test_load_startup_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(STARTUP_FILE);
+        fs::write(
+            &path,
+            "bind = '0.0.0.0:8080'\n\
+             log_level = 'debug'\n\
+             log_fallback = 'mock.log'\n\
+             control_socket = 'mock.sock'\n\
+             default_headers = ['X-Environment: mock']\n\
+             [tls]\n\
+             cert = 'expired'\n\
+             [profile]\n\
+             banner = 'Shared, ask before changing'\n\
+             [tui]\n\
+             tab = 'requests'\n\
+             [[endpoints]]\n\
+             method = 'GET'\n\
+             path = '/users'\n\
+             body = '[]'\n",
+        )
+        .unwrap();
+
+        let (_, config) = StartupConfig::load(Some(&path)).unwrap().unwrap();
+        assert_eq!(config.bind.as_deref(), Some("0.0.0.0:8080"));
+        assert!(matches!(config.log_level, Some(LogLevel::Debug)));
+        assert_eq!(
+            config.log_fallback,
+            Some(LogFallback::File(PathBuf::from("mock.log")))
+        );
+        assert_eq!(config.control_socket, Some(PathBuf::from("mock.sock")));
+        assert_eq!(config.default_headers[0].to_string(), "x-environment: mock");
+        let tls = TlsListener::from(config.tls.as_ref().unwrap());
+        assert_eq!(
+            (tls.port, tls.certificate),
+            (3443, CertificateKind::Expired)
+        );
+        assert_eq!(config.tui.tab, Some(Tab::Requests));
+        let profile = config.profile.unwrap();
+        assert_eq!(profile.name, "");
+        assert_eq!(profile.banner.unwrap(), "Shared, ask before changing");
+        assert_eq!(config.endpoints[0].path, "/users");
+
+        fs::write(&path, "bind = '0.0.0.0:8080'\nport = 80\n").unwrap();
+        assert!(matches!(
+            StartupConfig::load(Some(&path)),
+            Err(InternalError::Import { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_project_files() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(project_files(dir.path()).unwrap().is_empty());
+
+        let project_dir = dir.path().join(PROJECT_DIR);
+        fs::create_dir_all(project_dir.join("users")).unwrap();
+        for file in ["users/get.yaml", "orders.yml", "notes.txt"] {
+            fs::write(project_dir.join(file), "").unwrap();
+        }
+        fs::write(dir.path().join(PROJECT_FILE), "").unwrap();
+
+        assert_eq!(
+            project_files(dir.path()).unwrap(),
+            [
+                dir.path().join(PROJECT_FILE),
+                project_dir.join("orders.yml"),
+                project_dir.join("users/get.yaml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_save_dir_round_trip() {
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/", "root");
+        store.add(Method::GET, "/users/{id}", "{\"id\": 1}\n");
+        store.add(
+            Method::GET,
+            "/users/{id}",
+            Stub {
+                priority: -1,
+                budget: Budget {
+                    max_size: Some(100 * 1024),
+                    max_time: Some(Duration::from_millis(200)),
+                },
+                ..Stub::from("fallback")
+            },
+        );
+        store.add(Method::POST, "/users", "{}");
+        let dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(save_dir(&store, dir.path()).unwrap(), 3);
+        let saved = fs::read_to_string(dir.path().join("users/{id}/GET.yaml")).unwrap();
+        assert_eq!(
+            saved,
+            "endpoints:\n\
+             - method: GET\n\
+             \x20 path: /users/{id}\n\
+             \x20 body: |\n\
+             \x20   {\"id\": 1}\n\
+             - method: GET\n\
+             \x20 path: /users/{id}\n\
+             \x20 body: fallback\n\
+             \x20 max_size: 100kb\n\
+             \x20 max_time: 200ms\n\
+             \x20 priority: -1\n"
+        );
+
+        let mut loaded = EndpointStore::default();
+        let mut files = Vec::new();
+        collect_files(dir.path(), &mut files).unwrap();
+        for path in files {
+            for (method, path, stub) in EndpointFile::load(&path).unwrap().entries() {
+                loaded.add(method, &path, stub);
+            }
+        }
+        assert_eq!(loaded.entries(None), store.entries(None));
+
+        store.delete(&Method::GET, "/users/{id}");
+        assert_eq!(save_dir(&store, dir.path()).unwrap(), 2);
+        assert!(!dir.path().join("users/{id}").exists());
+        assert!(dir.path().join("users/POST.yaml").exists());
+    }
+
+    #[test]
+    fn test_save_dir_stays_inside() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("saved");
+        let unrelated = dir.join("docs/README.yaml");
+        fs::create_dir_all(unrelated.parent().unwrap()).unwrap();
+        fs::write(&unrelated, "kept").unwrap();
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/../../x", "escaped");
+
+        assert_eq!(save_dir(&store, &dir).unwrap(), 1);
+        assert!(dir.join("%2E%2E/%2E%2E/x/GET.yaml").is_file());
+        assert!(!root.path().join("x").exists());
+
+        store.delete(&Method::GET, "/../../x");
+        assert_eq!(save_dir(&store, &dir).unwrap(), 0);
+        assert!(!dir.join("%2E%2E").exists());
+        assert!(unrelated.is_file());
+    }
+}
@@ -0,0 +1,181 @@
+use std::path::Path;
+
+use actix_web::http::{Method, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    config::EndpointFile,
+    formats::{Importer, endpoint_file, request_path},
+    server::{ResponseHeader, Stub},
+    util::{error::InternalError, result::InternalResult},
+};
+
+/// A collection exported by Bruno as JSON, only the parts endpoints are made of
+#[derive(Debug, Deserialize)]
+struct Collection {
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+/// A request or a folder of them
+#[derive(Debug, Deserialize)]
+struct Item {
+    #[serde(default)]
+    request: Option<Request>,
+    #[serde(default)]
+    examples: Vec<Example>,
+    /// Items of a folder
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    method: String,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Example {
+    response: Response,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    /// A number, or a string in older exports
+    status: Value,
+    #[serde(default)]
+    headers: Vec<Header>,
+    #[serde(default)]
+    body: Option<Body>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+    name: String,
+    value: String,
+    #[serde(default = "enabled")]
+    enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+struct Body {
+    /// Text, or JSON as it was parsed
+    #[serde(default)]
+    content: Value,
+}
+
+/// Requests and their examples of a Bruno collection
+pub struct BrunoImporter;
+
+impl Importer for BrunoImporter {
+    fn name(&self) -> &'static str {
+        "bruno"
+    }
+
+    fn description(&self) -> &'static str {
+        "Requests of a Bruno collection exported as JSON, answering like their first example"
+    }
+
+    fn import(&self, content: &str, path: &Path) -> InternalResult<EndpointFile> {
+        parse(content, path)
+    }
+}
+
+/// Turn each request of the collection and its folders into an endpoint answering like the first
+/// example of the request, or with an empty body without one
+fn parse(content: &str, path: &Path) -> InternalResult<EndpointFile> {
+    let collection: Collection =
+        serde_json::from_str(content).map_err(|e| InternalError::Import {
+            path: path.to_owned(),
+            line: e.line(),
+            reason: e.to_string(),
+        })?;
+    let mut requests = Vec::new();
+    collect(&collection.items, &mut requests);
+    Ok(endpoint_file(requests))
+}
+
+fn collect(items: &[Item], requests: &mut Vec<(Method, String, Stub)>) {
+    for item in items {
+        collect(&item.items, requests);
+        let Some(request) = &item.request else {
+            continue;
+        };
+        let Ok(method) = Method::from_bytes(request.method.to_uppercase().as_bytes()) else {
+            continue;
+        };
+        let stub = item
+            .examples
+            .first()
+            .and_then(|example| stub(&example.response))
+            .unwrap_or_default();
+        requests.push((method, request_path(&request.url), stub));
+    }
+}
+
+/// A stub answering like an example response, unless its status is invalid
+fn stub(response: &Response) -> Option<Stub> {
+    let status = match &response.status {
+        Value::Number(n) => n.as_u64().and_then(|n| u16::try_from(n).ok())?,
+        Value::String(s) => s.trim().parse().ok()?,
+        _ => return None,
+    };
+    let status = StatusCode::from_u16(status).ok()?;
+    let headers = response
+        .headers
+        .iter()
+        .filter(|header| header.enabled)
+        .filter_map(|header| format!("{}: {}", header.name, header.value).parse().ok())
+        .collect::<Vec<ResponseHeader>>();
+    let body = match response.body.as_ref().map(|body| &body.content) {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(json) => json.to_string(),
+    };
+    Some(Stub {
+        status: Some(status).filter(|status| *status != StatusCode::OK),
+        headers,
+        ..Stub::from(body)
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_bruno() {
+        let content = r#"{"name": "Shop", "version": "1", "items": [
+                {"type": "http-request", "name": "Get user", "seq": 1,
+                  "request": {"method": "GET", "url": "{{baseUrl}}/users/:id"},
+                  "examples": [{"name": "Missing", "response": {"status": 404,
+                    "headers": [{"name": "Content-Type", "value": "application/json",
+                      "enabled": true}],
+                    "body": {"type": "json", "content": {"error": "gone"}}}}]},
+                {"type": "folder", "name": "Orders", "items": [
+                  {"type": "http-request", "name": "Create order",
+                    "request": {"method": "post", "url": "{{baseUrl}}/orders?dry_run=true"}}]}
+            ]}"#;
+        let entries = BrunoImporter
+            .import(content, Path::new("shop.json"))
+            .unwrap()
+            .entries();
+
+        assert_eq!(entries.len(), 2);
+        let (method, path, stub) = &entries[0];
+        assert_eq!((method, path.as_str()), (&Method::GET, "/users/{id}"));
+        assert_eq!(stub.status, Some(StatusCode::NOT_FOUND));
+        assert_eq!(stub.body, r#"{"error":"gone"}"#);
+        assert_eq!(stub.headers.len(), 1);
+        let (method, path, stub) = &entries[1];
+        assert_eq!((method, path.as_str()), (&Method::POST, "/orders"));
+        assert_eq!(stub.matchers.len(), 1);
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::Path;
+
+use actix_web::http::{Method, StatusCode, Uri};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::Deserialize;
+
+use crate::{
+    config::EndpointFile,
+    formats::{Importer, endpoint_file},
+    server::{ResponseHeader, Stub},
+    util::{error::InternalError, result::InternalResult},
+};
+
+/// Response headers describing the recorded transfer rather than the response, which the server
+/// sets itself
+const TRANSFER_HEADERS: [&str; 6] = [
+    "connection",
+    "content-encoding",
+    "content-length",
+    "date",
+    "keep-alive",
+    "transfer-encoding",
+];
+
+/// Traffic recorded by browser devtools or proxies, only the parts endpoints are made of
+#[derive(Debug, Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarLog {
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarEntry {
+    request: HarRequest,
+    response: HarResponse,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarRequest {
+    method: String,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarResponse {
+    status: u16,
+    #[serde(default)]
+    headers: Vec<HarHeader>,
+    #[serde(default)]
+    content: HarContent,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarHeader {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct HarContent {
+    #[serde(default)]
+    text: Option<String>,
+    /// `base64` for binary bodies
+    #[serde(default)]
+    encoding: Option<String>,
+}
+
+/// Traffic saved by browser devtools or proxies as a HAR file
+pub struct HarImporter;
+
+impl Importer for HarImporter {
+    fn name(&self) -> &'static str {
+        "har"
+    }
+
+    fn description(&self) -> &'static str {
+        "Responses recorded by browser devtools, every request becomes an endpoint"
+    }
+
+    fn import(&self, content: &str, path: &Path) -> InternalResult<EndpointFile> {
+        parse(content, path)
+    }
+}
+
+/// Turn each recorded request into an endpoint answering like the recorded response. The query
+/// of a request becomes matchers, and of requests recorded several times the last response wins.
+/// Requests without a response, e.g. blocked ones, are skipped.
+fn parse(content: &str, path: &Path) -> InternalResult<EndpointFile> {
+    let har: Har = serde_json::from_str(content).map_err(|e| InternalError::Import {
+        path: path.to_owned(),
+        line: e.line(),
+        reason: e.to_string(),
+    })?;
+    let mut skipped = 0;
+    let requests = har
+        .log
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            let request = endpoint(entry);
+            skipped += usize::from(request.is_none());
+            request
+        })
+        .collect::<Vec<_>>();
+    if skipped > 0 {
+        log::info!(
+            "Skipped {} request(s) of {} without a usable response",
+            skipped,
+            path.display()
+        );
+    }
+    Ok(endpoint_file(requests))
+}
+
+/// Method, path with query and stub of a recorded request, if it got a response
+fn endpoint(entry: &HarEntry) -> Option<(Method, String, Stub)> {
+    let method = Method::from_bytes(entry.request.method.as_bytes()).ok()?;
+    let uri: Uri = entry.request.url.parse().ok()?;
+    let url = uri.path_and_query()?.as_str().to_owned();
+    // Aborted and blocked requests are recorded with status 0
+    let status = StatusCode::from_u16(entry.response.status).ok()?;
+    let content = &entry.response.content;
+    let text = content.text.as_deref().unwrap_or_default();
+    let body = match content.encoding.as_deref() {
+        Some("base64") => STANDARD.decode(text).ok()?,
+        _ => text.as_bytes().to_vec(),
+    };
+    let headers = entry
+        .response
+        .headers
+        .iter()
+        .filter(|header| {
+            let name = header.name.to_ascii_lowercase();
+            // HTTP/2 pseudo headers like `:status` are recorded along the real ones
+            !name.starts_with(':') && !TRANSFER_HEADERS.contains(&name.as_str())
+        })
+        .filter_map(|header| format!("{}: {}", header.name, header.value).parse().ok())
+        .collect::<Vec<ResponseHeader>>();
+    let stub = Stub {
+        status: Some(status).filter(|status| *status != StatusCode::OK),
+        headers,
+        ..Stub::from(String::from_utf8_lossy(&body).into_owned())
+    };
+    Some((method, url, stub))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_har() {
+        let content = r#"{"log": {"version": "1.2", "entries": [
+                {"request": {"method": "GET", "url": "https://api.example.com/users?page=2"},
+                  "response": {"status": 200, "headers": [
+                      {"name": "content-type", "value": "application/json"},
+                      {"name": "content-length", "value": "2"}],
+                    "content": {"text": "[]"}}},
+                {"request": {"method": "GET", "url": "https://api.example.com/users?page=2"},
+                  "response": {"status": 200, "content": {"text": "[1]"}}},
+                {"request": {"method": "POST", "url": "https://api.example.com/users"},
+                  "response": {"status": 201, "content": {"text": "e30=", "encoding": "base64"}}},
+                {"request": {"method": "GET", "url": "https://ads.example.com/track"},
+                  "response": {"status": 0, "content": {}}}
+            ]}}"#;
+        let entries = HarImporter
+            .import(content, Path::new("session.har"))
+            .unwrap()
+            .entries();
+
+        assert_eq!(entries.len(), 2);
+        let (method, path, stub) = &entries[0];
+        assert_eq!((method, path.as_str()), (&Method::GET, "/users"));
+        assert_eq!(stub.body, "[1]");
+        assert_eq!(stub.matchers.len(), 1);
+        assert!(stub.headers.is_empty());
+        let (method, _, stub) = &entries[1];
+        assert_eq!(method, Method::POST);
+        assert_eq!(stub.status, Some(StatusCode::CREATED));
+        assert_eq!(stub.body, "{}");
+    }
+}
@@ -0,0 +1,166 @@
+use std::path::Path;
+
+use actix_web::http::{Method, StatusCode};
+use serde::Deserialize;
+
+use crate::{
+    config::EndpointFile,
+    formats::{Importer, endpoint_file, request_path},
+    server::{ResponseHeader, Stub},
+    util::{error::InternalError, result::InternalResult},
+};
+
+/// An export of Insomnia, in JSON or YAML, only the parts endpoints are made of
+#[derive(Debug, Deserialize)]
+struct Export {
+    resources: Vec<Resource>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "_type", rename_all = "snake_case")]
+enum Resource {
+    /// A saved request
+    Request { method: String, url: String },
+    /// A route of a mock server, answering with the response of the route
+    #[serde(rename_all = "camelCase")]
+    MockRoute {
+        /// The path, e.g. `/users`
+        name: String,
+        method: String,
+        status_code: u16,
+        #[serde(default)]
+        headers: Vec<Header>,
+        #[serde(default)]
+        body: String,
+        #[serde(default)]
+        mime_type: Option<String>,
+    },
+    /// Workspaces, folders, environments and the like
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+    name: String,
+    value: String,
+    #[serde(default)]
+    disabled: bool,
+}
+
+/// Requests and mock routes of an Insomnia export
+pub struct InsomniaImporter;
+
+impl Importer for InsomniaImporter {
+    fn name(&self) -> &'static str {
+        "insomnia"
+    }
+
+    fn description(&self) -> &'static str {
+        "Requests of an Insomnia export, answering like its mock routes or with an empty 200"
+    }
+
+    fn import(&self, content: &str, path: &Path) -> InternalResult<EndpointFile> {
+        parse(content, path)
+    }
+}
+
+/// Turn each saved request into an endpoint answering with an empty body, and each mock route
+/// into one answering with the response of the route. Mock routes win over requests of the
+/// same method and path, whatever their order in the export.
+fn parse(content: &str, path: &Path) -> InternalResult<EndpointFile> {
+    let export: Export = serde_yaml::from_str(content).map_err(|e| InternalError::Import {
+        path: path.to_owned(),
+        line: e.location().map_or(1, |l| l.line()),
+        reason: e.to_string(),
+    })?;
+    let mut requests = Vec::new();
+    let mut routes = Vec::new();
+    for resource in export.resources {
+        match resource {
+            Resource::Request { method, url } => {
+                if let Ok(method) = Method::from_bytes(method.as_bytes()) {
+                    requests.push((method, request_path(&url), Stub::default()));
+                }
+            }
+            Resource::MockRoute {
+                name,
+                method,
+                status_code,
+                headers,
+                body,
+                mime_type,
+            } => {
+                let (Ok(method), Ok(status)) = (
+                    Method::from_bytes(method.as_bytes()),
+                    StatusCode::from_u16(status_code),
+                ) else {
+                    continue;
+                };
+                let mut headers = headers
+                    .iter()
+                    .filter(|header| !header.disabled)
+                    .filter_map(|header| format!("{}: {}", header.name, header.value).parse().ok())
+                    .collect::<Vec<ResponseHeader>>();
+                if let Some(mime_type) = mime_type
+                    && !headers.iter().any(|h| h.name == "content-type")
+                    && let Ok(header) = format!("Content-Type: {}", mime_type).parse()
+                {
+                    headers.push(header);
+                }
+                let stub = Stub {
+                    status: Some(status).filter(|status| *status != StatusCode::OK),
+                    headers,
+                    ..Stub::from(body)
+                };
+                let path = request_path(&format!("/{}", name.trim_start_matches('/')));
+                routes.push((method, path, stub));
+            }
+            Resource::Other => {}
+        }
+    }
+    Ok(endpoint_file(requests.into_iter().chain(routes)))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_insomnia() {
+        let content = r#"{"_type": "export", "__export_format": 4, "resources": [
+                {"_id": "mock_1", "_type": "mock_route", "name": "users/{{ _.id }}",
+                  "method": "GET", "statusCode": 404, "body": "{\"error\": \"gone\"}",
+                  "mimeType": "application/json", "headers": []},
+                {"_id": "wrk_1", "_type": "workspace", "name": "Shop"},
+                {"_id": "req_1", "_type": "request", "method": "GET",
+                  "url": "{{ _.base_url }}/users/{{ _.id }}"},
+                {"_id": "req_2", "_type": "request", "method": "POST",
+                  "url": "https://api.example.com/orders?dry_run=true"}
+            ]}"#;
+        let entries = InsomniaImporter
+            .import(content, Path::new("insomnia.json"))
+            .unwrap()
+            .entries();
+
+        assert_eq!(entries.len(), 2);
+        let (method, path, stub) = &entries[0];
+        assert_eq!((method, path.as_str()), (&Method::GET, "/users/{id}"));
+        assert_eq!(stub.status, Some(StatusCode::NOT_FOUND));
+        assert_eq!(
+            stub.headers[0].to_string(),
+            "content-type: application/json"
+        );
+        let (method, path, stub) = &entries[1];
+        assert_eq!((method, path.as_str()), (&Method::POST, "/orders"));
+        assert_eq!(stub.matchers.len(), 1);
+        assert!(stub.body.is_empty());
+
+        assert!(
+            InsomniaImporter
+                .import("resources: 3", Path::new("insomnia.yaml"))
+                .is_err()
+        );
+    }
+}
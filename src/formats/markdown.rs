@@ -0,0 +1,23 @@
+use std::{io, path::Path};
+
+use crate::{
+    formats::Exporter,
+    server::{EndpointStore, markdown},
+};
+
+/// Documentation of the endpoints, e.g. to paste into a wiki
+pub struct MarkdownExporter;
+
+impl Exporter for MarkdownExporter {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn description(&self) -> &'static str {
+        "Descriptions with an example request and response per endpoint"
+    }
+
+    fn export(&self, endpoints: &EndpointStore, base_path: &str, _: &Path) -> io::Result<String> {
+        Ok(markdown(endpoints, base_path))
+    }
+}
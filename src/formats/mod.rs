@@ -0,0 +1,250 @@
+use std::{
+    fs, io,
+    path::Path,
+    sync::{LazyLock, OnceLock},
+};
+
+use actix_web::http::Method;
+use regex::Regex;
+
+use crate::{
+    config::{EndpointDef, EndpointFile},
+    server::{EndpointStore, Stub, split_query},
+    util::{error::InternalError, result::InternalResult},
+};
+
+mod bruno;
+mod har;
+mod insomnia;
+mod markdown;
+mod openapi;
+
+/// Reads endpoints from files saved by another tool, e.g. HAR files of browser devtools
+pub trait Importer: Send + Sync {
+    /// Name of the format on the command line, e.g. `har`
+    fn name(&self) -> &'static str;
+    /// Shown in the help of `import`
+    fn description(&self) -> &'static str;
+    /// Endpoints defined by `content`, read from `path`, which is only used for error messages
+    fn import(&self, content: &str, path: &Path) -> InternalResult<EndpointFile>;
+}
+
+/// Writes the endpoints in a format of another tool, e.g. an OpenAPI document
+pub trait Exporter: Send + Sync {
+    /// Name of the format on the command line, e.g. `openapi`
+    fn name(&self) -> &'static str;
+    /// Shown in the help of `export endpoints`
+    fn description(&self) -> &'static str;
+    /// Document of `endpoints` served below `base_path`, to be written to `path`. Exporters may
+    /// pick a flavor by its extension, e.g. YAML or JSON.
+    fn export(&self, endpoints: &EndpointStore, base_path: &str, path: &Path)
+    -> io::Result<String>;
+}
+
+/// Importers and exporters by name. Each format is a module of its own, registered in
+/// [`Registry::builtin`], so adding one doesn't touch the commands using them.
+#[derive(Default)]
+pub struct Registry {
+    importers: Vec<Box<dyn Importer>>,
+    exporters: Vec<Box<dyn Exporter>>,
+}
+
+impl Registry {
+    /// The formats built into the binary
+    fn builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register_importer(har::HarImporter);
+        registry.register_importer(insomnia::InsomniaImporter);
+        registry.register_importer(bruno::BrunoImporter);
+        registry.register_exporter(markdown::MarkdownExporter);
+        registry.register_exporter(openapi::OpenApiExporter);
+        registry
+    }
+
+    /// Add an importer, replacing one of the same name
+    pub fn register_importer(&mut self, importer: impl Importer + 'static) {
+        self.importers.retain(|i| i.name() != importer.name());
+        self.importers.push(Box::new(importer));
+    }
+
+    /// Add an exporter, replacing one of the same name
+    pub fn register_exporter(&mut self, exporter: impl Exporter + 'static) {
+        self.exporters.retain(|e| e.name() != exporter.name());
+        self.exporters.push(Box::new(exporter));
+    }
+
+    pub fn importers(&self) -> impl Iterator<Item = &dyn Importer> {
+        self.importers.iter().map(Box::as_ref)
+    }
+
+    pub fn exporters(&self) -> impl Iterator<Item = &dyn Exporter> {
+        self.exporters.iter().map(Box::as_ref)
+    }
+
+    pub fn importer(&self, name: &str) -> InternalResult<&dyn Importer> {
+        self.importers()
+            .find(|importer| importer.name() == name)
+            .ok_or_else(|| InternalError::InvalidArgument {
+                argument: "format",
+                reason: unknown(name, self.importers().map(|i| i.name())),
+            })
+    }
+
+    pub fn exporter(&self, name: &str) -> InternalResult<&dyn Exporter> {
+        self.exporters()
+            .find(|exporter| exporter.name() == name)
+            .ok_or_else(|| InternalError::InvalidArgument {
+                argument: "format",
+                reason: unknown(name, self.exporters().map(|e| e.name())),
+            })
+    }
+
+    /// Read a file and import it in the format `name`
+    pub fn load(&self, name: &str, path: &Path) -> InternalResult<EndpointFile> {
+        let importer = self.importer(name)?;
+        let content = fs::read_to_string(path).map_err(|source| InternalError::FileIo {
+            path: path.to_owned(),
+            source,
+        })?;
+        importer.import(&content, path)
+    }
+}
+
+fn unknown<'a>(name: &str, available: impl Iterator<Item = &'a str>) -> String {
+    format!(
+        "`{}` is not a known format, use one of {}",
+        name,
+        available.collect::<Vec<_>>().join(", ")
+    )
+}
+
+/// Endpoints of imported requests, given with the query in the path. The query becomes
+/// matchers, and of requests imported several times the last one wins.
+fn endpoint_file(requests: impl IntoIterator<Item = (Method, String, Stub)>) -> EndpointFile {
+    let mut endpoints: Vec<(String, EndpointDef)> = Vec::new();
+    for (method, url, stub) in requests {
+        let key = format!("{} {}", method, url);
+        let (path, matchers) = split_query(&url);
+        let def = EndpointDef::new(method, path.to_owned(), &Stub { matchers, ..stub });
+        match endpoints.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = def,
+            None => endpoints.push((key, def)),
+        }
+    }
+    EndpointFile {
+        endpoints: endpoints.into_iter().map(|(_, def)| def).collect(),
+        ..Default::default()
+    }
+}
+
+/// Path and query of a URL saved by an API client like Insomnia or Bruno, e.g. `/users/{id}` of
+/// `{{ _.base_url }}/users/{{ _.id }}` or `https://api.example.com/users/:id`. The base URL is
+/// dropped, variables and `:name` segments of the path become parameters, and query pairs with a
+/// variable for a value are left out as they can't be matched literally.
+fn request_path(url: &str) -> String {
+    static VARIABLE: LazyLock<Regex> = LazyLock::new(|| {
+        #[allow(clippy::unwrap_used)]
+        Regex::new(r"\{\{\s*(?:_\.)?([^{}\s]+)\s*\}\}").unwrap()
+    });
+    let url = url.trim();
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => rest,
+        // A base URL given as a variable
+        None if url.starts_with("{{") => url.find("}}").map_or("", |end| &url[end + 2..]),
+        None => url,
+    };
+    let rest = match rest.find(['/', '?']) {
+        Some(start) => &rest[start..],
+        None => "",
+    };
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let path = VARIABLE.replace_all(path, "{$1}");
+    let mut path = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("/{{{}}}", name),
+            None => format!("/{}", segment),
+        })
+        .collect::<String>();
+    if path.is_empty() {
+        path.push('/');
+    }
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty() && !pair.contains("{{"))
+        .collect::<Vec<_>>()
+        .join("&");
+    if !query.is_empty() {
+        path.push('?');
+        path.push_str(&query);
+    }
+    path
+}
+
+/// The formats available to `import` and `export endpoints`
+pub fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Registry::builtin)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    struct Empty;
+
+    impl Importer for Empty {
+        fn name(&self) -> &'static str {
+            "har"
+        }
+
+        fn description(&self) -> &'static str {
+            "nothing at all"
+        }
+
+        fn import(&self, _: &str, _: &Path) -> InternalResult<EndpointFile> {
+            Ok(EndpointFile::default())
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::builtin();
+        assert!(registry.exporter("openapi").is_ok());
+        match registry.importer("postman") {
+            Err(InternalError::InvalidArgument { reason, .. }) => {
+                assert_eq!(
+                    reason,
+                    "`postman` is not a known format, use one of har, insomnia, bruno"
+                );
+            }
+            _ => panic!("postman is not built in"),
+        }
+        registry.register_importer(Empty);
+        assert_eq!(registry.importers().count(), 3);
+        assert_eq!(
+            registry.importer("har").unwrap().description(),
+            "nothing at all"
+        );
+    }
+
+    #[test]
+    fn test_request_path() {
+        assert_eq!(
+            request_path("{{ _.base_url }}/users/{{ _.id }}"),
+            "/users/{id}"
+        );
+        assert_eq!(
+            request_path("{{baseUrl}}/users/{{userId}}/posts"),
+            "/users/{userId}/posts"
+        );
+        assert_eq!(
+            request_path("https://api.example.com/users/:id?page=2&q={{q}}"),
+            "/users/{id}?page=2"
+        );
+        assert_eq!(request_path("localhost:3000"), "/");
+        assert_eq!(request_path("{{host}}?search=x"), "/?search=x");
+    }
+}
@@ -0,0 +1,33 @@
+use std::{io, path::Path};
+
+use crate::{
+    formats::Exporter,
+    server::{EndpointStore, openapi_document},
+};
+
+/// A starting contract for the real API, YAML if the extension of the file says so and JSON
+/// otherwise
+pub struct OpenApiExporter;
+
+impl Exporter for OpenApiExporter {
+    fn name(&self) -> &'static str {
+        "openapi"
+    }
+
+    fn description(&self) -> &'static str {
+        "OpenAPI 3 document with the bodies as examples, YAML for .yaml files"
+    }
+
+    fn export(
+        &self,
+        endpoints: &EndpointStore,
+        base_path: &str,
+        path: &Path,
+    ) -> io::Result<String> {
+        let document = openapi_document(endpoints, base_path);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::to_string(&document).map_err(io::Error::other),
+            _ => serde_json::to_string_pretty(&document).map_err(io::Error::other),
+        }
+    }
+}
@@ -0,0 +1,462 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use prost::Message;
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorSet,
+    field_descriptor_proto::{Label, Type},
+};
+use serde_json::Value;
+
+use crate::util::{error::InternalError, result::InternalResult};
+
+/// A method of a loaded service
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrpcMethod {
+    /// Request message, e.g. `helloworld.HelloRequest`
+    pub input: String,
+    /// Response message, e.g. `helloworld.HelloReply`
+    pub output: String,
+    /// Whether the client or server sends a stream of messages
+    pub streaming: bool,
+}
+
+/// Services and messages of a descriptor set, as written by `protoc --include_imports
+/// --descriptor_set_out`. Names are fully qualified without the leading dot.
+#[derive(Debug, Default)]
+pub struct Descriptors {
+    messages: HashMap<String, DescriptorProto>,
+    enums: HashMap<String, EnumDescriptorProto>,
+    /// Methods by path, e.g. `/helloworld.Greeter/SayHello`
+    methods: BTreeMap<String, GrpcMethod>,
+}
+
+impl Descriptors {
+    pub fn load(path: &Path) -> InternalResult<Self> {
+        let content = std::fs::read(path).map_err(|source| InternalError::FileIo {
+            path: path.to_owned(),
+            source,
+        })?;
+        let set =
+            FileDescriptorSet::decode(content.as_slice()).map_err(|e| InternalError::Import {
+                path: path.to_owned(),
+                line: 1,
+                reason: format!("not a descriptor set: {}", e),
+            })?;
+        Ok(Self::new(set))
+    }
+
+    pub fn new(set: FileDescriptorSet) -> Self {
+        let mut descriptors = Self::default();
+        for file in set.file {
+            let package = file.package().to_owned();
+            for message in file.message_type {
+                descriptors.add_message(&package, message);
+            }
+            for descriptor in file.enum_type {
+                let name = qualify(&package, descriptor.name());
+                descriptors.enums.insert(name, descriptor);
+            }
+            for service in file.service {
+                let service_name = qualify(&package, service.name());
+                for method in service.method {
+                    descriptors.methods.insert(
+                        format!("/{}/{}", service_name, method.name()),
+                        GrpcMethod {
+                            input: method.input_type().trim_start_matches('.').to_owned(),
+                            output: method.output_type().trim_start_matches('.').to_owned(),
+                            streaming: method.client_streaming() || method.server_streaming(),
+                        },
+                    );
+                }
+            }
+        }
+        descriptors
+    }
+
+    /// Register a message with its nested messages and enums
+    fn add_message(&mut self, scope: &str, mut message: DescriptorProto) {
+        let name = qualify(scope, message.name());
+        for nested in std::mem::take(&mut message.nested_type) {
+            self.add_message(&name, nested);
+        }
+        for descriptor in std::mem::take(&mut message.enum_type) {
+            self.enums
+                .insert(qualify(&name, descriptor.name()), descriptor);
+        }
+        self.messages.insert(name, message);
+    }
+
+    pub fn methods(&self) -> &BTreeMap<String, GrpcMethod> {
+        &self.methods
+    }
+
+    pub fn method(&self, path: &str) -> Option<&GrpcMethod> {
+        self.methods.get(path)
+    }
+
+    /// Encode a message given as JSON, with fields by their name in the `.proto` file or in
+    /// lowerCamelCase. 64-bit integers may be strings, bytes are base64 and enums names or
+    /// numbers.
+    pub fn encode(&self, message: &str, json: &Value) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        self.encode_message(message, json, &mut out)?;
+        Ok(out)
+    }
+
+    fn encode_message(&self, name: &str, json: &Value, out: &mut Vec<u8>) -> Result<(), String> {
+        let message = self
+            .messages
+            .get(name)
+            .ok_or_else(|| format!("message `{}` is not in the descriptor set", name))?;
+        let Value::Object(fields) = json else {
+            return Err(format!("expected an object for `{}`, got `{}`", name, json));
+        };
+        for (key, value) in fields {
+            let field = message
+                .field
+                .iter()
+                .find(|field| field.name() == key || field.json_name() == key)
+                .ok_or_else(|| format!("`{}` is not a field of `{}`", key, name))?;
+            match (field.label(), value) {
+                (_, Value::Null) => {}
+                (Label::Repeated, Value::Object(entries)) if self.is_map(field) => {
+                    for (key, value) in entries {
+                        let entry = serde_json::json!({"key": key, "value": value});
+                        self.encode_field(field, &entry, out)?;
+                    }
+                }
+                (Label::Repeated, Value::Array(items)) => {
+                    for item in items {
+                        self.encode_field(field, item, out)?;
+                    }
+                }
+                (Label::Repeated, _) => {
+                    return Err(format!("expected a list for `{}.{}`", name, key));
+                }
+                _ => self.encode_field(field, value, out)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn is_map(&self, field: &FieldDescriptorProto) -> bool {
+        field.r#type() == Type::Message
+            && self
+                .messages
+                .get(field.type_name().trim_start_matches('.'))
+                .and_then(|message| message.options.as_ref())
+                .is_some_and(|options| options.map_entry())
+    }
+
+    /// Encode a single value of a field with its tag
+    fn encode_field(
+        &self,
+        field: &FieldDescriptorProto,
+        value: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<(), String> {
+        let number = field.number() as u64;
+        let invalid = || {
+            format!(
+                "`{}` is not a valid {:?} for `{}`",
+                value,
+                field.r#type(),
+                field.name()
+            )
+        };
+        match field.r#type() {
+            Type::Int32 | Type::Int64 => {
+                put_tag(number, VARINT, out);
+                put_varint(integer::<i64>(value).ok_or_else(invalid)? as u64, out);
+            }
+            Type::Uint32 | Type::Uint64 => {
+                put_tag(number, VARINT, out);
+                put_varint(integer::<u64>(value).ok_or_else(invalid)?, out);
+            }
+            Type::Sint32 | Type::Sint64 => {
+                let n = integer::<i64>(value).ok_or_else(invalid)?;
+                put_tag(number, VARINT, out);
+                put_varint(((n << 1) ^ (n >> 63)) as u64, out);
+            }
+            Type::Bool => {
+                let b = match value {
+                    Value::Bool(b) => *b,
+                    Value::String(s) => s.parse().map_err(|_| invalid())?,
+                    _ => return Err(invalid()),
+                };
+                put_tag(number, VARINT, out);
+                put_varint(u64::from(b), out);
+            }
+            Type::Enum => {
+                let n = match value {
+                    Value::String(name) => self
+                        .enums
+                        .get(field.type_name().trim_start_matches('.'))
+                        .and_then(|descriptor| descriptor.value.iter().find(|v| v.name() == name))
+                        .map(|v| v.number())
+                        .ok_or_else(invalid)?,
+                    _ => integer::<i32>(value).ok_or_else(invalid)?,
+                };
+                put_tag(number, VARINT, out);
+                put_varint(i64::from(n) as u64, out);
+            }
+            Type::Fixed64 | Type::Sfixed64 => {
+                let n = match field.r#type() {
+                    Type::Fixed64 => integer::<u64>(value),
+                    _ => integer::<i64>(value).map(|n| n as u64),
+                };
+                put_tag(number, FIXED64, out);
+                out.extend_from_slice(&n.ok_or_else(invalid)?.to_le_bytes());
+            }
+            Type::Fixed32 | Type::Sfixed32 => {
+                let n = match field.r#type() {
+                    Type::Fixed32 => integer::<u32>(value),
+                    _ => integer::<i32>(value).map(|n| n as u32),
+                };
+                put_tag(number, FIXED32, out);
+                out.extend_from_slice(&n.ok_or_else(invalid)?.to_le_bytes());
+            }
+            Type::Double => {
+                put_tag(number, FIXED64, out);
+                out.extend_from_slice(&value.as_f64().ok_or_else(invalid)?.to_le_bytes());
+            }
+            Type::Float => {
+                put_tag(number, FIXED32, out);
+                let f = value.as_f64().ok_or_else(invalid)? as f32;
+                out.extend_from_slice(&f.to_le_bytes());
+            }
+            Type::String => {
+                let s = value.as_str().ok_or_else(invalid)?;
+                put_bytes(number, s.as_bytes(), out);
+            }
+            Type::Bytes => {
+                let bytes = value
+                    .as_str()
+                    .and_then(|s| STANDARD.decode(s).ok())
+                    .ok_or_else(invalid)?;
+                put_bytes(number, &bytes, out);
+            }
+            Type::Message => {
+                let mut nested = Vec::new();
+                self.encode_message(
+                    field.type_name().trim_start_matches('.'),
+                    value,
+                    &mut nested,
+                )?;
+                put_bytes(number, &nested, out);
+            }
+            Type::Group => return Err(format!("group field `{}` is not supported", field.name())),
+        }
+        Ok(())
+    }
+}
+
+const VARINT: u64 = 0;
+const FIXED64: u64 = 1;
+const LENGTH_DELIMITED: u64 = 2;
+const FIXED32: u64 = 5;
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+/// An integer given as a JSON number or string, as the JSON mapping of protobuf writes 64-bit
+/// integers
+fn integer<T: TryFrom<i64> + TryFrom<u64> + std::str::FromStr>(value: &Value) -> Option<T> {
+    match value {
+        Value::Number(n) => n
+            .as_i64()
+            .and_then(|n| T::try_from(n).ok())
+            .or_else(|| n.as_u64().and_then(|n| T::try_from(n).ok())),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn put_varint(mut n: u64, out: &mut Vec<u8>) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn put_tag(number: u64, wire_type: u64, out: &mut Vec<u8>) {
+    put_varint((number << 3) | wire_type, out);
+}
+
+fn put_bytes(number: u64, bytes: &[u8], out: &mut Vec<u8>) {
+    put_tag(number, LENGTH_DELIMITED, out);
+    put_varint(bytes.len() as u64, out);
+    out.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use prost_types::{
+        EnumValueDescriptorProto, FileDescriptorProto, MessageOptions, MethodDescriptorProto,
+        ServiceDescriptorProto,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Reply {
+        #[prost(string, tag = "1")]
+        message: String,
+        #[prost(sint64, repeated, tag = "2")]
+        scores: Vec<i64>,
+        #[prost(enumeration = "Mood", tag = "3")]
+        mood: i32,
+        #[prost(message, optional, tag = "4")]
+        nested: Option<Nested>,
+        #[prost(map = "string, int32", tag = "5")]
+        counts: std::collections::HashMap<String, i32>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Nested {
+        #[prost(bytes = "vec", tag = "1")]
+        data: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, prost::Enumeration)]
+    enum Mood {
+        Unknown = 0,
+        Happy = 1,
+    }
+
+    fn field(
+        name: &str,
+        number: i32,
+        r#type: Type,
+        label: Label,
+        type_name: &str,
+    ) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_owned()),
+            number: Some(number),
+            r#type: Some(r#type as i32),
+            label: Some(label as i32),
+            type_name: Some(type_name.to_owned()).filter(|name| !name.is_empty()),
+            ..Default::default()
+        }
+    }
+
+    fn descriptors() -> Descriptors {
+        let reply = DescriptorProto {
+            name: Some("Reply".to_owned()),
+            field: vec![
+                field("message", 1, Type::String, Label::Optional, ""),
+                field("scores", 2, Type::Sint64, Label::Repeated, ""),
+                field("mood", 3, Type::Enum, Label::Optional, ".test.Mood"),
+                field(
+                    "nested",
+                    4,
+                    Type::Message,
+                    Label::Optional,
+                    ".test.Reply.Nested",
+                ),
+                field(
+                    "counts",
+                    5,
+                    Type::Message,
+                    Label::Repeated,
+                    ".test.Reply.CountsEntry",
+                ),
+            ],
+            nested_type: vec![
+                DescriptorProto {
+                    name: Some("Nested".to_owned()),
+                    field: vec![field("data", 1, Type::Bytes, Label::Optional, "")],
+                    ..Default::default()
+                },
+                DescriptorProto {
+                    name: Some("CountsEntry".to_owned()),
+                    field: vec![
+                        field("key", 1, Type::String, Label::Optional, ""),
+                        field("value", 2, Type::Int32, Label::Optional, ""),
+                    ],
+                    options: Some(MessageOptions {
+                        map_entry: Some(true),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let file = FileDescriptorProto {
+            package: Some("test".to_owned()),
+            message_type: vec![reply],
+            enum_type: vec![EnumDescriptorProto {
+                name: Some("Mood".to_owned()),
+                value: vec![EnumValueDescriptorProto {
+                    name: Some("HAPPY".to_owned()),
+                    number: Some(1),
+                    options: None,
+                }],
+                ..Default::default()
+            }],
+            service: vec![ServiceDescriptorProto {
+                name: Some("Greeter".to_owned()),
+                method: vec![MethodDescriptorProto {
+                    name: Some("SayHello".to_owned()),
+                    input_type: Some(".test.Reply".to_owned()),
+                    output_type: Some(".test.Reply".to_owned()),
+                    ..Default::default()
+                }],
+                options: None,
+            }],
+            ..Default::default()
+        };
+        let set = FileDescriptorSet { file: vec![file] };
+        // Round trip through the bytes `protoc` would write
+        Descriptors::new(FileDescriptorSet::decode(set.encode_to_vec().as_slice()).unwrap())
+    }
+
+    #[test]
+    fn test_encode_json() {
+        let descriptors = descriptors();
+        let method = descriptors.method("/test.Greeter/SayHello").unwrap();
+        assert_eq!(method.output, "test.Reply");
+        assert!(!method.streaming);
+
+        let json = json!({
+            "message": "hi",
+            "scores": [-1, "2"],
+            "mood": "HAPPY",
+            "nested": {"data": "AAE="},
+            "counts": {"a": 3},
+        });
+        let bytes = descriptors.encode("test.Reply", &json).unwrap();
+        let reply = Reply::decode(bytes.as_slice()).unwrap();
+        assert_eq!(reply.message, "hi");
+        assert_eq!(reply.scores, [-1, 2]);
+        assert_eq!(reply.mood, Mood::Happy as i32);
+        assert_eq!(reply.nested.unwrap().data, [0, 1]);
+        assert_eq!(reply.counts["a"], 3);
+
+        assert!(
+            descriptors
+                .encode("test.Reply", &json!({"nope": 1}))
+                .is_err()
+        );
+        assert!(
+            descriptors
+                .encode("test.Reply", &json!({"mood": "SAD"}))
+                .is_err()
+        );
+        assert!(descriptors.encode("test.Missing", &json!({})).is_err());
+    }
+}
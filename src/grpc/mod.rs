@@ -0,0 +1,410 @@
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt, io,
+    net::TcpListener,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError, RwLock},
+    task::{Context, Poll},
+    thread::{self, JoinHandle},
+};
+
+use prost::bytes::{Buf, BufMut};
+use serde_json::Value;
+use tokio::sync::oneshot;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{
+    Code, Status,
+    body::Body,
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    codegen::{BoxFuture, Bytes, Service, http},
+    server::{Grpc, StreamingService},
+    transport::Server,
+};
+
+use crate::util::{error::InternalError, result::InternalResult};
+
+mod descriptor;
+
+pub use descriptor::{Descriptors, GrpcMethod};
+
+/// A status code like `not-found` or `5`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrpcCode(pub Code);
+
+/// Codes by the name they go by on the command line
+const CODES: [(&str, Code); 17] = [
+    ("ok", Code::Ok),
+    ("cancelled", Code::Cancelled),
+    ("unknown", Code::Unknown),
+    ("invalid-argument", Code::InvalidArgument),
+    ("deadline-exceeded", Code::DeadlineExceeded),
+    ("not-found", Code::NotFound),
+    ("already-exists", Code::AlreadyExists),
+    ("permission-denied", Code::PermissionDenied),
+    ("resource-exhausted", Code::ResourceExhausted),
+    ("failed-precondition", Code::FailedPrecondition),
+    ("aborted", Code::Aborted),
+    ("out-of-range", Code::OutOfRange),
+    ("unimplemented", Code::Unimplemented),
+    ("internal", Code::Internal),
+    ("unavailable", Code::Unavailable),
+    ("data-loss", Code::DataLoss),
+    ("unauthenticated", Code::Unauthenticated),
+];
+
+impl fmt::Display for GrpcCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = CODES
+            .iter()
+            .find(|(_, code)| *code == self.0)
+            .map_or("unknown", |(name, _)| name);
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for GrpcCode {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim().to_lowercase().replace('_', "-");
+        if let Ok(number) = input.parse::<i32>() {
+            return match CODES.iter().find(|(_, code)| *code as i32 == number) {
+                Some((_, code)) => Ok(Self(*code)),
+                None => Err(format!("`{}` is not a gRPC status, use 0 to 16", number)),
+            };
+        }
+        CODES
+            .iter()
+            .find(|(name, _)| *name == input)
+            .map(|(_, code)| Self(*code))
+            .ok_or_else(|| {
+                format!(
+                    "`{}` is not a gRPC status, use a number or a name like not-found",
+                    input
+                )
+            })
+    }
+}
+
+/// The canned answer of a method
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrpcReply {
+    /// A response message, kept as JSON for listing and encoded once when registered
+    Message { json: Value, encoded: Bytes },
+    /// A status other than OK, with a message
+    Status { code: GrpcCode, message: String },
+}
+
+impl fmt::Display for GrpcReply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrpcReply::Message { json, .. } => write!(f, "{}", json),
+            GrpcReply::Status { code, message } if message.is_empty() => write!(f, "{}", code),
+            GrpcReply::Status { code, message } => write!(f, "{} '{}'", code, message),
+        }
+    }
+}
+
+/// Methods of loaded descriptor sets and their canned replies, shared with the gRPC listener
+#[derive(Debug, Default)]
+pub struct GrpcMock {
+    descriptors: RwLock<Descriptors>,
+    replies: RwLock<BTreeMap<String, GrpcReply>>,
+    /// Calls by method path, including methods that aren't loaded
+    calls: Mutex<BTreeMap<String, u64>>,
+}
+
+impl GrpcMock {
+    /// Load a descriptor set, replacing the one loaded before. Replies of methods that are gone
+    /// are kept, they answer again once a set defining them is loaded.
+    pub fn load(&self, path: &Path) -> InternalResult<usize> {
+        let descriptors = Descriptors::load(path)?;
+        let methods = descriptors.methods().len();
+        *self
+            .descriptors
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = descriptors;
+        Ok(methods)
+    }
+
+    /// Answer `method` with a message given as JSON, or with a status
+    pub fn reply(
+        &self,
+        method: &str,
+        reply: Option<&Value>,
+        status: Option<(GrpcCode, String)>,
+    ) -> InternalResult<()> {
+        let path = method_path(method);
+        let descriptors = self
+            .descriptors
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let Some(grpc_method) = descriptors.method(&path) else {
+            return Err(InternalError::InvalidArgument {
+                argument: "method",
+                reason: format!(
+                    "`{}` is not a method of the loaded descriptor set, see `grpc list`",
+                    path
+                ),
+            });
+        };
+        let reply = match (status, reply) {
+            (Some((code, message)), _) if code.0 != Code::Ok => GrpcReply::Status { code, message },
+            (_, json) => {
+                let json = json
+                    .cloned()
+                    .unwrap_or_else(|| Value::Object(Default::default()));
+                let encoded = descriptors
+                    .encode(&grpc_method.output, &json)
+                    .map_err(|reason| InternalError::InvalidArgument {
+                        argument: "reply",
+                        reason,
+                    })?;
+                GrpcReply::Message {
+                    json,
+                    encoded: Bytes::from(encoded),
+                }
+            }
+        };
+        drop(descriptors);
+        self.replies
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(path, reply);
+        Ok(())
+    }
+
+    pub fn delete(&self, method: &str) -> Option<GrpcReply> {
+        self.replies
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&method_path(method))
+    }
+
+    /// Loaded methods with their reply and number of calls, e.g. to list them
+    pub fn methods(&self) -> Vec<(String, GrpcMethod, Option<GrpcReply>, u64)> {
+        let descriptors = self
+            .descriptors
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let replies = self.replies.read().unwrap_or_else(PoisonError::into_inner);
+        let calls = self.calls.lock().unwrap_or_else(PoisonError::into_inner);
+        descriptors
+            .methods()
+            .iter()
+            .map(|(path, method)| {
+                (
+                    path.clone(),
+                    method.clone(),
+                    replies.get(path).cloned(),
+                    calls.get(path).copied().unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    /// The answer to a call of the method at `path`. Loaded methods without a reply answer
+    /// with an empty message, all fields at their defaults.
+    fn answer(&self, path: &str) -> Result<Bytes, Status> {
+        *self
+            .calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(path.to_owned())
+            .or_default() += 1;
+        let loaded = self
+            .descriptors
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .method(path)
+            .is_some();
+        let answer = match self
+            .replies
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(path)
+        {
+            _ if !loaded => Err(Status::unimplemented(format!("{} is not loaded", path))),
+            Some(GrpcReply::Message { encoded, .. }) => Ok(encoded.clone()),
+            Some(GrpcReply::Status { code, message }) => Err(Status::new(code.0, message)),
+            None => Ok(Bytes::new()),
+        };
+        match &answer {
+            Ok(_) => log::info!("gRPC {} -> ok", path),
+            Err(status) => log::info!("gRPC {} -> {}", path, GrpcCode(status.code())),
+        }
+        answer
+    }
+}
+
+/// `helloworld.Greeter/SayHello` as the path it is called on
+fn method_path(method: &str) -> String {
+    format!("/{}", method.trim().trim_start_matches('/'))
+}
+
+/// A listener answering gRPC calls with the replies of a [`GrpcMock`], on a port of its own
+/// next to the HTTP server. Stops once dropped.
+#[derive(Debug)]
+pub struct GrpcListener {
+    pub port: u16,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GrpcListener {
+    /// Listen on `port` of `host`, e.g. the host the HTTP server is bound to
+    pub fn start(mock: Arc<GrpcMock>, host: &str, port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((host, port))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (shutdown, stopped) = oneshot::channel();
+        let thread = thread::Builder::new()
+            .name("grpc".to_owned())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    let incoming = match tokio::net::TcpListener::from_std(listener) {
+                        Ok(listener) => TcpListenerStream::new(listener),
+                        Err(e) => return log::error!("gRPC listener failed: {}", e),
+                    };
+                    let served = Server::builder()
+                        .serve_with_incoming_shutdown(GrpcService(mock), incoming, async {
+                            let _ = stopped.await;
+                        })
+                        .await;
+                    if let Err(e) = served {
+                        log::error!("gRPC listener failed: {}", e);
+                    }
+                });
+            })?;
+        Ok(Self {
+            port,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for GrpcListener {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Routes every call to its canned reply, whatever the service
+#[derive(Debug, Clone)]
+struct GrpcService(Arc<GrpcMock>);
+
+impl Service<http::Request<Body>> for GrpcService {
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let answer = Canned(self.0.answer(request.uri().path()));
+        // Every kind of method is served as a bidirectional stream, as they share the framing.
+        // Requests are not decoded, the reply doesn't depend on them.
+        Box::pin(async move { Ok(Grpc::new(RawCodec).streaming(answer, request).await) })
+    }
+}
+
+/// A reply of a single message or a status
+struct Canned(Result<Bytes, Status>);
+
+impl StreamingService<Bytes> for Canned {
+    type Response = Bytes;
+    type ResponseStream = tokio_stream::Once<Result<Bytes, Status>>;
+    type Future = std::future::Ready<Result<tonic::Response<Self::ResponseStream>, Status>>;
+
+    fn call(&mut self, _: tonic::Request<tonic::Streaming<Bytes>>) -> Self::Future {
+        let answer = std::mem::replace(&mut self.0, Err(Status::internal("answered twice")));
+        std::future::ready(
+            answer.map(|message| tonic::Response::new(tokio_stream::once(Ok(message)))),
+        )
+    }
+}
+
+/// Passes messages through as the bytes they are on the wire
+#[derive(Debug, Clone, Copy)]
+struct RawCodec;
+
+impl Codec for RawCodec {
+    type Encode = Bytes;
+    type Decode = Bytes;
+    type Encoder = RawCodec;
+    type Decoder = RawCodec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        RawCodec
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        RawCodec
+    }
+}
+
+impl Encoder for RawCodec {
+    type Item = Bytes;
+    type Error = Status;
+
+    fn encode(&mut self, item: Bytes, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        dst.put(item);
+        Ok(())
+    }
+}
+
+impl Decoder for RawCodec {
+    type Item = Bytes;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Bytes>, Status> {
+        Ok(Some(src.copy_to_bytes(src.remaining())))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_codes() {
+        assert_eq!("not-found".parse(), Ok(GrpcCode(Code::NotFound)));
+        assert_eq!("NOT_FOUND".parse(), Ok(GrpcCode(Code::NotFound)));
+        assert_eq!("14".parse(), Ok(GrpcCode(Code::Unavailable)));
+        assert_eq!(
+            GrpcCode(Code::DeadlineExceeded).to_string(),
+            "deadline-exceeded"
+        );
+        assert!("17".parse::<GrpcCode>().is_err());
+        assert!("gone".parse::<GrpcCode>().is_err());
+    }
+
+    #[test]
+    fn test_unloaded_methods_are_unimplemented() {
+        let mock = GrpcMock::default();
+        let status = mock.answer("/helloworld.Greeter/SayHello").unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+        assert!(
+            mock.reply("helloworld.Greeter/SayHello", None, None)
+                .is_err()
+        );
+        assert_eq!(
+            mock.calls.lock().unwrap()["/helloworld.Greeter/SayHello"],
+            1
+        );
+    }
+}
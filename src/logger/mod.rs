@@ -0,0 +1,428 @@
+mod otlp;
+mod sink;
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError, RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use chrono::{DateTime, Local};
+use log::{Level, LevelFilter, Log, kv::Key};
+use tokio::sync::mpsc::{UnboundedSender, error::SendError};
+
+use crate::{
+    server::{Redaction, redact_text},
+    util::{error::InternalError, result::InternalResult},
+};
+
+pub use sink::{LogSink, SinkTarget};
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub level: Level,
+    pub timestamp: DateTime<Local>,
+    /// Module path or custom target of the log call
+    pub target: String,
+    pub message: String,
+    /// Journal id of the request an access log entry belongs to
+    pub request_id: Option<u64>,
+}
+
+impl LogEntry {
+    /// e.g. `12:01:02 INFO  Application starting`
+    pub fn line(&self) -> String {
+        format!(
+            "{} {:<5} {}",
+            self.timestamp.format("%H:%M:%S"),
+            self.level,
+            self.message
+        )
+    }
+}
+
+/// Where log entries go once nothing receives them anymore, e.g. after the TUI exited while the
+/// server keeps serving
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum LogFallback {
+    #[default]
+    Stderr,
+    /// Appended to, created if missing
+    File(PathBuf),
+}
+
+impl LogFallback {
+    /// Open the destination, falling back to stderr if the file can't be opened
+    fn open(&self) -> Box<dyn Write + Send> {
+        let LogFallback::File(path) = self else {
+            return Box::new(io::stderr());
+        };
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("Can't log to {}: {}, logging to stderr", path.display(), e);
+                Box::new(io::stderr())
+            }
+        }
+    }
+}
+
+impl FromStr for LogFallback {
+    type Err = String;
+
+    /// `stderr` or a file path
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err("expected `stderr` or a file path".to_owned()),
+            "stderr" => Ok(LogFallback::Stderr),
+            path => Ok(LogFallback::File(PathBuf::from(path))),
+        }
+    }
+}
+
+impl fmt::Display for LogFallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFallback::Stderr => write!(f, "stderr"),
+            LogFallback::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+thread_local! {
+    /// Entries logged on this thread while capturing, see [`capture`]
+    static CAPTURED: RefCell<Option<Vec<LogEntry>>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, returning along with its result the entries it logged on this thread at the level of
+/// the TUI, e.g. the output of a command. They are sent to the TUI and the sinks as usual too.
+pub fn capture<R>(f: impl FnOnce() -> R) -> (R, Vec<LogEntry>) {
+    let outer = CAPTURED.replace(Some(Vec::new()));
+    let result = f();
+    let captured = CAPTURED.replace(outer).unwrap_or_default();
+    (result, captured)
+}
+
+/// Shared handle to the levels and sinks of the [`TuiLogger`], allowing them to change at runtime
+#[derive(Debug, Clone)]
+pub struct LoggerHandle {
+    /// Level of entries sent to the TUI
+    level: Arc<AtomicUsize>,
+    /// Most verbose level of the TUI and all sinks, entries above it are skipped right away
+    max_level: Arc<AtomicUsize>,
+    sinks: Arc<Mutex<Vec<LogSink>>>,
+    /// Applied to messages before they reach the TUI or any sink
+    redactions: Arc<RwLock<Vec<Redaction>>>,
+}
+
+impl LoggerHandle {
+    fn new(level: LevelFilter) -> Self {
+        let handle = Self {
+            level: Arc::new(AtomicUsize::new(level as usize)),
+            max_level: Arc::new(AtomicUsize::new(level as usize)),
+            sinks: Arc::default(),
+            redactions: Arc::default(),
+        };
+        handle.update_max_level();
+        handle
+    }
+
+    /// Level of entries shown in the TUI
+    pub fn level(&self) -> LevelFilter {
+        load_level(&self.level)
+    }
+
+    pub fn set_level(&self, level: LevelFilter) {
+        self.level.store(level as usize, Ordering::Relaxed);
+        self.update_max_level();
+    }
+
+    /// Targets and levels of the sinks besides the TUI
+    pub fn sinks(&self) -> Vec<(SinkTarget, LevelFilter)> {
+        self.lock_sinks()
+            .iter()
+            .map(|sink| (sink.target.clone(), sink.level))
+            .collect()
+    }
+
+    /// Add a sink, replacing the one with the same target. Returns whether it replaced one.
+    pub fn add_sink(&self, sink: LogSink) -> bool {
+        let mut sinks = self.lock_sinks();
+        let replaced = match sinks.iter_mut().find(|s| s.target == sink.target) {
+            Some(existing) => {
+                *existing = sink;
+                true
+            }
+            None => {
+                sinks.push(sink);
+                false
+            }
+        };
+        drop(sinks);
+        self.update_max_level();
+        replaced
+    }
+
+    /// Returns whether there was a sink with the target
+    pub fn remove_sink(&self, target: &SinkTarget) -> bool {
+        let mut sinks = self.lock_sinks();
+        let len = sinks.len();
+        sinks.retain(|sink| sink.target != *target);
+        let removed = sinks.len() < len;
+        drop(sinks);
+        self.update_max_level();
+        removed
+    }
+
+    /// Redact matches of the regex rules among `rules` in every message from now on
+    pub fn set_redactions(&self, rules: Vec<Redaction>) {
+        *self
+            .redactions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = rules;
+    }
+
+    fn redact(&self, message: String) -> String {
+        let rules = self
+            .redactions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        match redact_text(&rules, &message) {
+            Cow::Owned(redacted) => redacted,
+            Cow::Borrowed(_) => message,
+        }
+    }
+
+    /// Sinks only hold their destinations, so a poisoned lock can safely be ignored
+    fn lock_sinks(&self) -> MutexGuard<'_, Vec<LogSink>> {
+        self.sinks.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update_max_level(&self) {
+        let max_level = self
+            .lock_sinks()
+            .iter()
+            .map(|sink| sink.level)
+            .fold(self.level(), |a, b| a.max(b));
+        self.max_level.store(max_level as usize, Ordering::Relaxed);
+        log::set_max_level(max_level);
+    }
+}
+
+fn load_level(level: &AtomicUsize) -> LevelFilter {
+    let level = level.load(Ordering::Relaxed);
+    LevelFilter::iter()
+        .find(|l| *l as usize == level)
+        .unwrap_or(LevelFilter::Off)
+}
+
+/// Dispatches log entries to the TUI and the sinks added at runtime, each filtered by its own
+/// level
+pub struct TuiLogger {
+    sender: UnboundedSender<LogEntry>,
+    handle: LoggerHandle,
+    fallback: LogFallback,
+    /// Opened once the channel rejects the first entry
+    fallback_writer: Mutex<Option<Box<dyn Write + Send>>>,
+}
+
+impl TuiLogger {
+    pub fn init(
+        sender: UnboundedSender<LogEntry>,
+        level: Level,
+        fallback: LogFallback,
+    ) -> InternalResult<LoggerHandle> {
+        let handle = LoggerHandle::new(level.to_level_filter());
+        let logger = Box::new(TuiLogger {
+            sender,
+            handle: handle.clone(),
+            fallback,
+            fallback_writer: Mutex::new(None),
+        });
+        log::set_boxed_logger(logger).map_err(|_| InternalError::LoggerInitError)?;
+        Ok(handle)
+    }
+
+    /// Write an entry nothing received to the fallback, opening it on the first one
+    fn write_fallback(&self, entry: &LogEntry) {
+        // The writer holds no state besides the destination, so a poisoned lock can safely be
+        // ignored
+        let mut writer = self
+            .fallback_writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let writer = writer.get_or_insert_with(|| {
+            let mut writer = self.fallback.open();
+            let _ = writeln!(
+                writer,
+                "{} {:<5} Log channel closed, logging to {}",
+                Local::now().format("%H:%M:%S"),
+                Level::Warn,
+                self.fallback
+            );
+            writer
+        });
+        let _ = writeln!(writer, "{}", entry.line());
+        let _ = writer.flush();
+    }
+}
+
+impl Log for TuiLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= load_level(&self.handle.max_level)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let entry = LogEntry {
+            level: record.level(),
+            timestamp: Local::now(),
+            target: record.target().to_owned(),
+            message: self.handle.redact(record.args().to_string()),
+            request_id: record
+                .key_values()
+                .get(Key::from_str("request_id"))
+                .and_then(|v| v.to_u64()),
+        };
+        for sink in self.handle.lock_sinks().iter_mut() {
+            sink.write(&entry);
+        }
+        if entry.level > self.handle.level() {
+            return;
+        }
+        CAPTURED.with_borrow_mut(|captured| {
+            if let Some(captured) = captured {
+                captured.push(entry.clone());
+            }
+        });
+        if let Err(SendError(entry)) = self.sender.send(entry) {
+            self.write_fallback(&entry);
+        }
+    }
+
+    fn flush(&self) {
+        for sink in self.handle.lock_sinks().iter_mut() {
+            sink.flush();
+        }
+        if let Some(writer) = self
+            .fallback_writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            let _ = writer.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::thread;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn test_fallback_after_channel_closed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fallback.log");
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let logger = TuiLogger {
+            sender,
+            handle: LoggerHandle::new(LevelFilter::Info),
+            fallback: format!("{}", path.display()).parse().unwrap(),
+            fallback_writer: Mutex::new(None),
+        };
+        let log = |message: &str| {
+            logger.log(
+                &log::Record::builder()
+                    .level(Level::Info)
+                    .args(format_args!("{}", message))
+                    .build(),
+            )
+        };
+        log("received");
+        assert!(!path.exists());
+
+        drop(receiver);
+        log("dropped");
+        log("dropped too");
+        let written = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with(&format!(
+            "Log channel closed, logging to {}",
+            path.display()
+        )));
+        assert!(lines[1].ends_with("INFO  dropped"));
+        assert!(lines[2].ends_with("INFO  dropped too"));
+    }
+
+    #[test]
+    fn test_redact_before_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fallback.log");
+        let (sender, receiver) = mpsc::unbounded_channel();
+        drop(receiver);
+        let logger = TuiLogger {
+            sender,
+            handle: LoggerHandle::new(LevelFilter::Info),
+            fallback: format!("{}", path.display()).parse().unwrap(),
+            fallback_writer: Mutex::new(None),
+        };
+        logger
+            .handle
+            .set_redactions(vec![r"regex:token=\w+".parse().unwrap()]);
+        logger.log(
+            &log::Record::builder()
+                .level(Level::Info)
+                .args(format_args!("GET /?token=abc"))
+                .build(),
+        );
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.ends_with("INFO  GET /?[redacted]\n"));
+    }
+
+    #[test]
+    fn test_capture() {
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        let logger = TuiLogger {
+            sender,
+            handle: LoggerHandle::new(LevelFilter::Info),
+            fallback: LogFallback::Stderr,
+            fallback_writer: Mutex::new(None),
+        };
+        let log = |level: Level, message: &str| {
+            logger.log(
+                &log::Record::builder()
+                    .level(level)
+                    .args(format_args!("{}", message))
+                    .build(),
+            )
+        };
+        log(Level::Info, "before");
+        let (result, captured) = capture(|| {
+            log(Level::Info, "during");
+            log(Level::Debug, "too verbose");
+            thread::scope(|scope| scope.spawn(|| log(Level::Info, "elsewhere")).join()).unwrap();
+            42
+        });
+        log(Level::Info, "after");
+
+        assert_eq!(result, 42);
+        let messages: Vec<&str> = captured.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["during"]);
+    }
+}
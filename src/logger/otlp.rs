@@ -0,0 +1,148 @@
+use std::{cell::Cell, io, thread};
+
+use log::Level;
+use serde_json::{Value, json};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::logger::LogEntry;
+
+/// Most entries sent in one request, more wait for the next
+const MAX_BATCH: usize = 100;
+
+thread_local! {
+    /// Set on exporter threads, whose own log entries, e.g. of the HTTP client, aren't exported
+    /// to not feed on themselves
+    static EXPORTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sends log entries to an OpenTelemetry collector as OTLP/HTTP JSON, batched on a thread of its
+/// own. The thread ends once the exporter is dropped.
+pub struct OtlpExporter {
+    sender: UnboundedSender<LogEntry>,
+}
+
+impl OtlpExporter {
+    /// Start exporting to the collector at `url`, e.g. `http://localhost:4318`
+    pub fn start(url: &str) -> io::Result<Self> {
+        let logs_url = format!("{}/v1/logs", url);
+        let (sender, mut receiver) = mpsc::unbounded_channel::<LogEntry>();
+        thread::Builder::new()
+            .name("otlp-exporter".to_owned())
+            .spawn(move || {
+                EXPORTING.set(true);
+                actix_web::rt::System::new().block_on(async move {
+                    let client = awc::Client::default();
+                    let mut failing = false;
+                    while let Some(entry) = receiver.recv().await {
+                        let mut batch = vec![entry];
+                        while batch.len() < MAX_BATCH
+                            && let Ok(entry) = receiver.try_recv()
+                        {
+                            batch.push(entry);
+                        }
+                        let error = match client.post(&logs_url).send_json(&payload(&batch)).await {
+                            Ok(response) if response.status().is_success() => None,
+                            Ok(response) => Some(format!("status {}", response.status())),
+                            Err(e) => Some(e.to_string()),
+                        };
+                        // Only changes are logged, a collector that is down would flood the logs
+                        match &error {
+                            Some(error) if !failing => {
+                                log::warn!("Exporting logs to {} failed: {}", logs_url, error)
+                            }
+                            None if failing => log::info!("Exporting logs to {} again", logs_url),
+                            _ => {}
+                        }
+                        failing = error.is_some();
+                    }
+                })
+            })?;
+        Ok(Self { sender })
+    }
+
+    pub fn export(&self, entry: &LogEntry) {
+        if !EXPORTING.get() {
+            let _ = self.sender.send(entry.clone());
+        }
+    }
+}
+
+/// Body of an OTLP logs export request
+fn payload(entries: &[LogEntry]) -> Value {
+    let records: Vec<Value> = entries
+        .iter()
+        .map(|entry| {
+            let mut attributes = vec![attribute("target", json!({"stringValue": entry.target}))];
+            if let Some(request_id) = entry.request_id {
+                // 64-bit integers are strings in OTLP JSON
+                attributes.push(attribute(
+                    "request_id",
+                    json!({"intValue": request_id.to_string()}),
+                ));
+            }
+            json!({
+                "timeUnixNano": entry
+                    .timestamp
+                    .timestamp_nanos_opt()
+                    .unwrap_or_default()
+                    .to_string(),
+                "severityNumber": severity_number(entry.level),
+                "severityText": entry.level.as_str(),
+                "body": {"stringValue": entry.message},
+                "attributes": attributes,
+            })
+        })
+        .collect();
+    json!({
+        "resourceLogs": [{
+            "resource": {
+                "attributes": [attribute("service.name", json!({"stringValue": "adaptive_rest"}))],
+            },
+            "scopeLogs": [{
+                "scope": {"name": "adaptive_rest"},
+                "logRecords": records,
+            }],
+        }],
+    })
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({"key": key, "value": value})
+}
+
+/// The lowest OpenTelemetry severity number of the level's range
+fn severity_number(level: Level) -> u8 {
+    match level {
+        Level::Trace => 1,
+        Level::Debug => 5,
+        Level::Info => 9,
+        Level::Warn => 13,
+        Level::Error => 17,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use chrono::Local;
+
+    use super::*;
+
+    #[test]
+    fn test_payload() {
+        let entry = LogEntry {
+            level: Level::Warn,
+            timestamp: Local::now(),
+            target: "access".to_owned(),
+            message: "GET /users 404".to_owned(),
+            request_id: Some(3),
+        };
+        let payload = payload(&[entry]);
+        let record = &payload["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["severityNumber"], 13);
+        assert_eq!(record["severityText"], "WARN");
+        assert_eq!(record["body"]["stringValue"], "GET /users 404");
+        assert_eq!(record["attributes"][1]["value"]["intValue"], "3");
+        assert!(record["timeUnixNano"].as_str().unwrap().len() > 18);
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use log::LevelFilter;
+
+use crate::{
+    logger::{LogEntry, otlp::OtlpExporter},
+    util::{error::InternalError, result::InternalResult},
+};
+
+/// Where a sink sends log entries
+#[derive(Debug, Clone, PartialEq)]
+pub enum SinkTarget {
+    /// Appended to, one line per entry
+    File(PathBuf),
+    /// One JSON object per line, e.g. for a log shipper reading a headless instance
+    StdoutJson,
+    /// An OpenTelemetry collector receiving OTLP over HTTP, e.g. `http://localhost:4318`
+    Otlp(String),
+}
+
+/// `stdout`, `otlp:` followed by the URL of a collector, or a file path
+impl FromStr for SinkTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(url) = s.strip_prefix("otlp:") {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(format!(
+                    "expected the URL of an OTLP collector, e.g. `otlp:http://localhost:4318`, got \
+                     `{}`",
+                    url
+                ));
+            }
+            return Ok(SinkTarget::Otlp(url.trim_end_matches('/').to_owned()));
+        }
+        match s {
+            "" => Err("expected `stdout`, `otlp:URL` or a file path".to_owned()),
+            "stdout" => Ok(SinkTarget::StdoutJson),
+            path => Ok(SinkTarget::File(PathBuf::from(path))),
+        }
+    }
+}
+
+impl fmt::Display for SinkTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkTarget::File(path) => write!(f, "{}", path.display()),
+            SinkTarget::StdoutJson => write!(f, "stdout"),
+            SinkTarget::Otlp(url) => write!(f, "otlp:{}", url),
+        }
+    }
+}
+
+enum Output {
+    Writer(Box<dyn Write + Send>),
+    Otlp(OtlpExporter),
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::Writer(_) => write!(f, "Writer"),
+            Output::Otlp(_) => write!(f, "Otlp"),
+        }
+    }
+}
+
+/// A destination of log entries besides the TUI, with a level of its own
+#[derive(Debug)]
+pub struct LogSink {
+    pub target: SinkTarget,
+    pub level: LevelFilter,
+    output: Output,
+}
+
+impl LogSink {
+    /// Open the file or start exporting to the collector
+    pub fn open(target: SinkTarget, level: LevelFilter) -> InternalResult<Self> {
+        let output = match &target {
+            SinkTarget::File(path) => Output::Writer(Box::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|source| InternalError::FileIo {
+                        path: path.clone(),
+                        source,
+                    })?,
+            )),
+            SinkTarget::StdoutJson => Output::Writer(Box::new(io::stdout())),
+            SinkTarget::Otlp(url) => Output::Otlp(OtlpExporter::start(url)?),
+        };
+        Ok(Self {
+            target,
+            level,
+            output,
+        })
+    }
+
+    pub fn write(&mut self, entry: &LogEntry) {
+        if entry.level > self.level {
+            return;
+        }
+        match (&mut self.output, &self.target) {
+            (Output::Writer(writer), SinkTarget::StdoutJson) => {
+                let _ = writeln!(writer, "{}", json_line(entry));
+                let _ = writer.flush();
+            }
+            (Output::Writer(writer), _) => {
+                let _ = writeln!(writer, "{}", entry.line());
+                let _ = writer.flush();
+            }
+            (Output::Otlp(exporter), _) => exporter.export(entry),
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let Output::Writer(writer) = &mut self.output {
+            let _ = writer.flush();
+        }
+    }
+}
+
+/// e.g. `{"timestamp":"2024-05-01T12:01:02.345+02:00","level":"INFO",...}`
+fn json_line(entry: &LogEntry) -> String {
+    let mut json = serde_json::json!({
+        "timestamp": entry.timestamp.to_rfc3339(),
+        "level": entry.level.as_str(),
+        "target": entry.target,
+        "message": entry.message,
+    });
+    if let Some(request_id) = entry.request_id {
+        json["request_id"] = request_id.into();
+    }
+    json.to_string()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use chrono::Local;
+    use log::Level;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!("stdout".parse(), Ok(SinkTarget::StdoutJson));
+        assert_eq!(
+            "otlp:http://localhost:4318/".parse(),
+            Ok(SinkTarget::Otlp("http://localhost:4318".to_owned()))
+        );
+        assert_eq!(
+            "mock.log".parse(),
+            Ok(SinkTarget::File(PathBuf::from("mock.log")))
+        );
+        assert!("otlp:localhost:4318".parse::<SinkTarget>().is_err());
+        assert_eq!(
+            SinkTarget::Otlp("http://localhost:4318".to_owned()).to_string(),
+            "otlp:http://localhost:4318"
+        );
+    }
+
+    #[test]
+    fn test_file_sink_level() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sink.log");
+        let mut sink = LogSink::open(SinkTarget::File(path.clone()), LevelFilter::Warn).unwrap();
+        let entry = |level: Level, message: &str| LogEntry {
+            level,
+            timestamp: Local::now(),
+            target: "adaptable_rest".to_owned(),
+            message: message.to_owned(),
+            request_id: Some(7),
+        };
+        sink.write(&entry(Level::Info, "skipped"));
+        sink.write(&entry(Level::Warn, "kept"));
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written.lines().count(), 1);
+        assert!(written.ends_with("WARN  kept\n"));
+        let json: serde_json::Value =
+            serde_json::from_str(&json_line(&entry(Level::Error, "failed"))).unwrap();
+        assert_eq!(json["level"], "ERROR");
+        assert_eq!(json["message"], "failed");
+        assert_eq!(json["request_id"], 7);
+    }
+}
@@ -1,31 +1,103 @@
 #![deny(warnings)]
 #![deny(clippy::redundant_clone)]
-use std::{io, sync::Arc, thread};
+use std::{
+    io,
+    path::Path,
+    process::ExitCode,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
 use tokio::sync::mpsc;
 
 use crate::{
-    app::App,
-    server::{ServerState, run_server},
+    app::{App, ExitStatus},
+    command::Args,
+    config::{STARTUP_FILE, StartupConfig},
+    server::{ServerState, ServerSupervisor, TlsListener},
 };
 
 mod app;
 mod command;
+mod config;
+mod formats;
+mod grpc;
 mod logger;
+mod preset;
 mod server;
 mod util;
+mod watch;
+
+/// Address served on unless configured otherwise
+const DEFAULT_BIND: &str = "127.0.0.1:3000";
 
-fn main() -> io::Result<()> {
+fn main() -> io::Result<ExitCode> {
+    let args = Args::load();
+    // The TUI isn't up yet to report a broken configuration
+    let (path, mut config) = match StartupConfig::load(args.config.as_deref()) {
+        Ok(Some((path, config))) => (Some(path), config),
+        Ok(None) => (None, StartupConfig::default()),
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(ExitStatus::ConfigError.into());
+        }
+    };
+    config.bind = args.bind.or(config.bind);
+    config.log_level = args.log_level.or(config.log_level);
+    let log_fallback = args.log_fallback.or(config.log_fallback.take());
+    let control_socket = args.control_socket.or(config.control_socket.take());
     let (log_tx, log_rx) = mpsc::unbounded_channel();
-    logger::TuiLogger::init(log_tx, log::Level::Info)?;
+    let logger =
+        logger::TuiLogger::init(log_tx, log::Level::Info, log_fallback.unwrap_or_default())?;
     log::info!("Application starting");
     let server_state = Arc::new(ServerState::new());
-    let server_state_clone = server_state.clone();
-    thread::spawn(move || {
-        let rt = tokio::runtime::Runtime::new()?;
-        rt.block_on(run_server(server_state_clone, "127.0.0.1:3000"))
-    });
+    *server_state.tls_listener_mut() = config.tls.as_ref().map(TlsListener::from);
+    *server_state.default_headers_mut() = std::mem::take(&mut config.default_headers);
+    let bind = config.bind.as_deref().unwrap_or(DEFAULT_BIND);
+    let supervisor = ServerSupervisor::start(server_state.clone(), bind)?;
+    let mut app = App::new(log_rx, logger, server_state, supervisor);
+    if let Some(path) = &path {
+        log::info!("Loaded {}", path.display());
+    }
+    app.apply_startup_config(path.as_deref().unwrap_or(Path::new(STARTUP_FILE)), config);
+    app.load_project(&std::env::current_dir()?);
+    if let Some(path) = &control_socket {
+        app.open_control_socket(path);
+    }
+    if args.headless {
+        let exit = Arc::new(AtomicBool::new(false));
+        exit_on_signal(exit.clone())?;
+        return Ok(app.run_headless(&exit, args.stdin, &args.on_exit).into());
+    }
     let mut terminal = ratatui::init();
-    let app_result = App::new(log_rx, server_state).run(&mut terminal);
+    let app_result = app.run(&mut terminal);
     ratatui::restore();
-    app_result
+    app_result.map(|()| ExitCode::SUCCESS)
+}
+
+/// Set `exit` once the process is interrupted, or terminated on unix
+fn exit_on_signal(exit: Arc<AtomicBool>) -> io::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    thread::spawn(move || {
+        runtime.block_on(async {
+            #[cfg(unix)]
+            if let Ok(mut terminate) =
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            let _ = tokio::signal::ctrl_c().await;
+        });
+        exit.store(true, Ordering::Relaxed);
+    });
+    Ok(())
 }
@@ -0,0 +1,152 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::EndpointFile,
+    server::is_param,
+    util::{error::InternalError, result::InternalResult},
+};
+
+/// Presets shipped with the binary, overridden by user presets of the same name
+const BUILTIN: &[(&str, &str)] = &[
+    ("health", include_str!("../presets/health.yaml")),
+    ("rest-crud", include_str!("../presets/rest-crud.yaml")),
+];
+
+/// Where a preset is defined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresetSource {
+    Builtin(&'static str),
+    File(PathBuf),
+}
+
+/// Directory holding user presets, one endpoint file per preset named `<preset>.yaml`:
+/// `$XDG_CONFIG_HOME/adaptive_rest/presets`, falling back to `~/.config/adaptive_rest/presets`
+pub fn preset_dir() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("adaptive_rest").join("presets"))
+}
+
+/// All available presets by name, user presets taking precedence over builtin ones
+pub fn presets(dir: Option<&Path>) -> BTreeMap<String, PresetSource> {
+    let mut presets: BTreeMap<String, PresetSource> = BUILTIN
+        .iter()
+        .map(|(name, content)| (name.to_string(), PresetSource::Builtin(content)))
+        .collect();
+    let files = dir
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten();
+    for path in files.filter_map(|entry| entry.ok().map(|e| e.path())) {
+        if path.extension().is_some_and(|ext| ext == "yaml")
+            && let Some(name) = path.file_stem().and_then(|s| s.to_str())
+        {
+            presets.insert(name.to_owned(), PresetSource::File(path.clone()));
+        }
+    }
+    presets
+}
+
+/// Load the preset `name` with its placeholders replaced for the collection at `base`
+pub fn expand(name: &str, base: &str, dir: Option<&Path>) -> InternalResult<EndpointFile> {
+    let mut presets = presets(dir);
+    let mut file = match presets.remove(name) {
+        Some(PresetSource::Builtin(content)) => {
+            EndpointFile::parse_named(content, Path::new(&format!("builtin preset {}", name)))?
+        }
+        Some(PresetSource::File(path)) => EndpointFile::load(&path)?,
+        None => {
+            return Err(InternalError::PresetNotFound {
+                name: name.to_owned(),
+                available: presets.into_keys().collect(),
+            });
+        }
+    };
+    let base = format!("/{}", base.trim_matches('/'));
+    let resource = singular(&base);
+    let substitute = |s: &str| {
+        s.replace("{{base}}", base.trim_end_matches('/'))
+            .replace("{{name}}", &resource)
+    };
+    for def in &mut file.endpoints {
+        def.path = substitute(&def.path);
+        def.body = substitute(&def.body);
+    }
+    Ok(file)
+}
+
+/// Singular name of the resource in a collection path, e.g. `widget` for `/api/widgets`
+fn singular(base: &str) -> String {
+    let collection = base
+        .rsplit('/')
+        .find(|segment| !segment.is_empty() && !is_param(segment))
+        .unwrap_or("item");
+    if let Some(stem) = collection.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if collection.ends_with("ss") || collection.ends_with("us") {
+        collection.to_owned()
+    } else {
+        collection
+            .strip_suffix('s')
+            .unwrap_or(collection)
+            .to_owned()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::http::Method;
+
+    use super::*;
+
+    #[test]
+    fn test_expand_builtin() {
+        let file = expand("rest-crud", "api/widgets/", None).unwrap();
+        let entries = file.entries();
+
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[1].0, Method::GET);
+        assert_eq!(entries[1].1, "/api/widgets/{id}");
+        assert_eq!(
+            entries[1].2.body.as_ref(),
+            br#"{"id": 1, "name": "widget 1"}"#
+        );
+        assert_eq!(
+            expand("health", "/", None).unwrap().entries()[0].1,
+            "/health"
+        );
+    }
+
+    #[test]
+    fn test_user_presets_override_builtin() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("rest-crud.yaml"),
+            "endpoints:\n- method: GET\n  path: '{{base}}/custom'\n",
+        )
+        .unwrap();
+
+        let file = expand("rest-crud", "/things", Some(dir.path())).unwrap();
+        assert_eq!(file.entries()[0].1, "/things/custom");
+
+        let err = expand("missing", "/", Some(dir.path())).unwrap_err();
+        assert!(
+            matches!(err, InternalError::PresetNotFound { available, .. } if available.len() == 2)
+        );
+    }
+
+    #[test]
+    fn test_singular() {
+        assert_eq!(singular("/api/widgets"), "widget");
+        assert_eq!(singular("/users/{id}/posts"), "post");
+        assert_eq!(singular("/categories"), "category");
+        assert_eq!(singular("/status"), "status");
+        assert_eq!(singular("/"), "item");
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use actix_web::http::Method;
+
+use crate::server::{EndpointStore, RequestJournal};
+
+/// A position varying across at least this many otherwise identical paths becomes a parameter
+const MIN_VARIANTS: usize = 3;
+/// Number of example paths kept per suggestion
+const MAX_EXAMPLES: usize = 3;
+
+/// A proposed endpoint covering requests that no endpoint answered
+#[derive(Debug, Clone, PartialEq)]
+pub struct StubSuggestion {
+    pub method: Method,
+    /// Path template, varying segments replaced by `{param}` placeholders
+    pub path: String,
+    /// Number of unmatched requests the suggestion would have answered
+    pub hits: usize,
+    pub examples: Vec<String>,
+}
+
+/// Path shape with `None` for parameter segments
+type Shape = Vec<Option<String>>;
+
+#[derive(Debug, Default)]
+struct Group {
+    hits: usize,
+    examples: BTreeSet<String>,
+}
+
+/// Propose endpoints for the unmatched requests in the journal that are still unmatched by `store`.
+/// Segments that look like ids (numbers, UUIDs, long hex strings) become parameters, as do
+/// positions that vary across several otherwise identical paths. Most requested first.
+pub fn suggest_stubs(journal: &RequestJournal, store: &EndpointStore) -> Vec<StubSuggestion> {
+    let mut groups: BTreeMap<(String, Shape), Group> = BTreeMap::new();
+    for record in journal.records() {
+        if record.matched() || store.get(&record.method, &record.path).is_some() {
+            continue;
+        }
+        let shape = record
+            .path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|segment| (!looks_like_id(segment)).then(|| segment.to_owned()))
+            .collect::<Shape>();
+        let group = groups
+            .entry((record.method.to_string(), shape))
+            .or_default();
+        group.hits += 1;
+        group.examples.insert(record.path.clone());
+    }
+
+    let max_len = groups
+        .keys()
+        .map(|(_, shape)| shape.len())
+        .max()
+        .unwrap_or(0);
+    for position in 0..max_len {
+        groups = merge_varying(groups, position);
+    }
+
+    let mut suggestions: Vec<StubSuggestion> = groups
+        .into_iter()
+        .filter_map(|((method, shape), group)| {
+            Some(StubSuggestion {
+                method: method.parse().ok()?,
+                path: template(&shape),
+                hits: group.hits,
+                examples: group.examples.into_iter().take(MAX_EXAMPLES).collect(),
+            })
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.hits.cmp(&a.hits).then_with(|| a.path.cmp(&b.path)));
+    suggestions
+}
+
+/// Replace the segment at `position` with a parameter wherever enough groups differ only there
+fn merge_varying(
+    groups: BTreeMap<(String, Shape), Group>,
+    position: usize,
+) -> BTreeMap<(String, Shape), Group> {
+    let wildcard = |(method, shape): &(String, Shape)| {
+        let mut shape = shape.clone();
+        if let Some(segment) = shape.get_mut(position) {
+            *segment = None;
+        }
+        (method.clone(), shape)
+    };
+    let mut variants: BTreeMap<(String, Shape), usize> = BTreeMap::new();
+    for key in groups.keys() {
+        *variants.entry(wildcard(key)).or_default() += 1;
+    }
+    let mut merged: BTreeMap<(String, Shape), Group> = BTreeMap::new();
+    for (key, group) in groups {
+        let wildcarded = wildcard(&key);
+        let key = if variants.get(&wildcarded).copied().unwrap_or(0) >= MIN_VARIANTS {
+            wildcarded
+        } else {
+            key
+        };
+        let target = merged.entry(key).or_default();
+        target.hits += group.hits;
+        target.examples.extend(group.examples);
+    }
+    merged
+}
+
+fn template(shape: &Shape) -> String {
+    let mut params = 0;
+    let segments: Vec<String> = shape
+        .iter()
+        .map(|segment| match segment {
+            Some(segment) => segment.clone(),
+            None => {
+                params += 1;
+                match params {
+                    1 => "{id}".to_owned(),
+                    n => format!("{{id{}}}", n),
+                }
+            }
+        })
+        .collect();
+    format!("/{}", segments.join("/"))
+}
+
+/// Path template for a concrete path, with id-like segments replaced by parameters
+pub fn path_template(path: &str) -> String {
+    let shape: Shape = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|segment| (!looks_like_id(segment)).then(|| segment.to_owned()))
+        .collect();
+    template(&shape)
+}
+
+/// Heuristic for segments that identify a resource rather than name a collection
+pub fn looks_like_id(segment: &str) -> bool {
+    let is_number = segment.chars().all(|c| c.is_ascii_digit());
+    let is_uuid = segment.len() == 36
+        && segment.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        });
+    let is_hex = segment.len() >= 16 && segment.chars().all(|c| c.is_ascii_hexdigit());
+    !segment.is_empty() && (is_number || is_uuid || is_hex)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{http::StatusCode, web::Bytes};
+    use chrono::Local;
+
+    use super::*;
+    use crate::server::RequestRecord;
+
+    fn journal(requests: &[(Method, &str)]) -> RequestJournal {
+        let mut journal = RequestJournal::default();
+        for (method, path) in requests {
+            journal.record(RequestRecord {
+                id: 0,
+                timestamp: Local::now(),
+                method: method.clone(),
+                path: (*path).to_owned(),
+                query: String::new(),
+                headers: Vec::new(),
+                body: Bytes::new(),
+                body_size: 0,
+                encoded_size: None,
+                status: StatusCode::NOT_FOUND,
+                stub: None,
+                response: None,
+                endpoint: None,
+                elapsed: Duration::ZERO,
+                fault: None,
+                failures: Vec::new(),
+                violations: Vec::new(),
+            });
+        }
+        journal
+    }
+
+    #[test]
+    fn test_id_segments_become_params() {
+        let journal = journal(&[
+            (Method::GET, "/users/1"),
+            (Method::GET, "/users/2"),
+            (
+                Method::GET,
+                "/users/3f2504e0-4f89-11d3-9a0c-0305e82c3301/posts",
+            ),
+            (Method::POST, "/users"),
+        ]);
+        let suggestions = suggest_stubs(&journal, &EndpointStore::default());
+
+        let paths: Vec<_> = suggestions
+            .iter()
+            .map(|s| (s.method.as_str(), s.path.as_str(), s.hits))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                ("GET", "/users/{id}", 2),
+                ("POST", "/users", 1),
+                ("GET", "/users/{id}/posts", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_varying_segments_become_params() {
+        let journal = journal(&[
+            (Method::GET, "/repos/alice/settings"),
+            (Method::GET, "/repos/bob/settings"),
+            (Method::GET, "/repos/carol/settings"),
+            (Method::GET, "/about"),
+        ]);
+        let suggestions = suggest_stubs(&journal, &EndpointStore::default());
+
+        assert_eq!(suggestions[0].path, "/repos/{id}/settings");
+        assert_eq!(suggestions[0].hits, 3);
+        assert_eq!(suggestions[1].path, "/about");
+    }
+
+    #[test]
+    fn test_path_template() {
+        assert_eq!(
+            path_template("/users/42/posts/7"),
+            "/users/{id}/posts/{id2}"
+        );
+        assert_eq!(path_template("/users/me"), "/users/me");
+        assert_eq!(path_template("/"), "/");
+    }
+
+    #[test]
+    fn test_skips_requests_matched_since() {
+        let journal = journal(&[(Method::GET, "/users/1")]);
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/users/{id}", Bytes::from("{}"));
+
+        assert!(suggest_stubs(&journal, &store).is_empty());
+    }
+}
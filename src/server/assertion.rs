@@ -0,0 +1,120 @@
+use std::{fmt, str::FromStr};
+
+use actix_web::http::header::HeaderMap;
+
+/// An expectation on the requests an endpoint receives, e.g. `header:Authorization`.
+/// Failing requests are still answered, but recorded as failures.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assertion {
+    /// The request has this header
+    Header(String),
+    /// The body is a JSON object with this field, nested fields separated by dots
+    Field(String),
+    /// The body contains this text
+    Contains(String),
+}
+
+impl Assertion {
+    /// Describe how a request with `headers` and `body` violates the assertion, if it does
+    pub fn check(&self, headers: &HeaderMap, body: &[u8]) -> Option<String> {
+        match self {
+            Assertion::Header(name) if !headers.contains_key(name.as_str()) => {
+                Some(format!("header `{}` is missing", name))
+            }
+            Assertion::Field(field) => {
+                let Ok(json) = serde_json::from_slice::<serde_json::Value>(body) else {
+                    return Some(format!("body is not JSON, expected field `{}`", field));
+                };
+                field
+                    .split('.')
+                    .try_fold(&json, |value, key| value.get(key))
+                    .is_none()
+                    .then(|| format!("body field `{}` is missing", field))
+            }
+            Assertion::Contains(text) if !String::from_utf8_lossy(body).contains(text.as_str()) => {
+                Some(format!("body does not contain `{}`", text))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assertion::Header(name) => write!(f, "header:{}", name),
+            Assertion::Field(field) => write!(f, "field:{}", field),
+            Assertion::Contains(text) => write!(f, "contains:{}", text),
+        }
+    }
+}
+
+impl FromStr for Assertion {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = input
+            .split_once(':')
+            .filter(|(_, value)| !value.is_empty())
+            .ok_or_else(|| format!("`{}` is not of the form `header:Authorization`", input))?;
+        let value = value.to_owned();
+        match kind {
+            "header" => Ok(Assertion::Header(value)),
+            "field" => Ok(Assertion::Field(value)),
+            "contains" => Ok(Assertion::Contains(value)),
+            _ => Err(format!(
+                "unknown assertion `{}`, use header, field or contains",
+                kind
+            )),
+        }
+    }
+}
+
+/// Descriptions of every assertion a request violates
+pub fn failed_assertions(
+    assertions: &[Assertion],
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Vec<String> {
+    assertions
+        .iter()
+        .filter_map(|assertion| assertion.check(headers, body))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::http::header::{AUTHORIZATION, HeaderValue};
+
+    use super::*;
+
+    #[test]
+    fn test_check_assertions() {
+        let assertions: Vec<Assertion> = ["header:Authorization", "field:meta.trace_id"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(assertions[1].to_string(), "field:meta.trace_id");
+        assert!("header:".parse::<Assertion>().is_err());
+        assert!("cookie:x".parse::<Assertion>().is_err());
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            failed_assertions(&assertions, &headers, b"{\"meta\": {}}"),
+            [
+                "header `Authorization` is missing",
+                "body field `meta.trace_id` is missing"
+            ]
+        );
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer x"));
+        let body = b"{\"meta\": {\"trace_id\": 1}}";
+        assert!(failed_assertions(&assertions, &headers, body).is_empty());
+        assert!(
+            Assertion::Contains("trace".to_owned())
+                .check(&headers, body)
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+
+use actix_web::{
+    HttpResponse,
+    http::header::{AUTHORIZATION, HeaderMap, WWW_AUTHENTICATE},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+/// Realm named in `WWW-Authenticate` challenges
+const REALM: &str = "adaptive_rest";
+
+/// Credentials a request has to present
+#[derive(Debug, Clone, PartialEq)]
+pub enum Credentials {
+    Basic { user: String, password: String },
+    Bearer(String),
+}
+
+impl Credentials {
+    /// Whether the `Authorization` header presents these credentials
+    fn presented_in(&self, headers: &HeaderMap) -> bool {
+        let Some((scheme, value)) = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().split_once(' '))
+        else {
+            return false;
+        };
+        match self {
+            Credentials::Basic { user, password } => {
+                scheme.eq_ignore_ascii_case("basic")
+                    && STANDARD
+                        .decode(value.trim())
+                        .is_ok_and(|decoded| decoded == format!("{}:{}", user, password).as_bytes())
+            }
+            Credentials::Bearer(token) => {
+                scheme.eq_ignore_ascii_case("bearer") && value.trim() == token
+            }
+        }
+    }
+
+    fn scheme(&self) -> &'static str {
+        match self {
+            Credentials::Basic { .. } => "Basic",
+            Credentials::Bearer(_) => "Bearer",
+        }
+    }
+}
+
+/// e.g. `basic user:***` or `bearer ***`, secrets are never shown
+impl fmt::Display for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credentials::Basic { user, .. } => write!(f, "basic {}:***", user),
+            Credentials::Bearer(_) => write!(f, "bearer ***"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthRule {
+    /// Path prefix the rule applies to, `/` for all requests
+    pub scope: String,
+    pub credentials: Credentials,
+}
+
+impl AuthRule {
+    pub fn describe(&self) -> String {
+        format!("{} on {}", self.credentials, self.scope)
+    }
+
+    /// Whether `path` is `scope` or below it, comparing whole segments
+    pub fn applies_to(&self, path: &str) -> bool {
+        let scope = self.scope.trim_end_matches('/');
+        path.strip_prefix(scope)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// The 401 response for a request to `path` lacking the credentials required there, `None` if
+/// no rule applies or it presents the credentials of one of them
+pub fn challenge(rules: &[AuthRule], path: &str, headers: &HeaderMap) -> Option<HttpResponse> {
+    let applying: Vec<&AuthRule> = rules.iter().filter(|r| r.applies_to(path)).collect();
+    if applying.is_empty()
+        || applying
+            .iter()
+            .any(|rule| rule.credentials.presented_in(headers))
+    {
+        return None;
+    }
+    let mut response = HttpResponse::Unauthorized();
+    let mut schemes: Vec<&str> = Vec::new();
+    for scheme in applying.iter().map(|r| r.credentials.scheme()) {
+        if !schemes.contains(&scheme) {
+            schemes.push(scheme);
+        }
+    }
+    for scheme in schemes {
+        response.append_header((WWW_AUTHENTICATE, format!("{} realm=\"{}\"", scheme, REALM)));
+    }
+    Some(response.json(serde_json::json!({"error": "unauthorized", "path": path})))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::http::header::HeaderValue;
+
+    use super::*;
+
+    fn authorized(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_challenge() {
+        let rules = [
+            AuthRule {
+                scope: "/api/private".to_owned(),
+                credentials: Credentials::Bearer("my-token".to_owned()),
+            },
+            AuthRule {
+                scope: "/api/private".to_owned(),
+                credentials: Credentials::Basic {
+                    user: "admin".to_owned(),
+                    password: "secret".to_owned(),
+                },
+            },
+        ];
+
+        assert!(challenge(&rules, "/api/public", &HeaderMap::new()).is_none());
+        assert!(challenge(&rules, "/api/private-ish", &HeaderMap::new()).is_none());
+        assert!(challenge(&rules, "/api/private/1", &authorized("Bearer my-token")).is_none());
+        // admin:secret
+        assert!(
+            challenge(
+                &rules,
+                "/api/private",
+                &authorized("Basic YWRtaW46c2VjcmV0")
+            )
+            .is_none()
+        );
+
+        let denied = challenge(&rules, "/api/private/1", &authorized("Bearer wrong")).unwrap();
+        assert_eq!(denied.status(), 401);
+        let challenges: Vec<_> = denied.headers().get_all(WWW_AUTHENTICATE).collect();
+        assert_eq!(
+            challenges,
+            [
+                "Bearer realm=\"adaptive_rest\"",
+                "Basic realm=\"adaptive_rest\""
+            ]
+        );
+    }
+}
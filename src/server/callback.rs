@@ -0,0 +1,127 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use actix_web::http::{Method, Uri, header::CONTENT_TYPE};
+use awc::Client;
+
+use crate::util::units::format_time;
+
+/// Time the receiver has to answer a callback
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An outgoing request sent some time after the endpoint received one, like the webhook an
+/// async API confirms an order with, e.g. `POST http://localhost:8080/notify`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Callback {
+    pub method: Method,
+    pub url: Uri,
+    /// Sent as JSON if it is valid JSON, as text otherwise
+    pub body: String,
+    /// Time between receiving the request and sending the callback
+    pub delay: Duration,
+}
+
+impl Callback {
+    /// Send the callback once the delay passed, without holding up the response. The outcome
+    /// is only logged, a receiver that is down must not fail the endpoint.
+    pub fn fire(&self, client: Client) {
+        let callback = self.clone();
+        actix_web::rt::spawn(async move {
+            if !callback.delay.is_zero() {
+                actix_web::rt::time::sleep(callback.delay).await;
+            }
+            let content_type = if serde_json::from_str::<serde_json::Value>(&callback.body).is_ok()
+            {
+                "application/json"
+            } else {
+                "text/plain; charset=utf-8"
+            };
+            let mut request = client
+                .request(callback.method.clone(), &callback.url)
+                .timeout(CALLBACK_TIMEOUT);
+            if !callback.body.is_empty() {
+                request = request.insert_header((CONTENT_TYPE, content_type));
+            }
+            match request.send_body(callback.body.clone()).await {
+                Ok(response) => log::info!(
+                    "Callback {} {} answered {}",
+                    callback.method,
+                    callback.url,
+                    response.status().as_u16()
+                ),
+                Err(e) => log::warn!(
+                    "Callback {} {} failed: {}",
+                    callback.method,
+                    callback.url,
+                    e
+                ),
+            }
+        });
+    }
+
+    /// e.g. `POST http://localhost:8080/notify after 2s`
+    pub fn describe(&self) -> String {
+        if self.delay.is_zero() {
+            self.to_string()
+        } else {
+            format!("{} after {}", self, format_time(self.delay))
+        }
+    }
+}
+
+/// The method and URL, the body and delay are set separately
+impl fmt::Display for Callback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.url)
+    }
+}
+
+impl FromStr for Callback {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (method, url) = input.trim().split_once(' ').ok_or_else(|| {
+            format!(
+                "`{}` is not of the form `POST http://localhost:8080/notify`",
+                input
+            )
+        })?;
+        let method = Method::from_bytes(method.to_uppercase().as_bytes())
+            .map_err(|_| format!("`{}` is not a method", method))?;
+        let url: Uri = url
+            .trim()
+            .parse()
+            .map_err(|_| format!("`{}` is not a URL", url.trim()))?;
+        if url.scheme().is_none() || url.host().is_none() {
+            return Err(format!(
+                "`{}` is not an absolute URL like `http://localhost:8080/notify`",
+                url
+            ));
+        }
+        Ok(Self {
+            method,
+            url,
+            body: String::new(),
+            delay: Duration::ZERO,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_callback() {
+        let mut callback: Callback = "post http://localhost:8080/notify".parse().unwrap();
+        assert_eq!(callback.method, Method::POST);
+        assert_eq!(callback.to_string(), "POST http://localhost:8080/notify");
+        callback.delay = Duration::from_secs(2);
+        assert_eq!(
+            callback.describe(),
+            "POST http://localhost:8080/notify after 2s"
+        );
+        assert!("POST /notify".parse::<Callback>().is_err());
+        assert!("http://localhost:8080/notify".parse::<Callback>().is_err());
+    }
+}
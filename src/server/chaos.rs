@@ -0,0 +1,637 @@
+use std::{
+    fmt, io,
+    num::NonZeroU64,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use actix_web::{
+    HttpResponse,
+    body::{BodySize, BoxBody, MessageBody},
+    http::StatusCode,
+    web::Bytes,
+};
+use chrono::NaiveTime;
+use rand::RngExt;
+
+use crate::{server::Malformation, util::units::format_time};
+
+/// A fault injected into responses
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// Delay responses by a random duration in the range
+    Latency { min: Duration, max: Duration },
+    /// Answer a percentage of requests with `status` instead of the stub
+    Error { percent: f64, status: StatusCode },
+    /// Drop the connection halfway through a percentage of responses
+    Abort { percent: f64 },
+    /// Answer a percentage of requests with deliberately invalid HTTP
+    Malformed {
+        percent: f64,
+        malformation: Malformation,
+    },
+}
+
+impl Fault {
+    /// Rules of the same kind and scope replace each other
+    fn kind(&self) -> &'static str {
+        match self {
+            Fault::Latency { .. } => "latency",
+            Fault::Error { .. } => "error-rate",
+            Fault::Abort { .. } => "abort-rate",
+            Fault::Malformed { .. } => "malformed-rate",
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Fault::Latency { min, max } if min == max => format!("latency {}", format_time(*min)),
+            Fault::Latency { min, max } => {
+                format!("latency {}..{}", format_time(*min), format_time(*max))
+            }
+            Fault::Error { percent, status } => {
+                format!("error-rate {}% with {}", percent, status.as_u16())
+            }
+            Fault::Abort { percent } => format!("abort-rate {}%", percent),
+            Fault::Malformed {
+                percent,
+                malformation,
+            } => format!("malformed-rate {}% with {}", percent, malformation),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosRule {
+    /// Path prefix the rule applies to, `/` for all requests
+    pub scope: String,
+    pub fault: Fault,
+}
+
+impl ChaosRule {
+    pub fn describe(&self) -> String {
+        format!("{} on {}", self.fault.describe(), self.scope)
+    }
+
+    /// Whether `path` is `scope` or below it, comparing whole segments
+    fn applies_to(&self, path: &str) -> bool {
+        let scope = self.scope.trim_end_matches('/');
+        path.strip_prefix(scope)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// Faults chosen for a single request
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Faults {
+    pub delay: Option<Duration>,
+    pub error: Option<StatusCode>,
+    pub abort: bool,
+    pub malformed: Option<Malformation>,
+}
+
+impl Faults {
+    /// Combine with faults decided independently, e.g. for a flaky endpoint
+    pub fn merge(&mut self, other: Faults) {
+        self.delay = self.delay.max(other.delay);
+        self.error = self.error.or(other.error);
+        self.abort |= other.abort;
+        self.malformed = self.malformed.or(other.malformed);
+    }
+
+    /// Short description for the journal, `None` if no fault was injected
+    pub fn describe(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(delay) = self.delay {
+            parts.push(format!("delayed {}", format_time(delay)));
+        }
+        if let Some(status) = self.error {
+            parts.push(format!("error {}", status.as_u16()));
+        }
+        if self.abort {
+            parts.push("aborted".to_owned());
+        }
+        if let Some(malformation) = self.malformed {
+            parts.push(format!("malformed: {}", malformation));
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// Fault injection rules. For every kind of fault the rule with the most specific scope matching a
+/// request applies.
+#[derive(Debug, Default)]
+pub struct Chaos {
+    rules: Vec<ChaosRule>,
+}
+
+impl Chaos {
+    pub fn rules(&self) -> &[ChaosRule] {
+        &self.rules
+    }
+
+    /// Add a rule, replacing the one of the same kind and scope. Rules with a rate of zero only
+    /// remove the existing one.
+    pub fn set(&mut self, rule: ChaosRule) {
+        self.rules
+            .retain(|r| r.scope != rule.scope || r.fault.kind() != rule.fault.kind());
+        let is_noop = matches!(
+            rule.fault,
+            Fault::Latency {
+                max: Duration::ZERO,
+                ..
+            } | Fault::Error { percent: 0.0, .. }
+                | Fault::Abort { percent: 0.0 }
+                | Fault::Malformed { percent: 0.0, .. }
+        );
+        if !is_noop {
+            self.rules.push(rule);
+        }
+    }
+
+    /// Remove all rules, or those with exactly `scope`. Returns the number of removed rules.
+    pub fn clear(&mut self, scope: Option<&str>) -> usize {
+        let before = self.rules.len();
+        self.rules.retain(|r| scope.is_some_and(|s| r.scope != s));
+        before - self.rules.len()
+    }
+
+    /// Roll the dice for a request to `path`
+    pub fn decide(&self, path: &str, rng: &mut impl RngExt) -> Faults {
+        let mut faults = Faults::default();
+        let mut specific: Vec<&ChaosRule> =
+            self.rules.iter().filter(|r| r.applies_to(path)).collect();
+        specific.sort_by_key(|r| std::cmp::Reverse(r.scope.len()));
+        let mut seen = Vec::new();
+        for rule in specific {
+            if seen.contains(&rule.fault.kind()) {
+                continue;
+            }
+            seen.push(rule.fault.kind());
+            match &rule.fault {
+                Fault::Latency { min, max } => {
+                    faults.delay = Some(if min < max {
+                        rng.random_range(*min..=*max)
+                    } else {
+                        *min
+                    })
+                }
+                Fault::Error { percent, status } => {
+                    if rng.random_bool(percent / 100.0) {
+                        faults.error = Some(*status);
+                    }
+                }
+                Fault::Abort { percent } => faults.abort = rng.random_bool(percent / 100.0),
+                Fault::Malformed {
+                    percent,
+                    malformation,
+                } => {
+                    if rng.random_bool(percent / 100.0) {
+                        faults.malformed = Some(*malformation);
+                    }
+                }
+            }
+        }
+        faults
+    }
+}
+
+/// How long the `timeout` fault delays a response, longer than typical client timeouts
+const TIMEOUT_DELAY: Duration = Duration::from_secs(60);
+/// How long the `slow` fault delays a response
+const SLOW_DELAY: Duration = Duration::from_secs(3);
+
+/// A fault attached to a single endpoint, e.g. for flaky endpoints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NamedFault {
+    /// Answer only after a delay longer than typical client timeouts
+    Timeout,
+    /// Answer after a noticeable delay
+    Slow,
+    /// Drop the connection halfway through the response
+    Abort,
+    /// Answer with an error status instead of the stub
+    Status(StatusCode),
+    /// Answer with deliberately invalid HTTP
+    Malformed(Malformation),
+}
+
+impl NamedFault {
+    fn apply(&self, faults: &mut Faults) {
+        match self {
+            NamedFault::Timeout => faults.delay = Some(TIMEOUT_DELAY),
+            NamedFault::Slow => faults.delay = Some(SLOW_DELAY),
+            NamedFault::Abort => faults.abort = true,
+            NamedFault::Status(status) => faults.error = Some(*status),
+            NamedFault::Malformed(malformation) => faults.malformed = Some(*malformation),
+        }
+    }
+}
+
+impl fmt::Display for NamedFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NamedFault::Timeout => write!(f, "timeout"),
+            NamedFault::Slow => write!(f, "slow"),
+            NamedFault::Abort => write!(f, "abort"),
+            NamedFault::Status(status) => write!(f, "{}", status.as_u16()),
+            NamedFault::Malformed(malformation) => write!(f, "{}", malformation),
+        }
+    }
+}
+
+impl FromStr for NamedFault {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(match input {
+            "timeout" => NamedFault::Timeout,
+            "slow" => NamedFault::Slow,
+            "abort" => NamedFault::Abort,
+            "error" => NamedFault::Status(StatusCode::INTERNAL_SERVER_ERROR),
+            "unavailable" => NamedFault::Status(StatusCode::SERVICE_UNAVAILABLE),
+            other => match other.parse() {
+                Ok(malformation) => NamedFault::Malformed(malformation),
+                Err(_) => NamedFault::Status(parse_status(other).map_err(|_| {
+                    format!(
+                        "unknown fault `{}`, use timeout, slow, abort, error, unavailable, a \
+                         malformation like bad-length or a status",
+                        other
+                    )
+                })?),
+            },
+        })
+    }
+}
+
+/// An endpoint hitting `fault` with a `probability` between 0 and 1, e.g. `0.1:timeout`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flaky {
+    pub probability: f64,
+    pub fault: NamedFault,
+}
+
+impl fmt::Display for Flaky {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.probability, self.fault)
+    }
+}
+
+impl FromStr for Flaky {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (probability, fault) = input
+            .split_once(':')
+            .ok_or_else(|| format!("`{}` is not of the form `0.1:timeout`", input))?;
+        let probability: f64 = probability
+            .parse()
+            .ok()
+            .filter(|p| (0.0..=1.0).contains(p))
+            .ok_or_else(|| format!("probability `{}` is not between 0 and 1", probability))?;
+        Ok(Self {
+            probability,
+            fault: fault.parse()?,
+        })
+    }
+}
+
+/// When a [`ScheduledFault`] applies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    /// Local time of day from `start` until before `end`, wrapping around midnight if `end` is
+    /// earlier than `start`
+    During { start: NaiveTime, end: NaiveTime },
+    /// Every nth request to the endpoint
+    Every(NonZeroU64),
+}
+
+/// A fault hit on a schedule, e.g. `during 00:00..00:05=503` or `every 10=slow`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScheduledFault {
+    pub schedule: Schedule,
+    pub fault: NamedFault,
+}
+
+impl ScheduledFault {
+    /// Whether the fault applies to the `nth` request to the endpoint, arriving at `now`
+    fn applies(&self, now: NaiveTime, nth: u64) -> bool {
+        match self.schedule {
+            Schedule::During { start, end } if start <= end => start <= now && now < end,
+            Schedule::During { start, end } => start <= now || now < end,
+            Schedule::Every(n) => nth.is_multiple_of(n.get()),
+        }
+    }
+}
+
+impl fmt::Display for ScheduledFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.schedule {
+            Schedule::During { start, end } => write!(
+                f,
+                "during {}..{}",
+                start.format("%H:%M"),
+                end.format("%H:%M")
+            )?,
+            Schedule::Every(n) => write!(f, "every {}", n)?,
+        }
+        write!(f, "={}", self.fault)
+    }
+}
+
+impl FromStr for ScheduledFault {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let usage = || {
+            format!(
+                "`{}` is not of the form `during 00:00..00:05=503` or `every 10=slow`",
+                input
+            )
+        };
+        let (schedule, fault) = input.rsplit_once('=').ok_or_else(usage)?;
+        let schedule = match schedule.trim().split_once(' ').ok_or_else(usage)? {
+            ("during", window) => {
+                let (start, end) = window.trim().split_once("..").ok_or_else(usage)?;
+                let time = |t: &str| {
+                    NaiveTime::parse_from_str(t.trim(), "%H:%M")
+                        .map_err(|_| format!("`{}` is not a time like 00:05", t))
+                };
+                Schedule::During {
+                    start: time(start)?,
+                    end: time(end)?,
+                }
+            }
+            ("every", n) => Schedule::Every(
+                n.trim()
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a positive number", n.trim()))?,
+            ),
+            _ => return Err(usage()),
+        };
+        Ok(Self {
+            schedule,
+            fault: fault.trim().parse()?,
+        })
+    }
+}
+
+/// Faults of the schedules that apply to the `nth` request to an endpoint, arriving at `now`
+pub fn scheduled_faults(scheduled: &[ScheduledFault], now: NaiveTime, nth: u64) -> Faults {
+    let mut faults = Faults::default();
+    for rule in scheduled.iter().filter(|rule| rule.applies(now, nth)) {
+        rule.fault.apply(&mut faults);
+    }
+    faults
+}
+
+/// Roll once for a flaky endpoint; the faults share one roll, so their probabilities add up
+pub fn roll_flaky(flaky: &[Flaky], rng: &mut impl RngExt) -> Faults {
+    let mut faults = Faults::default();
+    let roll: f64 = rng.random();
+    let mut threshold = 0.0;
+    for rule in flaky {
+        threshold += rule.probability;
+        if roll < threshold {
+            rule.fault.apply(&mut faults);
+            break;
+        }
+    }
+    faults
+}
+
+/// Chunks of a streamed body, e.g. a drip or an event stream, passed on before it is aborted
+const ABORTED_STREAM_CHUNKS: usize = 2;
+
+/// Replace the body of `response` with one that breaks off halfway, or after its first chunks if
+/// it is streamed, so the client sees the connection drop mid-response
+pub fn abort_midway(response: HttpResponse) -> HttpResponse {
+    break_off(
+        response,
+        |body| body.slice(..body.len() / 2),
+        Some(ABORTED_STREAM_CHUNKS),
+    )
+}
+
+/// Replace the body of `response` with the part `head` keeps of it, after which the connection
+/// drops. Streamed bodies aren't collected but pass on `chunks` of theirs, all if `None`.
+pub(super) fn break_off(
+    response: HttpResponse,
+    head: impl FnOnce(Bytes) -> Bytes,
+    chunks: Option<usize>,
+) -> HttpResponse {
+    let (response, body) = response.into_parts();
+    let (body, chunks) = match body.try_into_bytes() {
+        Ok(body) => (BoxBody::new(head(body)), None),
+        Err(streamed) => (streamed, chunks),
+    };
+    response
+        .set_body(AbortedBody {
+            body,
+            chunks,
+            passed: false,
+            flushed: false,
+        })
+        .map_into_boxed_body()
+}
+
+/// Streams chunks of `body`, then fails, which makes actix close the connection
+struct AbortedBody {
+    body: BoxBody,
+    /// Chunks left to pass on, all if `None`
+    chunks: Option<usize>,
+    /// Whether all chunks to pass on were
+    passed: bool,
+    /// Whether the connection had a chance to flush the chunks before failing
+    flushed: bool,
+}
+
+impl MessageBody for AbortedBody {
+    type Error = io::Error;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let body = self.get_mut();
+        if !body.passed && body.chunks != Some(0) {
+            match ready!(Pin::new(&mut body.body).poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    body.chunks = body.chunks.map(|chunks| chunks - 1);
+                    return Poll::Ready(Some(Ok(chunk)));
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(io::Error::other(e.to_string())))),
+                None => {}
+            }
+        }
+        body.passed = true;
+        // Yield once so the headers and chunks get written before the connection is dropped
+        if !body.flushed {
+            body.flushed = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        Poll::Ready(Some(Err(io::Error::other(
+            "connection aborted by chaos rule",
+        ))))
+    }
+}
+
+/// Parse an HTTP status code such as `503`
+pub fn parse_status(input: &str) -> Result<StatusCode, String> {
+    input
+        .parse::<u16>()
+        .ok()
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .ok_or_else(|| format!("`{}` is not a valid status code", input))
+}
+
+/// Parse a percentage between 0 and 100
+pub fn parse_percent(input: &str) -> Result<f64, String> {
+    let percent: f64 = input
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("`{}` is not a number", input))?;
+    if (0.0..=100.0).contains(&percent) {
+        Ok(percent)
+    } else {
+        Err(format!("{} is not between 0 and 100", percent))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::server::Drip;
+
+    fn rule(scope: &str, fault: Fault) -> ChaosRule {
+        ChaosRule {
+            scope: scope.to_owned(),
+            fault,
+        }
+    }
+
+    #[test]
+    fn test_most_specific_scope_wins() {
+        let mut chaos = Chaos::default();
+        let latency = |ms| Fault::Latency {
+            min: Duration::from_millis(ms),
+            max: Duration::from_millis(ms),
+        };
+        chaos.set(rule("/", latency(100)));
+        chaos.set(rule("/api", latency(200)));
+        chaos.set(rule(
+            "/api/orders",
+            Fault::Error {
+                percent: 100.0,
+                status: StatusCode::SERVICE_UNAVAILABLE,
+            },
+        ));
+        let mut rng = rand::rng();
+
+        assert_eq!(
+            chaos.decide("/api/orders/1", &mut rng),
+            Faults {
+                delay: Some(Duration::from_millis(200)),
+                error: Some(StatusCode::SERVICE_UNAVAILABLE),
+                abort: false,
+                malformed: None,
+            }
+        );
+        assert_eq!(
+            chaos.decide("/apiary", &mut rng).delay,
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn test_set_replaces_and_zero_rate_removes() {
+        let mut chaos = Chaos::default();
+        chaos.set(rule("/", Fault::Abort { percent: 5.0 }));
+        chaos.set(rule("/", Fault::Abort { percent: 10.0 }));
+        assert_eq!(chaos.rules(), [rule("/", Fault::Abort { percent: 10.0 })]);
+
+        chaos.set(rule("/", Fault::Abort { percent: 0.0 }));
+        assert!(chaos.rules().is_empty());
+    }
+
+    #[test]
+    fn test_flaky() {
+        let flaky: Flaky = "0.1:timeout".parse().unwrap();
+        assert_eq!(
+            flaky,
+            Flaky {
+                probability: 0.1,
+                fault: NamedFault::Timeout
+            }
+        );
+        assert_eq!(flaky.to_string(), "0.1:timeout");
+        assert_eq!("1:503".parse::<Flaky>().unwrap().to_string(), "1:503");
+        assert!("2:timeout".parse::<Flaky>().is_err());
+        assert!("0.5:explode".parse::<Flaky>().is_err());
+
+        let mut rng = rand::rng();
+        let always = ["1:abort".parse().unwrap()];
+        assert!(roll_flaky(&always, &mut rng).abort);
+        let never = ["0:abort".parse().unwrap()];
+        assert_eq!(roll_flaky(&never, &mut rng), Faults::default());
+    }
+
+    #[test]
+    fn test_scheduled_faults() {
+        let window: ScheduledFault = "during 23:55..00:05=503".parse().unwrap();
+        let every: ScheduledFault = "every 10=slow".parse().unwrap();
+        assert_eq!(window.to_string(), "during 23:55..00:05=503");
+        assert_eq!(every.to_string(), "every 10=slow");
+        assert!("every 0=slow".parse::<ScheduledFault>().is_err());
+        assert!("sometimes=slow".parse::<ScheduledFault>().is_err());
+
+        let time = |t| NaiveTime::parse_from_str(t, "%H:%M").unwrap();
+        let rules = [window, every];
+        assert_eq!(
+            scheduled_faults(&rules, time("00:01"), 1).error,
+            Some(StatusCode::SERVICE_UNAVAILABLE)
+        );
+        assert_eq!(
+            scheduled_faults(&rules, time("00:05"), 1),
+            Faults::default()
+        );
+        assert_eq!(
+            scheduled_faults(&rules, time("12:00"), 20).delay,
+            Some(SLOW_DELAY)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_abort_streamed_body() {
+        let drip: Drip = "1b/1ms".parse().unwrap();
+        let response = HttpResponse::Ok().body(drip.stream(Bytes::from_static(b"abcdef")));
+        let mut body = abort_midway(response).into_body();
+        let mut received = Vec::new();
+        let error = loop {
+            match std::future::poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await {
+                Some(Ok(chunk)) => received.extend_from_slice(&chunk),
+                Some(Err(e)) => break e,
+                None => panic!("the body ended without aborting"),
+            }
+        };
+        assert_eq!(received, b"ab");
+        assert!(error.to_string().contains("aborted"));
+    }
+
+    #[test]
+    fn test_parse_percent() {
+        assert_eq!(parse_percent("10"), Ok(10.0));
+        assert_eq!(parse_percent("2.5%"), Ok(2.5));
+        assert!(parse_percent("101").is_err());
+        assert_eq!(parse_status("503"), Ok(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(parse_status("42").is_err());
+    }
+}
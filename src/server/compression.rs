@@ -0,0 +1,109 @@
+use std::sync::{Arc, atomic::Ordering};
+
+use actix_web::{
+    Error,
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{CONTENT_ENCODING, HeaderValue},
+    middleware::Next,
+    web::Data,
+};
+
+use crate::server::ServerState;
+
+/// Whether the response of an endpoint is compressed, overriding the server-wide setting
+#[derive(Debug, Clone, Copy)]
+pub struct CompressOverride(pub bool);
+
+/// Mark responses that must not be compressed with `Content-Encoding: identity`, which makes the
+/// `Compress` middleware wrapping this one leave them alone
+pub async fn skip_unwanted(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let enabled = req
+        .app_data::<Data<Arc<ServerState>>>()
+        .is_some_and(|state| state.compress.load(Ordering::Relaxed));
+    let mut res = next.call(req).await?;
+    let compress = res
+        .response()
+        .extensions()
+        .get::<CompressOverride>()
+        .map_or(enabled, |o| o.0);
+    if !compress && !res.headers().contains_key(CONTENT_ENCODING) {
+        res.headers_mut()
+            .insert(CONTENT_ENCODING, HeaderValue::from_static("identity"));
+    }
+    Ok(res)
+}
+
+/// Remove the marker of [`skip_unwanted`] once `Compress` has seen it, so clients get
+/// uncompressed responses without a `Content-Encoding`
+pub async fn strip_identity(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let mut res = next.call(req).await?;
+    if res
+        .headers()
+        .get(CONTENT_ENCODING)
+        .is_some_and(|encoding| encoding == "identity")
+    {
+        res.headers_mut().remove(CONTENT_ENCODING);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App, HttpResponse,
+        http::header::ACCEPT_ENCODING,
+        middleware::{Compress, from_fn},
+        test::{TestRequest, call_service, init_service},
+        web::to,
+    };
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_compress_by_setting_and_override() {
+        let state = Arc::new(ServerState::new());
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(state.clone()))
+                .wrap(from_fn(skip_unwanted))
+                .wrap(Compress::default())
+                .wrap(from_fn(strip_identity))
+                .route(
+                    "/default",
+                    to(|| async { HttpResponse::Ok().body("default") }),
+                )
+                .route(
+                    "/never",
+                    to(|| async {
+                        let mut res = HttpResponse::Ok().body("never");
+                        res.extensions_mut().insert(CompressOverride(false));
+                        res
+                    }),
+                ),
+        )
+        .await;
+        let encoding = async |path: &str| {
+            let req = TestRequest::get()
+                .uri(path)
+                .insert_header((ACCEPT_ENCODING, "gzip"))
+                .to_request();
+            call_service(&app, req)
+                .await
+                .headers()
+                .get(CONTENT_ENCODING)
+                .map(|encoding| encoding.to_str().unwrap_or_default().to_owned())
+        };
+
+        assert_eq!(encoding("/default").await, None);
+        state.compress.store(true, Ordering::Relaxed);
+        assert_eq!(encoding("/default").await.as_deref(), Some("gzip"));
+        assert_eq!(encoding("/never").await, None);
+    }
+}
@@ -0,0 +1,144 @@
+use std::{cell::Cell, fmt, num::NonZeroU64, str::FromStr};
+
+use actix_web::{HttpRequest, HttpResponse, http::StatusCode, web::Bytes};
+use rand::RngExt;
+
+use crate::server::RawResponse;
+
+/// When the server closes connections instead of keeping them alive, to exercise the
+/// connection pools of clients
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionPolicy {
+    /// Answer every request with `Connection: close`
+    pub close: bool,
+    /// Close connections after this many requests
+    pub max_requests: Option<NonZeroU64>,
+    /// Percentage of responses after which the connection is closed shortly, without
+    /// `Connection: close`, so clients find out when reusing it
+    pub drop: f64,
+}
+
+impl ConnectionPolicy {
+    /// Why the response to the `served`th request on a connection announces closing it, `None`
+    /// to keep it
+    pub fn close_reason(&self, served: u64) -> Option<&'static str> {
+        if self.close {
+            Some("connections are closed")
+        } else if self.max_requests.is_some_and(|max| served >= max.get()) {
+            Some("request limit reached")
+        } else {
+            None
+        }
+    }
+
+    /// Whether to close the connection unannounced after a response
+    pub fn drops(&self, rng: &mut impl RngExt) -> bool {
+        self.drop > 0.0 && rng.random_bool(self.drop / 100.0)
+    }
+}
+
+/// Requests served on a connection so far, attached to each connection as it is accepted
+#[derive(Debug, Default)]
+pub struct ServedRequests(Cell<u64>);
+
+impl ServedRequests {
+    /// Count another request, returns the count including it
+    pub fn next(&self) -> u64 {
+        self.0.set(self.0.get() + 1);
+        self.0.get()
+    }
+}
+
+/// How an endpoint leaves requests unanswered, to test the timeouts and retries of clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hangup {
+    /// Never respond, holding the connection open until the client gives up
+    NoResponse,
+    /// Drop the connection without writing anything
+    Close,
+}
+
+impl Hangup {
+    /// Status recorded for unanswered requests, as nginx uses it for closing without a response
+    pub fn status() -> StatusCode {
+        StatusCode::from_u16(444).unwrap_or(StatusCode::REQUEST_TIMEOUT)
+    }
+
+    /// e.g. `no response`, as shown in the journal
+    pub fn describe(self) -> &'static str {
+        match self {
+            Hangup::NoResponse => "no response",
+            Hangup::Close => "closed without response",
+        }
+    }
+
+    /// Stand-in for the journal. Nothing of it reaches the client: the connection of `req` is
+    /// dropped or, for [`Hangup::NoResponse`], the handler never returns it.
+    pub fn respond(self, req: &HttpRequest) -> HttpResponse {
+        let mut response = match self {
+            Hangup::NoResponse => HttpResponse::build(Self::status()).finish(),
+            Hangup::Close => RawResponse(Bytes::new()).respond(req),
+        };
+        *response.status_mut() = Self::status();
+        response
+    }
+}
+
+impl fmt::Display for Hangup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hangup::NoResponse => write!(f, "no-response"),
+            Hangup::Close => write!(f, "close-connection"),
+        }
+    }
+}
+
+impl FromStr for Hangup {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "no-response" => Ok(Hangup::NoResponse),
+            "close-connection" => Ok(Hangup::Close),
+            other => Err(format!(
+                "unknown hangup `{}`, use no-response or close-connection",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_close_reason() {
+        let mut rng = rand::rng();
+        let mut policy = ConnectionPolicy::default();
+        assert_eq!(policy.close_reason(100), None);
+        assert!(!policy.drops(&mut rng));
+
+        policy.max_requests = NonZeroU64::new(3);
+        let served = ServedRequests::default();
+        let reasons: Vec<_> = (0..3).map(|_| policy.close_reason(served.next())).collect();
+        assert_eq!(reasons, [None, None, Some("request limit reached")]);
+
+        policy.max_requests = None;
+        policy.drop = 100.0;
+        assert_eq!(policy.close_reason(1), None);
+        assert!(policy.drops(&mut rng));
+        policy.close = true;
+        assert_eq!(policy.close_reason(1), Some("connections are closed"));
+    }
+
+    #[test]
+    fn test_parse_hangup() {
+        for hangup in [Hangup::NoResponse, Hangup::Close] {
+            assert_eq!(hangup.to_string().parse::<Hangup>().unwrap(), hangup);
+        }
+        assert!("hang".parse::<Hangup>().is_err());
+        assert_eq!(Hangup::status().as_u16(), 444);
+    }
+}
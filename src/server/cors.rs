@@ -0,0 +1,95 @@
+use actix_cors::Cors;
+use actix_web::http::{Uri, header::HeaderName};
+
+/// CORS settings of the mock server. CORS is enabled once at least one origin is allowed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CorsConfig {
+    /// Allowed origins, `*` allows any
+    pub origins: Vec<String>,
+    /// Allowed request headers, any if empty or `*`
+    pub headers: Vec<String>,
+}
+
+impl CorsConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.origins.is_empty()
+    }
+
+    /// Middleware answering preflight requests and adding CORS headers to all responses. Origins
+    /// and headers are expected to have been validated by [`parse_origin`] and [`parse_header`].
+    pub fn middleware(&self) -> Cors {
+        let mut cors = Cors::default()
+            .allow_any_method()
+            .expose_any_header()
+            .max_age(3600);
+        if self.origins.iter().any(|o| o == "*") {
+            cors = cors.allow_any_origin();
+        } else {
+            for origin in &self.origins {
+                cors = cors.allowed_origin(origin);
+            }
+        }
+        if self.headers.is_empty() || self.headers.iter().any(|h| h == "*") {
+            cors.allow_any_header()
+        } else {
+            cors.allowed_headers(&self.headers)
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        if !self.is_enabled() {
+            return "CORS is off".to_owned();
+        }
+        let headers = match self.headers.as_slice() {
+            [] => "*".to_owned(),
+            headers => headers.join(", "),
+        };
+        format!(
+            "CORS allows origins {} with headers {}",
+            self.origins.join(", "),
+            headers
+        )
+    }
+}
+
+/// Validate an origin such as `http://localhost:8080`, or `*` for any origin
+pub fn parse_origin(input: &str) -> Result<String, String> {
+    if input == "*" {
+        return Ok(input.to_owned());
+    }
+    let uri: Uri = input
+        .parse()
+        .map_err(|e| format!("invalid origin `{}`: {}", input, e))?;
+    if uri.scheme().is_none() || uri.host().is_none() {
+        return Err(format!(
+            "origin `{}` needs a scheme and host, e.g. `http://localhost:8080`",
+            input
+        ));
+    }
+    Ok(input.trim_end_matches('/').to_owned())
+}
+
+/// Validate a request header name, or `*` for any header
+pub fn parse_header(input: &str) -> Result<String, String> {
+    if input != "*" {
+        HeaderName::try_from(input).map_err(|e| format!("invalid header `{}`: {}", input, e))?;
+    }
+    Ok(input.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_origin() {
+        assert_eq!(parse_origin("*"), Ok("*".to_owned()));
+        assert_eq!(
+            parse_origin("http://localhost:8080/"),
+            Ok("http://localhost:8080".to_owned())
+        );
+        assert!(parse_origin("localhost").is_err());
+        assert!(parse_header("X-Request-Id").is_ok());
+        assert!(parse_header("bad header").is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use std::path::{Path, PathBuf};
+
+use actix_web::http::{Method, Uri};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::server::is_param;
+
+/// Methods an OpenAPI path item may document
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Which operations of a loaded OpenAPI document real traffic has hit, so QA can see which
+/// parts of the contract their tests never touch
+#[derive(Debug)]
+pub struct Coverage {
+    spec: PathBuf,
+    /// Path of the first server, prefixed to the documented paths, e.g. `/api/v2`
+    base_path: String,
+    operations: Vec<CoveredOperation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoveredOperation {
+    pub method: String,
+    /// As documented, e.g. `/users/{id}`
+    pub path: String,
+    pub hits: u64,
+}
+
+/// Coverage of a spec as answered by `coverage report` and `/__admin/coverage`
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageReport {
+    pub spec: PathBuf,
+    pub operations: usize,
+    pub covered: usize,
+    pub percent: f64,
+    /// By path
+    pub details: Vec<CoveredOperation>,
+}
+
+impl Coverage {
+    /// Track the operations of `document`, an OpenAPI 2 or 3 document read from `spec`
+    pub fn new(spec: &Path, document: &Value) -> Result<Self, String> {
+        let paths = document
+            .get("paths")
+            .and_then(Value::as_object)
+            .ok_or("it has no `paths`")?;
+        let operations = paths
+            .iter()
+            .flat_map(|(path, item)| {
+                METHODS
+                    .iter()
+                    .filter(|method| item.get(**method).is_some())
+                    .map(|method| CoveredOperation {
+                        method: method.to_ascii_uppercase(),
+                        path: path.clone(),
+                        hits: 0,
+                    })
+            })
+            .collect();
+        // OpenAPI 3 names the servers, 2 the base path
+        let base_path = document
+            .pointer("/servers/0/url")
+            .or_else(|| document.get("basePath"))
+            .and_then(Value::as_str)
+            .and_then(|url| url.parse::<Uri>().ok())
+            .map(|url| url.path().trim_end_matches('/').to_owned())
+            .unwrap_or_default();
+        Ok(Self {
+            spec: spec.to_owned(),
+            base_path,
+            operations,
+        })
+    }
+
+    /// Count a request towards the operation documenting it, the one with the most literal
+    /// segments if several do. Returns false if none does.
+    pub fn record(&mut self, method: &Method, path: &str) -> bool {
+        let Some(path) = path
+            .strip_prefix(&self.base_path)
+            .filter(|path| path.is_empty() || path.starts_with('/'))
+        else {
+            return false;
+        };
+        let operation = self
+            .operations
+            .iter_mut()
+            .filter(|operation| operation.method == method.as_str())
+            .filter_map(|operation| {
+                let literals = literal_segments(&operation.path, path)?;
+                Some((literals, operation))
+            })
+            .max_by_key(|(literals, _)| *literals);
+        match operation {
+            Some((_, operation)) => {
+                operation.hits += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget all hits, e.g. before another test run
+    pub fn reset(&mut self) {
+        for operation in &mut self.operations {
+            operation.hits = 0;
+        }
+    }
+
+    pub fn report(&self) -> CoverageReport {
+        let covered = self.operations.iter().filter(|o| o.hits > 0).count();
+        CoverageReport {
+            spec: self.spec.clone(),
+            operations: self.operations.len(),
+            covered,
+            percent: match self.operations.len() {
+                0 => 100.0,
+                total => covered as f64 * 100.0 / total as f64,
+            },
+            details: self.operations.clone(),
+        }
+    }
+}
+
+/// Number of literal segments of `template` if `path` matches it, e.g. 1 for `/users/{id}` and
+/// `/users/42`
+fn literal_segments(template: &str, path: &str) -> Option<usize> {
+    let template: Vec<&str> = template.trim_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if template.len() != path.len() {
+        return None;
+    }
+    let mut literals = 0;
+    for (expected, actual) in template.iter().zip(&path) {
+        if is_param(expected) {
+            if actual.is_empty() {
+                return None;
+            }
+        } else if expected == actual {
+            literals += 1;
+        } else {
+            return None;
+        }
+    }
+    Some(literals)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_record_coverage() {
+        let document = json!({
+            "openapi": "3.0.3",
+            "servers": [{"url": "https://api.example.com/v2/"}],
+            "paths": {
+                "/users": {"get": {}, "post": {}},
+                "/users/{id}": {"get": {}, "parameters": []},
+                "/users/me": {"get": {}},
+            },
+        });
+        let mut coverage = Coverage::new(Path::new("api.yaml"), &document).unwrap();
+        assert!(coverage.record(&Method::GET, "/v2/users/me"));
+        assert!(coverage.record(&Method::GET, "/v2/users/42/"));
+        assert!(coverage.record(&Method::GET, "/v2/users/7"));
+        assert!(!coverage.record(&Method::DELETE, "/v2/users/7"));
+        assert!(!coverage.record(&Method::GET, "/users"));
+
+        let report = coverage.report();
+        assert_eq!((report.operations, report.covered), (4, 2));
+        assert_eq!(report.percent, 50.0);
+        let hits = |path: &str| {
+            report
+                .details
+                .iter()
+                .find(|operation| operation.method == "GET" && operation.path == path)
+                .unwrap()
+                .hits
+        };
+        assert_eq!((hits("/users/{id}"), hits("/users/me")), (2, 1));
+
+        coverage.reset();
+        assert_eq!(coverage.report().covered, 0);
+        assert!(Coverage::new(Path::new("api.yaml"), &json!({})).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use actix_web::http::{Method, header::COOKIE};
+
+use crate::{
+    server::{Credentials, Matcher, RequestRecord, Stub},
+    util::units::format_size,
+};
+
+/// Headers curl sets itself from the URL and body, which would be wrong for the replayed body.
+/// `Content-Encoding` is only dropped for bodies the journal decoded.
+const DERIVED_HEADERS: [&str; 3] = ["host", "content-length", "transfer-encoding"];
+
+/// A curl command sending the same request as `record`, with its method, headers and body, to
+/// the host named by its Host header over plain HTTP. Binary bodies are piped in with `printf`.
+/// Bodies cut off by the journal are sent as kept, with a comment saying so.
+pub fn curl_command(record: &RequestRecord) -> String {
+    let host = record
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+        .map_or("localhost", |(_, host)| host.as_str());
+    let url = match record.query.as_str() {
+        "" => format!("http://{}{}", host, record.path),
+        query => format!("http://{}{}?{}", host, record.path, query),
+    };
+    let mut args = vec!["curl".to_owned()];
+    match record.method {
+        Method::GET => {}
+        Method::HEAD => args.push("--head".to_owned()),
+        ref method => args.extend(["-X".to_owned(), quote(method.as_str())]),
+    }
+    for (name, value) in &record.headers {
+        let name = name.to_ascii_lowercase();
+        if DERIVED_HEADERS.contains(&name.as_str())
+            || (name == "content-encoding" && record.encoded_size.is_some())
+        {
+            continue;
+        }
+        args.extend(["-H".to_owned(), quote(&format!("{}: {}", name, value))]);
+    }
+    let mut pipe = None;
+    if !record.body.is_empty() {
+        match std::str::from_utf8(&record.body) {
+            // curl reads a file for a body starting with `@`
+            Ok(body) if !body.contains('\0') && !body.starts_with('@') => {
+                args.extend(["--data-binary".to_owned(), quote(body)])
+            }
+            _ => {
+                pipe = Some(format!("printf {} | ", quote(&octal_escaped(&record.body))));
+                args.extend(["--data-binary".to_owned(), "@-".to_owned()]);
+            }
+        }
+    }
+    args.push(quote(&url));
+    let mut command = format!("{}{}", pipe.unwrap_or_default(), args.join(" "));
+    if record.truncated() {
+        command.push_str(&format!(
+            " # body cut off by the journal, {} were received",
+            format_size(record.body_size)
+        ));
+    }
+    command
+}
+
+/// A curl command for `url` answered by `stub`, with the query, headers and cookies its matchers
+/// ask for, and `credentials` an auth rule requires. Bodies are left out, JSON matchers and
+/// schemas don't tell a whole body.
+pub fn endpoint_curl(
+    method: &Method,
+    url: &str,
+    stub: &Stub,
+    credentials: Option<&Credentials>,
+) -> String {
+    let mut args = vec!["curl".to_owned()];
+    match method {
+        &Method::GET => {}
+        &Method::HEAD => args.push("--head".to_owned()),
+        method => args.extend(["-X".to_owned(), quote(method.as_str())]),
+    }
+    let mut query = Vec::new();
+    let mut cookies = Vec::new();
+    for matcher in &stub.matchers {
+        match matcher {
+            Matcher::Query { name, value } => query.push((name.as_str(), value.as_str())),
+            Matcher::Header { name, value } => {
+                args.extend(["-H".to_owned(), quote(&format!("{}: {}", name, value))])
+            }
+            Matcher::Cookie { name, value } => cookies.push(format!("{}={}", name, value)),
+            Matcher::BodyJson(_) | Matcher::Calls(_) | Matcher::ClientIp(_) => {}
+        }
+    }
+    if !cookies.is_empty() {
+        args.extend([
+            "-H".to_owned(),
+            quote(&format!("{}: {}", COOKIE, cookies.join("; "))),
+        ]);
+    }
+    match credentials {
+        Some(Credentials::Basic { user, password }) => {
+            args.extend(["-u".to_owned(), quote(&format!("{}:{}", user, password))])
+        }
+        Some(Credentials::Bearer(token)) => args.extend([
+            "-H".to_owned(),
+            quote(&format!("Authorization: Bearer {}", token)),
+        ]),
+        None => {}
+    }
+    let url = match serde_urlencoded::to_string(&query) {
+        Ok(query) if !query.is_empty() => format!("{}?{}", url, query),
+        _ => url.to_owned(),
+    };
+    args.push(quote(&url));
+    args.join(" ")
+}
+
+/// A shell script reproducing `records` in order, each command preceded by a comment naming
+/// the request
+pub fn curl_script<'a>(records: impl IntoIterator<Item = &'a RequestRecord>) -> String {
+    let mut script = "#!/bin/sh\n".to_owned();
+    for record in records {
+        script.push_str(&format!(
+            "\n# #{} {} {} {} at {}\n{}\n",
+            record.id,
+            record.method,
+            record.path,
+            record.status.as_u16(),
+            record.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            curl_command(record)
+        ));
+    }
+    script
+}
+
+/// `word` as a single argument of a POSIX shell, in single quotes unless it is plain
+fn quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:@%+=,".contains(c));
+    if plain {
+        word.to_owned()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// A `printf` format writing `bytes`, with every byte but plain ASCII as an octal escape
+fn octal_escaped(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            b' '..=b'~' if byte != b'%' && byte != b'\\' => (byte as char).to_string(),
+            _ => format!("\\{:03o}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{http::StatusCode, web::Bytes};
+    use chrono::Local;
+
+    use super::*;
+
+    fn record(method: Method, body: &'static [u8], headers: &[(&str, &str)]) -> RequestRecord {
+        RequestRecord {
+            id: 7,
+            timestamp: Local::now(),
+            method,
+            path: "/users".to_owned(),
+            query: "q=it's".to_owned(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: Bytes::from_static(body),
+            body_size: body.len(),
+            encoded_size: None,
+            status: StatusCode::OK,
+            stub: None,
+            response: None,
+            endpoint: None,
+            elapsed: Duration::ZERO,
+            fault: None,
+            failures: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_endpoint_curl() {
+        let stub = Stub {
+            matchers: vec![
+                "query:q=rust cli".parse::<Matcher>().unwrap(),
+                "header:X-Tenant: acme".parse().unwrap(),
+            ],
+            ..Stub::from("[]")
+        };
+        let credentials = Credentials::Bearer("secret".to_owned());
+        assert_eq!(
+            endpoint_curl(
+                &Method::POST,
+                "http://127.0.0.1:8080/search",
+                &stub,
+                Some(&credentials)
+            ),
+            "curl -X POST -H 'x-tenant: acme' -H 'Authorization: Bearer secret' \
+             'http://127.0.0.1:8080/search?q=rust+cli'"
+        );
+        assert_eq!(
+            endpoint_curl(
+                &Method::GET,
+                "http://localhost:8080/users/1",
+                &Stub::default(),
+                None
+            ),
+            "curl http://localhost:8080/users/1"
+        );
+    }
+
+    #[test]
+    fn test_curl_command() {
+        let post = record(
+            Method::POST,
+            br#"{"name":"O'Brien"}"#,
+            &[
+                ("host", "127.0.0.1:3000"),
+                ("content-type", "application/json"),
+                ("content-length", "18"),
+            ],
+        );
+        assert_eq!(
+            curl_command(&post),
+            r#"curl -X POST -H 'content-type: application/json' --data-binary '{"name":"O'\''Brien"}' 'http://127.0.0.1:3000/users?q=it'\''s'"#
+        );
+
+        let get = record(Method::GET, b"", &[]);
+        assert_eq!(
+            curl_command(&get),
+            r"curl 'http://localhost/users?q=it'\''s'"
+        );
+
+        let binary = record(Method::PUT, b"a\0%", &[]);
+        assert!(
+            curl_command(&binary)
+                .starts_with(r"printf 'a\000\045' | curl -X PUT --data-binary @- ")
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use actix_web::http::{
+    Method,
+    header::{CONTENT_TYPE, COOKIE, ETAG, LOCATION},
+};
+
+use crate::server::{EndpointStore, Matcher, Stub};
+
+/// What `endpoint inspect` and `export markdown` tell about one candidate of an endpoint
+#[derive(Debug, PartialEq)]
+pub struct CandidateDoc {
+    pub description: Option<String>,
+    /// Settings besides the body, see [`Stub::options`]
+    pub options: Vec<String>,
+    /// A request the candidate answers, e.g. `GET /search?q=rust` followed by matched headers
+    pub request: String,
+    /// The response as sent, with the body pretty-printed if it is JSON
+    pub response: String,
+}
+
+impl CandidateDoc {
+    pub fn new(method: &Method, path: &str, stub: &Stub) -> Self {
+        Self {
+            description: stub.description.clone(),
+            options: stub.options(),
+            request: example_request(method, path, stub),
+            response: example_response(stub),
+        }
+    }
+}
+
+fn example_request(method: &Method, path: &str, stub: &Stub) -> String {
+    let mut query = Vec::new();
+    let mut lines = Vec::new();
+    let mut cookies = Vec::new();
+    for matcher in &stub.matchers {
+        match matcher {
+            Matcher::Query { name, value } => query.push(format!("{}={}", name, value)),
+            Matcher::Header { name, value } => lines.push(format!("{}: {}", name, value)),
+            Matcher::Cookie { name, value } => cookies.push(format!("{}={}", name, value)),
+            Matcher::BodyJson(_) | Matcher::Calls(_) | Matcher::ClientIp(_) => {}
+        }
+    }
+    let request_line = match query.as_slice() {
+        [] => format!("{} {}", method, path),
+        query => format!("{} {}?{}", method, path, query.join("&")),
+    };
+    if !cookies.is_empty() {
+        lines.push(format!("{}: {}", COOKIE, cookies.join("; ")));
+    }
+    lines.insert(0, request_line);
+    lines.join("\n")
+}
+
+fn example_response(stub: &Stub) -> String {
+    if let Some(hangup) = stub.hangup {
+        return format!("({})", hangup.describe());
+    }
+    if let Some(raw) = &stub.raw {
+        return String::from_utf8_lossy(&raw.0).into_owned();
+    }
+    let status = stub
+        .redirect
+        .as_ref()
+        .map_or(stub.response_status(), |redirect| redirect.status);
+    let mut lines = vec![format!(
+        "HTTP/1.1 {} {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    )];
+    if let Some(redirect) = &stub.redirect {
+        lines.push(format!(
+            "{}: {}",
+            LOCATION,
+            String::from_utf8_lossy(redirect.location.as_bytes())
+        ));
+    }
+    if stub.events.is_some() {
+        lines.push("content-type: text/event-stream".to_owned());
+    }
+    // Of several representations the one sent to clients accepting anything
+    let body = match stub.representations.first() {
+        Some(representation) => {
+            lines.push(format!("{}: {}", CONTENT_TYPE, representation.content_type));
+            &representation.body
+        }
+        None => &stub.body,
+    };
+    if let Some(etag) = &stub.etag {
+        lines.push(format!("{}: {}", ETAG, etag.tag(body)));
+    }
+    lines.extend(stub.headers.iter().map(ToString::to_string));
+    let body = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(json) => serde_json::to_string_pretty(&json).unwrap_or_default(),
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    };
+    if !body.is_empty() {
+        lines.push(String::new());
+        lines.push(body);
+    }
+    lines.join("\n")
+}
+
+/// Markdown document of all endpoints with their descriptions and example requests and
+/// responses, e.g. to paste into a wiki
+pub fn markdown(endpoints: &EndpointStore, base_path: &str) -> String {
+    let mut sections = vec!["# Endpoints".to_owned()];
+    if !base_path.is_empty() {
+        sections.push(format!("All paths are below `{}`.", base_path));
+    }
+    for (method, entries) in endpoints.entries(None) {
+        // Candidates of an endpoint are listed one after another
+        let mut grouped: Vec<(&str, Vec<&Stub>)> = Vec::new();
+        for (path, stub) in &entries {
+            match grouped.last_mut() {
+                Some((last, stubs)) if last == path => stubs.push(stub),
+                _ => grouped.push((path, vec![stub])),
+            }
+        }
+        for (path, stubs) in grouped {
+            sections.push(format!("## {} {}", method, path));
+            for (i, stub) in stubs.iter().enumerate() {
+                if stubs.len() > 1 {
+                    sections.push(format!("### Candidate {}", i + 1));
+                }
+                let doc = CandidateDoc::new(method, &format!("{}{}", base_path, path), stub);
+                sections.extend(doc.description);
+                if !doc.options.is_empty() {
+                    let options: Vec<String> = doc
+                        .options
+                        .iter()
+                        .map(|option| format!("`{}`", option))
+                        .collect();
+                    sections.push(format!("Options: {}", options.join(", ")));
+                }
+                sections.push(format!("Request:\n\n```http\n{}\n```", doc.request));
+                sections.push(format!("Response:\n\n```http\n{}\n```", doc.response));
+            }
+        }
+    }
+    sections.join("\n\n") + "\n"
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::server::parse_query_pair;
+
+    #[test]
+    fn test_markdown() {
+        let mut endpoints = EndpointStore::default();
+        endpoints.add(
+            Method::GET,
+            "/search",
+            Stub {
+                description: Some("Searches users by name".to_owned()),
+                headers: vec!["Content-Type: application/json".parse().unwrap()],
+                matchers: vec![parse_query_pair("q=rust").unwrap()],
+                ..Stub::from(r#"[{"id":1}]"#)
+            },
+        );
+        endpoints.add(Method::GET, "/search", "[]");
+
+        assert_eq!(
+            markdown(&endpoints, "/api"),
+            "# Endpoints\n\n\
+             All paths are below `/api`.\n\n\
+             ## GET /search\n\n\
+             ### Candidate 1\n\n\
+             Searches users by name\n\n\
+             Options: `header content-type: application/json`, `match query:q=rust`\n\n\
+             Request:\n\n```http\nGET /api/search?q=rust\n```\n\n\
+             Response:\n\n```http\nHTTP/1.1 200 OK\ncontent-type: application/json\n\n\
+             [\n  {\n    \"id\": 1\n  }\n]\n```\n\n\
+             ### Candidate 2\n\n\
+             Request:\n\n```http\nGET /api/search\n```\n\n\
+             Response:\n\n```http\nHTTP/1.1 200 OK\n\n[]\n```\n"
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    convert::Infallible,
+    fmt,
+    future::Future,
+    num::NonZeroUsize,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_web::{
+    body::{BodySize, MessageBody},
+    rt::time::{Sleep, sleep},
+    web::Bytes,
+};
+
+use crate::util::units::{format_size, format_time, parse_duration, parse_size};
+
+/// Stream a body in chunks with a delay between them, to exercise streaming parsers and slow
+/// networks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drip {
+    pub chunk: NonZeroUsize,
+    /// Delay between chunks, the first one is sent right away
+    pub interval: Duration,
+}
+
+impl Drip {
+    /// `body` sent chunk by chunk with chunked transfer encoding
+    pub fn stream(&self, body: Bytes) -> impl MessageBody<Error = Infallible> + use<> {
+        DripBody {
+            rest: body,
+            drip: *self,
+            next: None,
+        }
+    }
+}
+
+/// e.g. `1kb/500ms`
+impl fmt::Display for Drip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}",
+            format_size(self.chunk.get()),
+            format_time(self.interval)
+        )
+    }
+}
+
+impl FromStr for Drip {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (chunk, interval) = input
+            .split_once('/')
+            .ok_or_else(|| format!("`{}` is not of the form `1kb/500ms`", input))?;
+        Ok(Self {
+            chunk: NonZeroUsize::new(parse_size(chunk.trim())?)
+                .ok_or_else(|| format!("chunks of `{}` are empty", chunk.trim()))?,
+            interval: parse_duration(interval.trim())?,
+        })
+    }
+}
+
+/// Emits a chunk of `rest`, then waits for the interval before the next one
+struct DripBody {
+    rest: Bytes,
+    drip: Drip,
+    /// Delay before the next chunk, `None` if it is due
+    next: Option<Pin<Box<Sleep>>>,
+}
+
+impl MessageBody for DripBody {
+    type Error = Infallible;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let body = self.get_mut();
+        if body.rest.is_empty() {
+            return Poll::Ready(None);
+        }
+        if let Some(next) = &mut body.next
+            && next.as_mut().poll(cx).is_pending()
+        {
+            return Poll::Pending;
+        }
+        let chunk = body
+            .rest
+            .split_to(body.drip.chunk.get().min(body.rest.len()));
+        body.next = Some(Box::pin(sleep(body.drip.interval)));
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::time::Instant;
+
+    use actix_web::body;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_drip() {
+        let drip: Drip = "1KB/500ms".parse().unwrap();
+        assert_eq!(drip.chunk.get(), 1024);
+        assert_eq!(drip.interval, Duration::from_millis(500));
+        assert_eq!(drip.to_string(), "1kb/500ms");
+        assert!("0b/1s".parse::<Drip>().is_err());
+        assert!("1kb".parse::<Drip>().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_streams_whole_body() {
+        let drip: Drip = "4/10ms".parse().unwrap();
+        let start = Instant::now();
+        let bytes = body::to_bytes(drip.stream(Bytes::from("0123456789")))
+            .await
+            .unwrap();
+        assert_eq!(bytes.as_ref(), b"0123456789");
+        // Three chunks, so two delays
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,106 @@
+use std::io::{self, Read};
+
+use actix_web::{
+    http::header::{CONTENT_ENCODING, HeaderMap},
+    web::Bytes,
+};
+use brotli::Decompressor;
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+/// Size of the buffer brotli decompresses through
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// Decode a request body per its `Content-Encoding` header, undoing the encodings in reverse order
+/// of application. Returns `None` if the body isn't encoded. Decoded bodies larger than `limit`
+/// are an error, so a small compressed upload can't blow up in memory.
+pub fn decode_body(headers: &HeaderMap, raw: &Bytes, limit: usize) -> Option<io::Result<Bytes>> {
+    let encodings: Vec<String> = headers
+        .get_all(CONTENT_ENCODING)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|encoding| encoding.trim().to_ascii_lowercase())
+        .filter(|encoding| !encoding.is_empty() && encoding != "identity")
+        .collect();
+    if encodings.is_empty() {
+        return None;
+    }
+    let decoded = encodings
+        .iter()
+        .rev()
+        .try_fold(raw.clone(), |body, encoding| decode(encoding, &body, limit));
+    Some(decoded)
+}
+
+fn decode(encoding: &str, body: &[u8], limit: usize) -> io::Result<Bytes> {
+    let reader: Box<dyn Read + '_> = match encoding {
+        "gzip" | "x-gzip" => Box::new(GzDecoder::new(body)),
+        "deflate" => Box::new(ZlibDecoder::new(body)),
+        "br" => Box::new(Decompressor::new(body, BROTLI_BUFFER_SIZE)),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported content encoding `{}`", encoding),
+            ));
+        }
+    };
+    let mut decoded = Vec::new();
+    // One byte beyond the limit tells a body of exactly the limit from a larger one
+    reader.take(limit as u64 + 1).read_to_end(&mut decoded)?;
+    if decoded.len() > limit {
+        return Err(io::Error::other(format!(
+            "decoded body exceeds {} bytes",
+            limit
+        )));
+    }
+    Ok(Bytes::from(decoded))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::io::Write;
+
+    use actix_web::http::header::HeaderValue;
+    use flate2::{Compression, write::GzEncoder};
+
+    use super::*;
+
+    fn encoded(encoding: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        headers
+    }
+
+    fn gzip(data: &[u8]) -> Bytes {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        Bytes::from(encoder.finish().unwrap())
+    }
+
+    #[test]
+    fn test_decode_body() {
+        let body = br#"{"type":"refund"}"#;
+        let decoded = decode_body(&encoded("gzip"), &gzip(body), 1024).unwrap();
+        assert_eq!(decoded.unwrap().as_ref(), body);
+
+        let mut brotli = Vec::new();
+        brotli::CompressorWriter::new(&mut brotli, 4096, 5, 22)
+            .write_all(body)
+            .unwrap();
+        let decoded = decode_body(&encoded("br"), &Bytes::from(brotli), 1024).unwrap();
+        assert_eq!(decoded.unwrap().as_ref(), body);
+
+        assert!(decode_body(&HeaderMap::new(), &Bytes::from_static(body), 1024).is_none());
+        assert!(decode_body(&encoded("identity"), &Bytes::from_static(body), 1024).is_none());
+        assert!(
+            decode_body(&encoded("compress"), &Bytes::from_static(body), 1024)
+                .unwrap()
+                .is_err()
+        );
+        assert!(
+            decode_body(&encoded("gzip"), &gzip(&[0; 2048]), 1024)
+                .unwrap()
+                .is_err()
+        );
+    }
+}
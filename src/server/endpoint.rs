@@ -1,26 +1,56 @@
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    num::NonZeroU32,
+};
 
-use actix_web::{http::Method, web::Bytes};
+use actix_web::http::Method;
+use rand::{Rng, seq::IndexedRandom};
+
+use crate::{
+    server::{RequestInfo, Stub},
+    util::suggest,
+};
 
 #[derive(Debug, Default)]
 pub struct PathNode {
-    body: Option<Bytes>,
+    /// Candidate stubs, highest rank first, see [`Stub::rank`]
+    stubs: Vec<Stub>,
     children: BTreeMap<String, PathNode>,
 }
 
 impl PathNode {
     fn is_empty(&self) -> bool {
-        self.body.is_none() && self.children.is_empty()
+        self.stubs.is_empty() && self.children.is_empty()
     }
 
-    fn walk(&self, path: &str) -> Option<&PathNode> {
-        let segments = path.trim_matches('/').split('/').filter(|s| !s.is_empty());
-
-        let mut current = self;
-        for segment in segments {
-            current = current.children.get(segment)?;
+    /// Find the node with stubs matching `segments`. Literal segments take precedence over
+    /// `{param}` segments, which match any single segment. The keys of the nodes on the way are
+    /// left in `matched`.
+    fn resolve<'a>(
+        &'a self,
+        segments: &[&str],
+        matched: &mut Vec<&'a str>,
+    ) -> Option<&'a PathNode> {
+        let Some((segment, rest)) = segments.split_first() else {
+            return (!self.stubs.is_empty()).then_some(self);
+        };
+        let literal = self.children.get_key_value(*segment);
+        let params = self.children.iter().filter(|(key, _)| is_param(key));
+        for (key, child) in literal.into_iter().chain(params) {
+            matched.push(key);
+            if let Some(node) = child.resolve(rest, matched) {
+                return Some(node);
+            }
+            matched.pop();
         }
-        Some(current)
+        None
+    }
+
+    /// Walk the path without creating nodes and without resolving parameters
+    fn walk(&self, path: &str) -> Option<&PathNode> {
+        path.split('/')
+            .filter(|s| !s.is_empty())
+            .try_fold(self, |node, segment| node.children.get(segment))
     }
 
     /// Walk the path, creating nodes as needed. Always succeeds.
@@ -35,34 +65,49 @@ impl PathNode {
     }
 
     /// Recursively delete at path and prune empty nodes.
-    /// Returns (removed_body, should_prune_self)
-    fn delete_recursive(&mut self, segments: &[&str]) -> (Option<Bytes>, bool) {
+    /// Returns (removed_stubs, should_prune_self)
+    fn delete_recursive(&mut self, segments: &[&str]) -> (Vec<Stub>, bool) {
         if segments.is_empty() {
-            let body = self.body.take();
-            return (body, self.is_empty());
+            let stubs = std::mem::take(&mut self.stubs);
+            return (stubs, self.is_empty());
         }
 
         let segment = segments[0];
         let rest = &segments[1..];
 
         if let Some(child) = self.children.get_mut(segment) {
-            let (body, should_prune) = child.delete_recursive(rest);
+            let (stubs, should_prune) = child.delete_recursive(rest);
             if should_prune {
                 self.children.remove(segment);
             }
-            return (body, self.is_empty());
+            return (stubs, self.is_empty());
         }
 
-        (None, false)
+        (Vec::new(), false)
+    }
+
+    /// Remove all empty descendants. Returns the number of removed nodes.
+    fn prune(&mut self) -> usize {
+        let mut pruned = 0;
+        self.children.retain(|_, child| {
+            pruned += child.prune();
+            let keep = !child.is_empty();
+            if !keep {
+                pruned += 1;
+            }
+            keep
+        });
+        pruned
     }
-    fn collect_entries<'a>(&'a self, path: String, results: &mut Vec<(String, &'a Bytes)>) {
-        if let Some(body) = &self.body {
-            let full_path = if path.is_empty() {
-                "/".to_string()
-            } else {
-                path.clone()
-            };
-            results.push((full_path, body));
+
+    fn collect_entries<'a>(&'a self, path: String, results: &mut Vec<(String, &'a Stub)>) {
+        let full_path = if path.is_empty() {
+            "/".to_string()
+        } else {
+            path.clone()
+        };
+        for stub in &self.stubs {
+            results.push((full_path.clone(), stub));
         }
         for (segment, child) in &self.children {
             child.collect_entries(format!("{}/{}", path, segment), results);
@@ -70,69 +115,201 @@ impl PathNode {
     }
 }
 
+/// One of the variants among the highest ranked of `candidates`, picked at random in proportion to
+/// their weights, or the first candidate without any variants
+fn choose_variant<'a>(
+    candidates: impl Iterator<Item = &'a Stub>,
+    rng: &mut impl Rng,
+) -> Option<&'a Stub> {
+    let mut candidates = candidates.peekable();
+    let first = *candidates.peek()?;
+    let variants: Vec<&Stub> = candidates
+        .take_while(|stub| stub.rank() == first.rank())
+        .filter(|stub| stub.weight.is_some())
+        .collect();
+    match variants.choose_weighted(rng, |stub| stub.weight.map_or(0, NonZeroU32::get)) {
+        Ok(variant) => Some(variant),
+        Err(_) => Some(first),
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct EndpointStore {
     entries: HashMap<Method, PathNode>,
 }
 
 impl EndpointStore {
-    /// Add or update an endpoint. Returns true if it was an update. *Note:* `method` needs to be
-    /// owned for potential insertion (if not updating)
-    pub fn add(&mut self, method: Method, path: &str, body: Bytes) -> bool {
+    /// Add or update an endpoint. A stub replaces the candidate of equal priority and matchers and
+    /// is otherwise kept alongside the existing ones. Returns true if it was an update. *Note:* `method` needs
+    /// to be owned for potential insertion (if not updating)
+    pub fn add(&mut self, method: Method, path: &str, stub: impl Into<Stub>) -> bool {
+        let stub = stub.into();
         let root = self.entries.entry(method).or_default();
         let node = root.walk_or_create(path);
-        let was_update = node.body.is_some();
-        node.body = Some(body);
-        was_update
+        if let Some(existing) = node.stubs.iter_mut().find(|s| s.same_slot(&stub)) {
+            *existing = stub;
+            return true;
+        }
+        let index = node.stubs.partition_point(|s| s.rank() > stub.rank());
+        node.stubs.insert(index, stub);
+        false
+    }
+
+    /// The stub answering a request, i.e. the highest priority candidate of the matching endpoint
+    pub fn get(&self, method: &Method, path: &str) -> Option<&Stub> {
+        self.lookup(method, path).map(|(_, stub)| stub)
+    }
+
+    /// Like [`Self::get`], along with the path of the matching endpoint as registered, e.g.
+    /// `/users/{id}` for `/users/42`
+    pub fn lookup(&self, method: &Method, path: &str) -> Option<(String, &Stub)> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut matched = Vec::new();
+        let node = self.entries.get(method)?.resolve(&segments, &mut matched)?;
+        Some((format!("/{}", matched.join("/")), node.stubs.first()?))
+    }
+
+    /// Like [`Self::lookup`], but only considering candidates whose matchers accept `request`.
+    /// `call` numbers the request among those to the matching endpoint, given its path as
+    /// registered.
+    pub fn lookup_request(
+        &self,
+        method: &Method,
+        path: &str,
+        request: &RequestInfo,
+        call: impl Fn(&str) -> u64,
+    ) -> Option<(String, &Stub)> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut matched = Vec::new();
+        let node = self.entries.get(method)?.resolve(&segments, &mut matched)?;
+        let endpoint = format!("/{}", matched.join("/"));
+        let request = RequestInfo {
+            call: call(&endpoint),
+            ..request.clone()
+        };
+        let matching = node.stubs.iter().filter(|stub| stub.matches(&request));
+        let stub = choose_variant(matching, &mut rand::rng())?;
+        Some((endpoint, stub))
     }
 
-    pub fn get(&self, method: &Method, path: &str) -> Option<&Bytes> {
-        self.entries.get(method)?.walk(path)?.body.as_ref()
+    /// All candidates registered at exactly `path`, without resolving parameters
+    pub fn candidates(&self, method: &Method, path: &str) -> &[Stub] {
+        self.entries
+            .get(method)
+            .and_then(|root| root.walk(path))
+            .map_or(&[], |node| &node.stubs)
     }
 
-    /// Delete an endpoint. Returns the removed body if it existed.
+    /// Delete an endpoint with all its candidates. Returns the removed stubs.
     /// Prunes empty nodes up to (and including) the method root.
-    pub fn delete(&mut self, method: &Method, path: &str) -> Option<Bytes> {
+    pub fn delete(&mut self, method: &Method, path: &str) -> Vec<Stub> {
         let segments: Vec<&str> = path
             .trim_matches('/')
             .split('/')
             .filter(|s| !s.is_empty())
             .collect();
 
-        let root = self.entries.get_mut(method)?;
-        let (body, should_prune_root) = root.delete_recursive(&segments);
+        let Some(root) = self.entries.get_mut(method) else {
+            return Vec::new();
+        };
+        let (stubs, should_prune_root) = root.delete_recursive(&segments);
 
         if should_prune_root {
             self.entries.remove(method);
         }
 
-        body
+        stubs
+    }
+
+    /// Remove the candidate of `path` in the same slot as `stub`, keeping the others. Returns
+    /// whether there was one.
+    pub fn remove(&mut self, method: &Method, path: &str, stub: &Stub) -> bool {
+        let stubs = self.delete(method, path);
+        let removed = stubs.iter().any(|s| s.same_slot(stub));
+        for s in stubs.into_iter().filter(|s| !s.same_slot(stub)) {
+            self.add(method.clone(), path, s);
+        }
+        removed
     }
 
-    fn entries_by(&self, method: &Method) -> Vec<(String, &Bytes)> {
+    fn entries_by(&self, method: &Method) -> Vec<(String, &Stub)> {
         let mut results = Vec::new();
         if let Some(root) = self.entries.get(method) {
             root.collect_entries(String::new(), &mut results)
         }
         results
     }
-    pub fn entries(&self, by_method: Option<&Method>) -> Vec<(&Method, Vec<(String, &Bytes)>)> {
-        self.entries
+    /// All endpoints grouped by method, ordered by method name for stable output. Endpoints with
+    /// several candidates appear once per candidate.
+    pub fn entries(&self, by_method: Option<&Method>) -> Vec<(&Method, Vec<(String, &Stub)>)> {
+        let mut entries: Vec<_> = self
+            .entries
             .keys()
             .filter(|k| by_method.is_none_or(|m| *k == m))
             .map(|m| (m, self.entries_by(m)))
-            .collect()
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+        entries
     }
 
     pub fn is_empty(&self) -> bool {
         self.entries.is_empty()
     }
+
+    /// Number of stubs across all methods
+    pub fn len(&self) -> usize {
+        self.entries(None)
+            .iter()
+            .map(|(_, children)| children.len())
+            .sum()
+    }
+
+    /// Restore structural invariants (no empty nodes, no empty method roots), e.g. after a panic
+    /// interrupted a modification. Returns the number of removed nodes.
+    pub fn repair(&mut self) -> usize {
+        let mut pruned = 0;
+        self.entries.retain(|_, root| {
+            pruned += root.prune();
+            !root.is_empty()
+        });
+        pruned
+    }
+
+    /// Find the registered endpoint most similar to `method` `path`, e.g. to suggest a fix for a
+    /// typo. A differing method counts as one edit.
+    pub fn closest(&self, method: &Method, path: &str) -> Option<(Method, String)> {
+        let target = normalize(path);
+        self.entries(None)
+            .into_iter()
+            .flat_map(|(m, children)| children.into_iter().map(move |(p, _)| (m, p)))
+            .map(|(m, p)| {
+                let distance = suggest::edit_distance(&target, &p) + usize::from(m != method);
+                (distance, m, p)
+            })
+            .filter(|(distance, ..)| *distance <= suggest::threshold(&target))
+            .min_by_key(|(distance, ..)| *distance)
+            .map(|(_, m, p)| (m.clone(), p))
+    }
+}
+
+/// Whether a path segment is a `{param}` placeholder
+pub fn is_param(segment: &str) -> bool {
+    segment.len() > 2 && segment.starts_with('{') && segment.ends_with('}')
+}
+
+/// Canonical form of a path as produced by [`EndpointStore::entries`]
+fn normalize(path: &str) -> String {
+    format!("/{}", path.trim_matches('/'))
 }
 
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
+    use actix_web::{http::StatusCode, web::Bytes};
+    use rand::{SeedableRng, rngs::StdRng};
+
     use super::*;
+    use crate::server::parse_query_pair;
 
     #[test]
     fn test_add_endpoint() {
@@ -151,7 +328,7 @@ mod tests {
 
         assert!(was_update);
         assert_eq!(
-            store.get(&Method::GET, "/users").unwrap().as_ref(),
+            store.get(&Method::GET, "/users").unwrap().body.as_ref(),
             b"[1,2,3]"
         );
     }
@@ -162,9 +339,12 @@ mod tests {
         store.add(Method::GET, "/users", Bytes::from("get"));
         store.add(Method::POST, "/users", Bytes::from("post"));
 
-        assert_eq!(store.get(&Method::GET, "/users").unwrap().as_ref(), b"get");
         assert_eq!(
-            store.get(&Method::POST, "/users").unwrap().as_ref(),
+            store.get(&Method::GET, "/users").unwrap().body.as_ref(),
+            b"get"
+        );
+        assert_eq!(
+            store.get(&Method::POST, "/users").unwrap().body.as_ref(),
             b"post"
         );
     }
@@ -199,7 +379,7 @@ mod tests {
         store.add(Method::GET, "/users", Bytes::from("[]"));
 
         let removed = store.delete(&Method::GET, "/users");
-        assert!(removed.is_some());
+        assert_eq!(removed.len(), 1);
         assert!(store.get(&Method::GET, "/users").is_none());
     }
 
@@ -207,7 +387,7 @@ mod tests {
     fn test_delete_nonexistent() {
         let mut store = EndpointStore::default();
         let removed = store.delete(&Method::GET, "/nothing");
-        assert!(removed.is_none());
+        assert!(removed.is_empty());
     }
 
     #[test]
@@ -241,11 +421,158 @@ mod tests {
         assert!(store.get(&Method::GET, "/users/").is_some());
     }
 
+    #[test]
+    fn test_entries_ordered_by_method() {
+        let mut store = EndpointStore::default();
+        store.add(Method::POST, "/users", Bytes::from("post"));
+        store.add(Method::DELETE, "/users", Bytes::from("delete"));
+        store.add(Method::GET, "/users", Bytes::from("get"));
+
+        let methods: Vec<_> = store.entries(None).into_iter().map(|(m, _)| m).collect();
+        assert_eq!(methods, [Method::DELETE, Method::GET, Method::POST]);
+    }
+
+    #[test]
+    fn test_repair_prunes_empty_nodes() {
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/a/b", Bytes::from("keep"));
+        store
+            .entries
+            .entry(Method::GET)
+            .or_default()
+            .walk_or_create("/a/c/d");
+        store.entries.entry(Method::POST).or_default();
+
+        assert_eq!(store.repair(), 2);
+        assert_eq!(store.entries(None).len(), 1);
+        assert!(store.get(&Method::GET, "/a/b").is_some());
+    }
+
+    #[test]
+    fn test_closest_suggests_typo_fix() {
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/users", Bytes::from("[]"));
+        store.add(Method::GET, "/orders", Bytes::from("[]"));
+
+        assert_eq!(
+            store.closest(&Method::GET, "/usres"),
+            Some((Method::GET, "/users".to_owned()))
+        );
+        assert_eq!(
+            store.closest(&Method::POST, "users"),
+            Some((Method::GET, "/users".to_owned()))
+        );
+        assert!(
+            store
+                .closest(&Method::GET, "/completely/different")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_path_params() {
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/users/{id}", Bytes::from("user"));
+        store.add(Method::GET, "/users/me", Bytes::from("me"));
+        store.add(Method::GET, "/users/{id}/posts", Bytes::from("posts"));
+
+        assert_eq!(
+            store.get(&Method::GET, "/users/42").unwrap().body.as_ref(),
+            b"user"
+        );
+        assert_eq!(
+            store.get(&Method::GET, "/users/me").unwrap().body.as_ref(),
+            b"me"
+        );
+        assert_eq!(
+            store
+                .get(&Method::GET, "/users/me/posts")
+                .unwrap()
+                .body
+                .as_ref(),
+            b"posts"
+        );
+        assert!(store.get(&Method::GET, "/users").is_none());
+        assert!(store.get(&Method::GET, "/users/42/comments").is_none());
+        assert_eq!(
+            store.lookup(&Method::GET, "/users/42/posts").unwrap().0,
+            "/users/{id}/posts"
+        );
+    }
+
     #[test]
     fn test_root_path() {
         let mut store = EndpointStore::default();
         store.add(Method::GET, "/", Bytes::from("root"));
 
-        assert_eq!(store.get(&Method::GET, "/").unwrap().as_ref(), b"root");
+        assert_eq!(store.get(&Method::GET, "/").unwrap().body.as_ref(), b"root");
+    }
+
+    #[test]
+    fn test_candidates_ordered_by_priority() {
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/users", Bytes::from("default"));
+        let preferred = Stub {
+            priority: 1,
+            ..Stub::from("preferred")
+        };
+        assert!(!store.add(Method::GET, "/users", preferred));
+
+        assert_eq!(
+            store.get(&Method::GET, "/users").unwrap().body.as_ref(),
+            b"preferred"
+        );
+        assert_eq!(store.candidates(&Method::GET, "/users").len(), 2);
+        assert!(store.candidates(&Method::GET, "/users/1").is_empty());
+        assert_eq!(store.delete(&Method::GET, "/users").len(), 2);
+    }
+
+    #[test]
+    fn test_lookup_request_prefers_matching_candidates() {
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/search", "all");
+        let rust = Stub {
+            matchers: vec![parse_query_pair("q=rust").unwrap()],
+            ..Stub::from("rust")
+        };
+        assert!(!store.add(Method::GET, "/search", rust));
+        let body = |query| {
+            let request = RequestInfo::new(query);
+            let (_, stub) = store
+                .lookup_request(&Method::GET, "/search", &request, |_| 1)
+                .unwrap();
+            stub.body.clone()
+        };
+
+        assert_eq!(body("q=rust&page=2").as_ref(), b"rust");
+        assert_eq!(body("q=go").as_ref(), b"all");
+        assert_eq!(body("").as_ref(), b"all");
+    }
+
+    #[test]
+    fn test_choose_weighted_variant() {
+        let variant = |status: u16, weight: u32| Stub {
+            status: Some(StatusCode::from_u16(status).unwrap()),
+            weight: NonZeroU32::new(weight),
+            ..Stub::default()
+        };
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/pay", variant(200, 90));
+        store.add(Method::GET, "/pay", variant(500, 10));
+        // Variants take precedence over a candidate of the same rank without a weight
+        store.add(Method::GET, "/pay", Stub::from("unreachable"));
+        assert_eq!(store.candidates(&Method::GET, "/pay").len(), 3);
+
+        let candidates = store.candidates(&Method::GET, "/pay");
+        let mut rng = StdRng::seed_from_u64(7);
+        let errors = (0..1000)
+            .filter_map(|_| choose_variant(candidates.iter(), &mut rng))
+            .filter(|stub| stub.status == Some(StatusCode::INTERNAL_SERVER_ERROR))
+            .count();
+        assert!((50..150).contains(&errors), "{} errors", errors);
+
+        // Adding a variant again replaces it rather than adding another
+        store.add(Method::GET, "/pay", variant(500, 30));
+        assert_eq!(store.candidates(&Method::GET, "/pay").len(), 3);
     }
 }
@@ -0,0 +1,142 @@
+use std::{fmt, str::FromStr};
+
+use actix_web::{
+    HttpResponse,
+    http::{
+        Method,
+        header::{ETAG, EntityTag, HeaderMap, HeaderValue, IF_NONE_MATCH},
+    },
+};
+use sha2::{Digest, Sha256};
+
+/// The ETag sent with the responses of an endpoint, so clients can revalidate their cached copy
+/// with `If-None-Match`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ETag {
+    /// Derived from the body, so it changes whenever the body does
+    Auto,
+    /// The same tag whatever the body, e.g. to keep a client's copy current after a change
+    Fixed(EntityTag),
+}
+
+impl ETag {
+    /// Tag of a response with `body`
+    pub fn tag(&self, body: &[u8]) -> EntityTag {
+        match self {
+            ETag::Auto => {
+                let digest = Sha256::digest(body);
+                let hex: String = digest[..8]
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                EntityTag::new_strong(hex)
+            }
+            ETag::Fixed(tag) => tag.clone(),
+        }
+    }
+}
+
+impl FromStr for ETag {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        if input == "auto" {
+            return Ok(ETag::Auto);
+        }
+        // Quotes are optional for strong tags, e.g. `v1` for `"v1"`
+        let quoted = if input.ends_with('"') {
+            input.to_owned()
+        } else {
+            format!("\"{}\"", input)
+        };
+        quoted.parse().map(ETag::Fixed).map_err(|_| {
+            format!(
+                "`{}` is not an ETag, use `auto` or a tag like `v1` or `W/\"v1\"`",
+                input
+            )
+        })
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ETag::Auto => write!(f, "auto"),
+            ETag::Fixed(tag) => write!(f, "{}", tag),
+        }
+    }
+}
+
+/// Whether the `If-None-Match` header of a request lists `tag` or is `*`, i.e. the client
+/// already has the current version. Weak tags match like strong ones, as the header asks for.
+pub fn is_cached(headers: &HeaderMap, tag: &EntityTag) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|listed| {
+            listed == "*"
+                || listed
+                    .parse::<EntityTag>()
+                    .is_ok_and(|listed| listed.weak_eq(tag))
+        })
+}
+
+/// Answer to a request whose client has the current version: 304 without a body for reads,
+/// 412 for other methods, which must not be performed
+pub fn not_modified(method: &Method, tag: &EntityTag) -> HttpResponse {
+    if method == Method::GET || method == Method::HEAD {
+        let mut response = HttpResponse::NotModified().finish();
+        set_header(&mut response, tag);
+        response
+    } else {
+        HttpResponse::PreconditionFailed()
+            .json(serde_json::json!({"error": "precondition failed", "etag": tag.to_string()}))
+    }
+}
+
+/// Send `tag` in the `ETag` header of `response`
+pub fn set_header(response: &mut HttpResponse, tag: &EntityTag) {
+    if let Ok(value) = HeaderValue::from_str(&tag.to_string()) {
+        response.headers_mut().insert(ETAG, value);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revalidate() {
+        let tag = ETag::Auto.tag(br#"{"id":1}"#);
+        assert_eq!(tag, ETag::Auto.tag(br#"{"id":1}"#));
+        assert_ne!(tag, ETag::Auto.tag(br#"{"id":2}"#));
+
+        let mut headers = HeaderMap::new();
+        assert!(!is_cached(&headers, &tag));
+        let listed = format!(r#""other", W/"{}""#, tag.tag());
+        headers.insert(IF_NONE_MATCH, listed.parse().unwrap());
+        assert!(is_cached(&headers, &tag));
+        headers.insert(IF_NONE_MATCH, "*".parse().unwrap());
+        assert!(is_cached(&headers, &tag));
+
+        assert_eq!(not_modified(&Method::GET, &tag).status().as_u16(), 304);
+        assert_eq!(not_modified(&Method::PUT, &tag).status().as_u16(), 412);
+    }
+
+    #[test]
+    fn test_parse_etag() {
+        assert_eq!("auto".parse::<ETag>().unwrap(), ETag::Auto);
+        let fixed: ETag = "v1".parse().unwrap();
+        assert_eq!(fixed.to_string(), "\"v1\"");
+        assert_eq!(fixed.to_string().parse::<ETag>().unwrap(), fixed);
+        assert_eq!(
+            r#"W/"v1""#.parse::<ETag>().unwrap().to_string(),
+            r#"W/"v1""#
+        );
+        assert!("a b".parse::<ETag>().is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use std::{fmt, str::FromStr, sync::Arc, time::Duration};
+
+use actix_http::{HttpMessage, Request, Response, StatusCode, body::BoxBody};
+use actix_web::HttpResponse;
+
+use crate::{
+    server::{RequestInfo, ServerState, parse_status},
+    util::units::{format_time, parse_duration},
+};
+
+/// How an endpoint answers `Expect: 100-continue`, sent by clients before uploading a large body
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContinueBehavior {
+    /// Send `100 Continue` only after a while, clients may give up waiting and send the body
+    Delay(Duration),
+    /// Answer with a final status instead, so the body is never sent
+    Refuse(StatusCode),
+}
+
+/// e.g. `delay:2s` or `refuse:417`
+impl fmt::Display for ContinueBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContinueBehavior::Delay(delay) => write!(f, "delay:{}", format_time(*delay)),
+            ContinueBehavior::Refuse(status) => write!(f, "refuse:{}", status.as_u16()),
+        }
+    }
+}
+
+impl FromStr for ContinueBehavior {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.split_once(':') {
+            Some(("delay", delay)) => parse_duration(delay.trim()).map(ContinueBehavior::Delay),
+            None if input == "refuse" => {
+                Ok(ContinueBehavior::Refuse(StatusCode::EXPECTATION_FAILED))
+            }
+            Some(("refuse", status)) => match parse_status(status.trim())? {
+                status if status.as_u16() >= 400 => Ok(ContinueBehavior::Refuse(status)),
+                status => Err(format!("{} is not an error status", status.as_u16())),
+            },
+            _ => Err(format!(
+                "`{}` is not of the form `delay:2s`, `refuse` or `refuse:413`",
+                input
+            )),
+        }
+    }
+}
+
+/// Decide on the `100 Continue` of a request by the endpoint it would be matched with. Matchers on
+/// the body don't hold yet, as it hasn't been sent.
+pub async fn handle_expect(
+    state: Arc<ServerState>,
+    req: Request,
+) -> Result<Request, Response<BoxBody>> {
+    let route = state.route(req.uri(), req.headers());
+    let request = RequestInfo {
+        headers: req.headers().clone(),
+        client_ip: req.peer_addr().map(|addr| addr.ip()),
+        ..RequestInfo::new(req.uri().query().unwrap_or_default())
+    };
+    let behavior = state
+        .find_endpoint(req.method(), &route, &request)
+        .and_then(|(_, _, stub)| stub.expect);
+    match behavior {
+        Some(ContinueBehavior::Delay(delay)) => {
+            log::info!(
+                "Delaying 100 Continue for {} {} by {}",
+                req.method(),
+                req.path(),
+                format_time(delay)
+            );
+            actix_web::rt::time::sleep(delay).await;
+            Ok(req)
+        }
+        Some(ContinueBehavior::Refuse(status)) => {
+            log::info!(
+                "Refused 100 Continue for {} {} with {}",
+                req.method(),
+                req.path(),
+                status.as_u16()
+            );
+            Err(HttpResponse::build(status)
+                .json(serde_json::json!({"error": "expectation refused", "path": req.path()}))
+                .into())
+        }
+        None => Ok(req),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_continue_behavior() {
+        assert_eq!(
+            "delay:2s".parse::<ContinueBehavior>().unwrap(),
+            ContinueBehavior::Delay(Duration::from_secs(2))
+        );
+        assert_eq!(
+            "refuse".parse::<ContinueBehavior>().unwrap().to_string(),
+            "refuse:417"
+        );
+        assert_eq!(
+            "refuse:413".parse::<ContinueBehavior>().unwrap(),
+            ContinueBehavior::Refuse(StatusCode::PAYLOAD_TOO_LARGE)
+        );
+        assert!("refuse:200".parse::<ContinueBehavior>().is_err());
+        assert!("never".parse::<ContinueBehavior>().is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use actix_web::{
+    HttpRequest, HttpResponse,
+    http::{
+        Uri,
+        header::{
+            CONNECTION, CONTENT_ENCODING, CONTENT_LENGTH, HOST, HeaderName, HeaderValue,
+            PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, TE, TRAILER, TRANSFER_ENCODING, UPGRADE,
+        },
+    },
+    web::Bytes,
+};
+use awc::Client;
+
+/// Upstream responses beyond this size are answered with 502 instead
+const MAX_UPSTREAM_SIZE: usize = 16 * 1024 * 1024;
+/// Time the upstream has to answer a forwarded request
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(30);
+/// Headers describing a single connection rather than the message, which proxies don't forward
+const HOP_BY_HOP: [HeaderName; 8] = [
+    CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    PROXY_AUTHENTICATE,
+    PROXY_AUTHORIZATION,
+    TE,
+    TRAILER,
+    TRANSFER_ENCODING,
+    UPGRADE,
+];
+
+/// An upstream API answering the requests no endpoint matches, so only some of its routes need
+/// to be mocked, e.g. `https://api.example.com` or `http://localhost:8080/v1`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fallback {
+    upstream: Uri,
+}
+
+impl Fallback {
+    /// Forward `req` with `body` to the upstream and answer with its response. Hop-by-hop
+    /// headers are dropped, `Host` names the upstream and the client is added to
+    /// `X-Forwarded-For`. `decoded` tells whether `body` was decoded from the request's
+    /// `Content-Encoding`. The body of the upstream's response is returned as well for the
+    /// journal, unless it is encoded.
+    pub async fn forward(
+        &self,
+        client: &Client,
+        req: &HttpRequest,
+        body: Bytes,
+        decoded: bool,
+    ) -> (HttpResponse, Option<Bytes>) {
+        let url = self.url(req.uri());
+        let mut request = client
+            .request(req.method().clone(), &url)
+            .no_decompress()
+            .timeout(UPSTREAM_TIMEOUT);
+        let headers = request.headers_mut();
+        for (name, value) in req.headers() {
+            if forwarded(name, req.headers().get(CONNECTION))
+                && *name != HOST
+                && *name != CONTENT_LENGTH
+                && !(decoded && *name == CONTENT_ENCODING)
+            {
+                headers.append(name.clone(), value.clone());
+            }
+        }
+        if let Some(peer) = req.peer_addr() {
+            let forwarded_for = match req.headers().get("x-forwarded-for") {
+                Some(previous) => {
+                    format!(
+                        "{}, {}",
+                        String::from_utf8_lossy(previous.as_bytes()),
+                        peer.ip()
+                    )
+                }
+                None => peer.ip().to_string(),
+            };
+            if let Ok(value) = HeaderValue::try_from(forwarded_for) {
+                headers.insert(HeaderName::from_static("x-forwarded-for"), value);
+            }
+        }
+        let mut response = match request.send_body(body).await {
+            Ok(response) => response,
+            Err(e) => return (bad_gateway(&url, &e.to_string()), None),
+        };
+        let body = match response.body().limit(MAX_UPSTREAM_SIZE).await {
+            Ok(body) => body,
+            Err(e) => return (bad_gateway(&url, &e.to_string()), None),
+        };
+        let recorded = (!response.headers().contains_key(CONTENT_ENCODING)).then(|| body.clone());
+        let mut relayed = HttpResponse::build(response.status());
+        for (name, value) in response.headers() {
+            if forwarded(name, response.headers().get(CONNECTION)) && *name != CONTENT_LENGTH {
+                relayed.append_header((name.clone(), value.clone()));
+            }
+        }
+        (relayed.body(body), recorded)
+    }
+
+    /// URL of the upstream for a request to `uri`, below the upstream's path
+    fn url(&self, uri: &Uri) -> String {
+        let base = self.to_string();
+        let path = uri.path_and_query().map_or("/", |path| path.as_str());
+        format!("{}{}", base.trim_end_matches('/'), path)
+    }
+}
+
+/// Whether a proxy passes the header `name` on, given the `Connection` header of the message
+pub(super) fn forwarded(name: &HeaderName, connection: Option<&HeaderValue>) -> bool {
+    let listed = connection
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|option| option.trim().eq_ignore_ascii_case(name.as_str()))
+        });
+    !HOP_BY_HOP.contains(name) && !listed
+}
+
+fn bad_gateway(url: &str, reason: &str) -> HttpResponse {
+    log::warn!("Fallback to {} failed: {}", url, reason);
+    HttpResponse::BadGateway()
+        .json(serde_json::json!({"error": "upstream failed", "url": url, "reason": reason}))
+}
+
+impl fmt::Display for Fallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.upstream)
+    }
+}
+
+impl FromStr for Fallback {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let upstream: Uri = input
+            .trim()
+            .parse()
+            .map_err(|_| format!("`{}` is not a valid URL", input))?;
+        match upstream.scheme_str() {
+            Some("http" | "https") if upstream.authority().is_some() => Ok(Self { upstream }),
+            _ => Err(format!(
+                "`{}` is not an http(s) URL, e.g. `https://api.example.com`",
+                input
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstream_url() {
+        let fallback: Fallback = "http://localhost:8080/v1/".parse().unwrap();
+        let uri: Uri = "/users?page=2".parse().unwrap();
+        assert_eq!(fallback.url(&uri), "http://localhost:8080/v1/users?page=2");
+        let fallback: Fallback = "https://api.example.com".parse().unwrap();
+        assert_eq!(fallback.url(&uri), "https://api.example.com/users?page=2");
+        assert!("api.example.com".parse::<Fallback>().is_err());
+        assert!("ftp://api.example.com".parse::<Fallback>().is_err());
+    }
+
+    #[test]
+    fn test_drop_hop_by_hop_headers() {
+        let connection = HeaderValue::from_static("close, X-Debug");
+        assert!(forwarded(&CONTENT_ENCODING, Some(&connection)));
+        assert!(!forwarded(&TRANSFER_ENCODING, None));
+        assert!(!forwarded(
+            &HeaderName::from_static("x-debug"),
+            Some(&connection)
+        ));
+    }
+}
@@ -0,0 +1,129 @@
+use std::{fmt, str::FromStr};
+
+use actix_web::{
+    HttpResponse,
+    body::{BodySize, MessageBody},
+    http::{
+        ConnectionType,
+        header::{CONTENT_LENGTH, HeaderValue},
+    },
+};
+
+use crate::server::{compression::CompressOverride, malformed::UnsizedBody};
+
+/// How the end of the body is announced, which actix picks by whether the size is known up
+/// front. Clients take different code paths for each, and some trust `Content-Length` blindly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framing {
+    /// `Content-Length`, also for bodies that are streamed, e.g. with `--drip`
+    Length,
+    /// `Transfer-Encoding: chunked`, also for bodies of known size
+    Chunked,
+    /// `Content-Length` claims half the body, so the rest is read as the start of the next
+    /// response on the connection
+    ShortLength,
+    /// `Content-Length` claims more than the body, then the connection closes
+    LongLength,
+}
+
+impl Framing {
+    /// Send `response`, whose body has `size` bytes, with this framing. Compression is skipped,
+    /// as it would replace the body and its framing.
+    pub fn apply(&self, response: HttpResponse, size: usize) -> HttpResponse {
+        if *self == Framing::Length && response.body().size() != BodySize::Stream {
+            return response;
+        }
+        let (response, body) = response.into_parts();
+        // Streamed bodies are written as they come, without chunking if the length is set by hand
+        let mut response = match body.try_into_bytes() {
+            Ok(bytes) => response
+                .set_body(UnsizedBody(Some(bytes)))
+                .map_into_boxed_body(),
+            Err(body) => response.set_body(body),
+        };
+        let claimed = match self {
+            Framing::Length => Some(size),
+            Framing::Chunked => None,
+            Framing::ShortLength => Some(size / 2),
+            Framing::LongLength => Some(2 * size + 1),
+        };
+        if let Some(claimed) = claimed {
+            response.head_mut().no_chunking(true);
+            response
+                .headers_mut()
+                .insert(CONTENT_LENGTH, HeaderValue::from(claimed));
+        }
+        if *self == Framing::LongLength {
+            response
+                .head_mut()
+                .set_connection_type(ConnectionType::Close);
+        }
+        response.extensions_mut().insert(CompressOverride(false));
+        response
+    }
+}
+
+impl fmt::Display for Framing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Framing::Length => write!(f, "length"),
+            Framing::Chunked => write!(f, "chunked"),
+            Framing::ShortLength => write!(f, "short-length"),
+            Framing::LongLength => write!(f, "long-length"),
+        }
+    }
+}
+
+impl FromStr for Framing {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "length" => Ok(Framing::Length),
+            "chunked" => Ok(Framing::Chunked),
+            "short-length" => Ok(Framing::ShortLength),
+            "long-length" => Ok(Framing::LongLength),
+            _ => Err(format!(
+                "unknown framing `{}`, use length, chunked, short-length or long-length",
+                input
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::web::Bytes;
+
+    use super::*;
+    use crate::server::Drip;
+
+    #[test]
+    fn test_parse_framing() {
+        for name in ["length", "chunked", "short-length", "long-length"] {
+            assert_eq!(name.parse::<Framing>().unwrap().to_string(), name);
+        }
+        assert!("eof".parse::<Framing>().is_err());
+    }
+
+    #[test]
+    fn test_apply_framing() {
+        let response = Framing::Chunked.apply(HttpResponse::Ok().body("abc"), 3);
+        assert_eq!(response.body().size(), BodySize::Stream);
+        assert!(response.headers().get(CONTENT_LENGTH).is_none());
+
+        let drip = Drip {
+            chunk: 1.try_into().unwrap(),
+            interval: Duration::from_millis(10),
+        };
+        let streamed = HttpResponse::Ok().body(drip.stream(Bytes::from("abcd")));
+        let response = Framing::Length.apply(streamed, 4);
+        assert_eq!(response.headers().get(CONTENT_LENGTH).unwrap(), "4");
+
+        let response = Framing::ShortLength.apply(HttpResponse::Ok().body("abcd"), 4);
+        assert_eq!(response.headers().get(CONTENT_LENGTH).unwrap(), "2");
+    }
+}
@@ -0,0 +1,176 @@
+use actix_web::http::Method;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Value, json};
+
+use crate::{
+    server::{RequestInfo, RequestRecord},
+    util::build,
+};
+
+/// A HAR 1.2 document of `records`, in order, for browser devtools and other tools reading
+/// recorded traffic. The journal doesn't keep what was sent back, so responses carry the status
+/// and the body of the endpoint that answered, before transformations, without headers.
+pub fn har_document<'a>(records: impl IntoIterator<Item = &'a RequestRecord>) -> Value {
+    let entries: Vec<Value> = records.into_iter().map(entry).collect();
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": {"name": "adaptive_rest", "version": build::VERSION},
+            "entries": entries,
+        }
+    })
+}
+
+fn entry(record: &RequestRecord) -> Value {
+    let host = record
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+        .map_or("localhost", |(_, host)| host.as_str());
+    let url = match record.query.as_str() {
+        "" => format!("http://{}{}", host, record.path),
+        query => format!("http://{}{}?{}", host, record.path, query),
+    };
+    let headers: Vec<Value> = record
+        .headers
+        .iter()
+        .map(|(name, value)| json!({"name": name, "value": value}))
+        .collect();
+    let query: Vec<Value> = RequestInfo::new(&record.query)
+        .query
+        .into_iter()
+        .map(|(name, value)| json!({"name": name, "value": value}))
+        .collect();
+    let millis = record.elapsed.as_secs_f64() * 1000.0;
+    let mut request = json!({
+        "method": record.method.as_str(),
+        "url": url,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": headers,
+        "queryString": query,
+        "headersSize": -1,
+        "bodySize": record.body_size,
+    });
+    if !record.body.is_empty() || ![Method::GET, Method::HEAD].contains(&record.method) {
+        let mime_type = record
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map_or("", |(_, value)| value.as_str());
+        request["postData"] = json!({
+            "mimeType": mime_type,
+            "text": String::from_utf8_lossy(&record.body),
+        });
+    }
+    let mut notes = Vec::new();
+    if let Some(endpoint) = &record.endpoint {
+        notes.push(format!("answered by {} {}", record.method, endpoint));
+    }
+    notes.extend(record.fault.iter().map(|fault| format!("fault: {}", fault)));
+    notes.extend(
+        record
+            .failures
+            .iter()
+            .map(|failure| format!("assertion failed: {}", failure)),
+    );
+    if record.truncated() {
+        notes.push("request body cut off by the journal".to_owned());
+    }
+    json!({
+        "startedDateTime": record.timestamp.to_rfc3339(),
+        "time": millis,
+        "request": request,
+        "response": {
+            "status": record.status.as_u16(),
+            "statusText": record.status.canonical_reason().unwrap_or_default(),
+            "httpVersion": "HTTP/1.1",
+            "cookies": [],
+            "headers": [],
+            "content": content(record),
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": -1,
+        },
+        "cache": {},
+        "timings": {"send": 0, "wait": millis, "receive": 0},
+        "comment": notes.join(", "),
+    })
+}
+
+/// The body of the endpoint that answered, base64 encoded unless it is text
+fn content(record: &RequestRecord) -> Value {
+    let Some(body) = &record.stub else {
+        return json!({"size": 0, "mimeType": ""});
+    };
+    match std::str::from_utf8(body) {
+        Ok(text) => {
+            let mime_type = if serde_json::from_str::<Value>(text).is_ok() {
+                "application/json"
+            } else {
+                "text/plain"
+            };
+            json!({"size": body.len(), "mimeType": mime_type, "text": text})
+        }
+        Err(_) => json!({
+            "size": body.len(),
+            "mimeType": "application/octet-stream",
+            "text": STANDARD.encode(body),
+            "encoding": "base64",
+        }),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{http::StatusCode, web::Bytes};
+    use chrono::Local;
+
+    use super::*;
+
+    #[test]
+    fn test_har_document() {
+        let record = RequestRecord {
+            id: 3,
+            timestamp: Local::now(),
+            method: Method::POST,
+            path: "/orders".to_owned(),
+            query: "dry_run=true&tag=a%20b".to_owned(),
+            headers: vec![
+                ("host".to_owned(), "localhost:8080".to_owned()),
+                ("content-type".to_owned(), "application/json".to_owned()),
+            ],
+            body: Bytes::from_static(br#"{"sku":"A1"}"#),
+            body_size: 12,
+            encoded_size: None,
+            status: StatusCode::CREATED,
+            stub: Some(Bytes::from_static(br#"{"id":1}"#)),
+            response: None,
+            endpoint: Some("/orders".to_owned()),
+            elapsed: Duration::from_millis(5),
+            fault: None,
+            failures: Vec::new(),
+            violations: Vec::new(),
+        };
+        let har = har_document([&record]);
+        let entry = &har["log"]["entries"][0];
+        assert_eq!(har["log"]["version"], "1.2");
+        assert_eq!(
+            entry["request"]["url"],
+            "http://localhost:8080/orders?dry_run=true&tag=a%20b"
+        );
+        assert_eq!(
+            entry["request"]["queryString"][1],
+            json!({"name": "tag", "value": "a b"})
+        );
+        assert_eq!(entry["request"]["postData"]["text"], r#"{"sku":"A1"}"#);
+        assert_eq!(entry["response"]["status"], 201);
+        assert_eq!(entry["response"]["statusText"], "Created");
+        assert_eq!(entry["response"]["content"]["mimeType"], "application/json");
+        assert_eq!(entry["time"], 5.0);
+        assert_eq!(entry["comment"], "answered by POST /orders");
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Mutex, PoisonError, RwLock,
+        mpsc::{self, Sender},
+    },
+    thread::{self, JoinHandle},
+};
+
+use actix_web::http::{
+    Method, StatusCode,
+    header::{HeaderMap, HeaderName},
+};
+use chrono::Local;
+use serde_json::{Value, json};
+
+use crate::server::ResponseHeader;
+
+/// When a hook runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookStage {
+    /// Before the endpoint's own response is built, skipped when a fault or a rejection answers
+    /// instead. Header actions edit the headers of the endpoint for this response.
+    Before,
+    /// On the finished response right before it is sent. Header actions edit the response as
+    /// sent, including default headers.
+    After,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookAction {
+    /// Send a header, replacing those of the same name
+    SetHeader(ResponseHeader),
+    RemoveHeader(HeaderName),
+    /// Count the request in a custom metric, see the `metrics` command
+    Metric(String),
+    /// Append the request as a JSON line to a file, after the response with its status
+    Append(PathBuf),
+}
+
+/// A small action run on every request an endpoint answers, e.g. `after metric checkouts`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hook {
+    pub stage: HookStage,
+    pub action: HookAction,
+}
+
+/// The request hooks run for
+#[derive(Debug)]
+pub struct HookRequest<'a> {
+    pub method: &'a Method,
+    pub path: &'a str,
+    pub query: &'a str,
+    pub body: &'a [u8],
+}
+
+impl Hook {
+    /// Run the hook before the response, editing the headers of the endpoint
+    fn before(
+        &self,
+        headers: &mut Vec<ResponseHeader>,
+        request: &HookRequest,
+        datasets: &Datasets,
+    ) -> Option<&str> {
+        match &self.action {
+            HookAction::SetHeader(header) => {
+                headers.retain(|h| h.name != header.name);
+                headers.push(header.clone());
+            }
+            HookAction::RemoveHeader(name) => headers.retain(|h| h.name != name),
+            HookAction::Metric(metric) => return Some(metric),
+            HookAction::Append(path) => datasets.append(path, request, None),
+        }
+        None
+    }
+
+    /// Run the hook on the response about to be sent
+    fn after(
+        &self,
+        headers: &mut HeaderMap,
+        request: &HookRequest,
+        status: StatusCode,
+        datasets: &Datasets,
+    ) -> Option<&str> {
+        match &self.action {
+            HookAction::SetHeader(header) => {
+                headers.insert(header.name.clone(), header.value.clone());
+            }
+            HookAction::RemoveHeader(name) => {
+                headers.remove(name);
+            }
+            HookAction::Metric(metric) => return Some(metric),
+            HookAction::Append(path) => datasets.append(path, request, Some(status)),
+        }
+        None
+    }
+}
+
+/// Run the hooks of [`HookStage::Before`] in order. Returns the metrics to count.
+pub fn run_before<'a>(
+    hooks: &'a [Hook],
+    headers: &mut Vec<ResponseHeader>,
+    request: &HookRequest,
+    datasets: &Datasets,
+) -> Vec<&'a str> {
+    hooks
+        .iter()
+        .filter(|hook| hook.stage == HookStage::Before)
+        .filter_map(|hook| hook.before(headers, request, datasets))
+        .collect()
+}
+
+/// Run the hooks of [`HookStage::After`] in order. Returns the metrics to count.
+pub fn run_after<'a>(
+    hooks: &'a [Hook],
+    headers: &mut HeaderMap,
+    request: &HookRequest,
+    status: StatusCode,
+    datasets: &Datasets,
+) -> Vec<&'a str> {
+    hooks
+        .iter()
+        .filter(|hook| hook.stage == HookStage::After)
+        .filter_map(|hook| hook.after(headers, request, status, datasets))
+        .collect()
+}
+
+/// Line of a dataset, e.g. to be appended to `./orders.jsonl`
+struct DatasetLine {
+    path: PathBuf,
+    line: String,
+    request: String,
+}
+
+/// Appends the lines of `append` hooks on a thread of its own, so requests don't wait for the
+/// disk. The thread starts with the first line and ends once the writer is dropped.
+#[derive(Debug, Default)]
+pub struct Datasets {
+    /// Directory of the loaded project, relative dataset paths are of it instead of the
+    /// working directory
+    dir: RwLock<Option<PathBuf>>,
+    writer: Mutex<Option<(Sender<DatasetLine>, JoinHandle<()>)>>,
+}
+
+impl Datasets {
+    pub fn set_dir(&self, dir: &Path) {
+        *self.dir.write().unwrap_or_else(PoisonError::into_inner) = Some(dir.to_path_buf());
+    }
+
+    fn append(&self, path: &Path, request: &HookRequest, status: Option<StatusCode>) {
+        let body = serde_json::from_slice::<Value>(request.body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(request.body).into_owned()));
+        let mut line = json!({
+            "timestamp": Local::now().to_rfc3339(),
+            "method": request.method.as_str(),
+            "path": request.path,
+            "query": request.query,
+            "body": body,
+        });
+        if let Some(status) = status {
+            line["status"] = json!(status.as_u16());
+        }
+        let path = match &*self.dir.read().unwrap_or_else(PoisonError::into_inner) {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        };
+        let line = DatasetLine {
+            path,
+            line: line.to_string(),
+            request: format!("{} {}", request.method, request.path),
+        };
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        if writer.is_none() {
+            let (sender, receiver) = mpsc::channel();
+            let spawned = thread::Builder::new()
+                .name("dataset-writer".to_owned())
+                .spawn(move || receiver.into_iter().for_each(write));
+            match spawned {
+                Ok(handle) => *writer = Some((sender, handle)),
+                Err(e) => return log::warn!("Can't start writing datasets: {}", e),
+            }
+        }
+        if let Some((sender, _)) = &*writer {
+            let _ = sender.send(line);
+        }
+    }
+}
+
+/// Lines still queued are written before the thread ends
+impl Drop for Datasets {
+    fn drop(&mut self) {
+        let writer = self
+            .writer
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some((sender, handle)) = writer {
+            drop(sender);
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Datasets are best effort, a file that can't be written must not fail the response
+fn write(line: DatasetLine) {
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&line.path)
+        .and_then(|mut file| writeln!(file, "{}", line.line));
+    if let Err(e) = written {
+        log::warn!(
+            "Can't append {} to {}: {}",
+            line.request,
+            line.path.display(),
+            e
+        );
+    }
+}
+
+/// Counters of custom metrics, by name
+#[derive(Debug, Default)]
+pub struct Metrics(BTreeMap<String, u64>);
+
+impl Metrics {
+    pub fn count(&mut self, metric: &str) {
+        *self.0.entry(metric.to_owned()).or_default() += 1;
+    }
+
+    pub fn counters(&self) -> &BTreeMap<String, u64> {
+        &self.0
+    }
+
+    pub fn reset(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// e.g. `after header X-Trace: abc`
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self.stage {
+            HookStage::Before => "before",
+            HookStage::After => "after",
+        };
+        match &self.action {
+            HookAction::SetHeader(header) => write!(f, "{} header {}", stage, header),
+            HookAction::RemoveHeader(name) => write!(f, "{} remove-header {}", stage, name),
+            HookAction::Metric(metric) => write!(f, "{} metric {}", stage, metric),
+            HookAction::Append(path) => write!(f, "{} append {}", stage, path.display()),
+        }
+    }
+}
+
+impl FromStr for Hook {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut parts = input.trim().splitn(3, ' ');
+        let (Some(stage), Some(action), Some(argument)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(format!(
+                "`{}` is not a hook like `after metric checkouts`",
+                input
+            ));
+        };
+        let stage = match stage {
+            "before" => HookStage::Before,
+            "after" => HookStage::After,
+            other => {
+                return Err(format!(
+                    "`{}` is not a hook stage, use `before` or `after`",
+                    other
+                ));
+            }
+        };
+        let argument = argument.trim();
+        let action = match action {
+            "header" => HookAction::SetHeader(argument.parse()?),
+            "remove-header" => HookAction::RemoveHeader(
+                HeaderName::try_from(argument)
+                    .map_err(|_| format!("`{}` is not a header name", argument))?,
+            ),
+            "metric" => HookAction::Metric(argument.to_owned()),
+            "append" => HookAction::Append(PathBuf::from(argument)),
+            other => {
+                return Err(format!(
+                    "unknown hook action `{}`, use header, remove-header, metric or append",
+                    other
+                ));
+            }
+        };
+        Ok(Self { stage, action })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::http::header::{HeaderValue, SERVER};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_hooks() {
+        let hook: Hook = "after header X-Trace: abc".parse().unwrap();
+        assert_eq!(hook.stage, HookStage::After);
+        assert_eq!(hook.to_string(), "after header x-trace: abc");
+        let hook: Hook = "before append ./orders.jsonl".parse().unwrap();
+        assert_eq!(
+            hook.action,
+            HookAction::Append(PathBuf::from("./orders.jsonl"))
+        );
+        assert!("after metric".parse::<Hook>().is_err());
+        assert!("during metric x".parse::<Hook>().is_err());
+        assert!("after log x".parse::<Hook>().is_err());
+    }
+
+    #[test]
+    fn test_run_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let dataset = dir.path().join("orders.jsonl");
+        let hooks: Vec<Hook> = [
+            "before header X-Stage: before".to_owned(),
+            "before metric orders".to_owned(),
+            "after remove-header Server".to_owned(),
+            "after append orders.jsonl".to_owned(),
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        let request = HookRequest {
+            method: &Method::POST,
+            path: "/orders",
+            query: "",
+            body: b"{\"id\": 7}",
+        };
+
+        let datasets = Datasets::default();
+        datasets.set_dir(dir.path());
+
+        let mut stub_headers = vec!["X-Stage: stub".parse().unwrap()];
+        assert_eq!(
+            run_before(&hooks, &mut stub_headers, &request, &datasets),
+            ["orders"]
+        );
+        assert_eq!(stub_headers.len(), 1);
+        assert_eq!(stub_headers[0].to_string(), "x-stage: before");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(SERVER, HeaderValue::from_static("mock"));
+        assert!(
+            run_after(
+                &hooks,
+                &mut headers,
+                &request,
+                StatusCode::CREATED,
+                &datasets
+            )
+            .is_empty()
+        );
+        assert!(headers.is_empty());
+        // Dropping the writer waits for the queued lines
+        drop(datasets);
+        let line: Value =
+            serde_json::from_str(&std::fs::read_to_string(&dataset).unwrap()).unwrap();
+        assert_eq!(line["body"], json!({"id": 7}));
+        assert_eq!(line["status"], 201);
+    }
+}
@@ -0,0 +1,166 @@
+use actix_web::http::Method;
+
+use crate::server::{EndpointStore, Stub};
+
+/// How to resolve an imported stub clashing with an existing one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Import nothing if there is any conflict, so the user can pick a resolution
+    Ask,
+    /// Keep the existing stub
+    Skip,
+    /// Replace the existing stub
+    Overwrite,
+    /// Keep both, the imported stub with a lower priority than all existing candidates
+    KeepBoth,
+}
+
+/// An imported stub registered at the same method, path, priority and matchers as an existing,
+/// different one
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportConflict {
+    pub method: Method,
+    pub path: String,
+    /// Names of the fields that differ, e.g. `body`
+    pub differences: Vec<&'static str>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub added: usize,
+    /// Stubs identical to an existing one, not imported again
+    pub duplicates: usize,
+    pub conflicts: Vec<ImportConflict>,
+    /// Whether the store was modified; false if conflicts need a decision first
+    pub applied: bool,
+}
+
+/// Add `entries` to `store`, detecting stubs that already exist unchanged or that would replace a
+/// different stub
+pub fn import(
+    store: &mut EndpointStore,
+    entries: Vec<(Method, String, Stub)>,
+    on_conflict: OnConflict,
+) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut accepted = Vec::new();
+    for (method, path, mut stub) in entries {
+        let candidates = store.candidates(&method, &path);
+        if candidates.contains(&stub) {
+            report.duplicates += 1;
+            continue;
+        }
+        match candidates.iter().find(|c| c.same_slot(&stub)) {
+            None => report.added += 1,
+            Some(existing) => {
+                report.conflicts.push(ImportConflict {
+                    method: method.clone(),
+                    path: path.clone(),
+                    differences: differences(existing, &stub),
+                });
+                match on_conflict {
+                    OnConflict::Ask | OnConflict::Skip => continue,
+                    OnConflict::Overwrite => {}
+                    OnConflict::KeepBoth => {
+                        let lowest = candidates.iter().map(|c| c.priority).min();
+                        stub.priority = lowest.unwrap_or(stub.priority).saturating_sub(1);
+                    }
+                }
+            }
+        }
+        accepted.push((method, path, stub));
+    }
+    if on_conflict == OnConflict::Ask && !report.conflicts.is_empty() {
+        return report;
+    }
+    for (method, path, stub) in accepted {
+        store.add(method, &path, stub);
+    }
+    report.applied = true;
+    report
+}
+
+/// Names of the fields in which `incoming` differs from `existing`, e.g. `body`
+pub fn differences(existing: &Stub, incoming: &Stub) -> Vec<&'static str> {
+    let mut differences = Vec::new();
+    if existing.body != incoming.body {
+        differences.push("body");
+    }
+    if existing.status != incoming.status {
+        differences.push("status");
+    }
+    if existing.headers != incoming.headers {
+        differences.push("headers");
+    }
+    if existing.budget != incoming.budget {
+        differences.push("budget");
+    }
+    if existing.description != incoming.description {
+        differences.push("description");
+    }
+    differences
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn store() -> EndpointStore {
+        let mut store = EndpointStore::default();
+        store.add(Method::GET, "/users", "[]");
+        store.add(Method::GET, "/orders", "[]");
+        store
+    }
+
+    fn entries() -> Vec<(Method, String, Stub)> {
+        vec![
+            (Method::GET, "/users".to_owned(), Stub::from("[]")),
+            (Method::GET, "/orders".to_owned(), Stub::from("[1]")),
+            (Method::POST, "/orders".to_owned(), Stub::from("{}")),
+        ]
+    }
+
+    #[test]
+    fn test_ask_applies_nothing_on_conflict() {
+        let mut store = store();
+        let report = import(&mut store, entries(), OnConflict::Ask);
+
+        assert!(!report.applied);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(
+            report.conflicts,
+            [ImportConflict {
+                method: Method::GET,
+                path: "/orders".to_owned(),
+                differences: vec!["body"],
+            }]
+        );
+        assert!(store.get(&Method::POST, "/orders").is_none());
+    }
+
+    #[test]
+    fn test_conflict_resolutions() {
+        let mut store = store();
+        import(&mut store, entries(), OnConflict::Skip);
+        assert_eq!(
+            store.get(&Method::GET, "/orders").unwrap().body.as_ref(),
+            b"[]"
+        );
+        assert!(store.get(&Method::POST, "/orders").is_some());
+
+        let mut store = self::store();
+        import(&mut store, entries(), OnConflict::Overwrite);
+        assert_eq!(
+            store.get(&Method::GET, "/orders").unwrap().body.as_ref(),
+            b"[1]"
+        );
+
+        let mut store = self::store();
+        import(&mut store, entries(), OnConflict::KeepBoth);
+        let candidates = store.candidates(&Method::GET, "/orders");
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].body.as_ref(), b"[]");
+        assert_eq!(candidates[1].priority, -1);
+    }
+}
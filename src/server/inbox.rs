@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use crate::server::RequestRecord;
+
+/// Number of captured requests kept, oldest are dropped first
+const INBOX_CAPACITY: usize = 500;
+
+/// Paths accepting any request with 200, keeping what was sent in full, so webhooks of third
+/// parties can be pointed at the server and looked at afterwards
+#[derive(Debug, Default)]
+pub struct Inbox {
+    paths: Vec<String>,
+    captured: VecDeque<Capture>,
+    /// Number of requests ever captured, including ones already dropped
+    total: usize,
+}
+
+/// A request received by an inbox path
+#[derive(Debug, Clone)]
+pub struct Capture {
+    /// 1-based, in order of arrival, referenced by `inbox show`
+    pub number: usize,
+    /// The request as journaled, with its body whole even if the journal cuts bodies off
+    pub record: RequestRecord,
+}
+
+impl Inbox {
+    /// Accept requests to `path`, e.g. `/hooks/stripe`. Returns false if it already did.
+    pub fn add(&mut self, path: &str) -> bool {
+        let path = normalize(path);
+        if self.paths.contains(&path) {
+            return false;
+        }
+        self.paths.push(path);
+        true
+    }
+
+    /// Stop accepting requests to `path`, keeping what it captured. Returns false if it didn't.
+    pub fn remove(&mut self, path: &str) -> bool {
+        let path = normalize(path);
+        let before = self.paths.len();
+        self.paths.retain(|p| *p != path);
+        self.paths.len() < before
+    }
+
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Whether requests to `path` are captured, ignoring a trailing slash
+    pub fn accepts(&self, path: &str) -> bool {
+        let path = normalize(path);
+        self.paths.contains(&path)
+    }
+
+    /// Keep a request, numbering it. Returns the number.
+    pub fn capture(&mut self, record: RequestRecord) -> usize {
+        self.total += 1;
+        if self.captured.len() == INBOX_CAPACITY {
+            self.captured.pop_front();
+        }
+        self.captured.push_back(Capture {
+            number: self.total,
+            record,
+        });
+        self.total
+    }
+
+    /// Look up a capture by number, if it hasn't been dropped yet
+    pub fn get(&self, number: usize) -> Option<&Capture> {
+        let index = self
+            .captured
+            .binary_search_by_key(&number, |c| c.number)
+            .ok()?;
+        self.captured.get(index)
+    }
+
+    /// Captures from oldest to newest
+    pub fn captured(&self) -> impl DoubleEndedIterator<Item = &Capture> {
+        self.captured.iter()
+    }
+
+    /// Drop the captures, numbering goes on where it was
+    pub fn clear(&mut self) -> usize {
+        let count = self.captured.len();
+        self.captured.clear();
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.captured.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.captured.is_empty()
+    }
+}
+
+fn normalize(path: &str) -> String {
+    format!("/{}", path.trim_matches('/'))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{
+        http::{Method, StatusCode},
+        web::Bytes,
+    };
+    use chrono::Local;
+
+    use super::*;
+
+    fn record(body: &'static str) -> RequestRecord {
+        RequestRecord {
+            id: 0,
+            timestamp: Local::now(),
+            method: Method::POST,
+            path: "/hooks/stripe".to_owned(),
+            query: String::new(),
+            headers: Vec::new(),
+            body: Bytes::from(body),
+            body_size: body.len(),
+            encoded_size: None,
+            status: StatusCode::OK,
+            stub: None,
+            response: None,
+            endpoint: None,
+            elapsed: Duration::ZERO,
+            fault: None,
+            failures: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_inbox() {
+        let mut inbox = Inbox::default();
+        assert!(inbox.add("hooks/stripe/"));
+        assert!(!inbox.add("/hooks/stripe"));
+        assert!(inbox.accepts("/hooks/stripe/"));
+        assert!(!inbox.accepts("/hooks"));
+
+        assert_eq!(inbox.capture(record("first")), 1);
+        assert_eq!(inbox.capture(record("second")), 2);
+        assert_eq!(inbox.get(2).unwrap().record.body, "second");
+        assert!(inbox.get(3).is_none());
+
+        assert_eq!(inbox.clear(), 2);
+        assert_eq!(inbox.capture(record("third")), 3);
+        assert!(inbox.get(1).is_none());
+        assert!(inbox.remove("/hooks/stripe"));
+        assert!(!inbox.accepts("/hooks/stripe"));
+        assert_eq!(inbox.len(), 1);
+    }
+}
@@ -0,0 +1,313 @@
+use chrono::DateTime;
+use serde_json::Value;
+
+use crate::server::{RequestInfo, RequestRecord, is_param};
+
+/// Fewest responses a template is inferred from, a single one has no varying fields
+pub const MIN_SAMPLES: usize = 2;
+
+/// Values of a request by placeholder name
+type Echoes = [(String, String)];
+
+/// A recorded JSON response and the parts of its request a template may echo
+#[derive(Debug)]
+pub struct Sample {
+    pub body: Value,
+    /// Values of the request by placeholder name, e.g. `param.id`, `query.page` or `body.user.id`
+    pub echoes: Vec<(String, String)>,
+}
+
+impl Sample {
+    /// The response the fallback upstream answered `record` with, if it was a successful JSON
+    /// response to a request for `endpoint`, e.g. `/users/{id}`
+    pub fn of(record: &RequestRecord, endpoint: &str) -> Option<Self> {
+        if !record.status.is_success() {
+            return None;
+        }
+        let body = serde_json::from_slice(record.response.as_ref()?).ok()?;
+        let segments: Vec<&str> = endpoint.split('/').collect();
+        let values: Vec<&str> = record.path.split('/').collect();
+        if segments.len() != values.len() {
+            return None;
+        }
+        let mut echoes = Vec::new();
+        for (segment, value) in segments.into_iter().zip(values) {
+            if is_param(segment) {
+                let name = &segment[1..segment.len() - 1];
+                echoes.push((format!("param.{}", name), value.to_owned()));
+            } else if segment != value {
+                return None;
+            }
+        }
+        echoes.extend(
+            RequestInfo::new(&record.query)
+                .query
+                .into_iter()
+                .map(|(name, value)| (format!("query.{}", name), value)),
+        );
+        if let Ok(json) = serde_json::from_slice::<Value>(&record.body) {
+            flatten("body", &json, &mut echoes);
+        }
+        Some(Self { body, echoes })
+    }
+}
+
+/// Add the scalar fields of a JSON request body by their dotted path
+fn flatten(prefix: &str, value: &Value, echoes: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                flatten(&format!("{}.{}", prefix, key), value, echoes);
+            }
+        }
+        Value::Array(_) | Value::Null => {}
+        Value::String(text) => echoes.push((prefix.to_owned(), text.clone())),
+        other => echoes.push((prefix.to_owned(), other.to_string())),
+    }
+}
+
+/// Infer a body for the `template` transformation from several responses of an endpoint.
+/// Fields that are the same in every response are kept as they are. Varying ones become an echo
+/// of the request part they always equal, e.g. `{{param.id}}`, or else a fake value of their
+/// type, e.g. `{{fake.word}}`. `None` with fewer than [`MIN_SAMPLES`] responses.
+pub fn infer_template(samples: &[Sample]) -> Option<String> {
+    if samples.len() < MIN_SAMPLES {
+        return None;
+    }
+    let values: Vec<&Value> = samples.iter().map(|sample| &sample.body).collect();
+    let echoes: Vec<&Echoes> = samples
+        .iter()
+        .map(|sample| sample.echoes.as_slice())
+        .collect();
+    let mut template = String::new();
+    write_field(&mut template, &values, &echoes);
+    Some(template)
+}
+
+/// Write the template of a field from its values in the responses having it
+fn write_field(out: &mut String, values: &[&Value], echoes: &[&Echoes]) {
+    let first = values[0];
+    if values.iter().all(|value| *value == first) {
+        out.push_str(&first.to_string());
+        return;
+    }
+    match first {
+        Value::Object(_) if values.iter().all(|value| value.is_object()) => {
+            // Keys missing from some responses are inferred from the others
+            let mut keys: Vec<&String> = Vec::new();
+            for (key, _) in values
+                .iter()
+                .filter_map(|value| value.as_object())
+                .flatten()
+            {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                let (values, echoes): (Vec<&Value>, Vec<&Echoes>) = values
+                    .iter()
+                    .zip(echoes)
+                    .filter_map(|(value, echoes)| Some((value.get(key)?, *echoes)))
+                    .unzip();
+                write_field(out, &values, &echoes);
+            }
+            out.push('}');
+        }
+        Value::Array(items)
+            if values
+                .iter()
+                .all(|value| value.as_array().is_some_and(|a| a.len() == items.len())) =>
+        {
+            out.push('[');
+            for i in 0..items.len() {
+                if i > 0 {
+                    out.push(',');
+                }
+                let values: Vec<&Value> = values.iter().map(|value| &value[i]).collect();
+                write_field(out, &values, echoes);
+            }
+            out.push(']');
+        }
+        _ => match placeholder(values, echoes) {
+            // Placeholders of strings stay in quotes, others replace the value itself
+            Some(name) if first.is_string() => out.push_str(&format!("\"{{{{{}}}}}\"", name)),
+            Some(name) => out.push_str(&format!("{{{{{}}}}}", name)),
+            // Arrays of varying length and fields of varying type can't be filled in
+            None => out.push_str(&first.to_string()),
+        },
+    }
+}
+
+/// Placeholder for a scalar field varying across responses of the same type
+fn placeholder(values: &[&Value], echoes: &[&Echoes]) -> Option<String> {
+    let texts = values
+        .iter()
+        .map(|value| match value {
+            Value::String(text) => Some(text.clone()),
+            Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+            _ => None,
+        })
+        .collect::<Option<Vec<String>>>()?;
+    let echo = echoes[0].iter().find(|(name, _)| {
+        texts.iter().zip(echoes).all(|(text, echoes)| {
+            echoes
+                .iter()
+                .any(|(other, value)| other == name && value == text)
+        })
+    });
+    if let Some((name, _)) = echo {
+        return Some(name.clone());
+    }
+    let fake = if values.iter().all(|value| value.is_string()) {
+        if texts.iter().all(|text| looks_like_uuid(text)) {
+            "fake.uuid"
+        } else if texts
+            .iter()
+            .all(|text| DateTime::parse_from_rfc3339(text).is_ok())
+        {
+            "now"
+        } else {
+            "fake.word"
+        }
+    } else if values.iter().all(|value| value.is_number()) {
+        "fake.number"
+    } else if values.iter().all(|value| value.is_boolean()) {
+        "fake.bool"
+    } else {
+        return None;
+    };
+    Some(fake.to_owned())
+}
+
+fn looks_like_uuid(text: &str) -> bool {
+    text.len() == 36
+        && text.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{
+        http::{Method, StatusCode},
+        web::Bytes,
+    };
+    use chrono::Local;
+    use serde_json::json;
+
+    use super::*;
+
+    fn record(path: &str, query: &str, response: Value) -> RequestRecord {
+        RequestRecord {
+            id: 0,
+            timestamp: Local::now(),
+            method: Method::GET,
+            path: path.to_owned(),
+            query: query.to_owned(),
+            headers: Vec::new(),
+            body: Bytes::new(),
+            body_size: 0,
+            encoded_size: None,
+            status: StatusCode::OK,
+            stub: None,
+            response: Some(Bytes::from(response.to_string())),
+            endpoint: None,
+            elapsed: Duration::ZERO,
+            fault: None,
+            failures: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_infer_template() {
+        let records = [
+            record(
+                "/users/1",
+                "lang=en",
+                json!({
+                    "id": 1,
+                    "kind": "user",
+                    "name": "ada",
+                    "lang": "en",
+                    "token": "0b6e31c2-3a4f-4d8e-9c1a-2f7b5e8d9a01",
+                    "created": "2026-01-02T10:00:00+00:00",
+                    "active": true,
+                    "tags": ["a"],
+                }),
+            ),
+            record(
+                "/users/7",
+                "lang=de",
+                json!({
+                    "id": 7,
+                    "kind": "user",
+                    "name": "grace",
+                    "lang": "de",
+                    "token": "7c1d2e3f-4a5b-4c6d-8e7f-9a0b1c2d3e4f",
+                    "created": "2026-03-04T12:30:00+00:00",
+                    "active": false,
+                    "tags": ["a", "b"],
+                }),
+            ),
+            record(
+                "/users/42",
+                "lang=fr",
+                json!({
+                    "id": 42,
+                    "kind": "user",
+                    "name": "linus",
+                    "lang": "fr",
+                    "token": "a1b2c3d4-e5f6-4a7b-8c9d-0e1f2a3b4c5d",
+                    "created": "2026-05-06T08:15:00+00:00",
+                    "active": true,
+                    "tags": [],
+                }),
+            ),
+            // Other endpoints and failed requests are left out
+            record("/orders/1", "", json!({"id": 1})),
+            RequestRecord {
+                status: StatusCode::NOT_FOUND,
+                ..record("/users/9", "", json!({"error": "not found"}))
+            },
+        ];
+        let samples: Vec<Sample> = records
+            .iter()
+            .filter_map(|record| Sample::of(record, "/users/{id}"))
+            .collect();
+        assert_eq!(samples.len(), 3);
+
+        let template: Value = serde_json::from_str(
+            &infer_template(&samples)
+                .unwrap()
+                .replace("{{param.id}}", "0")
+                .replace("{{fake.bool}}", "false"),
+        )
+        .unwrap();
+        assert_eq!(
+            template,
+            json!({
+                "id": 0,
+                "kind": "user",
+                "name": "{{fake.word}}",
+                "lang": "{{query.lang}}",
+                "token": "{{fake.uuid}}",
+                "created": "{{now}}",
+                "active": false,
+                "tags": ["a"],
+            })
+        );
+        assert!(infer_template(&samples[..1]).is_none());
+    }
+}
@@ -0,0 +1,234 @@
+use std::{collections::VecDeque, num::NonZeroUsize, time::Duration};
+
+use actix_web::{
+    http::{Method, StatusCode},
+    web::Bytes,
+};
+use chrono::{DateTime, Local};
+use rand::RngExt;
+
+/// Default number of requests kept in the journal, oldest are dropped first
+const JOURNAL_CAPACITY: NonZeroUsize = NonZeroUsize::new(1000).unwrap();
+
+#[derive(Debug, Clone)]
+pub struct RequestRecord {
+    /// Sequence number assigned by the journal, referenced by access log entries
+    pub id: u64,
+    pub timestamp: DateTime<Local>,
+    pub method: Method,
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    /// Cut off beyond the journal's body limit, see `body_size`
+    pub body: Bytes,
+    /// Size of the body as received, after decoding
+    pub body_size: usize,
+    /// Size of the body as sent if it was compressed, per `Content-Encoding`
+    pub encoded_size: Option<usize>,
+    pub status: StatusCode,
+    /// Body of the user defined endpoint that answered the request, if any
+    pub stub: Option<Bytes>,
+    /// Body the fallback upstream answered a forwarded request with, unless it was encoded
+    pub response: Option<Bytes>,
+    /// Path of that endpoint as registered, e.g. `/users/{id}`
+    pub endpoint: Option<String>,
+    /// Time taken to produce the response, including injected delays
+    pub elapsed: Duration,
+    /// Faults injected by chaos rules, e.g. `delayed 200ms, aborted`
+    pub fault: Option<String>,
+    /// Assertions of the matched endpoint the request violated
+    pub failures: Vec<String>,
+    /// How the body violated the request schema of the matched endpoint, which answered with 400
+    pub violations: Vec<String>,
+}
+
+impl RequestRecord {
+    /// Whether the request was answered by a user defined endpoint
+    pub fn matched(&self) -> bool {
+        self.stub.is_some()
+    }
+
+    /// Whether the request was answered with 4xx or 5xx, hit by a fault or failed an assertion
+    pub fn failed(&self) -> bool {
+        self.status.is_client_error()
+            || self.status.is_server_error()
+            || self.fault.is_some()
+            || !self.failures.is_empty()
+    }
+
+    /// Whether the body was cut off by the journal's body limit
+    pub fn truncated(&self) -> bool {
+        self.body.len() < self.body_size
+    }
+}
+
+/// How much of the traffic the journal keeps, so long-running instances don't hoard megabytes of
+/// payloads
+#[derive(Debug, Clone, PartialEq)]
+pub struct Retention {
+    /// Maximum number of records, oldest are dropped first
+    pub capacity: NonZeroUsize,
+    /// Bodies are cut off beyond this size, `None` keeps them whole
+    pub max_body: Option<usize>,
+    /// Percentage of requests recorded, the others only count towards the total
+    pub sample: f64,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            capacity: JOURNAL_CAPACITY,
+            max_body: None,
+            sample: 100.0,
+        }
+    }
+}
+
+/// In-memory history of requests served by the catch-all handler
+#[derive(Debug, Default)]
+pub struct RequestJournal {
+    records: VecDeque<RequestRecord>,
+    /// Number of requests ever recorded, including ones already dropped or not sampled
+    total: u64,
+    retention: Retention,
+}
+
+impl RequestJournal {
+    /// Assign a record the next id and append it if it is sampled, with its body cut off as
+    /// configured. Returns the assigned id.
+    pub fn record(&mut self, mut record: RequestRecord) -> u64 {
+        self.total += 1;
+        if self.retention.sample < 100.0 && !rand::rng().random_bool(self.retention.sample / 100.0)
+        {
+            return self.total;
+        }
+        record.id = self.total;
+        if let Some(max_body) = self.retention.max_body {
+            record.body.truncate(max_body);
+            if let Some(response) = &mut record.response {
+                response.truncate(max_body);
+            }
+        }
+        if self.records.len() == self.retention.capacity.get() {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+        self.total
+    }
+
+    /// Look up a record by id, if it was recorded and hasn't been dropped yet
+    pub fn get(&self, id: u64) -> Option<&RequestRecord> {
+        // Ids are assigned in order, with gaps for requests that weren't sampled
+        let index = self.records.binary_search_by_key(&id, |r| r.id).ok()?;
+        self.records.get(index)
+    }
+
+    pub fn retention(&self) -> &Retention {
+        &self.retention
+    }
+
+    /// Change what is kept from now on, dropping the oldest records beyond the new capacity.
+    /// Records already kept are not truncated or redacted.
+    pub fn set_retention(&mut self, retention: Retention) {
+        let excess = self.records.len().saturating_sub(retention.capacity.get());
+        self.records.drain(..excess);
+        self.retention = retention;
+    }
+
+    /// Size of the request bodies kept
+    pub fn body_bytes(&self) -> usize {
+        self.records.iter().map(|r| r.body.len()).sum()
+    }
+
+    /// Records from oldest to newest
+    pub fn records(&self) -> impl DoubleEndedIterator<Item = &RequestRecord> {
+        self.records.iter()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn record(path: &str) -> RequestRecord {
+        RequestRecord {
+            id: 0,
+            timestamp: Local::now(),
+            method: Method::GET,
+            path: path.to_owned(),
+            query: String::new(),
+            headers: Vec::new(),
+            body: Bytes::new(),
+            body_size: 0,
+            encoded_size: None,
+            status: StatusCode::OK,
+            stub: None,
+            response: None,
+            endpoint: None,
+            elapsed: Duration::ZERO,
+            fault: None,
+            failures: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_get_by_id_after_rollover() {
+        let mut journal = RequestJournal::default();
+        for i in 0..JOURNAL_CAPACITY.get() + 5 {
+            journal.record(record(&format!("/{}", i)));
+        }
+
+        assert_eq!(journal.len(), JOURNAL_CAPACITY.get());
+        assert!(journal.get(5).is_none());
+        assert_eq!(journal.get(6).unwrap().path, "/5");
+        let last = journal.total();
+        assert_eq!(journal.get(last).unwrap().id, last);
+        assert!(journal.get(last + 1).is_none());
+    }
+
+    #[test]
+    fn test_retention() {
+        let mut journal = RequestJournal::default();
+        journal.set_retention(Retention {
+            capacity: NonZeroUsize::new(2).unwrap(),
+            max_body: Some(3),
+            ..Retention::default()
+        });
+        let mut large = record("/upload");
+        large.body = Bytes::from("payload");
+        large.body_size = large.body.len();
+        let id = journal.record(large);
+
+        let kept = journal.get(id).unwrap();
+        assert_eq!(kept.body.as_ref(), b"pay");
+        assert!(kept.truncated());
+
+        journal.record(record("/a"));
+        journal.record(record("/b"));
+        assert_eq!(journal.len(), 2);
+        assert!(journal.get(id).is_none());
+
+        journal.set_retention(Retention {
+            sample: 0.0,
+            ..journal.retention().clone()
+        });
+        let skipped = journal.record(record("/c"));
+        assert_eq!(journal.total(), skipped);
+        assert!(journal.get(skipped).is_none());
+        assert_eq!(journal.len(), 2);
+    }
+}
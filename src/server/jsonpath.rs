@@ -0,0 +1,241 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use serde_json::Value;
+
+/// A step of a [`JsonPath`]
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Key(String),
+    Index(usize),
+    /// Every element of an array or value of an object
+    Wildcard,
+}
+
+/// A subset of JSONPath: `$`, `.key`, `['key']`, `[0]`, `[*]` and `.*`, e.g. `$.items[*].type`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath(Vec<Selector>);
+
+impl JsonPath {
+    /// All values the path selects in `json`
+    pub fn select<'a>(&self, json: &'a Value) -> Vec<&'a Value> {
+        self.0.iter().fold(vec![json], |values, selector| {
+            values
+                .into_iter()
+                .flat_map(|value| match (selector, value) {
+                    (Selector::Key(key), Value::Object(object)) => {
+                        object.get(key).into_iter().collect()
+                    }
+                    (Selector::Index(index), Value::Array(array)) => {
+                        array.get(*index).into_iter().collect()
+                    }
+                    (Selector::Wildcard, Value::Array(array)) => array.iter().collect(),
+                    (Selector::Wildcard, Value::Object(object)) => object.values().collect(),
+                    _ => Vec::new(),
+                })
+                .collect()
+        })
+    }
+
+    /// Replace all values the path selects in `json` with `with`, returns whether it selected any
+    pub fn replace(&self, json: &mut Value, with: &Value) -> bool {
+        replace(&self.0, json, with)
+    }
+}
+
+fn replace(selectors: &[Selector], value: &mut Value, with: &Value) -> bool {
+    let Some((selector, rest)) = selectors.split_first() else {
+        *value = with.clone();
+        return true;
+    };
+    match (selector, value) {
+        (Selector::Key(key), Value::Object(object)) => object
+            .get_mut(key)
+            .is_some_and(|value| replace(rest, value, with)),
+        (Selector::Index(index), Value::Array(array)) => array
+            .get_mut(*index)
+            .is_some_and(|value| replace(rest, value, with)),
+        (Selector::Wildcard, Value::Array(array)) => {
+            array
+                .iter_mut()
+                .map(|value| replace(rest, value, with))
+                .filter(|replaced| *replaced)
+                .count()
+                > 0
+        }
+        (Selector::Wildcard, Value::Object(object)) => {
+            object
+                .values_mut()
+                .map(|value| replace(rest, value, with))
+                .filter(|replaced| *replaced)
+                .count()
+                > 0
+        }
+        _ => false,
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut rest = input
+            .trim()
+            .strip_prefix('$')
+            .ok_or_else(|| format!("JSONPath `{}` does not start with `$`", input))?;
+        let mut selectors = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                selectors.push(match &after[..end] {
+                    "" => return Err(format!("empty key in JSONPath `{}`", input)),
+                    "*" => Selector::Wildcard,
+                    key => Selector::Key(key.to_owned()),
+                });
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let (inner, after) = after
+                    .split_once(']')
+                    .ok_or_else(|| format!("unclosed `[` in JSONPath `{}`", input))?;
+                let quoted = inner
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+                selectors.push(match (inner, quoted) {
+                    (_, Some(key)) => Selector::Key(key.to_owned()),
+                    ("*", None) => Selector::Wildcard,
+                    (index, None) => Selector::Index(
+                        index
+                            .parse()
+                            .map_err(|_| format!("`[{}]` is not an index or quoted key", index))?,
+                    ),
+                });
+                rest = after;
+            } else {
+                return Err(format!("unexpected `{}` in JSONPath `{}`", rest, input));
+            }
+        }
+        Ok(Self(selectors))
+    }
+}
+
+/// A condition on a JSON request body, e.g. `$.type == "refund"`, or `$.trace_id` for a value
+/// being present. Compares and orders by its source text.
+#[derive(Debug, Clone)]
+pub struct JsonCondition {
+    source: String,
+    path: JsonPath,
+    /// Whether the comparison is `==` and the value to compare with, `None` for presence
+    comparison: Option<(bool, Value)>,
+}
+
+impl JsonCondition {
+    /// Whether any selected value meets the condition. `!=` holds if none equals the value.
+    pub fn holds(&self, json: &Value) -> bool {
+        let selected = self.path.select(json);
+        match &self.comparison {
+            None => !selected.is_empty(),
+            Some((equal, value)) => selected.contains(&value) == *equal,
+        }
+    }
+
+    /// Whether both conditions can't hold for the same body, i.e. they require the same path to
+    /// equal different values
+    pub fn contradicts(&self, other: &JsonCondition) -> bool {
+        match (&self.comparison, &other.comparison) {
+            (Some((true, value)), Some((true, other_value))) => {
+                self.path == other.path && value != other_value
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for JsonCondition {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        // The first operator splits, so compared strings may contain operators themselves
+        let operator = [("==", true), ("!=", false)]
+            .into_iter()
+            .filter_map(|(operator, equal)| Some((input.find(operator)?, equal)))
+            .min();
+        let (path, comparison) = match operator {
+            Some((at, equal)) => {
+                let value = input[at + 2..].trim();
+                let value = serde_json::from_str(value)
+                    .map_err(|_| format!("`{}` is not a JSON value", value))?;
+                (&input[..at], Some((equal, value)))
+            }
+            None => (input, None),
+        };
+        Ok(Self {
+            source: input.trim().to_owned(),
+            path: path.parse()?,
+            comparison,
+        })
+    }
+}
+
+impl fmt::Display for JsonCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl PartialEq for JsonCondition {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for JsonCondition {}
+
+impl PartialOrd for JsonCondition {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for JsonCondition {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.source.cmp(&other.source)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let json = json!({"items": [{"type": "a"}, {"type": "b"}], "meta": {"id": 1}});
+        let select = |path: &str| path.parse::<JsonPath>().unwrap().select(&json);
+
+        assert_eq!(select("$.items[*].type"), [&json!("a"), &json!("b")]);
+        assert_eq!(select("$['meta'].id"), [&json!(1)]);
+        assert_eq!(select("$.items[1].type"), [&json!("b")]);
+        assert!(select("$.missing").is_empty());
+        assert!("items".parse::<JsonPath>().is_err());
+        assert!("$.items[x]".parse::<JsonPath>().is_err());
+    }
+
+    #[test]
+    fn test_conditions() {
+        let json = json!({"type": "refund", "amount": 5});
+        let holds = |condition: &str| condition.parse::<JsonCondition>().unwrap().holds(&json);
+
+        assert!(holds(r#"$.type == "refund""#));
+        assert!(!holds(r#"$.type == "charge""#));
+        assert!(holds("$.amount != 6"));
+        assert!(holds("$.amount"));
+        assert!(!holds("$.currency"));
+        assert!("$.type == refund".parse::<JsonCondition>().is_err());
+
+        let refund: JsonCondition = r#"$.type == "refund""#.parse().unwrap();
+        assert!(refund.contradicts(&r#"$.type=="charge""#.parse().unwrap()));
+        assert!(!refund.contradicts(&"$.amount == 5".parse().unwrap()));
+    }
+}
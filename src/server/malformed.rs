@@ -0,0 +1,141 @@
+use std::{
+    convert::Infallible,
+    fmt,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    HttpResponse,
+    body::{BodySize, MessageBody},
+    http::{
+        ConnectionType,
+        header::{CONTENT_LENGTH, HeaderValue},
+    },
+    web::Bytes,
+};
+
+use crate::server::{chaos, compression::CompressOverride};
+
+/// Reason phrase smuggling a header line with control characters into the response head
+const BAD_HEADER_REASON: &str = "OK\r\nX-Malformed: \x01bad\x00value\x7f";
+/// Reason phrase with control characters, which HTTP parsers reject in the status line
+const BAD_STATUS_REASON: &str = "B\x00GUS\x01\x7f";
+
+/// Deliberately invalid HTTP, to test how robust client HTTP stacks and their error reporting
+/// are. actix writes the status line and headers itself, so the broken parts of the head are
+/// smuggled in through the reason phrase.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Malformation {
+    /// `Content-Length` claims more bytes than are sent before the connection closes
+    Length,
+    /// A header line holding control characters
+    Header,
+    /// The body is sent chunked, but the connection closes before the final chunk
+    Chunks,
+    /// The status line holds control characters
+    Status,
+}
+
+impl Malformation {
+    /// Break `response` in this way
+    pub fn apply(&self, mut response: HttpResponse) -> HttpResponse {
+        let mut response = match self {
+            Malformation::Length => {
+                let (mut response, body) = response.into_parts();
+                let body = body.try_into_bytes().unwrap_or_default();
+                let claimed = HeaderValue::from(2 * body.len() + 1);
+                // Only unsized bodies sent without chunking keep a `Content-Length` set by hand
+                response.head_mut().no_chunking(true);
+                response.headers_mut().insert(CONTENT_LENGTH, claimed);
+                response
+                    .head_mut()
+                    .set_connection_type(ConnectionType::Close);
+                response
+                    .set_body(UnsizedBody(Some(body)))
+                    .map_into_boxed_body()
+            }
+            Malformation::Header => {
+                response.head_mut().reason = Some(BAD_HEADER_REASON);
+                response
+            }
+            Malformation::Chunks => chaos::break_off(response, |body| body, None),
+            Malformation::Status => {
+                response.head_mut().reason = Some(BAD_STATUS_REASON);
+                response
+            }
+        };
+        // Compression would replace the body and its framing
+        response.extensions_mut().insert(CompressOverride(false));
+        response
+    }
+}
+
+impl fmt::Display for Malformation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Malformation::Length => write!(f, "bad-length"),
+            Malformation::Header => write!(f, "bad-header"),
+            Malformation::Chunks => write!(f, "truncated-chunks"),
+            Malformation::Status => write!(f, "bad-status"),
+        }
+    }
+}
+
+impl FromStr for Malformation {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "bad-length" => Ok(Malformation::Length),
+            "bad-header" => Ok(Malformation::Header),
+            "truncated-chunks" => Ok(Malformation::Chunks),
+            "bad-status" => Ok(Malformation::Status),
+            _ => Err(format!(
+                "unknown malformation `{}`, use bad-length, bad-header, truncated-chunks or \
+                 bad-status",
+                input
+            )),
+        }
+    }
+}
+
+/// Sends its bytes as a stream of unknown size
+pub(super) struct UnsizedBody(pub(super) Option<Bytes>);
+
+impl MessageBody for UnsizedBody {
+    type Error = Infallible;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        Poll::Ready(self.get_mut().0.take().map(Ok))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_malformation() {
+        for name in ["bad-length", "bad-header", "truncated-chunks", "bad-status"] {
+            assert_eq!(name.parse::<Malformation>().unwrap().to_string(), name);
+        }
+        assert!("bad-body".parse::<Malformation>().is_err());
+    }
+
+    #[test]
+    fn test_claim_more_than_sent() {
+        let response = Malformation::Length.apply(HttpResponse::Ok().body("abc"));
+        assert_eq!(response.headers().get(CONTENT_LENGTH).unwrap(), "7");
+        assert_eq!(response.body().size(), BodySize::Stream);
+    }
+}
@@ -0,0 +1,490 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use actix_web::{
+    HttpRequest,
+    http::header::{COOKIE, HeaderMap, HeaderName, HeaderValue},
+    web::{Bytes, Query},
+};
+use serde_json::Value;
+
+use crate::server::jsonpath::JsonCondition;
+
+/// The parts of an incoming request matchers inspect
+#[derive(Debug, Clone, Default)]
+pub struct RequestInfo {
+    /// Decoded query parameters in request order
+    pub query: Vec<(String, String)>,
+    pub headers: HeaderMap,
+    /// The body if it is JSON
+    pub json: Option<Value>,
+    /// Which request to the endpoint this is, counting from 1. Set while looking up its
+    /// candidates, see
+    /// [`crate::server::EndpointStore::lookup_request`].
+    pub call: u64,
+    /// Address of the connected client, `None` outside of a connection, e.g. in `normalize`
+    pub client_ip: Option<IpAddr>,
+}
+
+impl RequestInfo {
+    /// A request with only a query, e.g. `q=rust&page=2`
+    pub fn new(query_string: &str) -> Self {
+        Self {
+            // Malformed queries have no parameters to match
+            query: Query::<Vec<(String, String)>>::from_query(query_string)
+                .map(Query::into_inner)
+                .unwrap_or_default(),
+            headers: HeaderMap::new(),
+            json: None,
+            call: 0,
+            client_ip: None,
+        }
+    }
+
+    pub fn from_request(req: &HttpRequest, body: &Bytes) -> Self {
+        Self {
+            headers: req.headers().clone(),
+            json: serde_json::from_slice(body).ok(),
+            client_ip: req.peer_addr().map(|addr| addr.ip()),
+            ..Self::new(req.query_string())
+        }
+    }
+}
+
+/// A condition a request must meet for a candidate stub to answer it, e.g. `query:q=rust`.
+/// Candidates with more matchers are tried first among those of equal priority.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Matcher {
+    /// The query has a parameter `name` with `value`
+    Query { name: String, value: String },
+    /// The request has a header `name`, in lowercase, with `value`
+    Header { name: String, value: String },
+    /// The request sends a cookie `name` with `value`
+    Cookie { name: String, value: String },
+    /// The body is JSON meeting a condition, e.g. `$.type == "refund"`
+    BodyJson(JsonCondition),
+    /// The request is one of a range of calls to the endpoint, e.g. the first two
+    Calls(CallRange),
+    /// The client connects from an address in a range, e.g. `10.0.0.0/8`
+    ClientIp(IpRange),
+}
+
+impl Matcher {
+    pub fn matches(&self, request: &RequestInfo) -> bool {
+        match self {
+            Matcher::Query { name, value } => {
+                request.query.iter().any(|(n, v)| n == name && v == value)
+            }
+            Matcher::Header { name, value } => request
+                .headers
+                .get_all(name.as_str())
+                .any(|v| v.as_bytes() == value.as_bytes()),
+            Matcher::Cookie { name, value } => request
+                .headers
+                .get_all(COOKIE)
+                .filter_map(|header| header.to_str().ok())
+                .flat_map(|header| header.split(';'))
+                .filter_map(|pair| pair.trim().split_once('='))
+                .any(|(n, v)| n == name && v == value),
+            Matcher::BodyJson(condition) => request
+                .json
+                .as_ref()
+                .is_some_and(|json| condition.holds(json)),
+            Matcher::Calls(range) => range.contains(request.call),
+            Matcher::ClientIp(range) => request.client_ip.is_some_and(|ip| range.contains(ip)),
+        }
+    }
+
+    /// Whether both matchers can't hold for the same request, e.g. `q=rust` and `q=go` of a
+    /// single-valued parameter
+    pub fn contradicts(&self, other: &Matcher) -> bool {
+        match (self, other) {
+            (
+                Matcher::Query { name, value },
+                Matcher::Query {
+                    name: other_name,
+                    value: other_value,
+                },
+            )
+            | (
+                Matcher::Header { name, value },
+                Matcher::Header {
+                    name: other_name,
+                    value: other_value,
+                },
+            )
+            | (
+                Matcher::Cookie { name, value },
+                Matcher::Cookie {
+                    name: other_name,
+                    value: other_value,
+                },
+            ) => name == other_name && value != other_value,
+            (Matcher::BodyJson(condition), Matcher::BodyJson(other)) => {
+                condition.contradicts(other)
+            }
+            (Matcher::Calls(range), Matcher::Calls(other)) => !range.overlaps(other),
+            (Matcher::ClientIp(range), Matcher::ClientIp(other)) => !range.overlaps(other),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Matcher::Query { name, value } => write!(f, "query:{}={}", name, value),
+            Matcher::Header { name, value } => write!(f, "header:{}: {}", name, value),
+            Matcher::Cookie { name, value } => write!(f, "cookie:{}={}", name, value),
+            Matcher::BodyJson(condition) => write!(f, "body:{}", condition),
+            Matcher::Calls(range) => write!(f, "calls:{}", range),
+            Matcher::ClientIp(range) => write!(f, "ip:{}", range),
+        }
+    }
+}
+
+impl FromStr for Matcher {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.split_once(':') {
+            Some(("query", pair)) => parse_query_pair(pair),
+            Some(("header", header)) => parse_header_pair(header),
+            Some(("cookie", pair)) => parse_cookie_pair(pair),
+            Some(("body", condition)) => parse_body_json(condition),
+            Some(("calls", range)) => parse_call_range(range),
+            Some(("ip", range)) => parse_ip_range(range),
+            _ => Err(format!(
+                "`{}` is not of the form `query:q=rust`, `header:Name: value`, `cookie:name=value`, `body:$.path == value`, `calls:1..3` or `ip:10.0.0.0/8`",
+                input
+            )),
+        }
+    }
+}
+
+/// Parse a query parameter matcher such as `q=rust`
+pub fn parse_query_pair(input: &str) -> Result<Matcher, String> {
+    match input.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok(Matcher::Query {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        }),
+        _ => Err(format!("`{}` is not of the form `name=value`", input)),
+    }
+}
+
+/// Parse a header matcher such as `Authorization: Bearer test`
+pub fn parse_header_pair(input: &str) -> Result<Matcher, String> {
+    let (name, value) = parse_header_line(input)?;
+    Ok(Matcher::Header {
+        name: name.as_str().to_owned(),
+        value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+    })
+}
+
+/// Parse a cookie matcher such as `session=abc`
+pub fn parse_cookie_pair(input: &str) -> Result<Matcher, String> {
+    match input.trim().split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() && !name.contains(';') => {
+            Ok(Matcher::Cookie {
+                name: name.trim().to_owned(),
+                value: value.trim().to_owned(),
+            })
+        }
+        _ => Err(format!("`{}` is not of the form `name=value`", input)),
+    }
+}
+
+/// Parse a header such as `Authorization: Bearer test`
+pub fn parse_header_line(input: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = input
+        .split_once(':')
+        .ok_or_else(|| format!("`{}` is not of the form `Name: value`", input))?;
+    let name = HeaderName::try_from(name.trim())
+        .map_err(|_| format!("`{}` is not a valid header name", name.trim()))?;
+    let value = HeaderValue::try_from(value.trim())
+        .map_err(|_| format!("`{}` is not a valid header value", value.trim()))?;
+    Ok((name, value))
+}
+
+/// Parse a JSON body matcher such as `$.type == "refund"`
+pub fn parse_body_json(input: &str) -> Result<Matcher, String> {
+    input.parse().map(Matcher::BodyJson)
+}
+
+/// Parse a call count matcher such as `1..3`, `3` or `6..`
+pub fn parse_call_range(input: &str) -> Result<Matcher, String> {
+    input.parse().map(Matcher::Calls)
+}
+
+/// Parse a call count matcher for the calls after the first `count`, e.g. `5` for the sixth on
+pub fn parse_after_calls(input: &str) -> Result<Matcher, String> {
+    let count: u64 = input
+        .trim()
+        .parse()
+        .map_err(|_| format!("`{}` is not a number of calls", input.trim()))?;
+    Ok(Matcher::Calls(CallRange {
+        first: count.saturating_add(1),
+        last: None,
+    }))
+}
+
+/// Parse a client address matcher such as `10.0.0.0/8` or `192.168.1.20`
+pub fn parse_ip_range(input: &str) -> Result<Matcher, String> {
+    input.parse().map(Matcher::ClientIp)
+}
+
+/// A block of addresses in CIDR notation, e.g. `10.0.0.0/8`, or a single address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IpRange {
+    /// With the bits after the prefix cleared
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 clients of a dual-stack listener connect from mapped IPv6 addresses
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            ip => ip,
+        };
+        mask(ip, self.prefix) == Some(self.network)
+    }
+
+    /// Whether an address is in both ranges. Blocks are either nested or apart, so the larger
+    /// one holds the network address of the other if they overlap.
+    pub fn overlaps(&self, other: &IpRange) -> bool {
+        self.contains(other.network) || other.contains(self.network)
+    }
+}
+
+/// `ip` with all bits after the first `prefix` cleared, `None` if it has fewer bits
+fn mask(ip: IpAddr, prefix: u8) -> Option<IpAddr> {
+    match ip {
+        IpAddr::V4(v4) => {
+            let keep = u32::MAX.checked_shl(32_u32.checked_sub(prefix.into())?);
+            Some(IpAddr::V4((u32::from(v4) & keep.unwrap_or(0)).into()))
+        }
+        IpAddr::V6(v6) => {
+            let keep = u128::MAX.checked_shl(128_u32.checked_sub(prefix.into())?);
+            Some(IpAddr::V6((u128::from(v6) & keep.unwrap_or(0)).into()))
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let (address, prefix) = input.split_once('/').unwrap_or((input, ""));
+        let address: IpAddr = address
+            .parse()
+            .map_err(|_| format!("`{}` is not an IP address", address))?;
+        let bits = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            "" => bits,
+            prefix => prefix
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= bits)
+                .ok_or_else(|| format!("`{}` is not a prefix length up to {}", prefix, bits))?,
+        };
+        Ok(Self {
+            network: mask(address, prefix).unwrap_or(address),
+            prefix,
+        })
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.network, self.prefix) {
+            (IpAddr::V4(_), 32) | (IpAddr::V6(_), 128) => write!(f, "{}", self.network),
+            (network, prefix) => write!(f, "{}/{}", network, prefix),
+        }
+    }
+}
+
+/// Calls to an endpoint, counting from 1, e.g. `1..3` for the first three or `6..` for all from
+/// the sixth on. Calls are counted once answered, so concurrent requests may share a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CallRange {
+    pub first: u64,
+    /// Inclusive, `None` for no end
+    pub last: Option<u64>,
+}
+
+impl CallRange {
+    pub fn contains(&self, call: u64) -> bool {
+        call >= self.first && self.last.is_none_or(|last| call <= last)
+    }
+
+    fn overlaps(&self, other: &CallRange) -> bool {
+        self.first <= other.last.unwrap_or(u64::MAX) && other.first <= self.last.unwrap_or(u64::MAX)
+    }
+}
+
+impl fmt::Display for CallRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.last {
+            Some(last) if last == self.first => write!(f, "{}", last),
+            Some(last) => write!(f, "{}..{}", self.first, last),
+            None => write!(f, "{}..", self.first),
+        }
+    }
+}
+
+impl FromStr for CallRange {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let parse = |call: &str| {
+            call.trim()
+                .parse::<u64>()
+                .ok()
+                .filter(|call| *call > 0)
+                .ok_or_else(|| {
+                    format!("`{}` is not a call number, calls count from 1", call.trim())
+                })
+        };
+        let (first, last) = match input.split_once("..") {
+            Some((first, "")) => (parse(first)?, None),
+            Some(("", last)) => (1, Some(parse(last)?)),
+            Some((first, last)) => (parse(first)?, Some(parse(last)?)),
+            None => {
+                let call = parse(input)?;
+                (call, Some(call))
+            }
+        };
+        if last.is_some_and(|last| last < first) {
+            return Err(format!("`{}` ends before it starts", input));
+        }
+        Ok(Self { first, last })
+    }
+}
+
+/// Split a path with a query such as `/search?q=rust` into the path and matchers for the query
+pub fn split_query(path: &str) -> (&str, Vec<Matcher>) {
+    let Some((path, query_string)) = path.split_once('?') else {
+        return (path, Vec::new());
+    };
+    let matchers = RequestInfo::new(query_string)
+        .query
+        .into_iter()
+        .map(|(name, value)| Matcher::Query { name, value })
+        .collect();
+    (path, matchers)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_query_matchers() {
+        let (path, matchers) = split_query("/search?q=rust%20lang&page=2");
+        assert_eq!(path, "/search");
+        assert_eq!(matchers[0].to_string(), "query:q=rust lang");
+        assert_eq!("query:page=2".parse::<Matcher>().unwrap(), matchers[1]);
+        assert!("page=2".parse::<Matcher>().is_err());
+
+        let request = RequestInfo::new("page=2&q=rust+lang");
+        assert!(matchers.iter().all(|m| m.matches(&request)));
+        assert!(!matchers[1].matches(&RequestInfo::new("page=3")));
+        assert!(matchers[1].contradicts(&parse_query_pair("page=3").unwrap()));
+        assert!(!matchers[0].contradicts(&matchers[1]));
+    }
+
+    #[test]
+    fn test_header_matchers() {
+        let authorized = parse_header_pair("Authorization: Bearer test").unwrap();
+        assert_eq!(authorized.to_string(), "header:authorization: Bearer test");
+        assert_eq!(
+            authorized.to_string().parse::<Matcher>().unwrap(),
+            authorized
+        );
+        assert!(parse_header_pair("Authorization").is_err());
+
+        let mut request = RequestInfo::default();
+        assert!(!authorized.matches(&request));
+        request.headers.insert(
+            HeaderName::from_static("authorization"),
+            "Bearer test".parse().unwrap(),
+        );
+        assert!(authorized.matches(&request));
+        assert!(authorized.contradicts(&parse_header_pair("authorization: x").unwrap()));
+    }
+
+    #[test]
+    fn test_cookie_matchers() {
+        let session: Matcher = "cookie:session=abc".parse().unwrap();
+        assert_eq!(session, parse_cookie_pair("session=abc").unwrap());
+        assert!(parse_cookie_pair("session").is_err());
+
+        let mut request = RequestInfo::default();
+        request
+            .headers
+            .insert(COOKIE, "theme=dark; session=abc".parse().unwrap());
+        assert!(session.matches(&request));
+        assert!(!parse_cookie_pair("session=xyz").unwrap().matches(&request));
+        assert!(!session.matches(&RequestInfo::default()));
+        assert!(session.contradicts(&parse_cookie_pair("session=xyz").unwrap()));
+    }
+
+    #[test]
+    fn test_call_matchers() {
+        let first_two: Matcher = "calls:1..2".parse().unwrap();
+        assert_eq!(first_two.to_string(), "calls:1..2");
+        assert_eq!(parse_after_calls("5").unwrap().to_string(), "calls:6..");
+        assert_eq!(parse_call_range("3").unwrap().to_string(), "calls:3");
+        assert!(parse_call_range("0..2").is_err());
+        assert!(parse_call_range("3..2").is_err());
+
+        let call = |call| RequestInfo {
+            call,
+            ..RequestInfo::default()
+        };
+        assert!(first_two.matches(&call(2)));
+        assert!(!first_two.matches(&call(3)));
+        assert!(first_two.contradicts(&parse_after_calls("2").unwrap()));
+        assert!(!first_two.contradicts(&parse_call_range("2..").unwrap()));
+    }
+
+    #[test]
+    fn test_body_json_matchers() {
+        let refund: Matcher = r#"body:$.type == "refund""#.parse().unwrap();
+        assert_eq!(refund.to_string(), r#"body:$.type == "refund""#);
+
+        let request = RequestInfo::from_request(
+            &TestRequest::default().to_http_request(),
+            &Bytes::from(r#"{"type": "refund"}"#),
+        );
+        assert!(refund.matches(&request));
+        assert!(!refund.matches(&RequestInfo::default()));
+    }
+
+    #[test]
+    fn test_client_ip_matchers() {
+        let lab: Matcher = "ip:10.1.2.3/8".parse().unwrap();
+        assert_eq!(lab.to_string(), "ip:10.0.0.0/8");
+        assert_eq!(parse_ip_range("::1").unwrap().to_string(), "ip:::1");
+        assert!(parse_ip_range("10.0.0.0/33").is_err());
+        assert!(parse_ip_range("lab").is_err());
+
+        let client = |ip: &str| RequestInfo {
+            client_ip: Some(ip.parse().unwrap()),
+            ..RequestInfo::default()
+        };
+        assert!(lab.matches(&client("10.20.30.40")));
+        assert!(lab.matches(&client("::ffff:10.0.0.1")));
+        assert!(!lab.matches(&client("192.168.1.20")));
+        assert!(!lab.matches(&RequestInfo::default()));
+        assert!(!lab.contradicts(&parse_ip_range("10.3.0.0/16").unwrap()));
+        assert!(lab.contradicts(&parse_ip_range("192.168.0.0/16").unwrap()));
+    }
+}
@@ -0,0 +1,153 @@
+use std::{fmt, str::FromStr};
+
+/// A cross-cutting feature requests pass through before reaching the endpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Answers preflights and adds CORS headers. Runs before all other stages, as browsers send
+    /// preflights without credentials, and only changes when the server restarts.
+    Cors,
+    /// Answers requests lacking required credentials with 401
+    Auth,
+    /// Answers requests beyond the rate limit with 429
+    RateLimit,
+    /// Injects faults, answering with an error status or delaying, aborting or breaking the
+    /// response
+    Chaos,
+    /// Masks secrets in the journal and log, only turned on or off
+    Redaction,
+    /// Writes the access log line of every request, only turned on or off
+    Logging,
+}
+
+impl Stage {
+    const ALL: [Stage; 6] = [
+        Stage::Cors,
+        Stage::Auth,
+        Stage::RateLimit,
+        Stage::Chaos,
+        Stage::Redaction,
+        Stage::Logging,
+    ];
+
+    /// Whether the stage only works on what is recorded, so where it runs doesn't matter
+    fn is_switch(self) -> bool {
+        matches!(self, Stage::Redaction | Stage::Logging)
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Cors => write!(f, "cors"),
+            Stage::Auth => write!(f, "auth"),
+            Stage::RateLimit => write!(f, "rate-limit"),
+            Stage::Chaos => write!(f, "chaos"),
+            Stage::Redaction => write!(f, "redaction"),
+            Stage::Logging => write!(f, "logging"),
+        }
+    }
+}
+
+impl FromStr for Stage {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Stage::ALL
+            .into_iter()
+            .find(|stage| stage.to_string() == input)
+            .ok_or_else(|| {
+                format!(
+                    "unknown stage `{}`, use cors, auth, rate-limit, chaos, redaction or logging",
+                    input
+                )
+            })
+    }
+}
+
+/// The stages requests pass through, in order, e.g. `cors,auth,rate-limit,chaos`. Stages left out
+/// are off. Of auth, rate limiting and chaos the first to answer a request wins and the later
+/// ones are skipped, e.g. with chaos before auth injected errors also hit unauthenticated
+/// requests, and rate limiting after auth doesn't count rejected ones. Redaction and logging
+/// apply to everything recorded wherever they are listed, so they are only turned on or off and
+/// have to come last, as `redaction,logging`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiddlewareChain(Vec<Stage>);
+
+impl MiddlewareChain {
+    pub fn stages(&self) -> &[Stage] {
+        &self.0
+    }
+
+    pub fn contains(&self, stage: Stage) -> bool {
+        self.0.contains(&stage)
+    }
+}
+
+/// All stages, so unauthenticated requests are rejected before they count against the rate limit
+impl Default for MiddlewareChain {
+    fn default() -> Self {
+        Self(Stage::ALL.to_vec())
+    }
+}
+
+impl fmt::Display for MiddlewareChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stages: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        write!(f, "{}", stages.join(","))
+    }
+}
+
+impl FromStr for MiddlewareChain {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut stages: Vec<Stage> = Vec::new();
+        for stage in input.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let stage = stage.parse()?;
+            if stages.contains(&stage) {
+                return Err(format!("`{}` is listed twice", stage));
+            }
+            stages.push(stage);
+        }
+        if stages.iter().position(|s| *s == Stage::Cors) > Some(0) {
+            return Err(
+                "cors has to come first, it answers preflights before any other stage".to_owned(),
+            );
+        }
+        let misplaced = stages.windows(2).any(|pair| {
+            (pair[0].is_switch() && !pair[1].is_switch())
+                || pair == [Stage::Logging, Stage::Redaction]
+        });
+        if misplaced {
+            return Err(
+                "redaction and logging are only turned on or off, list them last as `redaction,logging`"
+                    .to_owned(),
+            );
+        }
+        Ok(Self(stages))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chain() {
+        let chain: MiddlewareChain = "chaos, auth,logging".parse().unwrap();
+        assert_eq!(chain.stages(), [Stage::Chaos, Stage::Auth, Stage::Logging]);
+        assert_eq!(chain.to_string(), "chaos,auth,logging");
+        assert!(!chain.contains(Stage::Redaction));
+        assert_eq!(
+            MiddlewareChain::default().to_string(),
+            "cors,auth,rate-limit,chaos,redaction,logging"
+        );
+        assert!("auth,auth".parse::<MiddlewareChain>().is_err());
+        assert!("auth,cors".parse::<MiddlewareChain>().is_err());
+        assert!("tracing".parse::<MiddlewareChain>().is_err());
+        assert!("logging,auth".parse::<MiddlewareChain>().is_err());
+        assert!("auth,logging,redaction".parse::<MiddlewareChain>().is_err());
+        assert!("auth,redaction".parse::<MiddlewareChain>().is_ok());
+    }
+}
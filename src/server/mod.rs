@@ -1,23 +1,213 @@
+use actix_http::{HttpService, Protocol, error::DispatchError};
+use actix_server::Server;
+use actix_service::{ServiceFactory, ServiceFactoryExt, fn_service, map_config};
 use actix_web::{
-    App as ServerApp, HttpRequest, HttpResponse, HttpServer, Responder, get,
-    http::Method,
-    middleware::Logger,
+    App as ServerApp, HttpRequest, HttpResponse, Responder,
+    dev::{AppConfig, ServerHandle},
+    get,
+    guard::GuardContext,
+    http::{
+        ConnectionType, Method, StatusCode, Uri,
+        header::{
+            ACCEPT_RANGES, CONTENT_ENCODING, CONTENT_TYPE, HeaderMap, HeaderValue, LINK, VARY,
+        },
+    },
+    middleware::{Compress, Condition, from_fn},
+    rt::net::TcpStream,
     web::{self, Bytes, Data, to},
 };
+use chrono::Local;
+use serde::Deserialize;
 use std::{
-    io,
-    sync::{Arc, RwLock},
+    fs, io,
+    net::{SocketAddr, TcpListener},
+    num::NonZeroUsize,
+    path::Path,
+    sync::{
+        Arc, Mutex, MutexGuard, OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Instant,
 };
+use tokio::io::{AsyncRead, AsyncWrite};
 
+mod analysis;
+mod assertion;
+mod auth;
+mod callback;
+mod chaos;
+mod compression;
+mod connection;
+mod cors;
+mod coverage;
+mod curl;
+mod docs;
+mod drip;
+mod encoding;
 mod endpoint;
+mod etag;
+mod expect;
+mod fallback;
+mod framing;
+mod har;
+mod hook;
+mod import;
+mod inbox;
+mod infer;
+mod journal;
+mod jsonpath;
+mod malformed;
+mod matcher;
+mod middleware;
+mod mutation;
+mod negotiate;
+mod oauth;
+mod openapi;
+mod paging;
+mod profile;
+mod range;
+mod rate_limit;
+mod raw;
+mod redact;
+mod replay;
+mod report;
+mod rewrite;
+mod scenario;
+mod schema;
+mod source;
+mod sse;
+mod stats;
+mod stub;
+mod supervisor;
+mod tenant;
+mod tls;
+mod transform;
+mod verify;
+mod vhost;
+use analysis::path_template;
+pub use analysis::{StubSuggestion, suggest_stubs};
+pub use assertion::Assertion;
+pub use auth::{AuthRule, Credentials};
+pub use callback::Callback;
+pub use chaos::{Chaos, ChaosRule, Fault, Flaky, ScheduledFault, parse_percent, parse_status};
+pub use connection::{ConnectionPolicy, Hangup};
+pub use cors::{CorsConfig, parse_header, parse_origin};
+pub use coverage::{Coverage, CoverageReport};
+pub use curl::curl_command;
+pub use docs::markdown;
+pub use drip::Drip;
+pub use endpoint::{EndpointStore, is_param};
+pub use etag::ETag;
+pub use expect::ContinueBehavior;
+pub use fallback::Fallback;
+pub use framing::Framing;
+pub use hook::{Datasets, Hook, Metrics};
+pub use import::{ImportConflict, ImportReport, OnConflict, differences};
+pub use inbox::Inbox;
+pub use journal::{RequestJournal, RequestRecord};
+pub use malformed::Malformation;
+pub use matcher::{
+    Matcher, RequestInfo, parse_after_calls, parse_body_json, parse_call_range, parse_cookie_pair,
+    parse_header_line, parse_header_pair, parse_ip_range, parse_query_pair, split_query,
+};
+pub use middleware::{MiddlewareChain, Stage};
+pub use mutation::{MutationMode, MutationTesting};
+pub use negotiate::Representation;
+pub use oauth::{Claim, OAuthMock};
+pub use openapi::openapi_document;
+pub use paging::{PageStyle, Pagination};
+pub use profile::Profile;
+pub use range::ByteRange;
+pub use rate_limit::{RateLimit, RateLimiter};
+pub use raw::{HeaderOrder, RawResponse};
+use raw::{IdleClose, PeerClosed, RawStream};
+pub use redact::{Redaction, redact_text};
+pub use replay::{ReplayFilter, ReplaySelection, ReplayTarget, replay};
+pub use report::Report;
+pub use rewrite::{RewriteRule, parse_pattern};
+pub use scenario::{Scenario, ScenarioRun, ScenarioStep};
+pub use schema::RequestSchema;
+pub use source::{Definition, Definitions};
+pub use sse::EventStream;
+pub use stats::{EndpointStats, EndpointStatsTable};
+pub use stub::{Budget, Redirect, ResponseHeader, Stub, parse_set_cookie};
+pub use supervisor::{ServerHealth, ServerSupervisor};
+pub use tenant::{Tenancy, TenantSource};
+use tls::CertificateAuthority;
+pub use tls::{CertificateKind, TlsListener};
+pub use transform::{Overlay, Transform};
+pub use verify::{Expectation, Verification};
+pub use vhost::{VirtualHosts, parse_host, request_host};
+
 use crate::{
-    server::endpoint::EndpointStore,
+    formats::Exporter,
     util::{error::InternalError, result::InternalResult},
 };
 
+/// Request bodies beyond this size are refused, before and after decoding, same as actix's default
+const MAX_BODY_SIZE: usize = 256 * 1024;
+/// Body of endpoints registered in learning mode
+const LEARNED_BODY: &str = "{}";
+
+/// Everything the server shares between workers and the TUI. Besides the endpoint store, which is
+/// recovered, its locks only guard plain data, so accessors ignore a poisoned lock.
 #[derive(Debug)]
 pub struct ServerState {
     pub endpoints: RwLock<EndpointStore>,
+    pub journal: Mutex<RequestJournal>,
+    pub stats: Mutex<EndpointStatsTable>,
+    /// Whether unmatched requests register parameterized endpoints automatically
+    pub learning: AtomicBool,
+    /// Whether responses are compressed for clients sending `Accept-Encoding`, endpoints may
+    /// override it
+    pub compress: AtomicBool,
+    /// When connections are closed instead of kept alive
+    pub connections: RwLock<ConnectionPolicy>,
+    /// Applied when the server starts, so changes need a restart
+    pub cors: RwLock<CorsConfig>,
+    pub chaos: RwLock<Chaos>,
+    /// Answers requests before the regular endpoints while started
+    pub scenario: RwLock<Option<ScenarioRun>>,
+    /// Prefix of all registered paths at match time, e.g. `/api/v2`, empty for none
+    pub base_path: RwLock<String>,
+    /// Applied to request paths in order, before the base path is stripped
+    pub rewrites: RwLock<Vec<RewriteRule>>,
+    /// Credentials required below path prefixes
+    pub auth: RwLock<Vec<AuthRule>>,
+    /// Applied to requests before they are journaled and to log messages
+    pub redactions: RwLock<Vec<Redaction>>,
+    /// Token endpoint answering before the regular endpoints, exempt from auth rules
+    pub oauth: RwLock<Option<OAuthMock>>,
+    /// Order of the cross-cutting stages requests pass through, stages left out are off
+    pub middleware: RwLock<MiddlewareChain>,
+    /// Requests allowed across all endpoints, `None` for no limit
+    pub rate_limit: Mutex<Option<RateLimiter>>,
+    /// Added to responses that don't set them themselves
+    pub default_headers: RwLock<Vec<ResponseHeader>>,
+    /// Upstream answering requests no endpoint matches, instead of 404
+    pub fallback: RwLock<Option<Fallback>>,
+    /// Configuration described by `/__about` and the TUI banner
+    pub profile: RwLock<Option<Profile>>,
+    /// Applied when the server starts, so changes need a restart
+    pub tls: RwLock<Option<TlsListener>>,
+    certificate_authority: OnceLock<CertificateAuthority>,
+    pub tenancy: RwLock<Tenancy>,
+    pub virtual_hosts: RwLock<VirtualHosts>,
+    /// Where the endpoints loaded from files are defined
+    pub definitions: RwLock<Definitions>,
+    pub mutations: Mutex<MutationTesting>,
+    /// Verifications run so far, for the report
+    pub verifications: Mutex<Vec<Verification>>,
+    /// Operations of a loaded OpenAPI document and how often requests hit them
+    pub coverage: Mutex<Option<Coverage>>,
+    /// Counters of the `metric` hooks of endpoints
+    pub metrics: Mutex<Metrics>,
+    /// Paths capturing whatever is sent to them, answering before the regular endpoints
+    pub inbox: Mutex<Inbox>,
+    /// Files the `append` hooks of endpoints write to
+    pub datasets: Datasets,
 }
 
 #[get("/api/health")]
@@ -25,45 +215,1250 @@ async fn health() -> impl Responder {
     "OK"
 }
 
-pub async fn run_server(state: Arc<ServerState>, addr: &str) -> io::Result<()> {
-    HttpServer::new(move || {
-        ServerApp::new()
-            .wrap(Logger::default())
-            .app_data(Data::new(state.clone()))
-            .service(health)
-            .default_service(to(catch_all))
-    })
-    .bind(addr)?
-    .run()
-    .await
-}
-
-async fn catch_all(req: HttpRequest, state: web::Data<Arc<ServerState>>) -> impl Responder {
-    let path = req.path();
-    let endpoints = match state.endpoints.read() {
-        Ok(guard) => guard,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
+/// Describes the active profile and version to anyone sharing the instance
+#[get("/__about")]
+async fn about(state: web::Data<Arc<ServerState>>) -> HttpResponse {
+    let profile = state.profile().clone();
+    HttpResponse::Ok().json(profile::about(
+        profile.as_ref(),
+        state.read_endpoints().len(),
+        &state.base_path(),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyQuery {
+    method: String,
+    path: String,
+    times: Option<u64>,
+    #[serde(default)]
+    never: bool,
+}
+
+/// Admin API of `verify`, e.g. `/api/verify?method=get&path=/users&times=3`. Answers the
+/// verification as JSON, with status 417 if it failed.
+#[get("/api/verify")]
+async fn verify_requests(
+    query: web::Query<VerifyQuery>,
+    state: web::Data<Arc<ServerState>>,
+) -> HttpResponse {
+    let Ok(method) = Method::from_bytes(query.method.to_ascii_uppercase().as_bytes()) else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "invalid method", "method": query.method}));
     };
-    match endpoints.get(req.method(), path) {
-        Some(response) => HttpResponse::Ok().body(response.clone()),
-        None => {
-            HttpResponse::NotFound().json(serde_json::json!({"error": "not found", "path": path}))
+    let expected = Expectation::from_flags(query.times, query.never);
+    let verification = state.verify(&method, &query.path, expected);
+    if verification.passed {
+        HttpResponse::Ok().json(verification)
+    } else {
+        HttpResponse::ExpectationFailed().json(verification)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SourceQuery {
+    method: Option<String>,
+    path: Option<String>,
+    /// Id of a journaled request
+    request: Option<u64>,
+}
+
+/// Admin API for editors to jump to the definition of the endpoint answering a request, e.g.
+/// `/api/source?method=get&path=/users/42`, or `/api/source?request=17` for a journaled one.
+/// Answers `{"method", "endpoint", "file", "line"}`, with status 404 if the endpoint wasn't
+/// loaded from a file.
+#[get("/api/source")]
+async fn endpoint_source(
+    query: web::Query<SourceQuery>,
+    state: web::Data<Arc<ServerState>>,
+) -> HttpResponse {
+    let not_found =
+        |error: &str| HttpResponse::NotFound().json(serde_json::json!({"error": error}));
+    let (method, path) = match (query.request, &query.method, &query.path) {
+        (Some(id), _, _) => {
+            let journal = state.journal();
+            let Some(record) = journal.get(id) else {
+                return not_found("request not journaled");
+            };
+            let Some(endpoint) = &record.endpoint else {
+                return not_found("request not answered by an endpoint");
+            };
+            (record.method.clone(), endpoint.clone())
+        }
+        (None, Some(method), Some(path)) => {
+            let Ok(method) = Method::from_bytes(method.to_ascii_uppercase().as_bytes()) else {
+                return HttpResponse::BadRequest()
+                    .json(serde_json::json!({"error": "invalid method", "method": method}));
+            };
+            (method, path.clone())
+        }
+        _ => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({"error": "expected `request` or `method` and `path`"}));
+        }
+    };
+    match state.definition(&method, &path) {
+        Some((endpoint, definition)) => HttpResponse::Ok().json(serde_json::json!({
+            "method": method.as_str(),
+            "endpoint": endpoint,
+            "file": definition.file,
+            "line": definition.line,
+        })),
+        None => not_found("no endpoint loaded from a file matches"),
+    }
+}
+
+/// Admin API of `coverage report`, answers the coverage of the loaded OpenAPI document. Without
+/// one, requests are left to the endpoints.
+#[get("/__admin/coverage", guard = "spec_loaded")]
+async fn spec_coverage(state: web::Data<Arc<ServerState>>) -> HttpResponse {
+    match state.coverage_report() {
+        Some(report) => HttpResponse::Ok().json(report),
+        None => HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "no OpenAPI document loaded, see `spec load`"})),
+    }
+}
+
+fn spec_loaded(ctx: &GuardContext) -> bool {
+    ctx.app_data::<Data<Arc<ServerState>>>()
+        .is_some_and(|state| state.coverage().is_some())
+}
+
+/// Details about a server that has been bound and started
+#[derive(Debug, Clone)]
+pub struct RunningServer {
+    pub handle: ServerHandle,
+    pub addrs: Vec<SocketAddr>,
+    pub workers: usize,
+}
+
+pub async fn run_server(
+    state: Arc<ServerState>,
+    addr: &str,
+    running_tx: mpsc::Sender<RunningServer>,
+) -> io::Result<()> {
+    // Same default as actix, but set explicitly so it can be reported
+    let workers = thread::available_parallelism().map_or(2, NonZeroUsize::get);
+    let listener = TcpListener::bind(addr)?;
+    let mut addrs = vec![listener.local_addr()?];
+    let plain_state = state.clone();
+    // Built on actix-http rather than `HttpServer`, which has no way to customize the handling of
+    // `Expect: 100-continue`
+    let mut server =
+        Server::build()
+            .workers(workers)
+            .listen("adaptive_rest", listener, move || {
+                // Connections are wrapped so endpoints can answer with raw bytes
+                fn_service(|io: TcpStream| async move {
+                    let peer_addr = io.peer_addr().ok();
+                    Ok::<_, DispatchError>((RawStream::new(io), Protocol::Http1, peer_addr))
+                })
+                .and_then(http_service(plain_state.clone()))
+            })?;
+    let tls = *state.tls_listener();
+    if let Some(tls) = tls {
+        let acceptor = state.certificate_authority()?.acceptor(tls.certificate)?;
+        // A busy TLS port shouldn't take the plain listener down with it
+        match TcpListener::bind(SocketAddr::new(addrs[0].ip(), tls.port)) {
+            Ok(listener) => {
+                addrs.push(listener.local_addr()?);
+                server = server.listen("adaptive_rest_tls", listener, move || {
+                    let acceptor = acceptor.clone();
+                    fn_service(move |io: TcpStream| {
+                        let acceptor = acceptor.clone();
+                        async move {
+                            let peer_addr = io.peer_addr().ok();
+                            // Rejected certificates end up here, which is what clients are tested for
+                            let io = acceptor.accept(io).await.map_err(|e| {
+                                log::info!("TLS handshake failed: {}", e);
+                                DispatchError::Io(e)
+                            })?;
+                            Ok((RawStream::new(io), Protocol::Http1, peer_addr))
+                        }
+                    })
+                    .and_then(http_service(state.clone()))
+                })?;
+            }
+            Err(e) => log::warn!("Can't serve TLS on port {}: {}", tls.port, e),
         }
     }
+    let server = server.run();
+    let _ = running_tx.send(RunningServer {
+        handle: server.handle(),
+        addrs,
+        workers,
+    });
+    server.await
+}
+
+/// The app served on connections of either listener
+fn http_service<T>(
+    state: Arc<ServerState>,
+) -> impl ServiceFactory<
+    (RawStream<T>, Protocol, Option<SocketAddr>),
+    Config = (),
+    Response = (),
+    Error = DispatchError,
+    InitError = (),
+>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    let cors = state.cors_config();
+    let cors_stage = state.middleware_chain().contains(Stage::Cors);
+    let app = ServerApp::new()
+        .wrap(Condition::new(
+            cors.is_enabled() && cors_stage,
+            cors.middleware(),
+        ))
+        .wrap(from_fn(compression::skip_unwanted))
+        .wrap(Compress::default())
+        .wrap(from_fn(compression::strip_identity))
+        .app_data(Data::new(state.clone()))
+        // Connections to the fallback upstream are pooled per worker
+        .app_data(Data::new(awc::Client::default()))
+        .service(health)
+        .service(about)
+        .service(verify_requests)
+        .service(endpoint_source)
+        .service(spec_coverage)
+        .default_service(to(catch_all));
+    HttpService::build()
+        .on_connect_ext(|io: &RawStream<T>, connection| {
+            connection.insert(connection::ServedRequests::default());
+            connection.insert(io.slot());
+            connection.insert(io.closed());
+            connection.insert(io.idle_close());
+        })
+        .expect(fn_service(move |req| {
+            expect::handle_expect(state.clone(), req)
+        }))
+        .finish(map_config(app, |_| AppConfig::default()))
+}
+
+async fn catch_all(
+    req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<Arc<ServerState>>,
+    client: web::Data<awc::Client>,
+) -> impl Responder {
+    let start = Instant::now();
+    let raw = match payload.to_bytes_limited(MAX_BODY_SIZE).await {
+        Ok(Ok(raw)) => raw,
+        Ok(Err(e)) => return e.error_response(),
+        Err(_) => {
+            return HttpResponse::PayloadTooLarge()
+                .json(serde_json::json!({"error": "body too large", "limit": MAX_BODY_SIZE}));
+        }
+    };
+    // Matchers and the journal see compressed bodies decoded
+    let (body, encoded_size) = match encoding::decode_body(req.headers(), &raw, MAX_BODY_SIZE) {
+        Some(Ok(body)) => (body, Some(raw.len())),
+        Some(Err(e)) => {
+            log::warn!(
+                "{} {} sent a body that can't be decoded, matching it as is: {}",
+                req.method(),
+                req.path(),
+                e
+            );
+            (raw, None)
+        }
+        None => (raw, None),
+    };
+    let route = state.route(req.uri(), req.headers());
+    let routed = route.routed.as_deref();
+    let path = routed.unwrap_or(&route.rewritten);
+    let request = RequestInfo::from_request(&req, &body);
+    let token_endpoint = state
+        .oauth()
+        .clone()
+        .filter(|mock| req.method() == Method::POST && routed == Some(mock.path.as_str()));
+    // Inbox paths accept any method, like the webhooks pointed at them may use
+    let captured = routed.is_some_and(|path| state.inbox().accepts(path));
+    let mut matched = state
+        .find_endpoint(req.method(), &route, &request)
+        .map(|(_, endpoint, stub)| (endpoint, stub))
+        .filter(|_| token_endpoint.is_none() && !captured);
+    let chain = state.middleware_chain().clone();
+    let mut rng = rand::rng();
+    // Stages that may answer the request run in the order of the chain until one does
+    let mut denied = None;
+    let mut faults = chaos::Faults::default();
+    for stage in chain.stages() {
+        if denied.is_some() || faults.error.is_some() {
+            break;
+        }
+        match stage {
+            // Requests lacking required credentials are answered with 401 instead of being matched
+            Stage::Auth => {
+                denied = auth::challenge(&state.auth_rules(), path, req.headers())
+                    .filter(|_| token_endpoint.is_none() && !captured);
+            }
+            Stage::RateLimit => {
+                denied = state
+                    .rate_limiter()
+                    .as_mut()
+                    .and_then(|limiter| limiter.check(Instant::now()));
+            }
+            Stage::Chaos => faults = state.decide_faults(req.method(), path, matched.as_ref()),
+            // Redaction and logging apply to what is recorded, wherever they are listed
+            Stage::Cors | Stage::Redaction | Stage::Logging => {}
+        }
+    }
+    if denied.is_some() {
+        matched = None;
+    }
+    if let Some(path) = routed
+        && denied.is_none()
+        && token_endpoint.is_none()
+        && !captured
+        && matched.is_none()
+        && state.learning.load(Ordering::Relaxed)
+    {
+        matched = Some(state.learn_endpoint(req.method(), path));
+    }
+    let (mut endpoint, mut stub) = matched.unzip();
+    // Endpoints with several representations send the one the client accepts most
+    let mut not_acceptable = None;
+    if let Some(stub) = &mut stub
+        && !stub.representations.is_empty()
+    {
+        match negotiate::choose(&stub.representations, req.headers()) {
+            Some(chosen) => {
+                stub.body = chosen.body.clone();
+                let content_type = HeaderValue::try_from(chosen.content_type.as_ref());
+                if !stub
+                    .headers
+                    .iter()
+                    .any(|header| header.name == CONTENT_TYPE)
+                    && let Ok(value) = content_type
+                {
+                    stub.headers.push(ResponseHeader {
+                        name: CONTENT_TYPE,
+                        value,
+                    });
+                }
+                stub.headers.push(ResponseHeader {
+                    name: VARY,
+                    value: HeaderValue::from_static("accept"),
+                });
+            }
+            None => {
+                let available: Vec<String> = stub
+                    .representations
+                    .iter()
+                    .map(|representation| representation.content_type.to_string())
+                    .collect();
+                not_acceptable = Some(available);
+            }
+        }
+    }
+    // Paginated endpoints send the page the query asks for
+    let mut bad_page = None;
+    if let Some(stub) = &mut stub
+        && let Some(paging) = stub.paging
+    {
+        match paging.page(&stub.body, req.path(), req.query_string()) {
+            Ok(page) => {
+                stub.body = page.body;
+                if !stub
+                    .headers
+                    .iter()
+                    .any(|header| header.name == CONTENT_TYPE)
+                {
+                    stub.headers.push(ResponseHeader {
+                        name: CONTENT_TYPE,
+                        value: HeaderValue::from_static("application/json"),
+                    });
+                }
+                if let Some(link) = page.link {
+                    stub.headers.push(ResponseHeader {
+                        name: LINK,
+                        value: link,
+                    });
+                }
+            }
+            Err(reason) => bad_page = Some(reason),
+        }
+    }
+    // The body passes through the overlays of the active profile, then the transformations of
+    // the endpoint, in order
+    let mut failed_transform = None;
+    if let Some(stub) = &mut stub
+        && !(stub.transforms.is_empty() && stub.overlays.is_empty())
+    {
+        let profile = state.profile().as_ref().map(|profile| profile.name.clone());
+        let steps = transform::pipeline(&stub.overlays, &stub.transforms, profile.as_deref());
+        let request = transform::TransformRequest {
+            method: req.method(),
+            path,
+            endpoint: endpoint.as_deref(),
+            query: req.query_string(),
+            headers: req.headers(),
+            body: &body,
+        };
+        match transform::run(&steps, &stub.body, &request) {
+            Ok(transformed) => {
+                stub.body = transformed;
+                if transform::gzipped(&steps) {
+                    stub.headers.push(ResponseHeader {
+                        name: CONTENT_ENCODING,
+                        value: HeaderValue::from_static("gzip"),
+                    });
+                }
+            }
+            Err(reason) => failed_transform = Some(reason),
+        }
+    }
+    // Hooks of the endpoint run before its response is built and again on the finished response
+    let hook_request = hook::HookRequest {
+        method: req.method(),
+        path: req.path(),
+        query: req.query_string(),
+        body: &body,
+    };
+    if let Some(mock) = &token_endpoint {
+        endpoint = Some(mock.path.clone());
+    }
+    if let Some(delay) = faults.delay {
+        actix_web::rt::time::sleep(delay).await;
+    }
+    // Invalid bodies are answered with 400 instead of the stub
+    let violations = stub
+        .as_ref()
+        .and_then(|stub| stub.request_schema.as_ref())
+        .map_or_else(Vec::new, |schema| schema.violations(&body));
+    let mutated = match (&endpoint, &stub, faults.error) {
+        (Some(endpoint), Some(stub), None) if violations.is_empty() => {
+            state.mutations().mutate(req.method(), endpoint, &stub.body)
+        }
+        _ => None,
+    };
+    // Tags are of the body sent, which mutation testing may replace
+    let entity_tag = stub
+        .as_ref()
+        .filter(|stub| stub.raw.is_none())
+        .and_then(|stub| {
+            let body = mutated.as_ref().map_or(&stub.body, |(body, _)| body);
+            stub.etag.as_ref().map(|etag| etag.tag(body))
+        });
+    // Parts of the body for endpoints serving byte ranges, only of a plain 200
+    let byte_range = stub
+        .as_ref()
+        .filter(|stub| {
+            stub.ranges
+                && stub.raw.is_none()
+                && stub.redirect.is_none()
+                && stub.events.is_none()
+                && stub.response_status() == StatusCode::OK
+        })
+        .and_then(|stub| {
+            let body = mutated.as_ref().map_or(&stub.body, |(body, _)| body);
+            ByteRange::requested(req.method(), req.headers(), body.len(), entity_tag.as_ref())
+        });
+    // Only requests that would be answered with 404 are forwarded
+    let fallback = state
+        .fallback()
+        .clone()
+        .filter(|_| stub.is_none() && token_endpoint.is_none() && !captured && denied.is_none());
+    // Unanswered requests are still journaled, with a stand-in response
+    let hangup = stub
+        .as_ref()
+        .and_then(|stub| stub.hangup)
+        .filter(|_| denied.is_none() && faults.error.is_none());
+    // Before hooks only see requests the endpoint's own response answers
+    let serves_stub = denied.is_none()
+        && faults.error.is_none()
+        && hangup.is_none()
+        && not_acceptable.is_none()
+        && bad_page.is_none()
+        && failed_transform.is_none()
+        && violations.is_empty();
+    if let Some(stub) = &mut stub
+        && serves_stub
+        && !stub.hooks.is_empty()
+    {
+        let mut metrics = state.metrics();
+        for metric in hook::run_before(
+            &stub.hooks,
+            &mut stub.headers,
+            &hook_request,
+            &state.datasets,
+        ) {
+            metrics.count(metric);
+        }
+    }
+    let mut upstream_body = None;
+    let mut response = match (denied, &stub, faults.error, &mutated) {
+        (Some(denied), ..) => denied,
+        (None, _, Some(status), _) => HttpResponse::build(status)
+            .json(serde_json::json!({"error": "injected fault", "status": status.as_u16()})),
+        (None, None, None, _) if let Some(mock) = &token_endpoint => {
+            mock.respond(req.headers(), &body)
+        }
+        (None, None, None, _) if captured => {
+            HttpResponse::Ok().json(serde_json::json!({"received": true}))
+        }
+        (None, Some(stub), None, _) if let Some(hangup) = stub.hangup => hangup.respond(&req),
+        (None, Some(_), None, _) if let Some(available) = &not_acceptable => {
+            HttpResponse::NotAcceptable()
+                .json(serde_json::json!({"error": "not acceptable", "available": available}))
+        }
+        (None, Some(_), None, _) if let Some(reason) = &bad_page => HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "invalid page", "reason": reason})),
+        (None, Some(_), None, _) if let Some(reason) = &failed_transform => {
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({"error": "transformation failed", "reason": reason}))
+        }
+        (None, Some(_), None, _) if !violations.is_empty() => HttpResponse::BadRequest()
+            .json(serde_json::json!({"error": "invalid request body", "violations": violations})),
+        // Clients revalidating their current copy aren't sent the body again
+        (None, Some(stub), None, _)
+            if let Some(tag) = &entity_tag
+                && etag::is_cached(req.headers(), tag) =>
+        {
+            let mut response = etag::not_modified(req.method(), tag);
+            if response.status() == StatusCode::NOT_MODIFIED {
+                stub.add_headers(&mut response);
+            }
+            response
+        }
+        (None, Some(stub), None, mutated) if byte_range == Some(ByteRange::Unsatisfiable) => {
+            let size = mutated.as_ref().map_or(&stub.body, |(body, _)| body).len();
+            range::not_satisfiable(size)
+        }
+        (None, Some(stub), None, mutated) => {
+            let mut body = mutated
+                .as_ref()
+                .map_or(&stub.body, |(body, _)| body)
+                .clone();
+            let mut status = stub.response_status();
+            let full_size = body.len();
+            if let Some(ByteRange::Part(range)) = &byte_range {
+                body = body.slice(range.clone());
+                status = StatusCode::PARTIAL_CONTENT;
+            }
+            let size = body.len();
+            let mut response = match (&stub.raw, &stub.redirect, &stub.events, &stub.drip) {
+                (Some(raw), ..) => raw.respond(&req),
+                (None, Some(redirect), _, _) => redirect.respond(body),
+                (None, None, Some(events), _) => events.respond(body),
+                (None, None, None, Some(drip)) => {
+                    HttpResponse::build(status).body(drip.stream(body))
+                }
+                (None, None, None, None) => HttpResponse::build(status).body(body),
+            };
+            if let Some(tag) = &entity_tag {
+                etag::set_header(&mut response, tag);
+            }
+            if stub.ranges {
+                response
+                    .headers_mut()
+                    .insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+            }
+            if let Some(ByteRange::Part(range)) = &byte_range {
+                range::set_content_range(&mut response, range, full_size);
+            }
+            stub.add_headers(&mut response);
+            if let Some(framing) = stub.framing
+                && stub.raw.is_none()
+            {
+                response = framing.apply(response, size);
+            }
+            if let Some(order) = &stub.header_order
+                && stub.raw.is_none()
+            {
+                order.apply(&req);
+            }
+            // Only the endpoint's own response notifies, not faults or rejections
+            if let Some(callback) = &stub.callback {
+                callback.fire(client.get_ref().clone());
+            }
+            response
+        }
+        (None, None, None, _) if let Some(fallback) = &fallback => {
+            log::debug!("Forwarding {} {} to {}", req.method(), req.uri(), fallback);
+            let (response, body) = fallback
+                .forward(&client, &req, body.clone(), encoded_size.is_some())
+                .await;
+            upstream_body = body;
+            response
+        }
+        (None, None, None, _) => HttpResponse::NotFound()
+            .json(serde_json::json!({"error": "not found", "path": req.path()})),
+    };
+    // Headers the response sets itself win, repeated default headers are all added
+    let defaults = state
+        .default_headers()
+        .iter()
+        .filter(|header| !response.headers().contains_key(&header.name))
+        .cloned()
+        .collect::<Vec<_>>();
+    for header in defaults {
+        response.headers_mut().append(header.name, header.value);
+    }
+    // After hooks see the response as sent, default headers included
+    if let Some(stub) = &stub
+        && !stub.hooks.is_empty()
+    {
+        let status = response.status();
+        let mut metrics = state.metrics();
+        for metric in hook::run_after(
+            &stub.hooks,
+            response.headers_mut(),
+            &hook_request,
+            status,
+            &state.datasets,
+        ) {
+            metrics.count(metric);
+        }
+    }
+    if faults.abort {
+        response = chaos::abort_midway(response);
+    }
+    let served = req
+        .conn_data::<connection::ServedRequests>()
+        .map_or(1, connection::ServedRequests::next);
+    let (closing, dropped) = {
+        let policy = state.connection_policy();
+        let closing = if stub.as_ref().is_some_and(|stub| stub.close) {
+            Some("endpoint closes connections")
+        } else {
+            policy.close_reason(served)
+        };
+        (closing, closing.is_none() && policy.drops(&mut rng))
+    };
+    if closing.is_some() {
+        response
+            .head_mut()
+            .set_connection_type(ConnectionType::Close);
+    } else if dropped {
+        IdleClose::schedule(&req);
+    }
+    // Compressing would undo a framing set by the endpoint, and ranges refer to the body as is
+    if let Some(compress) = stub
+        .as_ref()
+        .filter(|stub| stub.framing.is_none())
+        .and_then(|stub| {
+            if stub.ranges {
+                Some(false)
+            } else {
+                stub.compress
+            }
+        })
+    {
+        response
+            .extensions_mut()
+            .insert(compression::CompressOverride(compress));
+    }
+    if let Some(malformation) = faults.malformed {
+        response = malformation.apply(response);
+    }
+    let failures = stub.as_ref().map_or_else(Vec::new, |stub| {
+        assertion::failed_assertions(&stub.assertions, req.headers(), &body)
+    });
+    let mut record = RequestRecord {
+        id: 0,
+        timestamp: Local::now(),
+        method: req.method().clone(),
+        path: req.path().to_owned(),
+        query: req.query_string().to_owned(),
+        headers: req
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect(),
+        body_size: body.len(),
+        encoded_size,
+        body,
+        status: response.status(),
+        stub: stub.as_ref().map(|stub| stub.body.clone()),
+        response: upstream_body,
+        endpoint: endpoint.clone(),
+        elapsed: start.elapsed(),
+        fault: match (
+            faults
+                .describe()
+                .or_else(|| hangup.map(|h| h.describe().to_owned())),
+            &mutated,
+        ) {
+            (fault, None) => fault,
+            (None, Some((_, mutation))) => Some(format!("mutated: {}", mutation)),
+            (Some(fault), Some((_, mutation))) => Some(format!("{}, mutated: {}", fault, mutation)),
+        },
+        failures: failures.clone(),
+        violations: violations.clone(),
+    };
+    if chain.contains(Stage::Redaction) {
+        redact::redact_record(&state.redactions(), &mut record);
+    }
+    // Captured before the journal cuts the body off, which the inbox keeps whole
+    let capture = captured.then(|| record.clone());
+    let request_id = state.journal().record(record);
+    if let Some(mut capture) = capture {
+        capture.id = request_id;
+        let number = state.inbox().capture(capture);
+        log::info!(request_id; "Inbox captured {} {} as #{}", req.method(), path, number);
+    }
+    if let Some(coverage) = state.coverage().as_mut() {
+        coverage.record(req.method(), req.path());
+    }
+    if let Some(endpoint) = &endpoint {
+        state.mutations().record(
+            request_id,
+            req.method(),
+            endpoint,
+            mutated.as_ref().map(|(_, mutation)| mutation),
+        );
+        let mut stats = state.stats();
+        stats.record(req.method(), endpoint, response.status());
+        if !failures.is_empty() || !violations.is_empty() {
+            stats.record_failure(req.method(), endpoint);
+        }
+    }
+    if !failures.is_empty() {
+        log::warn!(
+            request_id;
+            "{} {} failed assertions: {}",
+            req.method(),
+            path,
+            failures.join(", ")
+        );
+    }
+    if !violations.is_empty() {
+        log::warn!(
+            request_id;
+            "{} {} sent a body violating its schema: {}",
+            req.method(),
+            path,
+            violations.join(", ")
+        );
+    }
+    if let Some(reason) = closing {
+        log::debug!(
+            request_id;
+            "Closing the connection after request {} on it: {}",
+            served,
+            reason
+        );
+    } else if dropped {
+        log::debug!(
+            request_id;
+            "Dropping the connection shortly after request {} on it",
+            served
+        );
+    }
+    if let Some(run) = state.scenario().as_mut()
+        && run.observe(req.method(), path)
+    {
+        log::info!(
+            request_id;
+            "Scenario {} advanced to step {}",
+            run.name(),
+            run.describe_step()
+        );
+    }
+    if let Some(stub) = &stub {
+        let violations = stub.budget.violations(stub.body.len(), start.elapsed());
+        if !violations.is_empty() {
+            log::warn!(
+                request_id;
+                "{} {} exceeded its budget: {}",
+                req.method(),
+                path,
+                violations.join(", ")
+            );
+        }
+    }
+    // Access log line, linked to the journal so the TUI can jump to the request
+    if chain.contains(Stage::Logging) {
+        log::info!(
+            request_id;
+            "{} \"{} {} {:?}\" {} {:.6}",
+            req.peer_addr().map_or_else(|| "-".to_owned(), |a| a.ip().to_string()),
+            req.method(),
+            req.uri(),
+            req.version(),
+            response.status().as_u16(),
+            start.elapsed().as_secs_f64()
+        );
+    }
+    if hangup == Some(Hangup::NoResponse) {
+        match req.conn_data::<PeerClosed>() {
+            Some(closed) => closed.wait().await,
+            None => std::future::pending().await,
+        }
+        log::debug!(request_id; "{} {} gave up waiting for a response", req.method(), path);
+        // Nothing is written to what is left of the connection
+        return RawResponse(Bytes::new()).respond(&req);
+    }
+    response
+}
+
+/// Where a request path ends up before matching, see [`ServerState::route`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    /// The path after applying the rewrite rules
+    pub rewritten: String,
+    /// Path the endpoints are matched with, relative to the base path and without a tenant
+    /// prefix. `None` if outside of the base path.
+    pub routed: Option<String>,
+    pub tenant: Option<String>,
+    /// See [`request_host`]
+    pub host: Option<String>,
+}
+
+/// Which requests an added endpoint answers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EndpointScope<'a> {
+    /// Every request without an endpoint of its tenant or host
+    All,
+    /// Requests of a tenant, overriding the regular endpoints
+    Tenant(&'a str),
+    /// Requests sent to a host, see [`VirtualHosts`]
+    Host(&'a str),
+}
+
+/// `path` relative to `base_path`, e.g. `/users` for `/api/v2/users` below `/api/v2`. `None` if
+/// `path` is outside of `base_path`.
+pub fn strip_base_path<'a>(base_path: &str, path: &'a str) -> Option<&'a str> {
+    match path.strip_prefix(base_path.trim_end_matches('/'))? {
+        "" => Some("/"),
+        rest if rest.starts_with('/') => Some(rest),
+        _ => None,
+    }
 }
 
 impl ServerState {
     pub fn new() -> Self {
         Self {
             endpoints: RwLock::new(EndpointStore::default()),
+            journal: Mutex::new(RequestJournal::default()),
+            stats: Mutex::new(EndpointStatsTable::default()),
+            learning: AtomicBool::new(false),
+            compress: AtomicBool::new(false),
+            connections: RwLock::new(ConnectionPolicy::default()),
+            cors: RwLock::new(CorsConfig::default()),
+            chaos: RwLock::new(Chaos::default()),
+            scenario: RwLock::new(None),
+            base_path: RwLock::new(String::new()),
+            rewrites: RwLock::new(Vec::new()),
+            redactions: RwLock::new(Vec::new()),
+            auth: RwLock::new(Vec::new()),
+            oauth: RwLock::new(None),
+            fallback: RwLock::new(None),
+            profile: RwLock::new(None),
+            default_headers: RwLock::new(Vec::new()),
+            middleware: RwLock::new(MiddlewareChain::default()),
+            rate_limit: Mutex::new(None),
+            tls: RwLock::new(None),
+            certificate_authority: OnceLock::new(),
+            tenancy: RwLock::new(Tenancy::default()),
+            virtual_hosts: RwLock::new(VirtualHosts::default()),
+            definitions: RwLock::new(Definitions::default()),
+            mutations: Mutex::new(MutationTesting::default()),
+            verifications: Mutex::new(Vec::new()),
+            coverage: Mutex::new(None),
+            metrics: Mutex::new(Metrics::default()),
+            inbox: Mutex::new(Inbox::default()),
+            datasets: Datasets::default(),
         }
     }
 
-    pub fn list_endpoints(&self, by_method: Option<&Method>) -> InternalResult<()> {
-        let endpoints = self
-            .endpoints
+    pub fn chaos(&self) -> RwLockReadGuard<'_, Chaos> {
+        self.chaos.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn chaos_mut(&self) -> RwLockWriteGuard<'_, Chaos> {
+        self.chaos.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn scenario(&self) -> RwLockWriteGuard<'_, Option<ScenarioRun>> {
+        self.scenario
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn mutations(&self) -> MutexGuard<'_, MutationTesting> {
+        self.mutations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn base_path(&self) -> String {
+        self.base_path
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Prefix registered paths with `base_path` at match time, `/` to remove the prefix
+    pub fn set_base_path(&self, base_path: &str) {
+        let base_path = match base_path.trim_matches('/') {
+            "" => String::new(),
+            trimmed => format!("/{}", trimmed),
+        };
+        *self
+            .base_path
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = base_path;
+    }
+
+    pub fn rewrites(&self) -> RwLockReadGuard<'_, Vec<RewriteRule>> {
+        self.rewrites.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn rewrites_mut(&self) -> RwLockWriteGuard<'_, Vec<RewriteRule>> {
+        self.rewrites
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn auth_rules(&self) -> RwLockReadGuard<'_, Vec<AuthRule>> {
+        self.auth.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn auth_rules_mut(&self) -> RwLockWriteGuard<'_, Vec<AuthRule>> {
+        self.auth.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn connection_policy(&self) -> RwLockReadGuard<'_, ConnectionPolicy> {
+        self.connections
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn connection_policy_mut(&self) -> RwLockWriteGuard<'_, ConnectionPolicy> {
+        self.connections
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn oauth(&self) -> RwLockReadGuard<'_, Option<OAuthMock>> {
+        self.oauth.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn oauth_mut(&self) -> RwLockWriteGuard<'_, Option<OAuthMock>> {
+        self.oauth.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn middleware_chain(&self) -> RwLockReadGuard<'_, MiddlewareChain> {
+        self.middleware
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn middleware_chain_mut(&self) -> RwLockWriteGuard<'_, MiddlewareChain> {
+        self.middleware
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn rate_limiter(&self) -> MutexGuard<'_, Option<RateLimiter>> {
+        self.rate_limit
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn default_headers(&self) -> RwLockReadGuard<'_, Vec<ResponseHeader>> {
+        self.default_headers
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn default_headers_mut(&self) -> RwLockWriteGuard<'_, Vec<ResponseHeader>> {
+        self.default_headers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn fallback(&self) -> RwLockReadGuard<'_, Option<Fallback>> {
+        self.fallback.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn fallback_mut(&self) -> RwLockWriteGuard<'_, Option<Fallback>> {
+        self.fallback
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn profile(&self) -> RwLockReadGuard<'_, Option<Profile>> {
+        self.profile.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn profile_mut(&self) -> RwLockWriteGuard<'_, Option<Profile>> {
+        self.profile.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn tls_listener(&self) -> RwLockReadGuard<'_, Option<TlsListener>> {
+        self.tls.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn tls_listener_mut(&self) -> RwLockWriteGuard<'_, Option<TlsListener>> {
+        self.tls.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The authority issuing the certificates of the TLS listener, generated on first use
+    pub fn certificate_authority(&self) -> io::Result<&CertificateAuthority> {
+        if let Some(authority) = self.certificate_authority.get() {
+            return Ok(authority);
+        }
+        let authority = CertificateAuthority::generate()?;
+        Ok(self.certificate_authority.get_or_init(|| authority))
+    }
+
+    pub fn redactions(&self) -> RwLockReadGuard<'_, Vec<Redaction>> {
+        self.redactions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn redactions_mut(&self) -> RwLockWriteGuard<'_, Vec<Redaction>> {
+        self.redactions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn tenancy(&self) -> RwLockReadGuard<'_, Tenancy> {
+        self.tenancy.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn tenancy_mut(&self) -> RwLockWriteGuard<'_, Tenancy> {
+        self.tenancy.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn virtual_hosts(&self) -> RwLockReadGuard<'_, VirtualHosts> {
+        self.virtual_hosts
             .read()
-            .map_err(|_| InternalError::LockFailed)?;
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn virtual_hosts_mut(&self) -> RwLockWriteGuard<'_, VirtualHosts> {
+        self.virtual_hosts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn definitions(&self) -> RwLockReadGuard<'_, Definitions> {
+        self.definitions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn definitions_mut(&self) -> RwLockWriteGuard<'_, Definitions> {
+        self.definitions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The regular endpoint at `path`, which may also be a concrete request path, e.g.
+    /// `/users/42` for `/users/{id}`, along with where it is defined if it was loaded from a file
+    pub fn definition(&self, method: &Method, path: &str) -> Option<(String, Definition)> {
+        let (endpoint, _) = self.read_endpoints().lookup(method, path)?;
+        let definition = self.definitions().get(method, &endpoint)?.clone();
+        Some((endpoint, definition))
+    }
+
+    /// Apply the rewrite rules, the base path and tenant identification to a request path
+    pub fn route(&self, uri: &Uri, headers: &HeaderMap) -> Route {
+        let rewritten = rewrite::rewrite(&self.rewrites(), uri.path()).into_owned();
+        // Registered paths are relative to the base path, requests outside of it match no endpoint
+        let (tenant, routed) = match strip_base_path(&self.base_path(), &rewritten) {
+            Some(path) => {
+                let tenancy = self.tenancy();
+                let (tenant, path) = tenancy.identify(path, headers);
+                (tenant, Some(path.to_owned()))
+            }
+            None => (None, None),
+        };
+        Route {
+            rewritten,
+            routed,
+            tenant,
+            host: request_host(uri, headers),
+        }
+    }
+
+    /// Faults injected into a request to `path`, by the chaos rules and by the endpoint it
+    /// `matched`, if any
+    fn decide_faults(
+        &self,
+        method: &Method,
+        path: &str,
+        matched: Option<&(String, Stub)>,
+    ) -> chaos::Faults {
+        let mut rng = rand::rng();
+        let mut faults = self.chaos().decide(path, &mut rng);
+        let Some((endpoint, stub)) = matched else {
+            return faults;
+        };
+        faults.merge(chaos::roll_flaky(&stub.flaky, &mut rng));
+        if !stub.scheduled.is_empty() {
+            faults.merge(chaos::scheduled_faults(
+                &stub.scheduled,
+                Local::now().time(),
+                self.next_call(method, endpoint),
+            ));
+        }
+        faults
+    }
+
+    /// Which request to an endpoint, given by its path as registered, the next one is, counting
+    /// from 1 since it was last reset
+    fn next_call(&self, method: &Method, endpoint: &str) -> u64 {
+        self.stats()
+            .get(method, endpoint)
+            .map_or(0, |stats| stats.hits)
+            + 1
+    }
+
+    /// Endpoint answering a routed request along with where it comes from. The running scenario
+    /// goes first, then the endpoints of the host the request is sent to and tenant overrides,
+    /// falling back to the regular endpoints.
+    pub fn find_endpoint(
+        &self,
+        method: &Method,
+        route: &Route,
+        request: &RequestInfo,
+    ) -> Option<(&'static str, String, Stub)> {
+        let path = route.routed.as_deref()?;
+        let found = |source, (endpoint, stub): (String, &Stub)| (source, endpoint, stub.clone());
+        let call = |endpoint: &str| self.next_call(method, endpoint);
+        let scenario = self
+            .scenario
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .and_then(|run| run.lookup(method, path, request, call))
+            .map(|matched| found("scenario step", matched));
+        scenario
+            .or_else(|| {
+                self.virtual_hosts()
+                    .lookup(route.host.as_deref()?, method, path, request, call)
+                    .map(|matched| found("virtual host", matched))
+            })
+            .or_else(|| {
+                self.tenancy()
+                    .lookup(route.tenant.as_deref()?, method, path, request, call)
+                    .map(|matched| found("tenant override", matched))
+            })
+            .or_else(|| {
+                self.read_endpoints()
+                    .lookup_request(method, path, request, call)
+                    .map(|matched| found("endpoint", matched))
+            })
+    }
+
+    pub fn cors_config(&self) -> CorsConfig {
+        self.cors
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn set_cors_config(&self, config: CorsConfig) {
+        *self.cors.write().unwrap_or_else(PoisonError::into_inner) = config;
+    }
+
+    /// Register an endpoint for an unmatched request, collapsing id-like segments into parameters
+    /// so that related requests share one endpoint. Returns the path and stub of the new endpoint.
+    pub fn learn_endpoint(&self, method: &Method, path: &str) -> (String, Stub) {
+        let template = path_template(path);
+        let stub = Stub::from(Bytes::from_static(LEARNED_BODY.as_bytes()));
+        self.write_endpoints()
+            .add(method.clone(), &template, stub.clone());
+        log::info!("Learned endpoint {} {} from {}", method, template, path);
+        (template, stub)
+    }
+
+    pub fn stats(&self) -> MutexGuard<'_, EndpointStatsTable> {
+        self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn journal(&self) -> MutexGuard<'_, RequestJournal> {
+        self.journal.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn coverage(&self) -> MutexGuard<'_, Option<Coverage>> {
+        self.coverage.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn metrics(&self) -> MutexGuard<'_, Metrics> {
+        self.metrics.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn inbox(&self) -> MutexGuard<'_, Inbox> {
+        self.inbox.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn coverage_report(&self) -> Option<CoverageReport> {
+        self.coverage().as_ref().map(Coverage::report)
+    }
+
+    /// Track which operations of the OpenAPI document at `path`, JSON or YAML, requests hit,
+    /// replacing a document loaded before. Returns the number of operations.
+    pub fn load_spec(&self, path: &Path) -> InternalResult<usize> {
+        let content = fs::read_to_string(path).map_err(|source| InternalError::FileIo {
+            path: path.to_owned(),
+            source,
+        })?;
+        let import_error = |line, reason| InternalError::Import {
+            path: path.to_owned(),
+            line,
+            reason,
+        };
+        // JSON is YAML as well
+        let document: serde_json::Value = serde_yaml::from_str(&content)
+            .map_err(|e| import_error(e.location().map_or(1, |l| l.line()), e.to_string()))?;
+        let coverage = Coverage::new(path, &document).map_err(|reason| import_error(1, reason))?;
+        let operations = coverage.report().operations;
+        *self.coverage() = Some(coverage);
+        Ok(operations)
+    }
+
+    /// Acquire the endpoint store for reading, recovering it if a panicking thread poisoned the lock
+    pub fn read_endpoints(&self) -> RwLockReadGuard<'_, EndpointStore> {
+        if self.endpoints.is_poisoned() {
+            self.recover_endpoints();
+        }
+        self.endpoints
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Acquire the endpoint store for writing, recovering it if a panicking thread poisoned the lock
+    pub fn write_endpoints(&self) -> RwLockWriteGuard<'_, EndpointStore> {
+        if self.endpoints.is_poisoned() {
+            self.recover_endpoints();
+        }
+        self.endpoints
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Repair the store a panic may have left half-modified and clear the poison flag, so a single
+    /// failing handler doesn't make every following request fail
+    fn recover_endpoints(&self) {
+        let mut endpoints = self
+            .endpoints
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let pruned = endpoints.repair();
+        self.endpoints.clear_poison();
+        log::warn!(
+            "Recovered endpoint store from a poisoned lock ({} empty node(s) pruned)",
+            pruned
+        );
+    }
+
+    pub fn list_endpoints(&self, by_method: Option<&Method>) -> InternalResult<()> {
+        let endpoints = self.read_endpoints();
+        let stats = self.stats();
 
         if endpoints.is_empty() {
             log::info!("No user defined endpoints currently available");
@@ -72,8 +1467,21 @@ impl ServerState {
         for (method, children) in endpoints.entries(by_method) {
             let entries: Vec<_> = children
                 .iter()
-                .map(|(path, content)| {
-                    format!("  {} -> {}", path, String::from_utf8_lossy(content))
+                .map(|(path, stub)| {
+                    let options = match stub.options().as_slice() {
+                        [] => String::new(),
+                        options => format!(" ({})", options.join(", ")),
+                    };
+                    let hits = stats
+                        .get(method, path)
+                        .map_or_else(|| "never hit".to_owned(), EndpointStats::summary);
+                    format!(
+                        "  {} -> {}{} [{}]",
+                        path,
+                        String::from_utf8_lossy(&stub.body),
+                        options,
+                        hits
+                    )
                 })
                 .collect();
             log::info!(
@@ -86,18 +1494,72 @@ impl ServerState {
         Ok(())
     }
 
-    pub fn add_endpoint(&self, method: Method, path: &str, body: String) -> InternalResult<()> {
+    pub fn add_endpoint(
+        &self,
+        method: Method,
+        path: &str,
+        stub: impl Into<Stub>,
+    ) -> InternalResult<()> {
+        self.add_scoped_endpoint(EndpointScope::All, method, path, stub)
+    }
+
+    /// Add an endpoint answering the requests of `scope` only
+    pub fn add_scoped_endpoint(
+        &self,
+        scope: EndpointScope<'_>,
+        method: Method,
+        path: &str,
+        stub: impl Into<Stub>,
+    ) -> InternalResult<()> {
+        let mut stub = stub.into();
+        let (path, query) = matcher::split_query(path);
+        let mut matchers: Vec<Matcher> = Vec::new();
+        for matcher in query.into_iter().chain(std::mem::take(&mut stub.matchers)) {
+            if let Some(other) = matchers.iter().find(|m| m.contradicts(&matcher)) {
+                return Err(InternalError::MatcherConflict {
+                    method,
+                    path: path.to_owned(),
+                    reason: format!("`{}` contradicts `{}`", matcher, other),
+                });
+            }
+            if !matchers.contains(&matcher) {
+                matchers.push(matcher);
+            }
+        }
+        stub.matchers = matchers;
         let valid_path = if path.starts_with("/") {
             path.to_owned()
         } else {
             format!("/{}", path)
         };
-        let log_msg = format!("endpoint {} {} -> {}", method, &valid_path, &body);
-        let was_updated = self
-            .endpoints
-            .write()
-            .map_err(|_| InternalError::LockFailed)?
-            .add(method, &valid_path, Bytes::from(body));
+        let log_msg = format!(
+            "endpoint {} {} -> {}{}",
+            method,
+            &valid_path,
+            String::from_utf8_lossy(&stub.body),
+            match scope {
+                EndpointScope::All => String::new(),
+                EndpointScope::Tenant(tenant) => format!(" for tenant {}", tenant),
+                EndpointScope::Host(host) => format!(" for host {}", host),
+            }
+        );
+        let was_updated = match scope {
+            EndpointScope::All => {
+                // The endpoint no longer is what its file defines
+                self.definitions_mut().remove(&method, &valid_path);
+                self.write_endpoints().add(method, &valid_path, stub)
+            }
+            EndpointScope::Tenant(tenant) => {
+                self.tenancy_mut()
+                    .endpoints(tenant)
+                    .add(method, &valid_path, stub)
+            }
+            EndpointScope::Host(host) => {
+                self.virtual_hosts_mut()
+                    .endpoints(host)
+                    .add(method, &valid_path, stub)
+            }
+        };
 
         log::info!(
             "{}{}",
@@ -107,12 +1569,417 @@ impl ServerState {
         Ok(())
     }
 
+    /// Import endpoints, e.g. loaded from a file, see [`import::import`]. Endpoints of a
+    /// `tenant` override the regular ones for it.
+    pub fn import_endpoints(
+        &self,
+        tenant: Option<&str>,
+        entries: Vec<(Method, String, Stub)>,
+        on_conflict: OnConflict,
+    ) -> ImportReport {
+        match tenant {
+            Some(tenant) => {
+                import::import(self.tenancy_mut().endpoints(tenant), entries, on_conflict)
+            }
+            None => import::import(&mut self.write_endpoints(), entries, on_conflict),
+        }
+    }
+
+    /// Log the usage of the endpoints at `path`, of all methods unless `method` is given.
+    /// `path` may also be a concrete request path, e.g. `/users/42` for `/users/{id}`.
+    pub fn endpoint_stats(&self, method: Option<&Method>, path: &str) -> InternalResult<()> {
+        let endpoints = self.read_endpoints();
+        let stats = self.stats();
+        let lines: Vec<String> = endpoints
+            .entries(method)
+            .into_iter()
+            .filter_map(|(method, _)| {
+                let (endpoint, _) = endpoints.lookup(method, path)?;
+                let line = match stats.get(method, &endpoint) {
+                    Some(s) => format!(
+                        "{} {}: {} ({})",
+                        method,
+                        endpoint,
+                        s.summary(),
+                        s.status_distribution()
+                    ),
+                    None => format!("{} {}: never hit", method, endpoint),
+                };
+                Some(line)
+            })
+            .collect();
+        if lines.is_empty() {
+            let method = method.cloned().unwrap_or(Method::GET);
+            return Err(InternalError::EndpointNotFound {
+                suggestion: endpoints.closest(&method, path),
+                method,
+                path: path.to_owned(),
+            });
+        }
+        log::info!("{}", lines.join("\n"));
+        Ok(())
+    }
+
+    /// Check how often `path` was requested, see [`verify::verify`]
+    pub fn verify(&self, method: &Method, path: &str, expected: Expectation) -> Verification {
+        let verification = verify::verify(&self.journal(), method, path, expected);
+        self.verifications
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(verification.clone());
+        verification
+    }
+
+    /// Summarize the endpoints, their usage, verifications and journaled requests
+    pub fn report(&self) -> Report {
+        Report::new(
+            &self.read_endpoints(),
+            &self.stats(),
+            &self.journal(),
+            &self
+                .verifications
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+            self.coverage_report(),
+            &self.metrics(),
+        )
+    }
+
+    /// Write the endpoints to a file in the format of `exporter`
+    pub fn export_endpoints(&self, exporter: &dyn Exporter, path: &Path) -> InternalResult<()> {
+        let io_error = |source| InternalError::FileIo {
+            path: path.to_owned(),
+            source,
+        };
+        let content = exporter
+            .export(&self.read_endpoints(), &self.base_path(), path)
+            .map_err(io_error)?;
+        fs::write(path, content).map_err(io_error)
+    }
+
+    /// Write the certificate of the authority issuing the TLS listener's certificates as PEM
+    pub fn export_certificate_authority(&self, path: &Path) -> InternalResult<()> {
+        let pem = self.certificate_authority()?.pem();
+        fs::write(path, pem).map_err(|source| InternalError::FileIo {
+            path: path.to_owned(),
+            source,
+        })
+    }
+
+    /// Write the [`Self::report`] to a JSON file
+    pub fn export_report(&self, path: &Path) -> InternalResult<()> {
+        let io_error = |source| InternalError::FileIo {
+            path: path.to_owned(),
+            source,
+        };
+        let json = serde_json::to_string_pretty(&self.report())
+            .map_err(io::Error::other)
+            .map_err(io_error)?;
+        fs::write(path, json).map_err(io_error)
+    }
+
+    /// Write a shell script of curl commands reproducing the journaled requests, with `failed`
+    /// only those answered with an error status, hit by a fault or failing an assertion
+    pub fn export_curl(&self, path: &Path, failed: bool) -> InternalResult<usize> {
+        let journal = self.journal();
+        let records: Vec<&RequestRecord> = journal
+            .records()
+            .filter(|record| !failed || record.failed())
+            .collect();
+        fs::write(path, curl::curl_script(records.iter().copied())).map_err(|source| {
+            InternalError::FileIo {
+                path: path.to_owned(),
+                source,
+            }
+        })?;
+        Ok(records.len())
+    }
+
+    /// Write the journaled requests as a HAR file, with `failed` only those answered with an
+    /// error status, hit by a fault or failing an assertion
+    pub fn export_har(&self, path: &Path, failed: bool) -> InternalResult<usize> {
+        let journal = self.journal();
+        let records: Vec<&RequestRecord> = journal
+            .records()
+            .filter(|record| !failed || record.failed())
+            .collect();
+        let document = har::har_document(records.iter().copied());
+        fs::write(path, format!("{:#}\n", document)).map_err(|source| InternalError::FileIo {
+            path: path.to_owned(),
+            source,
+        })?;
+        Ok(records.len())
+    }
+
+    /// Log how a request would be normalized and matched, step by step, without sending it
+    pub fn normalize(&self, method: &Method, uri: &Uri, headers: HeaderMap) {
+        let mut request = RequestInfo::new(uri.query().unwrap_or_default());
+        request.headers = headers;
+        let route = self.route(uri, &request.headers);
+        let query = match request.query.as_slice() {
+            [] => "none".to_owned(),
+            query => query
+                .iter()
+                .map(|(name, value)| format!("{}={:?}", name, value))
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        let rewritten = if route.rewritten == uri.path() {
+            "unchanged".to_owned()
+        } else {
+            route.rewritten.clone()
+        };
+        let base_path = match (self.base_path().as_str(), &route.routed) {
+            ("", _) => "none".to_owned(),
+            (base_path, Some(_)) => format!("{} stripped", base_path),
+            (base_path, None) => format!("{}, the path is outside of it", base_path),
+        };
+        let tenant = match (&route.tenant, &self.tenancy().source) {
+            (Some(tenant), Some(source)) => format!("{} (by {})", tenant, source),
+            (None, Some(source)) => format!("none named (by {})", source),
+            (_, None) => "not identified".to_owned(),
+        };
+        let host = match &route.host {
+            Some(host) if self.virtual_hosts().hosts().any(|(known, _)| known == host) => {
+                format!("{} (has endpoints)", host)
+            }
+            Some(host) => format!("{} (no endpoints of its own)", host),
+            None => "none".to_owned(),
+        };
+        let segments = route.routed.as_deref().map_or_else(
+            || "-".to_owned(),
+            |path| {
+                let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+                format!("[{}]", segments.join(", "))
+            },
+        );
+        let matched = match (self.find_endpoint(method, &route, &request), &route.routed) {
+            (Some((source, endpoint, stub)), _) => {
+                let options = match stub.options().as_slice() {
+                    [] => String::new(),
+                    options => format!(" ({})", options.join(", ")),
+                };
+                format!(
+                    "{} {} {} -> {}{}",
+                    source,
+                    method,
+                    endpoint,
+                    String::from_utf8_lossy(&stub.body),
+                    options
+                )
+            }
+            (None, None) => "nothing, outside of the base path".to_owned(),
+            (None, Some(path)) => match self.read_endpoints().lookup(method, path) {
+                Some((endpoint, _)) => format!(
+                    "nothing, the candidates of {} {} don't accept the query, headers or body",
+                    method, endpoint
+                ),
+                None if self.learning.load(Ordering::Relaxed) => {
+                    format!(
+                        "nothing, learning would add {} {}",
+                        method,
+                        path_template(path)
+                    )
+                }
+                None => "nothing, 404".to_owned(),
+            },
+        };
+        log::info!(
+            "{} {}\n  \
+             path:      {}\n  \
+             query:     {}\n  \
+             rewritten: {}\n  \
+             base path: {}\n  \
+             tenant:    {}\n  \
+             host:      {}\n  \
+             segments:  {} (empty segments are ignored, percent-encoding is kept)\n  \
+             matches:   {}",
+            method,
+            uri,
+            uri.path(),
+            query,
+            rewritten,
+            base_path,
+            tenant,
+            host,
+            segments,
+            matched
+        );
+    }
+
+    /// Log the journaled requests that violated assertions of their endpoint
+    pub fn verify_report(&self) {
+        let journal = self.journal();
+        let failed: Vec<String> = journal
+            .records()
+            .filter(|record| !record.failures.is_empty())
+            .map(|record| {
+                format!(
+                    "  #{} {} {}: {}",
+                    record.id,
+                    record.method,
+                    record.path,
+                    record.failures.join(", ")
+                )
+            })
+            .collect();
+        if failed.is_empty() {
+            return log::info!(
+                "No assertion failures in the last {} request(s)",
+                journal.len()
+            );
+        }
+        log::warn!(
+            "{} of the last {} request(s) failed assertions:\n{}",
+            failed.len(),
+            journal.len(),
+            failed.join("\n")
+        );
+    }
+
+    /// Log the candidates of the endpoints at `path`, of all methods unless `method` is given,
+    /// see [`docs::CandidateDoc`]
+    pub fn inspect_endpoint(&self, method: Option<&Method>, path: &str) -> InternalResult<()> {
+        let endpoints = self.read_endpoints();
+        let base_path = self.base_path();
+        let sections: Vec<String> = endpoints
+            .entries(method)
+            .into_iter()
+            .filter_map(|(method, _)| {
+                let (endpoint, _) = endpoints.lookup(method, path)?;
+                let candidates = endpoints.candidates(method, &endpoint);
+                let lines: Vec<String> = candidates
+                    .iter()
+                    .enumerate()
+                    .map(|(i, stub)| {
+                        let doc = docs::CandidateDoc::new(
+                            method,
+                            &format!("{}{}", base_path, endpoint),
+                            stub,
+                        );
+                        let indent = |text: &str| text.replace('\n', "\n    ");
+                        let mut lines = vec![format!(
+                            "{} {} (candidate {} of {})",
+                            method,
+                            endpoint,
+                            i + 1,
+                            candidates.len()
+                        )];
+                        lines.extend(doc.description.map(|d| format!("  {}", d)));
+                        if !doc.options.is_empty() {
+                            lines.push(format!("  options: {}", doc.options.join(", ")));
+                        }
+                        lines.push(format!("  request:\n    {}", indent(&doc.request)));
+                        lines.push(format!("  response:\n    {}", indent(&doc.response)));
+                        lines.join("\n")
+                    })
+                    .collect();
+                Some(lines.join("\n"))
+            })
+            .collect();
+        if sections.is_empty() {
+            let method = method.cloned().unwrap_or(Method::GET);
+            return Err(InternalError::EndpointNotFound {
+                suggestion: endpoints.closest(&method, path),
+                method,
+                path: path.to_owned(),
+            });
+        }
+        log::info!("{}", sections.join("\n"));
+        Ok(())
+    }
+
+    /// A curl command sending a request to `path` that the endpoint there answers, through the
+    /// server at `origin`, e.g. `http://127.0.0.1:8080`. Parameters of a registered path like
+    /// `/users/{id}` are filled in with `1`.
+    pub fn endpoint_curl(
+        &self,
+        method: &Method,
+        path: &str,
+        origin: &str,
+    ) -> InternalResult<String> {
+        let endpoints = self.read_endpoints();
+        let Some((_, stub)) = endpoints.lookup(method, path) else {
+            return Err(InternalError::EndpointNotFound {
+                suggestion: endpoints.closest(method, path),
+                method: method.clone(),
+                path: path.to_owned(),
+            });
+        };
+        let path: Vec<&str> = path
+            .split('/')
+            .map(|segment| if is_param(segment) { "1" } else { segment })
+            .collect();
+        let path = path.join("/");
+        let auth = self.auth_rules();
+        let credentials = auth
+            .iter()
+            .find(|rule| rule.applies_to(&path))
+            .map(|rule| &rule.credentials);
+        let url = format!("{}{}{}", origin, self.base_path(), path);
+        Ok(curl::endpoint_curl(method, &url, stub, credentials))
+    }
+
+    /// Infer a body for the `template` transformation from the responses the fallback upstream
+    /// answered requests for `path` with, e.g. `/users/{id}` after a recording session
+    pub fn infer_template(&self, method: &Method, path: &str) -> InternalResult<String> {
+        let endpoint = format!("{}/{}", self.base_path(), path.trim_start_matches('/'));
+        let journal = self.journal();
+        let samples: Vec<infer::Sample> = journal
+            .records()
+            .filter(|record| record.method == *method)
+            .filter_map(|record| infer::Sample::of(record, &endpoint))
+            .collect();
+        infer::infer_template(&samples).ok_or_else(|| InternalError::InvalidArgument {
+            argument: "endpoint",
+            reason: format!(
+                "{} JSON response(s) to {} {} passed through by the fallback, at least {} are \
+                 needed",
+                samples.len(),
+                method,
+                path,
+                infer::MIN_SAMPLES
+            ),
+        })
+    }
+
+    /// Forget the usage of the endpoints at `path`, so their calls are counted from 1 again
+    pub fn reset_endpoint_stats(&self, method: Option<&Method>, path: &str) -> InternalResult<()> {
+        let endpoints = self.read_endpoints();
+        let mut stats = self.stats();
+        let reset: Vec<String> = endpoints
+            .entries(method)
+            .into_iter()
+            .filter_map(|(method, _)| {
+                let (endpoint, _) = endpoints.lookup(method, path)?;
+                stats.remove(method, &endpoint);
+                Some(format!("{} {}", method, endpoint))
+            })
+            .collect();
+        if reset.is_empty() {
+            let method = method.cloned().unwrap_or(Method::GET);
+            return Err(InternalError::EndpointNotFound {
+                suggestion: endpoints.closest(&method, path),
+                method,
+                path: path.to_owned(),
+            });
+        }
+        log::info!("Reset the usage of {}", reset.join(", "));
+        Ok(())
+    }
+
     pub fn delete_endpoint(&self, method: &Method, path: &str) -> InternalResult<()> {
-        self.endpoints
-            .write()
-            .map_err(|_| InternalError::LockFailed)?
-            .delete(method, path)
-            .ok_or_else(|| InternalError::EndpointNotFound(path.to_owned()))?;
+        let mut endpoints = self.write_endpoints();
+        self.stats()
+            .remove(method, &format!("/{}", path.trim_matches('/')));
+        if endpoints.delete(method, path).is_empty() {
+            return Err(InternalError::EndpointNotFound {
+                method: method.clone(),
+                path: path.to_owned(),
+                suggestion: endpoints.closest(method, path),
+            });
+        }
+        self.definitions_mut().remove(method, path);
         log::info!("Removed endpoint {}", path);
         Ok(())
     }
@@ -121,41 +1988,334 @@ impl ServerState {
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
+    use actix_web::http::{StatusCode, header};
+    use std::{
+        io::{Read, Write},
+        time::Duration,
+    };
+
     use super::*;
 
     fn test_state() -> Arc<ServerState> {
         Arc::new(ServerState::new())
     }
 
+    async fn test_app(
+        state: &Arc<ServerState>,
+    ) -> impl actix_web::dev::Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse,
+        Error = actix_web::Error,
+    > {
+        actix_web::test::init_service(
+            ServerApp::new()
+                .app_data(Data::new(state.clone()))
+                .app_data(Data::new(awc::Client::default()))
+                .default_service(to(catch_all)),
+        )
+        .await
+    }
+
+    #[test]
+    fn test_chunked_request_body() {
+        let state = test_state();
+        let stub = Stub {
+            matchers: vec![r#"body:$.type == "refund""#.parse().unwrap()],
+            ..Stub::from("refunded")
+        };
+        state.add_endpoint(Method::POST, "/payments", stub).unwrap();
+        let (running_tx, running_rx) = mpsc::channel();
+        let server_state = state.clone();
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(run_server(server_state, "127.0.0.1:0", running_tx))
+        });
+        let running = running_rx.recv().unwrap();
+
+        let payload = r#"{"type": "refund", "amount": 12}"#;
+        let mut stream = std::net::TcpStream::connect(running.addrs[0]).unwrap();
+        stream
+            .write_all(
+                b"POST /payments HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+                  Content-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n",
+            )
+            .unwrap();
+        for chunk in [&payload[..10], &payload[10..20], &payload[20..]] {
+            write!(stream, "{:x}\r\n{}\r\n", chunk.len(), chunk).unwrap();
+            stream.flush().unwrap();
+            thread::sleep(Duration::from_millis(50));
+        }
+        stream.write_all(b"0\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("refunded"), "{}", response);
+        {
+            let journal = state.journal();
+            let record = journal.records().next_back().unwrap();
+            assert_eq!(record.body, payload.as_bytes());
+            assert_eq!(record.endpoint.as_deref(), Some("/payments"));
+        }
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(running.handle.stop(false));
+    }
+
+    #[actix_web::test]
+    async fn test_faulted_request_sends_no_callback() {
+        let receiver = TcpListener::bind("127.0.0.1:0").unwrap();
+        receiver.set_nonblocking(true).unwrap();
+        let state = test_state();
+        let callback = format!("POST http://{}/notify", receiver.local_addr().unwrap());
+        let stub = Stub {
+            callback: Some(callback.parse().unwrap()),
+            ..Stub::from("{}")
+        };
+        state.add_endpoint(Method::POST, "/orders", stub).unwrap();
+        state.chaos_mut().set(ChaosRule {
+            scope: "/orders".to_string(),
+            fault: Fault::Error {
+                percent: 100.0,
+                status: StatusCode::SERVICE_UNAVAILABLE,
+            },
+        });
+        let app = test_app(&state).await;
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/orders")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        actix_web::rt::time::sleep(Duration::from_millis(300)).await;
+        assert!(receiver.accept().is_err());
+
+        // The served response does notify
+        state.chaos_mut().clear(None);
+        let request = actix_web::test::TestRequest::post()
+            .uri("/orders")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut notified = false;
+        for _ in 0..50 {
+            if receiver.accept().is_ok() {
+                notified = true;
+                break;
+            }
+            actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(notified);
+    }
+
+    #[actix_web::test]
+    async fn test_faulted_request_runs_no_before_hooks() {
+        let state = test_state();
+        let stub = Stub {
+            hooks: vec!["before metric orders".parse().unwrap()],
+            ..Stub::from("{}")
+        };
+        state.add_endpoint(Method::POST, "/orders", stub).unwrap();
+        state.chaos_mut().set(ChaosRule {
+            scope: "/orders".to_string(),
+            fault: Fault::Error {
+                percent: 100.0,
+                status: StatusCode::SERVICE_UNAVAILABLE,
+            },
+        });
+        let app = test_app(&state).await;
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/orders")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(state.metrics().counters().is_empty());
+
+        state.chaos_mut().clear(None);
+        let request = actix_web::test::TestRequest::post()
+            .uri("/orders")
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.metrics().counters().get("orders"), Some(&1));
+    }
+
     #[test]
     fn test_add_endpoint() {
         let state = test_state();
         state
-            .add_endpoint(Method::GET, "/test", "response".into())
+            .add_endpoint(Method::GET, "/test", "response")
             .unwrap();
 
         state
-            .add_endpoint(Method::GET, "no_leading_slash", "still_valid".into())
+            .add_endpoint(Method::GET, "no_leading_slash", "still_valid")
             .unwrap();
 
         let endpoints = state.endpoints.read().unwrap();
         assert_eq!(
-            endpoints.get(&Method::GET, "/test").map(|b| b.as_ref()),
+            endpoints
+                .get(&Method::GET, "/test")
+                .map(|stub| stub.body.as_ref()),
             Some(b"response".as_ref())
         );
         assert_eq!(
             endpoints
                 .get(&Method::GET, "/no_leading_slash")
-                .map(|b| b.as_ref()),
+                .map(|stub| stub.body.as_ref()),
             Some(b"still_valid".as_ref())
         );
     }
 
+    #[test]
+    fn test_strip_base_path() {
+        assert_eq!(strip_base_path("", "/users"), Some("/users"));
+        assert_eq!(strip_base_path("/api/v2", "/api/v2/users"), Some("/users"));
+        assert_eq!(strip_base_path("/api/v2", "/api/v2"), Some("/"));
+        assert_eq!(strip_base_path("/api/v2", "/api/v20/users"), None);
+        assert_eq!(strip_base_path("/api/v2", "/users"), None);
+    }
+
+    #[test]
+    fn test_route_and_find_tenant_override() {
+        let state = test_state();
+        state.rewrites_mut().push(RewriteRule {
+            pattern: parse_pattern("^/v1").unwrap(),
+            replacement: "/api".to_owned(),
+        });
+        state.set_base_path("/api");
+        state.tenancy_mut().source = Some("segment:2".parse().unwrap());
+        state.add_endpoint(Method::GET, "/plan", "free").unwrap();
+        state
+            .add_scoped_endpoint(
+                EndpointScope::Tenant("acme"),
+                Method::GET,
+                "/plan",
+                "enterprise",
+            )
+            .unwrap();
+
+        let route = state.route(
+            &Uri::from_static("/v1/tenants/acme/plan"),
+            &HeaderMap::new(),
+        );
+        assert_eq!(route.rewritten, "/api/tenants/acme/plan");
+        assert_eq!(route.routed.as_deref(), Some("/plan"));
+        assert_eq!(route.tenant.as_deref(), Some("acme"));
+        let (source, _, stub) = state
+            .find_endpoint(&Method::GET, &route, &RequestInfo::default())
+            .unwrap();
+        assert_eq!(
+            (source, stub.body.as_ref()),
+            ("tenant override", &b"enterprise"[..])
+        );
+
+        let route = state.route(
+            &Uri::from_static("/api/tenants/globex/plan"),
+            &HeaderMap::new(),
+        );
+        let (source, _, _) = state
+            .find_endpoint(&Method::GET, &route, &RequestInfo::default())
+            .unwrap();
+        assert_eq!(source, "endpoint");
+        assert_eq!(
+            state
+                .route(&Uri::from_static("/plan"), &HeaderMap::new())
+                .routed,
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_virtual_host_endpoint() {
+        let state = test_state();
+        state.add_endpoint(Method::GET, "/health", "ok").unwrap();
+        state
+            .add_scoped_endpoint(
+                EndpointScope::Host("billing.local"),
+                Method::GET,
+                "/health",
+                "billing",
+            )
+            .unwrap();
+        let find = |host: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::HOST, host.parse().unwrap());
+            let route = state.route(&Uri::from_static("/health"), &headers);
+            let (source, _, stub) = state
+                .find_endpoint(&Method::GET, &route, &RequestInfo::default())
+                .unwrap();
+            (source, String::from_utf8_lossy(&stub.body).into_owned())
+        };
+
+        assert_eq!(
+            find("Billing.local:3000"),
+            ("virtual host", "billing".to_owned())
+        );
+        assert_eq!(find("users.local"), ("endpoint", "ok".to_owned()));
+    }
+
+    #[test]
+    fn test_definition_of_request_path() {
+        let state = test_state();
+        state
+            .add_endpoint(Method::GET, "/users/{id}", "{}")
+            .unwrap();
+        let definition = Definition {
+            file: "users.yaml".into(),
+            line: 3,
+        };
+        state
+            .definitions_mut()
+            .record(Method::GET, "/users/{id}", definition.clone());
+
+        assert_eq!(
+            state.definition(&Method::GET, "/users/42"),
+            Some(("/users/{id}".to_owned(), definition))
+        );
+        state
+            .add_endpoint(Method::GET, "/users/{id}", "[]")
+            .unwrap();
+        assert_eq!(state.definition(&Method::GET, "/users/42"), None);
+    }
+
+    #[test]
+    fn test_find_endpoint_by_call() {
+        let state = test_state();
+        state
+            .add_endpoint(Method::GET, "/orders/{id}", "ok")
+            .unwrap();
+        let failing = Stub {
+            status: Some(StatusCode::SERVICE_UNAVAILABLE),
+            matchers: vec![parse_call_range("1..2").unwrap()],
+            ..Stub::default()
+        };
+        state
+            .add_endpoint(Method::GET, "/orders/{id}", failing)
+            .unwrap();
+        let route = state.route(&Uri::from_static("/orders/7"), &HeaderMap::new());
+        let status = || {
+            let (_, endpoint, stub) = state
+                .find_endpoint(&Method::GET, &route, &RequestInfo::default())
+                .unwrap();
+            state
+                .stats()
+                .record(&Method::GET, &endpoint, stub.response_status());
+            stub.response_status().as_u16()
+        };
+
+        assert_eq!([status(), status(), status()], [503, 503, 200]);
+        state
+            .reset_endpoint_stats(Some(&Method::GET), "/orders/8")
+            .unwrap();
+        assert_eq!(status(), 503);
+    }
+
     #[test]
     fn test_delete_endpoint() {
         let state = test_state();
         state
-            .add_endpoint(Method::GET, "/test/nested", "'{id: 123456}'".into())
+            .add_endpoint(Method::GET, "/test/nested", "'{id: 123456}'")
             .unwrap();
         state.delete_endpoint(&Method::GET, "/test/nested").unwrap();
 
@@ -163,11 +2323,57 @@ mod tests {
         assert!(endpoints.get(&Method::GET, "/test/nested").is_none());
     }
 
+    #[test]
+    fn test_learn_endpoint_collapses_ids() {
+        let state = test_state();
+        state.learn_endpoint(&Method::GET, "/users/17/orders/3");
+
+        let endpoints = state.read_endpoints();
+        assert_eq!(endpoints.len(), 1);
+        assert!(endpoints.get(&Method::GET, "/users/18/orders/4").is_some());
+    }
+
+    #[test]
+    fn test_recover_poisoned_lock() {
+        let state = test_state();
+        state
+            .add_endpoint(Method::GET, "/test", "response")
+            .unwrap();
+        let poisoner = state.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.endpoints.write().unwrap();
+            panic!("handler panicked while holding the lock");
+        })
+        .join();
+        assert!(state.endpoints.is_poisoned());
+
+        assert!(state.read_endpoints().get(&Method::GET, "/test").is_some());
+        assert!(!state.endpoints.is_poisoned());
+        state
+            .add_endpoint(Method::GET, "/other", "response")
+            .unwrap();
+    }
+
     #[test]
     fn test_delete_nonexistent_endpoint() {
         let state = test_state();
         let result = state.delete_endpoint(&Method::GET, "/nonexistent");
 
-        assert!(matches!(result, Err(InternalError::EndpointNotFound(_))));
+        assert!(matches!(
+            result,
+            Err(InternalError::EndpointNotFound {
+                suggestion: None,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_delete_typo_suggests_endpoint() {
+        let state = test_state();
+        state.add_endpoint(Method::GET, "/users", "[]").unwrap();
+        let err = state.delete_endpoint(&Method::GET, "/user").unwrap_err();
+
+        assert_eq!(err.help().as_deref(), Some("did you mean `GET /users`?"));
     }
 }
@@ -0,0 +1,304 @@
+use std::{collections::HashMap, fmt};
+
+use actix_web::{http::Method, web::Bytes};
+use chrono::{DateTime, Local, TimeDelta};
+use serde_json::Value;
+
+/// Field name added by [`Mutation::AddField`]
+const UNKNOWN_FIELD: &str = "unexpected_field";
+/// A repeated request within this time after a mutated response counts as a retry, suggesting the
+/// client rejected the mutated response
+const RETRY_WINDOW: TimeDelta = TimeDelta::seconds(5);
+
+/// A deliberate change to a JSON response body, e.g. dropping `$.user.id`.
+/// Paths use `[]` for every element of an array.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mutation {
+    DropField(Vec<String>),
+    /// Replace the value with one of another JSON type
+    ChangeType(Vec<String>),
+    /// Add a field the client doesn't know to the object at the path
+    AddField(Vec<String>),
+}
+
+impl Mutation {
+    /// Every mutation applicable to `json`, in document order
+    pub fn all(json: &Value) -> Vec<Mutation> {
+        let mut mutations = Vec::new();
+        collect(json, &mut Vec::new(), &mut mutations);
+        mutations
+    }
+
+    pub fn apply(&self, json: &mut Value) {
+        match self {
+            Mutation::DropField(path) => {
+                if let Some((key, parent)) = path.split_last() {
+                    visit(json, parent, &mut |value| {
+                        if let Value::Object(object) = value {
+                            object.remove(key);
+                        }
+                    });
+                }
+            }
+            Mutation::ChangeType(path) => visit(json, path, &mut |value| {
+                *value = other_type(value);
+            }),
+            Mutation::AddField(path) => visit(json, path, &mut |value| {
+                if let Value::Object(object) = value {
+                    object.insert(UNKNOWN_FIELD.to_owned(), Value::from("mutation"));
+                }
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Mutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (action, path) = match self {
+            Mutation::DropField(path) => ("drop", path),
+            Mutation::ChangeType(path) => ("change type of", path),
+            Mutation::AddField(path) => ("add unknown field to", path),
+        };
+        let path: String = path
+            .iter()
+            .map(|segment| match segment.as_str() {
+                "[]" => "[]".to_owned(),
+                key => format!(".{}", key),
+            })
+            .collect();
+        write!(f, "{} ${}", action, path)
+    }
+}
+
+fn collect(value: &Value, path: &mut Vec<String>, mutations: &mut Vec<Mutation>) {
+    match value {
+        Value::Object(object) => {
+            mutations.push(Mutation::AddField(path.clone()));
+            for (key, child) in object {
+                path.push(key.clone());
+                mutations.push(Mutation::DropField(path.clone()));
+                mutations.push(Mutation::ChangeType(path.clone()));
+                collect(child, path, mutations);
+                path.pop();
+            }
+        }
+        // Elements usually share a shape, so the first one stands for all
+        Value::Array(array) => {
+            if let Some(first) = array.first() {
+                path.push("[]".to_owned());
+                collect(first, path, mutations);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Call `f` on every value at `path`
+fn visit(value: &mut Value, path: &[String], f: &mut impl FnMut(&mut Value)) {
+    let Some((segment, rest)) = path.split_first() else {
+        return f(value);
+    };
+    match (segment.as_str(), value) {
+        ("[]", Value::Array(array)) => {
+            for element in array {
+                visit(element, rest, f);
+            }
+        }
+        (key, Value::Object(object)) => {
+            if let Some(child) = object.get_mut(key) {
+                visit(child, rest, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A value of a different JSON type, e.g. the number `42` becomes the string `"42"`
+fn other_type(value: &Value) -> Value {
+    match value {
+        Value::String(_) => Value::from(0),
+        Value::Number(number) => Value::from(number.to_string()),
+        Value::Bool(b) => Value::from(b.to_string()),
+        Value::Null => Value::from("null"),
+        Value::Array(_) => Value::Object(Default::default()),
+        Value::Object(_) => Value::Array(Vec::new()),
+    }
+}
+
+/// When responses get mutated
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum MutationMode {
+    #[default]
+    Off,
+    /// Each response of an endpoint gets the next mutation
+    PerRequest,
+    /// Each endpoint keeps the same mutation until the next session is started
+    PerSession,
+}
+
+/// A mutated response and how the client reacted
+#[derive(Debug, Clone)]
+pub struct MutationOutcome {
+    pub request_id: u64,
+    pub method: Method,
+    pub endpoint: String,
+    pub mutation: String,
+    pub served: DateTime<Local>,
+    /// Why the client is thought to have rejected the mutation, `None` if it tolerated it
+    pub rejected: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct MutationTesting {
+    mode: MutationMode,
+    /// Number of sessions started, selects the mutation of each endpoint in per session mode
+    session: usize,
+    /// Number of mutated responses per endpoint, selects the next mutation in per request mode
+    served: HashMap<(Method, String), usize>,
+    outcomes: Vec<MutationOutcome>,
+}
+
+impl MutationTesting {
+    /// Switch modes, starting a new session. Outcomes so far are kept for the report.
+    pub fn set_mode(&mut self, mode: MutationMode) {
+        self.mode = mode;
+        self.session += 1;
+        self.served.clear();
+    }
+
+    pub fn outcomes(&self) -> &[MutationOutcome] {
+        &self.outcomes
+    }
+
+    /// Mutate a JSON response `body` of an endpoint according to the mode. Returns the mutated
+    /// body and the mutation, `None` if the mode is off or the body isn't JSON.
+    pub fn mutate(
+        &mut self,
+        method: &Method,
+        endpoint: &str,
+        body: &Bytes,
+    ) -> Option<(Bytes, Mutation)> {
+        let index = match self.mode {
+            MutationMode::Off => return None,
+            MutationMode::PerRequest => *self
+                .served
+                .get(&(method.clone(), endpoint.to_owned()))
+                .unwrap_or(&0),
+            MutationMode::PerSession => self.session,
+        };
+        let mut json: Value = serde_json::from_slice(body).ok()?;
+        let mutations = Mutation::all(&json);
+        let mutation = mutations.get(index % mutations.len().max(1))?.clone();
+        mutation.apply(&mut json);
+        *self
+            .served
+            .entry((method.clone(), endpoint.to_owned()))
+            .or_default() += 1;
+        Some((Bytes::from(json.to_string()), mutation))
+    }
+
+    /// Record a request to an endpoint, along with the mutation of its response if any. The
+    /// previous mutated response of the endpoint counts as rejected if requested again right away.
+    pub fn record(
+        &mut self,
+        request_id: u64,
+        method: &Method,
+        endpoint: &str,
+        mutation: Option<&Mutation>,
+    ) {
+        let now = Local::now();
+        if let Some(previous) = self
+            .outcomes
+            .iter_mut()
+            .rev()
+            .find(|o| o.method == method && o.endpoint == endpoint)
+            && previous.rejected.is_none()
+            && now - previous.served < RETRY_WINDOW
+        {
+            previous.rejected = Some(format!("retried by #{}", request_id));
+        }
+        if let Some(mutation) = mutation {
+            self.outcomes.push(MutationOutcome {
+                request_id,
+                method: method.clone(),
+                endpoint: endpoint.to_owned(),
+                mutation: mutation.to_string(),
+                served: now,
+                rejected: None,
+            });
+        }
+    }
+
+    /// Mark the mutated response of a request as rejected, e.g. after the client showed an error.
+    /// Returns false if the request wasn't mutated.
+    pub fn reject(&mut self, request_id: u64) -> bool {
+        match self
+            .outcomes
+            .iter_mut()
+            .find(|o| o.request_id == request_id)
+        {
+            Some(outcome) => {
+                outcome.rejected = Some("marked as rejected".to_owned());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_mutations_of_json() {
+        let json = json!({"id": 1, "tags": [{"name": "a"}, {"name": "b"}]});
+        let mutations = Mutation::all(&json);
+        let described: Vec<String> = mutations.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            described,
+            [
+                "add unknown field to $",
+                "drop $.id",
+                "change type of $.id",
+                "drop $.tags",
+                "change type of $.tags",
+                "add unknown field to $.tags[]",
+                "drop $.tags[].name",
+                "change type of $.tags[].name",
+            ]
+        );
+
+        let mut mutated = json.clone();
+        mutations[2].apply(&mut mutated);
+        assert_eq!(mutated["id"], json!("1"));
+        let mut mutated = json;
+        mutations[6].apply(&mut mutated);
+        assert_eq!(mutated["tags"], json!([{}, {}]));
+    }
+
+    #[test]
+    fn test_per_request_cycles_and_detects_retries() {
+        let mut testing = MutationTesting::default();
+        let body = Bytes::from(r#"{"id": 1}"#);
+        assert!(testing.mutate(&Method::GET, "/user", &body).is_none());
+
+        testing.set_mode(MutationMode::PerRequest);
+        let (first, mutation) = testing.mutate(&Method::GET, "/user", &body).unwrap();
+        assert_eq!(first.as_ref(), br#"{"id":1,"unexpected_field":"mutation"}"#);
+        testing.record(1, &Method::GET, "/user", Some(&mutation));
+        let (second, mutation) = testing.mutate(&Method::GET, "/user", &body).unwrap();
+        assert_eq!(second.as_ref(), b"{}");
+        testing.record(2, &Method::GET, "/user", Some(&mutation));
+
+        let outcomes = testing.outcomes();
+        assert_eq!(outcomes[0].rejected.as_deref(), Some("retried by #2"));
+        assert!(outcomes[1].rejected.is_none());
+        assert!(testing.reject(2));
+        assert!(!testing.reject(3));
+    }
+}
@@ -0,0 +1,155 @@
+use std::{fmt, str::FromStr};
+
+use actix_web::{
+    http::header::{ACCEPT, HeaderMap, Quality, QualityItem},
+    mime::{self, Mime},
+    web::Bytes,
+};
+
+/// A body served to clients accepting its content type, e.g. `application/xml:<a>1</a>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Representation {
+    pub content_type: Mime,
+    pub body: Bytes,
+}
+
+impl Representation {
+    pub fn json(body: String) -> Self {
+        Self {
+            content_type: mime::APPLICATION_JSON,
+            body: body.into(),
+        }
+    }
+
+    pub fn xml(body: String) -> Self {
+        Self {
+            content_type: "application/xml".parse().unwrap_or(mime::TEXT_XML),
+            body: body.into(),
+        }
+    }
+}
+
+impl FromStr for Representation {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (content_type, body) = input
+            .split_once(':')
+            .ok_or_else(|| format!("`{}` is not of the form `text/csv:id,name`", input))?;
+        let content_type: Mime = content_type
+            .trim()
+            .parse()
+            .map_err(|_| format!("`{}` is not a content type", content_type.trim()))?;
+        Ok(Self {
+            content_type,
+            body: Bytes::copy_from_slice(body.as_bytes()),
+        })
+    }
+}
+
+impl fmt::Display for Representation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            self.content_type,
+            String::from_utf8_lossy(&self.body)
+        )
+    }
+}
+
+/// The representation the request's `Accept` header prefers, the first one for a request
+/// accepting anything. Of equally acceptable ones the first wins. `None` if it accepts none.
+pub fn choose<'a>(
+    representations: &'a [Representation],
+    headers: &HeaderMap,
+) -> Option<&'a Representation> {
+    let ranges: Vec<QualityItem<Mime>> = headers
+        .get_all(ACCEPT)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        // Malformed ranges are ignored like absent ones
+        .filter_map(|range| range.trim().parse().ok())
+        .collect();
+    if ranges.is_empty() {
+        return representations.first();
+    }
+    let mut best: Option<(&Representation, Quality)> = None;
+    for representation in representations {
+        let quality = quality(&ranges, &representation.content_type);
+        if quality > Quality::ZERO && best.is_none_or(|(_, best)| quality > best) {
+            best = Some((representation, quality));
+        }
+    }
+    best.map(|(representation, _)| representation)
+}
+
+/// Quality of the most specific range matching `content_type`, zero if none does
+fn quality(ranges: &[QualityItem<Mime>], content_type: &Mime) -> Quality {
+    ranges
+        .iter()
+        .filter_map(|range| {
+            let specificity = match (range.item.type_(), range.item.subtype()) {
+                (mime::STAR, mime::STAR) => 0,
+                (type_, mime::STAR) if type_ == content_type.type_() => 1,
+                (type_, subtype)
+                    if type_ == content_type.type_() && subtype == content_type.subtype() =>
+                {
+                    2
+                }
+                _ => return None,
+            };
+            Some((specificity, range.quality))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(Quality::ZERO, |(_, quality)| quality)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn accepting(accept: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, accept.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_choose_representation() {
+        let representations = [
+            Representation::json(r#"{"a":1}"#.to_owned()),
+            Representation::xml("<a>1</a>".to_owned()),
+        ];
+        let chosen = |headers: &HeaderMap| {
+            choose(&representations, headers).map(|chosen| chosen.content_type.to_string())
+        };
+
+        assert_eq!(
+            chosen(&HeaderMap::new()).as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(
+            chosen(&accepting("application/xml")).as_deref(),
+            Some("application/xml")
+        );
+        assert_eq!(
+            chosen(&accepting("application/*;q=0.5, application/xml;q=0.9")).as_deref(),
+            Some("application/xml")
+        );
+        assert_eq!(
+            chosen(&accepting("*/*, application/json;q=0")).as_deref(),
+            Some("application/xml")
+        );
+        assert_eq!(chosen(&accepting("text/html")), None);
+    }
+
+    #[test]
+    fn test_parse_representation() {
+        let csv: Representation = "text/csv:id,name".parse().unwrap();
+        assert_eq!(csv.content_type, mime::TEXT_CSV);
+        assert_eq!(csv.to_string(), "text/csv:id,name");
+        assert!("csv".parse::<Representation>().is_err());
+    }
+}
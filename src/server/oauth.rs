@@ -0,0 +1,263 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use actix_web::{
+    HttpResponse,
+    http::{
+        StatusCode,
+        header::{AUTHORIZATION, CACHE_CONTROL, HeaderMap},
+    },
+};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use sha2::Sha256;
+
+use crate::util::units::format_time;
+
+/// Issuer of every token
+const ISSUER: &str = "adaptive_rest";
+
+/// A claim added to issued tokens, e.g. `role=admin` or `tier=3`. Values are JSON if they parse
+/// as such, strings otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claim {
+    pub name: String,
+    pub value: Value,
+}
+
+impl fmt::Display for Claim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Value::String(value) => write!(f, "{}={}", self.name, value),
+            value => write!(f, "{}={}", self.name, value),
+        }
+    }
+}
+
+impl FromStr for Claim {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (name, value) = input
+            .split_once('=')
+            .filter(|(name, _)| !name.trim().is_empty())
+            .ok_or_else(|| format!("`{}` is not of the form `name=value`", input))?;
+        let value = value.trim();
+        Ok(Self {
+            name: name.trim().to_owned(),
+            value: serde_json::from_str(value).unwrap_or_else(|_| Value::from(value)),
+        })
+    }
+}
+
+/// An OAuth2 token endpoint granting every client and user a signed JWT, for the client
+/// credentials and password grants
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuthMock {
+    /// Path of the token endpoint, e.g. `/oauth/token`
+    pub path: String,
+    /// Key the tokens are signed with, using HS256
+    pub secret: String,
+    /// Time until issued tokens expire
+    pub expiry: Duration,
+    /// Added to every token, replacing standard claims of the same name
+    pub claims: Vec<Claim>,
+}
+
+/// Form fields of a token request
+#[derive(Debug, Default, Deserialize)]
+struct TokenRequest {
+    grant_type: Option<String>,
+    client_id: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    scope: Option<String>,
+}
+
+impl OAuthMock {
+    /// e.g. `Serving tokens at POST /oauth/token, expiring after 1h, with role=admin`
+    pub fn describe(&self) -> String {
+        let mut description = format!(
+            "Serving tokens at POST {}, expiring after {}",
+            self.path,
+            format_time(self.expiry)
+        );
+        if !self.claims.is_empty() {
+            let claims: Vec<String> = self.claims.iter().map(Claim::to_string).collect();
+            description.push_str(&format!(", with {}", claims.join(", ")));
+        }
+        description
+    }
+
+    /// Answer a token request with a token or an OAuth2 error, `body` being a form
+    pub fn respond(&self, headers: &HeaderMap, body: &[u8]) -> HttpResponse {
+        let request: TokenRequest = match serde_urlencoded::from_bytes(body) {
+            Ok(request) => request,
+            Err(e) => return token_error("invalid_request", &e.to_string()),
+        };
+        let (subject, client) = match request.grant_type.as_deref() {
+            Some("client_credentials") => match request.client_id.or_else(|| basic_user(headers)) {
+                Some(client) => (client.clone(), Some(client)),
+                None => return token_error("invalid_client", "missing client_id"),
+            },
+            Some("password") => match (request.username, request.password) {
+                (Some(username), Some(_)) => (username, request.client_id),
+                _ => return token_error("invalid_request", "missing username or password"),
+            },
+            Some(grant) => {
+                return token_error(
+                    "unsupported_grant_type",
+                    &format!("`{}` is not supported", grant),
+                );
+            }
+            None => return token_error("invalid_request", "missing grant_type"),
+        };
+        let Some(token) = self.issue(&subject, client.as_deref(), request.scope.as_deref()) else {
+            let mut response =
+                token_error("server_error", "tokens can't be signed with the secret");
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            return response;
+        };
+        let mut response = json!({
+            "access_token": token,
+            "token_type": "Bearer",
+            "expires_in": self.expiry.as_secs(),
+        });
+        if let Some(scope) = request.scope {
+            response["scope"] = Value::from(scope);
+        }
+        HttpResponse::Ok()
+            .insert_header((CACHE_CONTROL, "no-store"))
+            .json(response)
+    }
+
+    /// A JWT for `subject`, signed with the secret, `None` if the secret can't key the signature
+    fn issue(&self, subject: &str, client: Option<&str>, scope: Option<&str>) -> Option<String> {
+        let now = Utc::now().timestamp();
+        let mut claims = Map::new();
+        claims.insert("iss".to_owned(), Value::from(ISSUER));
+        claims.insert("sub".to_owned(), Value::from(subject));
+        claims.insert("iat".to_owned(), Value::from(now));
+        claims.insert(
+            "exp".to_owned(),
+            Value::from(now.saturating_add_unsigned(self.expiry.as_secs())),
+        );
+        if let Some(client) = client {
+            claims.insert("client_id".to_owned(), Value::from(client));
+        }
+        if let Some(scope) = scope {
+            claims.insert("scope".to_owned(), Value::from(scope));
+        }
+        for claim in &self.claims {
+            claims.insert(claim.name.clone(), claim.value.clone());
+        }
+        let header = json!({"alg": "HS256", "typ": "JWT"});
+        let unsigned = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(Value::Object(claims).to_string())
+        );
+        let signature = URL_SAFE_NO_PAD.encode(sign(&self.secret, &unsigned)?);
+        Some(format!("{}.{}", unsigned, signature))
+    }
+}
+
+fn sign(secret: &str, message: &str) -> Option<Vec<u8>> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).ok()?;
+    mac.update(message.as_bytes());
+    Some(mac.finalize().into_bytes().to_vec())
+}
+
+/// Client id of HTTP basic authentication, which clients may use instead of form fields
+fn basic_user(headers: &HeaderMap) -> Option<String> {
+    let (scheme, value) = headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .trim()
+        .split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = String::from_utf8(STANDARD.decode(value.trim()).ok()?).ok()?;
+    decoded
+        .split_once(':')
+        .map(|(user, _)| user.to_owned())
+        .filter(|user| !user.is_empty())
+}
+
+/// An OAuth2 error response, e.g. `unsupported_grant_type`
+fn token_error(error: &str, description: &str) -> HttpResponse {
+    HttpResponse::BadRequest()
+        .insert_header((CACHE_CONTROL, "no-store"))
+        .json(json!({"error": error, "error_description": description}))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::body;
+
+    use super::*;
+
+    fn mock() -> OAuthMock {
+        OAuthMock {
+            path: "/oauth/token".to_owned(),
+            secret: "secret".to_owned(),
+            expiry: Duration::from_secs(60),
+            claims: vec!["role=admin".parse().unwrap(), "tier=3".parse().unwrap()],
+        }
+    }
+
+    async fn respond(body: &str) -> (u16, Value) {
+        let response = mock().respond(&HeaderMap::new(), body.as_bytes());
+        let status = response.status().as_u16();
+        let bytes = body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[actix_web::test]
+    async fn test_issue_signed_token() {
+        let (status, response) =
+            respond("grant_type=password&username=ada&password=x&scope=read").await;
+        assert_eq!(status, 200);
+        assert_eq!(response["expires_in"], 60);
+        let token = response["access_token"].as_str().unwrap();
+        let (unsigned, signature) = token.rsplit_once('.').unwrap();
+        assert_eq!(
+            URL_SAFE_NO_PAD.decode(signature).unwrap(),
+            sign("secret", unsigned).unwrap()
+        );
+        let payload = unsigned.split('.').nth(1).unwrap();
+        let claims: Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+        assert_eq!(claims["sub"], "ada");
+        assert_eq!(claims["scope"], "read");
+        assert_eq!(
+            (&claims["role"], &claims["tier"]),
+            (&json!("admin"), &json!(3))
+        );
+        assert_eq!(
+            claims["exp"].as_i64().unwrap() - claims["iat"].as_i64().unwrap(),
+            60
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_reject_bad_requests() {
+        let (status, response) = respond("grant_type=client_credentials").await;
+        assert_eq!(
+            (status, &response["error"]),
+            (400, &json!("invalid_client"))
+        );
+        let (_, response) = respond("grant_type=implicit").await;
+        assert_eq!(response["error"], "unsupported_grant_type");
+        let (status, _) = respond("grant_type=client_credentials&client_id=app").await;
+        assert_eq!(status, 200);
+    }
+}
@@ -0,0 +1,157 @@
+use serde_json::{Map, Value, json};
+
+use crate::server::{EndpointStore, Stub, is_param};
+
+/// OpenAPI 3 document describing the endpoints, with their bodies as examples, e.g. to hand a
+/// prototyped mock to backend engineers as a starting contract
+pub fn openapi_document(endpoints: &EndpointStore, base_path: &str) -> Value {
+    let mut paths = Map::new();
+    for (method, entries) in endpoints.entries(None) {
+        // Candidates of an endpoint are listed one after another
+        let mut grouped: Vec<(String, Vec<&Stub>)> = Vec::new();
+        for (path, stub) in entries {
+            match grouped.last_mut() {
+                Some((last, stubs)) if *last == path => stubs.push(stub),
+                _ => grouped.push((path, vec![stub])),
+            }
+        }
+        for (path, stubs) in grouped {
+            let item = paths
+                .entry(path.clone())
+                .or_insert_with(|| Value::Object(Map::new()));
+            item[method.as_str().to_ascii_lowercase()] = operation(&path, &stubs);
+        }
+    }
+    let mut document = json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "adaptive_rest mock",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+    });
+    if !base_path.is_empty() {
+        document["servers"] = json!([{"url": base_path}]);
+    }
+    document
+}
+
+/// The operation of one method on `path`, answered by `stubs` in order of precedence
+fn operation(path: &str, stubs: &[&Stub]) -> Value {
+    let mut operation = Map::new();
+    let parameters: Vec<Value> = path
+        .split('/')
+        .filter(|segment| is_param(segment))
+        .map(|segment| {
+            json!({
+                "name": &segment[1..segment.len() - 1],
+                "in": "path",
+                "required": true,
+                "schema": {"type": "string"},
+            })
+        })
+        .collect();
+    if !parameters.is_empty() {
+        operation.insert("parameters".to_owned(), Value::from(parameters));
+    }
+    if let Some(schema) = stubs.iter().find_map(|stub| stub.request_schema.as_ref()) {
+        operation.insert(
+            "requestBody".to_owned(),
+            json!({"content": {"application/json": {"schema": schema.schema()}}}),
+        );
+    }
+    let mut responses = Map::new();
+    // Bodies of several candidates become named examples
+    let named = stubs.len() > 1;
+    for (i, stub) in stubs.iter().enumerate() {
+        let status = stub
+            .redirect
+            .as_ref()
+            .map_or(stub.response_status(), |r| r.status);
+        let response = responses.entry(status.as_str().to_owned()).or_insert_with(
+            || json!({"description": status.canonical_reason().unwrap_or("Response")}),
+        );
+        if let Some(redirect) = &stub.redirect {
+            response["headers"] = json!({
+                "Location": {
+                    "schema": {"type": "string"},
+                    "example": String::from_utf8_lossy(redirect.location.as_bytes()),
+                }
+            });
+        }
+        if stub.body.is_empty() {
+            continue;
+        }
+        let (content_type, example) = example(stub);
+        let media = &mut response["content"][content_type];
+        if named {
+            let mut named = json!({"value": example});
+            let options = stub.options();
+            if !options.is_empty() {
+                named["summary"] = Value::from(options.join(", "));
+            }
+            media["examples"][format!("candidate{}", i + 1)] = named;
+        } else {
+            media["example"] = example;
+        }
+    }
+    if stubs.iter().any(|stub| stub.request_schema.is_some()) {
+        responses.insert(
+            "400".to_owned(),
+            json!({"description": "The request body violates the schema"}),
+        );
+    }
+    operation.insert("responses".to_owned(), Value::Object(responses));
+    Value::Object(operation)
+}
+
+/// Content type and example of the body a stub answers with
+fn example(stub: &Stub) -> (&'static str, Value) {
+    if stub.events.is_some() {
+        return (
+            "text/event-stream",
+            Value::from(String::from_utf8_lossy(&stub.body)),
+        );
+    }
+    match serde_json::from_slice(&stub.body) {
+        Ok(json) => ("application/json", json),
+        Err(_) => (
+            "text/plain",
+            Value::from(String::from_utf8_lossy(&stub.body)),
+        ),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::http::Method;
+
+    use super::*;
+
+    #[test]
+    fn test_openapi_document() {
+        let mut endpoints = EndpointStore::default();
+        endpoints.add(Method::GET, "/users/{id}", r#"{"id": 1}"#);
+        endpoints.add(
+            Method::GET,
+            "/old",
+            Stub {
+                redirect: Some("301 /new".parse().unwrap()),
+                ..Stub::default()
+            },
+        );
+        let document = openapi_document(&endpoints, "/api");
+
+        assert_eq!(document["servers"][0]["url"], "/api");
+        let user = &document["paths"]["/users/{id}"]["get"];
+        assert_eq!(user["parameters"][0]["name"], "id");
+        assert_eq!(
+            user["responses"]["200"]["content"]["application/json"]["example"],
+            json!({"id": 1})
+        );
+        let old = &document["paths"]["/old"]["get"]["responses"]["301"];
+        assert_eq!(old["headers"]["Location"]["example"], "/new");
+        assert!(old.get("content").is_none());
+    }
+}
@@ -0,0 +1,251 @@
+use std::{fmt, num::NonZeroUsize, str::FromStr};
+
+use actix_web::{http::header::HeaderValue, web::Bytes};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde_json::{Value, json};
+
+/// How clients walk through the pages of a collection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageStyle {
+    /// `?offset=40&limit=20`, with the total and links to the first, previous, next and last page
+    Offset,
+    /// `?cursor=...&limit=20`, with an opaque cursor to the next page only
+    Cursor,
+}
+
+impl fmt::Display for PageStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageStyle::Offset => write!(f, "offset"),
+            PageStyle::Cursor => write!(f, "cursor"),
+        }
+    }
+}
+
+/// Serve a JSON array as pages picked by the query instead of at once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pagination {
+    pub style: PageStyle,
+    /// Items per page, and the most a `limit` query parameter may ask for
+    pub page_size: NonZeroUsize,
+}
+
+/// The items of a page wrapped with pagination metadata and links
+#[derive(Debug)]
+pub struct Page {
+    pub body: Bytes,
+    /// `Link` header to the neighbouring pages, if there are any
+    pub link: Option<HeaderValue>,
+}
+
+impl Pagination {
+    /// The page of `items`, a JSON array, that a request to `path` with `query` asks for. Fails
+    /// on query parameters that don't name a page.
+    pub fn page(&self, items: &[u8], path: &str, query: &str) -> Result<Page, String> {
+        let items = match serde_json::from_slice::<Value>(items) {
+            Ok(Value::Array(items)) => items,
+            _ => return Err("the endpoint body is not a JSON array".to_owned()),
+        };
+        let params: Vec<(String, String)> = serde_urlencoded::from_str(query)
+            .map_err(|e| format!("`{}` is not a query string: {}", query, e))?;
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let limit = match param("limit") {
+            Some(limit) => limit
+                .parse::<NonZeroUsize>()
+                .map_err(|_| format!("`{}` is not a positive limit", limit))?
+                .min(self.page_size),
+            None => self.page_size,
+        }
+        .get();
+        let start = match (self.style, param("offset"), param("cursor")) {
+            (PageStyle::Offset, Some(offset), _) => offset
+                .parse()
+                .map_err(|_| format!("`{}` is not an offset", offset))?,
+            (PageStyle::Cursor, _, Some(cursor)) => decode_cursor(cursor)?,
+            _ => 0,
+        };
+        let total = items.len();
+        let end = total.min(start.saturating_add(limit));
+        let data = items.get(start..end).unwrap_or_default();
+        // Links keep the other query parameters, replacing those that pick the page
+        let link = |start: usize| {
+            let mut params: Vec<(&str, String)> = params
+                .iter()
+                .filter(|(key, _)| !matches!(key.as_str(), "offset" | "cursor" | "limit"))
+                .map(|(key, value)| (key.as_str(), value.clone()))
+                .collect();
+            match self.style {
+                PageStyle::Offset => params.push(("offset", start.to_string())),
+                PageStyle::Cursor if start > 0 => params.push(("cursor", encode_cursor(start))),
+                PageStyle::Cursor => {}
+            }
+            params.push(("limit", limit.to_string()));
+            match serde_urlencoded::to_string(&params) {
+                Ok(query) => format!("{}?{}", path, query),
+                Err(_) => path.to_owned(),
+            }
+        };
+        let next = (end < total).then_some(end);
+        let (body, rels) = match self.style {
+            PageStyle::Offset => {
+                let prev = (start > 0).then(|| start.saturating_sub(limit).min(total));
+                let last = total.saturating_sub(1) / limit * limit;
+                let body = json!({
+                    "data": data,
+                    "meta": {"total": total, "offset": start, "limit": limit},
+                    "links": {
+                        "self": link(start),
+                        "first": link(0),
+                        "prev": prev.map(link),
+                        "next": next.map(link),
+                        "last": link(last),
+                    },
+                });
+                let rels = [
+                    ("first", Some(0)),
+                    ("prev", prev),
+                    ("next", next),
+                    ("last", Some(last)),
+                ];
+                (body, rels.to_vec())
+            }
+            PageStyle::Cursor => {
+                let body = json!({
+                    "data": data,
+                    "meta": {
+                        "limit": limit,
+                        "next_cursor": next.map(encode_cursor),
+                        "has_more": next.is_some(),
+                    },
+                    "links": {"self": link(start), "next": next.map(link)},
+                });
+                (body, vec![("next", next)])
+            }
+        };
+        let links: Vec<String> = rels
+            .into_iter()
+            .filter_map(|(rel, start)| Some(format!("<{}>; rel=\"{}\"", link(start?), rel)))
+            .collect();
+        Ok(Page {
+            body: Bytes::from(body.to_string()),
+            link: HeaderValue::try_from(links.join(", "))
+                .ok()
+                .filter(|_| !links.is_empty()),
+        })
+    }
+}
+
+/// The cursor to the page starting at item `start`. Base64 keeps clients from treating it as a
+/// number.
+fn encode_cursor(start: usize) -> String {
+    URL_SAFE_NO_PAD.encode(format!("item:{}", start))
+}
+
+fn decode_cursor(cursor: &str) -> Result<usize, String> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .and_then(|decoded| decoded.strip_prefix("item:")?.parse().ok())
+        .ok_or_else(|| format!("`{}` is not a cursor of this endpoint", cursor))
+}
+
+/// e.g. `offset:20`
+impl fmt::Display for Pagination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.style, self.page_size)
+    }
+}
+
+impl FromStr for Pagination {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (style, page_size) = input
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| format!("`{}` is not a pagination like `offset:20`", input))?;
+        let style = match style.trim() {
+            "offset" => PageStyle::Offset,
+            "cursor" => PageStyle::Cursor,
+            other => {
+                return Err(format!(
+                    "`{}` is not a pagination style, use `offset` or `cursor`",
+                    other
+                ));
+            }
+        };
+        let page_size = page_size
+            .trim()
+            .parse()
+            .map_err(|_| format!("`{}` is not a positive page size", page_size.trim()))?;
+        Ok(Self { style, page_size })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn page(pagination: &str, query: &str) -> Value {
+        let items = serde_json::to_vec(&(1..=45).collect::<Vec<_>>()).unwrap();
+        let page = pagination
+            .parse::<Pagination>()
+            .unwrap()
+            .page(&items, "/items", query)
+            .unwrap();
+        serde_json::from_slice(&page.body).unwrap()
+    }
+
+    #[test]
+    fn test_parse_pagination() {
+        let pagination: Pagination = "cursor:20".parse().unwrap();
+        assert_eq!(pagination.style, PageStyle::Cursor);
+        assert_eq!(pagination.to_string(), "cursor:20");
+        assert!("offset:0".parse::<Pagination>().is_err());
+        assert!("pages:10".parse::<Pagination>().is_err());
+    }
+
+    #[test]
+    fn test_offset_pages() {
+        let first = page("offset:20", "sort=name");
+        assert_eq!(first["data"].as_array().unwrap().len(), 20);
+        assert_eq!(
+            first["meta"],
+            json!({"total": 45, "offset": 0, "limit": 20})
+        );
+        assert_eq!(
+            first["links"]["next"],
+            "/items?sort=name&offset=20&limit=20"
+        );
+        assert_eq!(first["links"]["prev"], Value::Null);
+        assert_eq!(
+            first["links"]["last"],
+            "/items?sort=name&offset=40&limit=20"
+        );
+        let last = page("offset:20", "offset=40&limit=50");
+        assert_eq!(last["data"], json!([41, 42, 43, 44, 45]));
+        assert_eq!(last["links"]["next"], Value::Null);
+        assert_eq!(last["links"]["prev"], "/items?offset=20&limit=20");
+    }
+
+    #[test]
+    fn test_cursor_pages() {
+        let first = page("cursor:10", "limit=30");
+        assert_eq!(first["meta"]["limit"], 10);
+        let cursor = first["meta"]["next_cursor"].as_str().unwrap();
+        let second = page("cursor:10", &format!("cursor={}", cursor));
+        assert_eq!(second["data"][0], 11);
+        let items = b"[1, 2]";
+        let pagination: Pagination = "cursor:10".parse().unwrap();
+        assert!(pagination.page(items, "/items", "cursor=10").is_err());
+        let page = pagination.page(items, "/items", "").unwrap();
+        assert!(page.link.is_none());
+    }
+}
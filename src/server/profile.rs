@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::util::build;
+
+/// Describes the configuration an instance runs with, so anyone sharing it can tell what is
+/// live, e.g. the `[profile]` table of `adaptive_rest.toml`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Defaults to the name of the file defining it
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Shown above the tabs of the TUI, e.g. who to ask before changing endpoints
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub banner: Option<String>,
+}
+
+/// Body of `/__about`: the active profile, `null` without one, the version and what is served
+pub fn about(profile: Option<&Profile>, endpoints: usize, base_path: &str) -> Value {
+    serde_json::json!({
+        "profile": profile,
+        "version": build::VERSION,
+        "commit": build::GIT_HASH,
+        "endpoints": endpoints,
+        "base_path": if base_path.is_empty() { "/" } else { base_path },
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_about() {
+        let profile = Profile {
+            name: "checkout-staging".to_owned(),
+            description: Some("Payments mocked".to_owned()),
+            banner: None,
+        };
+        let about = about(Some(&profile), 3, "");
+        assert_eq!(
+            about["profile"],
+            serde_json::json!({"name": "checkout-staging", "description": "Payments mocked"})
+        );
+        assert_eq!(about["endpoints"], 3);
+        assert_eq!(about["base_path"], "/");
+        assert!(
+            super::about(None, 0, "/api")
+                .get("profile")
+                .unwrap()
+                .is_null()
+        );
+    }
+}
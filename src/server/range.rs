@@ -0,0 +1,132 @@
+use std::ops::Range;
+
+use actix_web::{
+    HttpResponse,
+    http::{
+        Method,
+        header::{CONTENT_RANGE, EntityTag, HeaderMap, HeaderValue, IF_RANGE, RANGE},
+    },
+};
+
+/// The part of a body a request asks for with a `Range` header
+#[derive(Debug, Clone, PartialEq)]
+pub enum ByteRange {
+    Part(Range<usize>),
+    /// Starts beyond the body, answered with 416
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// The range a request for a body of `size` bytes asks for. `None` to send the whole body:
+    /// for requests without a single valid byte range, other methods than GET, or an `If-Range`
+    /// not naming `tag`, the current version of the body. Several ranges are answered with the
+    /// whole body too, which the RFC allows.
+    pub fn requested(
+        method: &Method,
+        headers: &HeaderMap,
+        size: usize,
+        tag: Option<&EntityTag>,
+    ) -> Option<Self> {
+        if method != Method::GET {
+            return None;
+        }
+        // A date can't match, as bodies have no modification time
+        if let Some(if_range) = headers.get(IF_RANGE) {
+            let current = if_range
+                .to_str()
+                .ok()
+                .and_then(|value| value.trim().parse::<EntityTag>().ok())
+                .is_some_and(|listed| tag.is_some_and(|tag| listed.strong_eq(tag)));
+            if !current {
+                return None;
+            }
+        }
+        let spec = headers.get(RANGE)?.to_str().ok()?.trim();
+        let (unit, range) = spec.split_once('=')?;
+        if !unit.trim().eq_ignore_ascii_case("bytes") || range.contains(',') {
+            return None;
+        }
+        let (first, last) = range.trim().split_once('-')?;
+        let range = match (first.trim(), last.trim()) {
+            ("", suffix) => {
+                let suffix: usize = suffix.parse().ok()?;
+                size.saturating_sub(suffix)..size
+            }
+            (first, "") => first.parse().ok()?..size,
+            (first, last) => {
+                let (first, last): (usize, usize) = (first.parse().ok()?, last.parse().ok()?);
+                if last < first {
+                    return None;
+                }
+                first..size.min(last.saturating_add(1))
+            }
+        };
+        Some(if range.start < range.end {
+            ByteRange::Part(range)
+        } else {
+            ByteRange::Unsatisfiable
+        })
+    }
+}
+
+/// Name the part of a body of `size` bytes that `response` carries in its `Content-Range`
+pub fn set_content_range(response: &mut HttpResponse, range: &Range<usize>, size: usize) {
+    let value = format!("bytes {}-{}/{}", range.start, range.end - 1, size);
+    if let Ok(value) = HeaderValue::try_from(value) {
+        response.headers_mut().insert(CONTENT_RANGE, value);
+    }
+}
+
+/// Answer to a range starting beyond a body of `size` bytes
+pub fn not_satisfiable(size: usize) -> HttpResponse {
+    HttpResponse::RangeNotSatisfiable()
+        .insert_header((CONTENT_RANGE, format!("bytes */{}", size)))
+        .finish()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn requested(range: &str) -> Option<ByteRange> {
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, range.parse().unwrap());
+        ByteRange::requested(&Method::GET, &headers, 100, None)
+    }
+
+    #[test]
+    fn test_requested_range() {
+        assert_eq!(requested("bytes=0-9"), Some(ByteRange::Part(0..10)));
+        assert_eq!(requested("bytes=90-"), Some(ByteRange::Part(90..100)));
+        assert_eq!(requested("bytes=-30"), Some(ByteRange::Part(70..100)));
+        assert_eq!(requested("bytes=50-500"), Some(ByteRange::Part(50..100)));
+        assert_eq!(requested("bytes=100-"), Some(ByteRange::Unsatisfiable));
+        assert_eq!(requested("bytes=0-1,5-6"), None);
+        assert_eq!(requested("items=0-9"), None);
+        assert_eq!(requested("bytes=9-0"), None);
+        let mut response = HttpResponse::PartialContent().finish();
+        set_content_range(&mut response, &(0..10), 100);
+        assert_eq!(
+            response.headers().get(CONTENT_RANGE).unwrap(),
+            "bytes 0-9/100"
+        );
+    }
+
+    #[test]
+    fn test_if_range() {
+        let tag = EntityTag::new_strong("v2".to_owned());
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, "bytes=10-".parse().unwrap());
+        headers.insert(IF_RANGE, "\"v1\"".parse().unwrap());
+        assert_eq!(
+            ByteRange::requested(&Method::GET, &headers, 100, Some(&tag)),
+            None
+        );
+        headers.insert(IF_RANGE, "\"v2\"".parse().unwrap());
+        assert_eq!(
+            ByteRange::requested(&Method::GET, &headers, 100, Some(&tag)),
+            Some(ByteRange::Part(10..100))
+        );
+    }
+}
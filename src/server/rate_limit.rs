@@ -0,0 +1,120 @@
+use std::{
+    fmt,
+    num::NonZeroU32,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use actix_web::{HttpResponse, http::header::RETRY_AFTER};
+
+use crate::util::units::{format_time, parse_duration};
+
+/// Requests allowed per window across all endpoints, e.g. `10/s` or `100/1m`. Requests beyond it
+/// are answered with 429 until the window ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests: NonZeroU32,
+    pub window: Duration,
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.requests, format_time(self.window))
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (requests, window) = input
+            .split_once('/')
+            .ok_or_else(|| format!("`{}` is not of the form `10/s`", input))?;
+        let requests = requests
+            .trim()
+            .parse()
+            .map_err(|_| format!("`{}` is not a positive number of requests", requests))?;
+        let window = window.trim();
+        // A bare unit is a window of one, e.g. `s` for `1s`
+        let window = if window.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            parse_duration(&format!("1{}", window))?
+        } else {
+            parse_duration(window)?
+        };
+        if window.is_zero() {
+            return Err("the window must be longer than 0".to_owned());
+        }
+        Ok(Self { requests, window })
+    }
+}
+
+/// Counts the requests of the current window of a [`RateLimit`]
+#[derive(Debug)]
+pub struct RateLimiter {
+    pub limit: RateLimit,
+    window_start: Instant,
+    count: u32,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            window_start: Instant::now(),
+            count: 0,
+        }
+    }
+
+    /// Count a request arriving at `now`. Answers 429 if it exceeds the limit.
+    pub fn check(&mut self, now: Instant) -> Option<HttpResponse> {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= self.limit.window {
+            self.window_start = now;
+            self.count = 0;
+        }
+        self.count = self.count.saturating_add(1);
+        if self.count <= self.limit.requests.get() {
+            return None;
+        }
+        let retry_after = self
+            .limit
+            .window
+            .saturating_sub(now.saturating_duration_since(self.window_start));
+        Some(
+            HttpResponse::TooManyRequests()
+                .insert_header((RETRY_AFTER, retry_after.as_secs_f64().ceil().to_string()))
+                .json(
+                    serde_json::json!({"error": "rate limited", "limit": self.limit.to_string()}),
+                ),
+        )
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_limit() {
+        assert_eq!("10/s".parse::<RateLimit>().unwrap().to_string(), "10/1s");
+        assert_eq!(
+            "100/1m".parse::<RateLimit>().unwrap().to_string(),
+            "100/60s"
+        );
+        assert!("0/s".parse::<RateLimit>().is_err());
+        assert!("10".parse::<RateLimit>().is_err());
+    }
+
+    #[test]
+    fn test_limit_per_window() {
+        let mut limiter = RateLimiter::new("2/1s".parse().unwrap());
+        let start = Instant::now();
+        assert!(limiter.check(start).is_none());
+        assert!(limiter.check(start).is_none());
+        let limited = limiter.check(start + Duration::from_millis(300)).unwrap();
+        assert_eq!(limited.status(), 429);
+        assert_eq!(limited.headers().get(RETRY_AFTER).unwrap(), "1");
+        assert!(limiter.check(start + Duration::from_secs(1)).is_none());
+    }
+}
@@ -0,0 +1,439 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    future::poll_fn,
+    io,
+    pin::Pin,
+    rc::Rc,
+    str::FromStr,
+    task::{Context, Poll, Waker, ready},
+    time::Duration,
+};
+
+use actix_web::{
+    HttpRequest, HttpResponse,
+    http::{ConnectionType, StatusCode, header::HeaderName},
+    rt::time::{Sleep, sleep},
+    web::{Bytes, BytesMut},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Bytes written to the socket as they are instead of a response built by actix, to reproduce
+/// protocol-level bugs of servers, e.g. `HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\nshort`.
+/// Escapes `\r`, `\n`, `\t`, `\\` and `\xHH` are resolved when parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawResponse(pub Bytes);
+
+impl RawResponse {
+    /// Status code of the status line the bytes start with, if any
+    pub fn status(&self) -> Option<StatusCode> {
+        let line = self.0.split(|b| *b == b'\n').next()?;
+        let code = line.split(|b| *b == b' ').nth(1)?;
+        StatusCode::from_bytes(code).ok()
+    }
+
+    /// Hand the bytes to the connection of `req`. The returned response only stands in for them
+    /// in the journal and closes the connection, it never reaches the client.
+    pub fn respond(&self, req: &HttpRequest) -> HttpResponse {
+        let status = self.status().unwrap_or(StatusCode::OK);
+        let Some(slot) = req.conn_data::<RawSlot>() else {
+            log::warn!("{} {} can't be answered raw", req.method(), req.path());
+            return HttpResponse::build(status).body(self.0.clone());
+        };
+        *slot.0.borrow_mut() = Some(Takeover::Raw(self.0.clone()));
+        let mut response = HttpResponse::build(status).finish();
+        response
+            .head_mut()
+            .set_connection_type(ConnectionType::Close);
+        response
+    }
+}
+
+/// Escaped, e.g. `HTTP/1.1 200 OK\r\n\r\n`
+impl fmt::Display for RawResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.iter() {
+            match byte {
+                b'\r' => write!(f, "\\r")?,
+                b'\n' => write!(f, "\\n")?,
+                b'\t' => write!(f, "\\t")?,
+                b'\\' => write!(f, "\\\\")?,
+                b' '..=b'~' => write!(f, "{}", *byte as char)?,
+                _ => write!(f, "\\x{:02x}", byte)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RawResponse {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut bytes = Vec::with_capacity(input.len());
+        let mut rest = input.as_bytes();
+        while let Some((&byte, after)) = rest.split_first() {
+            rest = after;
+            if byte != b'\\' {
+                bytes.push(byte);
+                continue;
+            }
+            let Some((&escape, after)) = rest.split_first() else {
+                return Err("trailing `\\` in raw response, use `\\\\` for a backslash".to_owned());
+            };
+            rest = after;
+            bytes.push(match escape {
+                b'r' => b'\r',
+                b'n' => b'\n',
+                b't' => b'\t',
+                b'\\' => b'\\',
+                b'x' => {
+                    let hex = rest
+                        .get(..2)
+                        .and_then(|hex| std::str::from_utf8(hex).ok())
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                        .ok_or("`\\x` takes two hex digits, e.g. `\\x00`")?;
+                    rest = &rest[2..];
+                    hex
+                }
+                other => return Err(format!("unknown escape `\\{}`", other as char)),
+            });
+        }
+        Ok(Self(Bytes::from(bytes)))
+    }
+}
+
+/// Names of response headers in the order and spelling they are written in, e.g.
+/// `Content-Type,X-Legacy-ID,Content-Length`, for clients relying on either. Headers not listed
+/// follow them as actix writes them, lowercase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderOrder(Vec<String>);
+
+impl HeaderOrder {
+    /// Have the connection of `req` write the head of the response in this order
+    pub fn apply(&self, req: &HttpRequest) {
+        match req.conn_data::<RawSlot>() {
+            Some(slot) => *slot.0.borrow_mut() = Some(Takeover::Reorder(self.clone())),
+            None => log::warn!("{} {} can't reorder its headers", req.method(), req.path()),
+        }
+    }
+
+    /// Rewrite a response head, ending with an empty line, so the listed headers come first
+    fn reorder(&self, head: &[u8]) -> Vec<u8> {
+        let mut lines: Vec<Option<&[u8]>> = head
+            .split(|b| *b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .filter(|line| !line.is_empty())
+            .map(Some)
+            .collect();
+        let mut reordered = Vec::with_capacity(head.len());
+        if let Some(status_line) = lines.first_mut().and_then(Option::take) {
+            reordered.extend_from_slice(status_line);
+            reordered.extend_from_slice(b"\r\n");
+        }
+        for name in &self.0 {
+            for line in lines.iter_mut() {
+                let Some(value) = line
+                    .and_then(|l| l.split_at_checked(name.len()))
+                    .filter(|(n, rest)| {
+                        n.eq_ignore_ascii_case(name.as_bytes()) && rest.starts_with(b":")
+                    })
+                    .map(|(_, rest)| rest[1..].trim_ascii_start())
+                else {
+                    continue;
+                };
+                reordered.extend_from_slice(name.as_bytes());
+                reordered.extend_from_slice(b": ");
+                reordered.extend_from_slice(value);
+                reordered.extend_from_slice(b"\r\n");
+                *line = None;
+            }
+        }
+        for line in lines.into_iter().flatten() {
+            reordered.extend_from_slice(line);
+            reordered.extend_from_slice(b"\r\n");
+        }
+        reordered.extend_from_slice(b"\r\n");
+        reordered
+    }
+}
+
+impl fmt::Display for HeaderOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(","))
+    }
+}
+
+impl FromStr for HeaderOrder {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let names = input
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| match HeaderName::from_bytes(name.as_bytes()) {
+                Ok(_) => Ok(name.to_owned()),
+                Err(_) => Err(format!("`{}` is not a valid header name", name)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if names.is_empty() {
+            return Err(
+                "list at least one header name, e.g. `Content-Type,Content-Length`".to_owned(),
+            );
+        }
+        Ok(Self(names))
+    }
+}
+
+/// How a connection replaces what actix writes next
+#[derive(Debug)]
+enum Takeover {
+    /// Send these bytes instead, discarding everything actix writes after them
+    Raw(Bytes),
+    /// Rewrite the head of the next response
+    Reorder(HeaderOrder),
+}
+
+/// Takes over what actix writes next on a connection, attached to each connection as it is
+/// accepted
+#[derive(Debug, Clone, Default)]
+pub struct RawSlot(Rc<RefCell<Option<Takeover>>>);
+
+/// Whether the peer closed its side of a connection, attached to each connection as it is
+/// accepted. Actix keeps running handlers after that.
+#[derive(Debug, Clone, Default)]
+pub struct PeerClosed(Rc<(Cell<bool>, RefCell<Option<Waker>>)>);
+
+impl PeerClosed {
+    /// Resolves once the peer closed the connection, as far as actix read from it
+    pub async fn wait(&self) {
+        poll_fn(|cx| {
+            if self.0.0.get() {
+                return Poll::Ready(());
+            }
+            *self.0.1.borrow_mut() = Some(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+
+    fn close(&self) {
+        self.0.0.set(true);
+        if let Some(waker) = self.0.1.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// How long a connection stays open after the response it is dropped after
+const IDLE_CLOSE_DELAY: Duration = Duration::from_millis(100);
+
+/// Closes a connection without announcing it shortly after its next response, as servers reaping
+/// idle connections do, attached to each connection as it is accepted
+#[derive(Debug, Clone, Default)]
+pub struct IdleClose(Rc<Cell<bool>>);
+
+impl IdleClose {
+    /// Have the connection of `req` closed shortly after the response to it
+    pub fn schedule(req: &HttpRequest) {
+        match req.conn_data::<IdleClose>() {
+            Some(close) => close.0.set(true),
+            None => log::warn!("{} {} can't drop its connection", req.method(), req.path()),
+        }
+    }
+}
+
+/// A connection that writes the bytes put into its [`RawSlot`] instead of the response actix
+/// writes next, or rewrites the head of that response
+pub struct RawStream<T> {
+    io: T,
+    slot: RawSlot,
+    closed: PeerClosed,
+    idle_close: IdleClose,
+    /// Runs out when the connection is to be closed, reading ends there as if the peer closed it
+    closing: Option<Pin<Box<Sleep>>>,
+    /// Bytes not written yet, either raw ones taken from the slot or a rewritten head
+    pending: Option<Bytes>,
+    /// Whether actix's writes are discarded
+    hijacked: bool,
+    /// The head written so far while it is being reordered
+    reordering: Option<(HeaderOrder, BytesMut)>,
+}
+
+impl<T: AsyncWrite + Unpin> RawStream<T> {
+    pub fn new(io: T) -> Self {
+        Self {
+            io,
+            slot: RawSlot::default(),
+            closed: PeerClosed::default(),
+            idle_close: IdleClose::default(),
+            closing: None,
+            pending: None,
+            hijacked: false,
+            reordering: None,
+        }
+    }
+
+    pub fn slot(&self) -> RawSlot {
+        self.slot.clone()
+    }
+
+    pub fn closed(&self) -> PeerClosed {
+        self.closed.clone()
+    }
+
+    pub fn idle_close(&self) -> IdleClose {
+        self.idle_close.clone()
+    }
+
+    /// Take over what's in the slot and write the pending bytes, if there are any
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if !self.hijacked
+            && self.reordering.is_none()
+            && let Some(takeover) = self.slot.0.borrow_mut().take()
+        {
+            match takeover {
+                Takeover::Raw(raw) => {
+                    self.pending = Some(raw);
+                    self.hijacked = true;
+                }
+                Takeover::Reorder(order) => self.reordering = Some((order, BytesMut::new())),
+            }
+        }
+        while let Some(pending) = &mut self.pending {
+            let written = ready!(Pin::new(&mut self.io).poll_write(cx, pending))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            let _ = pending.split_to(written);
+            if pending.is_empty() {
+                self.pending = None;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for RawStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let stream = self.get_mut();
+        // Actix closes the connection once reading ends, without a response in flight
+        if let Some(closing) = &mut stream.closing
+            && closing.as_mut().poll(cx).is_ready()
+        {
+            return Poll::Ready(Ok(()));
+        }
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut stream.io).poll_read(cx, buf))?;
+        // Reading nothing into space left means the peer is done sending
+        if buf.filled().len() == filled && buf.remaining() > 0 {
+            stream.closed.close();
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for RawStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let stream = self.get_mut();
+        // The delay starts with writing the response, not when the handler asked for it. Actix
+        // is woken to read again, so the delay gets polled while the connection is idle.
+        if stream.idle_close.0.take() {
+            stream.closing = Some(Box::pin(sleep(IDLE_CLOSE_DELAY)));
+            cx.waker().wake_by_ref();
+        }
+        ready!(stream.poll_pending(cx))?;
+        if stream.hijacked {
+            return Poll::Ready(Ok(buf.len()));
+        }
+        let Some((order, head)) = &mut stream.reordering else {
+            return Pin::new(&mut stream.io).poll_write(cx, buf);
+        };
+        // The head is collected until its empty line, then written reordered with the rest
+        head.extend_from_slice(buf);
+        if let Some(end) = head.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = head.split_off(end + 4);
+            let mut rewritten = BytesMut::from(order.reorder(head).as_slice());
+            rewritten.extend_from_slice(&rest);
+            stream.pending = Some(rewritten.freeze());
+            stream.reordering = None;
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let stream = self.get_mut();
+        ready!(stream.poll_pending(cx))?;
+        Pin::new(&mut stream.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let stream = self.get_mut();
+        ready!(stream.poll_pending(cx))?;
+        Pin::new(&mut stream.io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_raw_response() {
+        let raw: RawResponse = r"HTTP/1.1 502 Bad\r\nX: \x00\r\n\r\n".parse().unwrap();
+        assert_eq!(raw.0.as_ref(), b"HTTP/1.1 502 Bad\r\nX: \x00\r\n\r\n");
+        assert_eq!(raw.status(), Some(StatusCode::BAD_GATEWAY));
+        assert_eq!(raw.to_string(), r"HTTP/1.1 502 Bad\r\nX: \x00\r\n\r\n");
+        assert_eq!("garbage".parse::<RawResponse>().unwrap().status(), None);
+        assert!(r"\q".parse::<RawResponse>().is_err());
+        assert!(r"\x0".parse::<RawResponse>().is_err());
+    }
+
+    #[test]
+    fn test_reorder_head() {
+        let order: HeaderOrder = "Content-Length, X-Legacy-ID".parse().unwrap();
+        let head = b"HTTP/1.1 200 OK\r\nx-legacy-id: 7\r\ndate: today\r\ncontent-length: 2\r\n\r\n";
+        assert_eq!(
+            order.reorder(head),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nX-Legacy-ID: 7\r\ndate: today\r\n\r\n"
+        );
+        assert_eq!(order.to_string(), "Content-Length,X-Legacy-ID");
+        assert!("Bad Name".parse::<HeaderOrder>().is_err());
+        assert!(",".parse::<HeaderOrder>().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_peer_closed() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut stream = RawStream::new(server);
+        let closed = stream.closed();
+        drop(client);
+
+        assert_eq!(stream.read(&mut [0; 8]).await.unwrap(), 0);
+        closed.wait().await;
+    }
+
+    #[actix_web::test]
+    async fn test_idle_close() {
+        let (_client, server) = tokio::io::duplex(64);
+        let mut stream = RawStream::new(server);
+        stream.idle_close().0.set(true);
+        stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await.unwrap();
+
+        let mut buf = [0; 8];
+        let read = actix_web::rt::time::timeout(Duration::from_secs(1), stream.read(&mut buf));
+        assert_eq!(read.await.unwrap().unwrap(), 0);
+    }
+}
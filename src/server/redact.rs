@@ -0,0 +1,190 @@
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use actix_web::{http::header::HeaderName, web::Bytes};
+use regex::Regex;
+use serde_json::Value;
+
+use crate::server::{RequestRecord, jsonpath::JsonPath};
+
+/// Replaces redacted values
+pub const REDACTED: &str = "[redacted]";
+
+/// A secret kept out of the logs, the journal and exports
+#[derive(Debug, Clone)]
+pub enum Redaction {
+    /// Values of a header, in lowercase
+    Header(String),
+    /// Values selected in JSON request bodies
+    Json(JsonPath, String),
+    /// Matches in request paths, queries, header values, bodies and log messages
+    Pattern(Regex),
+}
+
+impl fmt::Display for Redaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Redaction::Header(name) => write!(f, "header:{}", name),
+            Redaction::Json(_, source) => write!(f, "json:{}", source),
+            Redaction::Pattern(pattern) => write!(f, "regex:{}", pattern),
+        }
+    }
+}
+
+/// e.g. `header:Authorization`, `json:$.user.password` or `regex:token=\w+`
+impl FromStr for Redaction {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.split_once(':') {
+            Some(("header", name)) => HeaderName::try_from(name.trim())
+                .map(|name| Redaction::Header(name.as_str().to_owned()))
+                .map_err(|_| format!("`{}` is not a valid header name", name.trim())),
+            Some(("json", path)) => Ok(Redaction::Json(path.parse()?, path.trim().to_owned())),
+            Some(("regex", pattern)) => Regex::new(pattern)
+                .map(Redaction::Pattern)
+                .map_err(|e| e.to_string()),
+            _ => Err(format!(
+                "`{}` is not of the form `header:<name>`, `json:<JSONPath>` or `regex:<pattern>`",
+                input
+            )),
+        }
+    }
+}
+
+/// Whether two rules redact the same, compared by their source text
+impl PartialEq for Redaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+/// Replace matches of the regex rules in `text`
+pub fn redact_text<'a>(rules: &[Redaction], text: &'a str) -> Cow<'a, str> {
+    let mut text = Cow::Borrowed(text);
+    for rule in rules {
+        if let Redaction::Pattern(pattern) = rule
+            && let Cow::Owned(redacted) = pattern.replace_all(&text, REDACTED)
+        {
+            text = Cow::Owned(redacted);
+        }
+    }
+    text
+}
+
+/// Redact the selected fields of a JSON body, then matches of the regex rules in a text body
+pub fn redact_body(rules: &[Redaction], body: &Bytes) -> Bytes {
+    let mut body = body.clone();
+    let paths: Vec<&JsonPath> = rules
+        .iter()
+        .filter_map(|rule| match rule {
+            Redaction::Json(path, _) => Some(path),
+            _ => None,
+        })
+        .collect();
+    if !paths.is_empty()
+        && let Ok(mut json) = serde_json::from_slice::<Value>(&body)
+    {
+        let redacted = Value::from(REDACTED);
+        let replaced = paths
+            .iter()
+            .map(|path| path.replace(&mut json, &redacted))
+            .filter(|replaced| *replaced)
+            .count()
+            > 0;
+        if replaced && let Ok(bytes) = serde_json::to_vec(&json) {
+            body = Bytes::from(bytes);
+        }
+    }
+    if let Ok(text) = std::str::from_utf8(&body)
+        && let Cow::Owned(redacted) = redact_text(rules, text)
+    {
+        body = Bytes::from(redacted);
+    }
+    body
+}
+
+/// Redact what a request record captured from the client before it is journaled
+pub fn redact_record(rules: &[Redaction], record: &mut RequestRecord) {
+    if rules.is_empty() {
+        return;
+    }
+    record.path = redact_text(rules, &record.path).into_owned();
+    record.query = redact_text(rules, &record.query).into_owned();
+    for (name, value) in &mut record.headers {
+        if rules
+            .iter()
+            .any(|rule| matches!(rule, Redaction::Header(header) if header == name))
+        {
+            *value = REDACTED.to_owned();
+        } else if let Cow::Owned(redacted) = redact_text(rules, value) {
+            *value = redacted;
+        }
+    }
+    record.body = redact_body(rules, &record.body);
+    // Violations may quote values of the body
+    for violation in &mut record.violations {
+        if let Cow::Owned(redacted) = redact_text(rules, violation) {
+            *violation = redacted;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn rules(rules: &[&str]) -> Vec<Redaction> {
+        rules.iter().map(|rule| rule.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse_redaction() {
+        let rule: Redaction = "header:Authorization".parse().unwrap();
+        assert_eq!(rule.to_string(), "header:authorization");
+        assert_eq!(
+            "regex:token=\\w+".parse::<Redaction>().unwrap().to_string(),
+            "regex:token=\\w+"
+        );
+        assert!("json:password".parse::<Redaction>().is_err());
+        assert!("regex:(".parse::<Redaction>().is_err());
+        assert!("cookie".parse::<Redaction>().is_err());
+    }
+
+    #[test]
+    fn test_redact_body() {
+        let rules = rules(&["json:$.users[*].password", "regex:sk_live_\\w+"]);
+        let body = Bytes::from(
+            r#"{"users":[{"name":"a","password":"x"},{"name":"b","password":"y"}],"key":"sk_live_123"}"#,
+        );
+        let redacted: Value = serde_json::from_slice(&redact_body(&rules, &body)).unwrap();
+        assert_eq!(
+            redacted,
+            serde_json::json!({
+                "users": [
+                    {"name": "a", "password": REDACTED},
+                    {"name": "b", "password": REDACTED},
+                ],
+                "key": REDACTED,
+            })
+        );
+
+        let text = Bytes::from("key=sk_live_123&password=x");
+        assert_eq!(
+            redact_body(&rules, &text).as_ref(),
+            b"key=[redacted]&password=x"
+        );
+        let untouched = Bytes::from(r#"{"name":"a"}"#);
+        assert_eq!(redact_body(&rules, &untouched), untouched);
+    }
+
+    #[test]
+    fn test_redact_text() {
+        let rules = rules(&["header:cookie", "regex:Bearer \\S+"]);
+        assert_eq!(
+            redact_text(&rules, "GET /me with Bearer abc.def"),
+            "GET /me with [redacted]"
+        );
+        assert!(matches!(redact_text(&rules, "/users"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,393 @@
+use std::{fmt, io, str::FromStr, thread, time::Duration};
+
+use actix_web::http::{
+    Method, StatusCode, Uri,
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, HOST, HeaderName, HeaderValue},
+};
+use serde_json::Value;
+
+use crate::server::{RequestRecord, fallback::forwarded};
+
+/// Time the target has to answer a replayed request
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(30);
+/// Responses of the target beyond this size are reported as failed
+const MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+/// Differences listed per request, the rest are counted
+const MAX_DIFFERENCES: usize = 10;
+
+/// The server journaled requests are sent to again, e.g. `http://localhost:8081`, with the
+/// path of each request appended
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayTarget(Uri);
+
+/// Which journaled requests `replay` sends
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySelection {
+    /// By journal id
+    Request(u64),
+    /// All the journal holds, as filtered
+    All,
+}
+
+/// Which journaled requests `replay all` sends, e.g. `method=post`, `path=/users`, `status=404`
+/// or `unmatched`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayFilter {
+    Method(Method),
+    /// Paths starting with a prefix
+    Path(String),
+    Status(StatusCode),
+    /// Requests an endpoint answered
+    Matched,
+    /// Requests no endpoint answered
+    Unmatched,
+}
+
+impl ReplayFilter {
+    pub fn matches(&self, record: &RequestRecord) -> bool {
+        match self {
+            ReplayFilter::Method(method) => record.method == method,
+            ReplayFilter::Path(prefix) => record.path.starts_with(prefix.as_str()),
+            ReplayFilter::Status(status) => record.status == *status,
+            ReplayFilter::Matched => record.matched(),
+            ReplayFilter::Unmatched => !record.matched(),
+        }
+    }
+}
+
+/// Send `records` to `target` one after another on a thread of their own, logging for each how
+/// the response differs from what the mock answered. The body is compared with the body of the
+/// endpoint as defined, so templates and other transformations show up as differences.
+pub fn replay(target: ReplayTarget, records: Vec<RequestRecord>) -> io::Result<()> {
+    thread::Builder::new()
+        .name("replay".to_owned())
+        .spawn(move || {
+            actix_web::rt::System::new().block_on(async move {
+                let client = awc::Client::default();
+                let total = records.len();
+                let mut differing = 0;
+                for record in records {
+                    let url = target.url(&record);
+                    match send(&client, &url, &record).await {
+                        Ok((status, body)) => {
+                            let differences = compare(&record, status, &body);
+                            if differences.is_empty() {
+                                log::info!(
+                                    request_id = record.id;
+                                    "Replayed #{} {} {}: same response", record.id, record.method, url
+                                );
+                            } else {
+                                differing += 1;
+                                log::warn!(
+                                    request_id = record.id;
+                                    "Replayed #{} {} {}: {}",
+                                    record.id,
+                                    record.method,
+                                    url,
+                                    describe(&differences)
+                                );
+                            }
+                        }
+                        Err(e) => {
+                            differing += 1;
+                            log::warn!(
+                                request_id = record.id;
+                                "Replaying #{} {} {} failed: {}", record.id, record.method, url, e
+                            );
+                        }
+                    }
+                }
+                log::info!(
+                    "Replayed {} request(s) against {}, {} differ",
+                    total,
+                    target,
+                    differing
+                );
+            })
+        })?;
+    Ok(())
+}
+
+async fn send(
+    client: &awc::Client,
+    url: &str,
+    record: &RequestRecord,
+) -> Result<(StatusCode, Vec<u8>), String> {
+    let mut request = client
+        .request(record.method.clone(), url)
+        .timeout(REPLAY_TIMEOUT);
+    let headers = request.headers_mut();
+    for (name, value) in &record.headers {
+        let (Ok(name), Ok(value)) = (
+            HeaderName::try_from(name.as_str()),
+            HeaderValue::try_from(value.as_str()),
+        ) else {
+            continue;
+        };
+        // The journal keeps bodies decoded, and responses are compared uncompressed
+        if forwarded(&name, None)
+            && name != HOST
+            && name != CONTENT_LENGTH
+            && name != ACCEPT_ENCODING
+            && !(record.encoded_size.is_some() && name == CONTENT_ENCODING)
+        {
+            headers.append(name, value);
+        }
+    }
+    let mut response = request
+        .send_body(record.body.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    let body = response
+        .body()
+        .limit(MAX_RESPONSE_SIZE)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((response.status(), body.to_vec()))
+}
+
+/// How the response of the target differs from what the mock answered `record` with. Bodies
+/// are compared as JSON if both are, otherwise as text. Requests no endpoint answered only have
+/// their status compared.
+pub fn compare(record: &RequestRecord, status: StatusCode, body: &[u8]) -> Vec<String> {
+    let mut differences = Vec::new();
+    if record.status != status {
+        differences.push(format!(
+            "status {} instead of {}",
+            status.as_u16(),
+            record.status.as_u16()
+        ));
+    }
+    let Some(expected) = &record.stub else {
+        return differences;
+    };
+    match (
+        serde_json::from_slice::<Value>(expected),
+        serde_json::from_slice::<Value>(body),
+    ) {
+        (Ok(expected), Ok(actual)) => diff_json("$", &expected, &actual, &mut differences),
+        _ if expected[..] != *body => differences.push(format!(
+            "body {:?} instead of {:?}",
+            String::from_utf8_lossy(body),
+            String::from_utf8_lossy(expected)
+        )),
+        _ => {}
+    }
+    differences
+}
+
+/// Differences of `actual` from `expected` at JSONPath-like paths, e.g. `$.user.name: "bob"
+/// instead of "ada"`
+fn diff_json(path: &str, expected: &Value, actual: &Value, differences: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, value) in expected {
+                let path = format!("{}.{}", path, key);
+                match actual.get(key) {
+                    Some(actual) => diff_json(&path, value, actual, differences),
+                    None => differences.push(format!("{} missing", path)),
+                }
+            }
+            for key in actual.keys().filter(|key| !expected.contains_key(*key)) {
+                differences.push(format!("{}.{} added", path, key));
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                diff_json(&format!("{}[{}]", path, i), expected, actual, differences);
+            }
+            if expected.len() != actual.len() {
+                differences.push(format!(
+                    "{} has {} items instead of {}",
+                    path,
+                    actual.len(),
+                    expected.len()
+                ));
+            }
+        }
+        (expected, actual) if expected != actual => {
+            differences.push(format!("{}: {} instead of {}", path, actual, expected));
+        }
+        _ => {}
+    }
+}
+
+fn describe(differences: &[String]) -> String {
+    let mut text = differences
+        .iter()
+        .take(MAX_DIFFERENCES)
+        .map(|difference| format!("\n  {}", difference))
+        .collect::<String>();
+    if differences.len() > MAX_DIFFERENCES {
+        text.push_str(&format!(
+            "\n  and {} more",
+            differences.len() - MAX_DIFFERENCES
+        ));
+    }
+    format!("{} difference(s){}", differences.len(), text)
+}
+
+impl ReplayTarget {
+    /// URL of the target for `record`, below the target's path
+    fn url(&self, record: &RequestRecord) -> String {
+        let base = self.0.to_string();
+        let base = base.trim_end_matches('/');
+        if record.query.is_empty() {
+            format!("{}{}", base, record.path)
+        } else {
+            format!("{}{}?{}", base, record.path, record.query)
+        }
+    }
+}
+
+impl fmt::Display for ReplayTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ReplayTarget {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let target: Uri = input
+            .trim()
+            .parse()
+            .map_err(|_| format!("`{}` is not a valid URL", input))?;
+        match target.scheme_str() {
+            Some("http" | "https") if target.authority().is_some() => Ok(Self(target)),
+            _ => Err(format!(
+                "`{}` is not an http(s) URL, e.g. `http://localhost:8081`",
+                input
+            )),
+        }
+    }
+}
+
+impl FromStr for ReplaySelection {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().trim_start_matches('#') {
+            "all" => Ok(ReplaySelection::All),
+            id => id
+                .parse()
+                .map(ReplaySelection::Request)
+                .map_err(|_| format!("`{}` is neither the id of a request nor `all`", input)),
+        }
+    }
+}
+
+impl FromStr for ReplayFilter {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        match input.split_once('=') {
+            None if input == "matched" => Ok(ReplayFilter::Matched),
+            None if input == "unmatched" => Ok(ReplayFilter::Unmatched),
+            Some(("method", method)) => Method::from_bytes(method.to_uppercase().as_bytes())
+                .map(ReplayFilter::Method)
+                .map_err(|_| format!("`{}` is not a method", method)),
+            Some(("path", path)) if path.starts_with('/') => {
+                Ok(ReplayFilter::Path(path.to_owned()))
+            }
+            Some(("status", status)) => status
+                .parse()
+                .ok()
+                .and_then(|status| StatusCode::from_u16(status).ok())
+                .map(ReplayFilter::Status)
+                .ok_or_else(|| format!("`{}` is not a status", status)),
+            _ => Err(format!(
+                "unknown filter `{}`, use method=<method>, path=</prefix>, status=<code>, \
+                 matched or unmatched",
+                input
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::web::Bytes;
+    use chrono::Local;
+
+    use super::*;
+
+    fn record(stub: Option<&'static str>) -> RequestRecord {
+        RequestRecord {
+            id: 7,
+            timestamp: Local::now(),
+            method: Method::GET,
+            path: "/users/1".to_owned(),
+            query: "full=true".to_owned(),
+            headers: Vec::new(),
+            body: Bytes::new(),
+            body_size: 0,
+            encoded_size: None,
+            status: StatusCode::OK,
+            stub: stub.map(Bytes::from),
+            response: None,
+            endpoint: None,
+            elapsed: Duration::ZERO,
+            fault: None,
+            failures: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_compare_responses() {
+        let answered = record(Some(r#"{"name": "ada", "roles": ["admin"], "age": 36}"#));
+        assert!(
+            compare(
+                &answered,
+                StatusCode::OK,
+                br#"{"age":36,"roles":["admin"],"name":"ada"}"#
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            compare(
+                &answered,
+                StatusCode::CREATED,
+                br#"{"name": "bob", "roles": ["admin", "dev"], "email": null}"#
+            ),
+            [
+                "status 201 instead of 200",
+                "$.age missing",
+                r#"$.name: "bob" instead of "ada""#,
+                "$.roles has 2 items instead of 1",
+                "$.email added",
+            ]
+        );
+        assert_eq!(
+            compare(&answered, StatusCode::OK, b"ok"),
+            [r#"body "ok" instead of "{\"name\": \"ada\", \"roles\": [\"admin\"], \"age\": 36}""#]
+        );
+        assert!(compare(&record(None), StatusCode::OK, b"anything").is_empty());
+    }
+
+    #[test]
+    fn test_parse_target_and_filters() {
+        let target: ReplayTarget = "http://localhost:8081/v2/".parse().unwrap();
+        assert_eq!(
+            target.url(&record(None)),
+            "http://localhost:8081/v2/users/1?full=true"
+        );
+        assert!("localhost:8081".parse::<ReplayTarget>().is_err());
+
+        let record = record(Some("{}"));
+        let filters: Vec<ReplayFilter> = ["method=get", "path=/users", "status=200", "matched"]
+            .iter()
+            .map(|filter| filter.parse().unwrap())
+            .collect();
+        assert!(filters.iter().all(|filter| filter.matches(&record)));
+        assert!(!ReplayFilter::Unmatched.matches(&record));
+        assert!("path=users".parse::<ReplayFilter>().is_err());
+        assert_eq!("#17".parse(), Ok(ReplaySelection::Request(17)));
+        assert_eq!("all".parse(), Ok(ReplaySelection::All));
+        assert!("status=ok".parse::<ReplayFilter>().is_err());
+    }
+}
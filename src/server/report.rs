@@ -0,0 +1,179 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::Local;
+use serde::Serialize;
+
+use crate::server::{
+    CoverageReport, EndpointStatsTable, EndpointStore, Metrics, RequestJournal, Verification,
+};
+
+/// Machine-readable summary of a session, e.g. for CI jobs to archive and assert on
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// RFC 3339 time the report was generated
+    pub generated: String,
+    pub requests: RequestCounts,
+    pub endpoints: Vec<EndpointReport>,
+    /// Verifications run so far, by command or admin API
+    pub verifications: Vec<Verification>,
+    /// Journaled requests no endpoint answered, grouped by method and path
+    pub unmatched: Vec<UnmatchedRequests>,
+    /// Of all journaled requests
+    pub latency: Option<LatencyStats>,
+    /// Of the OpenAPI document loaded with `spec load`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<CoverageReport>,
+    /// Counters of the `metric` hooks of endpoints
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequestCounts {
+    /// Including requests already dropped from the journal
+    pub total: u64,
+    /// Requests the latency and unmatched sections are based on
+    pub journaled: usize,
+    pub unmatched: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EndpointReport {
+    pub method: String,
+    pub path: String,
+    pub hits: u64,
+    /// Number of responses by status code
+    pub statuses: BTreeMap<u16, u64>,
+    /// Requests violating the endpoint's assertions
+    pub failures: u64,
+    /// Of the journaled requests the endpoint answered
+    pub latency: Option<LatencyStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnmatchedRequests {
+    pub method: String,
+    pub path: String,
+    pub count: usize,
+}
+
+/// Response times in milliseconds, percentiles by nearest rank
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    /// `None` without any durations
+    pub fn of(durations: impl IntoIterator<Item = Duration>) -> Option<Self> {
+        let mut millis: Vec<f64> = durations
+            .into_iter()
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .collect();
+        if millis.is_empty() {
+            return None;
+        }
+        millis.sort_by(f64::total_cmp);
+        let percentile = |p: usize| millis[(millis.len() * p).div_ceil(100) - 1];
+        Some(Self {
+            count: millis.len(),
+            min_ms: millis[0],
+            mean_ms: millis.iter().sum::<f64>() / millis.len() as f64,
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            p99_ms: percentile(99),
+            max_ms: millis[millis.len() - 1],
+        })
+    }
+}
+
+impl Report {
+    pub fn new(
+        endpoints: &EndpointStore,
+        stats: &EndpointStatsTable,
+        journal: &RequestJournal,
+        verifications: &[Verification],
+        coverage: Option<CoverageReport>,
+        metrics: &Metrics,
+    ) -> Self {
+        let mut endpoint_reports: Vec<EndpointReport> = Vec::new();
+        for (method, children) in endpoints.entries(None) {
+            for (path, _) in children {
+                // Every candidate of an endpoint is listed, but they share their usage
+                if endpoint_reports
+                    .last()
+                    .is_some_and(|last| last.method == method.as_str() && last.path == path)
+                {
+                    continue;
+                }
+                let stats = stats.get(method, &path).cloned().unwrap_or_default();
+                let latency = LatencyStats::of(
+                    journal
+                        .records()
+                        .filter(|r| r.method == method && r.endpoint.as_ref() == Some(&path))
+                        .map(|r| r.elapsed),
+                );
+                endpoint_reports.push(EndpointReport {
+                    method: method.to_string(),
+                    path,
+                    hits: stats.hits,
+                    statuses: stats.statuses,
+                    failures: stats.failures,
+                    latency,
+                });
+            }
+        }
+        let mut unmatched: BTreeMap<(String, String), usize> = BTreeMap::new();
+        for record in journal.records().filter(|r| !r.matched()) {
+            *unmatched
+                .entry((record.method.to_string(), record.path.clone()))
+                .or_default() += 1;
+        }
+        Self {
+            generated: Local::now().to_rfc3339(),
+            requests: RequestCounts {
+                total: journal.total(),
+                journaled: journal.len(),
+                unmatched: unmatched.values().sum(),
+            },
+            endpoints: endpoint_reports,
+            verifications: verifications.to_vec(),
+            unmatched: unmatched
+                .into_iter()
+                .map(|((method, path), count)| UnmatchedRequests {
+                    method,
+                    path,
+                    count,
+                })
+                .collect(),
+            latency: LatencyStats::of(journal.records().map(|r| r.elapsed)),
+            coverage,
+            metrics: metrics.counters().clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let stats = LatencyStats::of((1..=100).map(Duration::from_millis)).unwrap();
+        assert_eq!(stats.count, 100);
+        assert_eq!((stats.min_ms, stats.max_ms), (1.0, 100.0));
+        assert_eq!(
+            (stats.p50_ms, stats.p95_ms, stats.p99_ms),
+            (50.0, 95.0, 99.0)
+        );
+        assert_eq!(stats.mean_ms, 50.5);
+        assert!(LatencyStats::of([]).is_none());
+    }
+}
@@ -0,0 +1,62 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+/// Regex find and replace on incoming paths before matching, e.g. `^/tenants/[^/]+` replaced
+/// with nothing serves `/tenants/acme/users` from `/users`
+#[derive(Debug, Clone)]
+pub struct RewriteRule {
+    pub pattern: Regex,
+    /// May refer to capture groups, e.g. `/$1`
+    pub replacement: String,
+}
+
+impl RewriteRule {
+    /// e.g. `^/tenants/[^/]+ -> ''`
+    pub fn describe(&self) -> String {
+        format!("{} -> '{}'", self.pattern, self.replacement)
+    }
+}
+
+pub fn parse_pattern(input: &str) -> Result<Regex, String> {
+    Regex::new(input).map_err(|e| e.to_string())
+}
+
+/// Apply every rule in order, each to the result of the previous one. A path rewritten to
+/// nothing becomes `/`.
+pub fn rewrite<'a>(rules: &[RewriteRule], path: &'a str) -> Cow<'a, str> {
+    let mut path = Cow::Borrowed(path);
+    for rule in rules {
+        if let Cow::Owned(rewritten) = rule.pattern.replace_all(&path, rule.replacement.as_str()) {
+            path = Cow::Owned(rewritten);
+        }
+    }
+    if path.is_empty() {
+        path = Cow::Borrowed("/");
+    }
+    path
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, replacement: &str) -> RewriteRule {
+        RewriteRule {
+            pattern: parse_pattern(pattern).unwrap(),
+            replacement: replacement.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_rewrite_in_order() {
+        let rules = [rule("^/tenants/[^/]+", ""), rule("^/v(\\d+)/", "/api/$1/")];
+
+        assert_eq!(rewrite(&rules, "/tenants/acme/users"), "/users");
+        assert_eq!(rewrite(&rules, "/tenants/acme/v2/users"), "/api/2/users");
+        assert_eq!(rewrite(&rules, "/tenants/acme"), "/");
+        assert!(matches!(rewrite(&rules, "/users"), Cow::Borrowed("/users")));
+        assert!(parse_pattern("(").is_err());
+    }
+}
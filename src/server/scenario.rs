@@ -0,0 +1,137 @@
+use actix_web::http::Method;
+
+use crate::server::{EndpointStore, RequestInfo, Stub};
+
+/// Endpoints coordinated through a sequence of steps, e.g. a logged out step whose `/profile`
+/// answers 401, advancing to a logged in step once `/login` is called
+#[derive(Debug, Default)]
+pub struct Scenario {
+    pub name: String,
+    pub steps: Vec<ScenarioStep>,
+}
+
+#[derive(Debug, Default)]
+pub struct ScenarioStep {
+    pub name: String,
+    /// Answer requests before the regular endpoints while the step is current
+    pub endpoints: EndpointStore,
+    /// Requests that advance the scenario to the next step, after being answered by this one.
+    /// Paths may contain parameters like endpoint paths.
+    pub triggers: EndpointStore,
+}
+
+/// A started scenario and its current step
+#[derive(Debug)]
+pub struct ScenarioRun {
+    scenario: Scenario,
+    current: usize,
+}
+
+impl ScenarioRun {
+    /// Start `scenario` at its first step, `None` if it has no steps
+    pub fn start(scenario: Scenario) -> Option<Self> {
+        (!scenario.steps.is_empty()).then_some(Self {
+            scenario,
+            current: 0,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.scenario.name
+    }
+
+    pub fn step(&self) -> &ScenarioStep {
+        &self.scenario.steps[self.current]
+    }
+
+    /// Current step as e.g. `2/3 logged-in`
+    pub fn describe_step(&self) -> String {
+        format!(
+            "{}/{} {}",
+            self.current + 1,
+            self.scenario.steps.len(),
+            self.step().name
+        )
+    }
+
+    /// Move to the next step, returns false if already at the last one
+    pub fn advance(&mut self) -> bool {
+        if self.current + 1 >= self.scenario.steps.len() {
+            return false;
+        }
+        self.current += 1;
+        true
+    }
+
+    pub fn reset(&mut self) {
+        self.current = 0;
+    }
+
+    /// Endpoint of the current step answering a request, see [`EndpointStore::lookup_request`]
+    pub fn lookup(
+        &self,
+        method: &Method,
+        path: &str,
+        request: &RequestInfo,
+        call: impl Fn(&str) -> u64,
+    ) -> Option<(String, &Stub)> {
+        self.step()
+            .endpoints
+            .lookup_request(method, path, request, call)
+    }
+
+    /// Advance if a request to `path` triggers the current step, returns whether it did
+    pub fn observe(&mut self, method: &Method, path: &str) -> bool {
+        self.step().triggers.lookup(method, path).is_some() && self.advance()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn step(name: &str, body: &str, trigger: Option<(Method, &str)>) -> ScenarioStep {
+        let mut step = ScenarioStep {
+            name: name.to_owned(),
+            ..Default::default()
+        };
+        step.endpoints.add(Method::GET, "/profile", body);
+        if let Some((method, path)) = trigger {
+            step.triggers.add(method, path, "");
+        }
+        step
+    }
+
+    #[test]
+    fn test_trigger_advances_to_next_step() {
+        let mut run = ScenarioRun::start(Scenario {
+            name: "login".to_owned(),
+            steps: vec![
+                step("logged-out", "401", Some((Method::POST, "/login"))),
+                step("logged-in", "alice", None),
+            ],
+        })
+        .unwrap();
+        let profile = |run: &ScenarioRun| {
+            let (_, stub) = run
+                .lookup(&Method::GET, "/profile", &RequestInfo::default(), |_| 1)
+                .unwrap();
+            stub.body.clone()
+        };
+
+        assert_eq!(profile(&run).as_ref(), b"401");
+        assert!(!run.observe(&Method::GET, "/login"));
+        assert!(run.observe(&Method::POST, "/login"));
+        assert_eq!(profile(&run).as_ref(), b"alice");
+        assert_eq!(run.describe_step(), "2/2 logged-in");
+        assert!(!run.advance());
+        run.reset();
+        assert_eq!(profile(&run).as_ref(), b"401");
+    }
+
+    #[test]
+    fn test_scenario_without_steps_does_not_start() {
+        assert!(ScenarioRun::start(Scenario::default()).is_none());
+    }
+}
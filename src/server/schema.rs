@@ -0,0 +1,107 @@
+use std::{fmt, fs, path::PathBuf, str::FromStr, sync::Arc};
+
+use jsonschema::Validator;
+use serde_json::Value;
+
+/// A JSON Schema the request bodies of an endpoint have to satisfy, loaded from a file. Compares
+/// by the path of the file.
+#[derive(Debug, Clone)]
+pub struct RequestSchema {
+    path: PathBuf,
+    schema: Arc<Value>,
+    validator: Arc<Validator>,
+}
+
+impl RequestSchema {
+    fn compile(path: PathBuf, schema: &Value) -> Result<Self, String> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|e| format!("`{}` is not a valid JSON Schema: {}", path.display(), e))?;
+        Ok(Self {
+            path,
+            schema: Arc::new(schema.clone()),
+            validator: Arc::new(validator),
+        })
+    }
+
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    /// How `body` violates the schema, e.g. `/age: "x" is not of type "integer"`, empty if it
+    /// doesn't
+    pub fn violations(&self, body: &[u8]) -> Vec<String> {
+        let json = match serde_json::from_slice::<Value>(body) {
+            Ok(json) => json,
+            Err(e) => return vec![format!("body is not JSON: {}", e)],
+        };
+        self.validator
+            .iter_errors(&json)
+            .map(|error| match error.instance_path().as_str() {
+                "" => format!("body: {}", error),
+                path => format!("{}: {}", path, error),
+            })
+            .collect()
+    }
+}
+
+/// The path of the schema file
+impl fmt::Display for RequestSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())
+    }
+}
+
+/// Reads and compiles the schema file at the path
+impl FromStr for RequestSchema {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let content =
+            fs::read_to_string(input).map_err(|e| format!("can't read `{}`: {}", input, e))?;
+        let schema = serde_json::from_str(&content)
+            .map_err(|e| format!("`{}` is not JSON: {}", input, e))?;
+        Self::compile(PathBuf::from(input), &schema)
+    }
+}
+
+impl PartialEq for RequestSchema {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_violations() {
+        let schema = RequestSchema::compile(
+            PathBuf::from("user.schema.json"),
+            &json!({
+                "type": "object",
+                "required": ["name"],
+                "properties": {"age": {"type": "integer"}}
+            }),
+        )
+        .unwrap();
+
+        assert!(
+            schema
+                .violations(br#"{"name": "Ada", "age": 36}"#)
+                .is_empty()
+        );
+        assert_eq!(
+            schema.violations(br#"{"age": "x"}"#),
+            [
+                r#"body: "name" is a required property"#,
+                r#"/age: "x" is not of type "integer""#
+            ]
+        );
+        assert_eq!(schema.violations(b"{").len(), 1);
+        assert!(RequestSchema::compile(PathBuf::new(), &json!({"type": 1})).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use actix_web::http::Method;
+use serde::Serialize;
+
+/// Where an endpoint is defined, so editors can jump from a request to the definition answering
+/// it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Definition {
+    pub file: PathBuf,
+    /// Counting from 1
+    pub line: usize,
+}
+
+/// Definitions of the endpoints loaded from files, by method and path as registered. Endpoints
+/// added by command have none, and lose the one of a file they replace.
+#[derive(Debug, Default)]
+pub struct Definitions {
+    by_endpoint: HashMap<(Method, String), Definition>,
+}
+
+impl Definitions {
+    pub fn record(&mut self, method: Method, path: &str, definition: Definition) {
+        self.by_endpoint.insert((method, key(path)), definition);
+    }
+
+    pub fn get(&self, method: &Method, path: &str) -> Option<&Definition> {
+        self.by_endpoint.get(&(method.clone(), key(path)))
+    }
+
+    pub fn remove(&mut self, method: &Method, path: &str) {
+        self.by_endpoint.remove(&(method.clone(), key(path)));
+    }
+}
+
+/// e.g. `/search` for `search/?q=rust`, the way the endpoint store registers paths
+fn key(path: &str) -> String {
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+    format!("/{}", path.trim_matches('/'))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definitions() {
+        let mut definitions = Definitions::default();
+        let definition = Definition {
+            file: PathBuf::from("users.yaml"),
+            line: 4,
+        };
+        definitions.record(Method::GET, "users/{id}/?full=true", definition.clone());
+
+        assert_eq!(
+            definitions.get(&Method::GET, "/users/{id}"),
+            Some(&definition)
+        );
+        assert_eq!(definitions.get(&Method::POST, "/users/{id}"), None);
+        definitions.remove(&Method::GET, "/users/{id}");
+        assert_eq!(definitions.get(&Method::GET, "/users/{id}"), None);
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    convert::Infallible,
+    fmt,
+    future::Future,
+    num::NonZeroU64,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_web::{
+    HttpResponse,
+    body::{BodySize, MessageBody},
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    rt::time::{Sleep, sleep},
+    web::Bytes,
+};
+
+use crate::util::units::{format_time, parse_duration};
+
+/// Server-Sent Events sent instead of a plain body, each carrying the stub body as its data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventStream {
+    /// Time between events, the first one is sent right away
+    pub interval: Duration,
+    /// Number of events before the stream ends, `None` to send events until the client leaves
+    pub count: Option<NonZeroU64>,
+}
+
+impl EventStream {
+    /// A `text/event-stream` response emitting `data` on schedule
+    pub fn respond(&self, data: Bytes) -> HttpResponse {
+        HttpResponse::Ok()
+            .insert_header((CONTENT_TYPE, "text/event-stream"))
+            .insert_header((CACHE_CONTROL, "no-cache"))
+            .body(EventBody {
+                data,
+                stream: *self,
+                sent: 0,
+                next: None,
+            })
+    }
+}
+
+/// e.g. `1s` or `1s x10`
+impl fmt::Display for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_time(self.interval))?;
+        if let Some(count) = self.count {
+            write!(f, " x{}", count)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EventStream {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (interval, count) = match input.trim().split_once(" x") {
+            Some((interval, count)) => {
+                let count = count
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a positive event count", count.trim()))?;
+                (interval, Some(count))
+            }
+            None => (input, None),
+        };
+        Ok(Self {
+            interval: parse_duration(interval.trim())?,
+            count,
+        })
+    }
+}
+
+/// Frame one event, e.g. `id: 1\ndata: {"tick":1}\n\n`. Every line of `data` becomes a data line,
+/// so multi-line bodies arrive intact.
+fn frame(id: u64, data: &[u8]) -> Bytes {
+    let mut event = format!("id: {}\n", id).into_bytes();
+    for line in data.split(|b| *b == b'\n') {
+        event.extend_from_slice(b"data: ");
+        event.extend_from_slice(line);
+        event.push(b'\n');
+    }
+    event.push(b'\n');
+    Bytes::from(event)
+}
+
+/// Emits an event, then waits for the interval before the next one
+struct EventBody {
+    data: Bytes,
+    stream: EventStream,
+    sent: u64,
+    /// Delay before the next event, `None` if it is due
+    next: Option<Pin<Box<Sleep>>>,
+}
+
+impl MessageBody for EventBody {
+    type Error = Infallible;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let body = self.get_mut();
+        if body
+            .stream
+            .count
+            .is_some_and(|count| body.sent >= count.get())
+        {
+            return Poll::Ready(None);
+        }
+        if let Some(next) = &mut body.next
+            && next.as_mut().poll(cx).is_pending()
+        {
+            return Poll::Pending;
+        }
+        body.sent += 1;
+        body.next = Some(Box::pin(sleep(body.stream.interval)));
+        Poll::Ready(Some(Ok(frame(body.sent, &body.data))))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::body;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_event_stream() {
+        let stream: EventStream = "1s x10".parse().unwrap();
+        assert_eq!(stream.interval, Duration::from_secs(1));
+        assert_eq!(stream.count, NonZeroU64::new(10));
+        assert_eq!(stream.to_string(), "1s x10");
+        assert_eq!("250ms".parse::<EventStream>().unwrap().count, None);
+        assert!("1s x0".parse::<EventStream>().is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_emits_counted_events() {
+        let stream: EventStream = "1ms x2".parse().unwrap();
+        let response = stream.respond(Bytes::from("a\nb"));
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        let bytes = body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            bytes.as_ref(),
+            b"id: 1\ndata: a\ndata: b\n\nid: 2\ndata: a\ndata: b\n\n"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::{BTreeMap, HashMap};
+
+use actix_web::http::{Method, StatusCode};
+use chrono::{DateTime, Local, Timelike};
+
+/// Usage of a single registered endpoint
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndpointStats {
+    pub hits: u64,
+    pub last_hit: Option<DateTime<Local>>,
+    /// Number of responses by status code
+    pub statuses: BTreeMap<u16, u64>,
+    /// Number of requests violating the endpoint's assertions
+    pub failures: u64,
+}
+
+impl EndpointStats {
+    /// e.g. `3 hits, last 12:01:02`
+    pub fn summary(&self) -> String {
+        match self.last_hit {
+            Some(last_hit) => format!(
+                "{} hit{}, last {}",
+                self.hits,
+                if self.hits == 1 { "" } else { "s" },
+                last_hit.format("%H:%M:%S")
+            ),
+            None => "never hit".to_owned(),
+        }
+    }
+
+    /// e.g. `200 ×5, 503 ×1`
+    pub fn status_distribution(&self) -> String {
+        self.statuses
+            .iter()
+            .map(|(status, count)| format!("{} ×{}", status, count))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Usage of all endpoints, keyed by method and path as registered, e.g. `/users/{id}`
+#[derive(Debug, Default)]
+pub struct EndpointStatsTable {
+    stats: HashMap<(Method, String), EndpointStats>,
+    /// Hits of all endpoints by local hour of the day
+    hourly: [u64; 24],
+}
+
+impl EndpointStatsTable {
+    pub fn record(&mut self, method: &Method, path: &str, status: StatusCode) {
+        let stats = self
+            .stats
+            .entry((method.clone(), path.to_owned()))
+            .or_default();
+        let now = Local::now();
+        stats.hits += 1;
+        stats.last_hit = Some(now);
+        *stats.statuses.entry(status.as_u16()).or_default() += 1;
+        self.hourly[now.hour() as usize] += 1;
+    }
+
+    pub fn record_failure(&mut self, method: &Method, path: &str) {
+        self.stats
+            .entry((method.clone(), path.to_owned()))
+            .or_default()
+            .failures += 1;
+    }
+
+    pub fn get(&self, method: &Method, path: &str) -> Option<&EndpointStats> {
+        self.stats.get(&(method.clone(), path.to_owned()))
+    }
+
+    /// Endpoints by hits, most hit first, e.g. for the usage tab
+    pub fn most_hit(&self, limit: usize) -> Vec<(&Method, &str, u64)> {
+        let mut hit: Vec<(&Method, &str, u64)> = self
+            .stats
+            .iter()
+            .filter(|(_, stats)| stats.hits > 0)
+            .map(|((method, path), stats)| (method, path.as_str(), stats.hits))
+            .collect();
+        hit.sort_by(|a, b| b.2.cmp(&a.2).then(a.1.cmp(b.1)));
+        hit.truncate(limit);
+        hit
+    }
+
+    /// Local hour of the day with the most hits, e.g. `14` for 14:00 to 15:00, along with them
+    pub fn busiest_hour(&self) -> Option<(u32, u64)> {
+        (0u32..)
+            .zip(self.hourly)
+            .filter(|(_, hits)| *hits > 0)
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+    }
+
+    /// Forget an endpoint, e.g. once it was deleted
+    pub fn remove(&mut self, method: &Method, path: &str) {
+        self.stats.remove(&(method.clone(), path.to_owned()));
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_hits() {
+        let mut table = EndpointStatsTable::default();
+        table.record(&Method::GET, "/users/{id}", StatusCode::OK);
+        table.record(&Method::GET, "/users/{id}", StatusCode::OK);
+        table.record(&Method::GET, "/users/{id}", StatusCode::SERVICE_UNAVAILABLE);
+
+        let stats = table.get(&Method::GET, "/users/{id}").unwrap();
+        assert_eq!(stats.hits, 3);
+        assert!(stats.summary().starts_with("3 hits, last "));
+        assert_eq!(stats.status_distribution(), "200 ×2, 503 ×1");
+        table.record_failure(&Method::GET, "/users/{id}");
+        assert_eq!(table.get(&Method::GET, "/users/{id}").unwrap().failures, 1);
+        assert!(table.get(&Method::POST, "/users/{id}").is_none());
+
+        table.record(&Method::POST, "/login", StatusCode::OK);
+        assert_eq!(
+            table.most_hit(5),
+            vec![
+                (&Method::GET, "/users/{id}", 3),
+                (&Method::POST, "/login", 1)
+            ]
+        );
+        assert!(table.busiest_hour().is_some());
+        assert!(EndpointStatsTable::default().busiest_hour().is_none());
+
+        table.remove(&Method::GET, "/users/{id}");
+        assert!(table.get(&Method::GET, "/users/{id}").is_none());
+    }
+}
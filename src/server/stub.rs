@@ -0,0 +1,431 @@
+use std::{fmt, num::NonZeroU32, str::FromStr, time::Duration};
+
+use actix_web::{
+    HttpResponse,
+    cookie::Cookie,
+    http::{
+        StatusCode,
+        header::{HeaderName, HeaderValue, LOCATION, SET_COOKIE},
+    },
+    web::Bytes,
+};
+
+use crate::{
+    server::{
+        Assertion, Callback, ContinueBehavior, Drip, ETag, EventStream, Flaky, Framing, Hangup,
+        HeaderOrder, Hook, Matcher, Overlay, Pagination, RawResponse, Representation, RequestInfo,
+        RequestSchema, ScheduledFault, Transform, parse_header_line, parse_status,
+    },
+    util::units::{format_size, format_time},
+};
+
+/// Response configuration of a single endpoint
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stub {
+    pub body: Bytes,
+    /// What the endpoint is for, shown by `endpoint inspect` and `export markdown`
+    pub description: Option<String>,
+    /// Status of the response, `None` for 200
+    pub status: Option<StatusCode>,
+    /// Added to the response in order, replacing headers of the same name set by the server.
+    /// Names may repeat, e.g. for several `Set-Cookie` headers, so this is a list, not a map.
+    pub headers: Vec<ResponseHeader>,
+    /// Order and spelling of header names in the response, which actix otherwise writes
+    /// lowercase in no particular order
+    pub header_order: Option<HeaderOrder>,
+    pub budget: Budget,
+    /// Candidates for the same endpoint are tried from the highest priority down
+    pub priority: i32,
+    /// Conditions a request must meet for this candidate to answer it
+    pub matchers: Vec<Matcher>,
+    /// Makes this candidate a variant: of the variants of equal rank each request picks one at
+    /// random, in proportion to their weights, e.g. 90 for a success and 10 for an error. They
+    /// take precedence over candidates of the same rank without a weight.
+    pub weight: Option<NonZeroU32>,
+    /// Faults hit by a share of the requests, to exercise client retries
+    pub flaky: Vec<Flaky>,
+    /// Faults hit during a time window or on every nth request
+    pub scheduled: Vec<ScheduledFault>,
+    /// Expectations on incoming requests, violations are recorded but still answered
+    pub assertions: Vec<Assertion>,
+    /// Send the body as Server-Sent Events instead of once
+    pub events: Option<EventStream>,
+    /// Stream the body in chunks with delays between them instead of at once
+    pub drip: Option<Drip>,
+    /// Answer with a redirect instead of 200
+    pub redirect: Option<Redirect>,
+    /// Whether to compress the response for clients accepting it, `None` to follow the server
+    pub compress: Option<bool>,
+    /// How to answer `Expect: 100-continue`, `None` to continue right away
+    pub expect: Option<ContinueBehavior>,
+    /// Answer with `Connection: close`, so clients need a new connection for the next request
+    pub close: bool,
+    /// Whether the body is sent with `Content-Length` or chunked, `None` to leave it to actix
+    pub framing: Option<Framing>,
+    /// Schema request bodies have to satisfy, others are answered with 400
+    pub request_schema: Option<RequestSchema>,
+    /// Bytes written to the socket instead of a response, the body is not sent
+    pub raw: Option<RawResponse>,
+    /// Leave requests unanswered instead, the body is not sent
+    pub hangup: Option<Hangup>,
+    /// Bodies by content type, sent instead of the body to clients accepting them. Requests
+    /// accepting none are answered with 406.
+    pub representations: Vec<Representation>,
+    /// ETag sent with the body, requests with it in `If-None-Match` are answered with 304
+    pub etag: Option<ETag>,
+    /// Answer GET requests with a `Range` header with the part of the body they ask for
+    pub ranges: bool,
+    /// Serve the body, a JSON array, as pages picked by the query
+    pub paging: Option<Pagination>,
+    /// Actions run before and after every response, e.g. counting a metric
+    pub hooks: Vec<Hook>,
+    /// Request sent some time after every request the stub answers, e.g. a webhook
+    pub callback: Option<Callback>,
+    /// Steps the body passes through when served, in order
+    pub transforms: Vec<Transform>,
+    /// Patches applied to the body while their profile is active, before `transforms`
+    pub overlays: Vec<Overlay>,
+}
+
+impl Stub {
+    /// Whether the stub may answer `request`
+    pub fn matches(&self, request: &RequestInfo) -> bool {
+        self.matchers.iter().all(|matcher| matcher.matches(request))
+    }
+
+    /// Whether `other` would take the place of this candidate, having the same priority and
+    /// matchers. Variants only take the place of the variant with the same response, so that
+    /// several can be added.
+    pub fn same_slot(&self, other: &Stub) -> bool {
+        let sorted = |stub: &Stub| {
+            let mut matchers = stub.matchers.clone();
+            matchers.sort();
+            matchers
+        };
+        let same_variant = match (self.weight, other.weight) {
+            (None, None) => true,
+            (Some(_), Some(_)) => self.status == other.status && self.body == other.body,
+            _ => false,
+        };
+        self.priority == other.priority && sorted(self) == sorted(other) && same_variant
+    }
+
+    /// Candidates are tried from the highest rank down: by priority, then by number of matchers
+    pub fn rank(&self) -> (i32, usize) {
+        (self.priority, self.matchers.len())
+    }
+
+    pub fn response_status(&self) -> StatusCode {
+        self.status.unwrap_or(StatusCode::OK)
+    }
+
+    /// Add the headers to `response`, replacing those of the same name it already has
+    pub fn add_headers(&self, response: &mut HttpResponse) {
+        let headers = response.headers_mut();
+        for header in &self.headers {
+            headers.remove(&header.name);
+        }
+        for header in &self.headers {
+            headers.append(header.name.clone(), header.value.clone());
+        }
+    }
+
+    /// Short descriptions of all settings besides the body, e.g. `priority 1`
+    pub fn options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if let Some(status) = self.status {
+            options.push(format!("status {}", status.as_u16()));
+        }
+        options.extend(
+            self.headers
+                .iter()
+                .map(|header| format!("header {}", header)),
+        );
+        if let Some(order) = &self.header_order {
+            options.push(format!("header order {}", order));
+        }
+        if self.priority != 0 {
+            options.push(format!("priority {}", self.priority));
+        }
+        if let Some(weight) = self.weight {
+            options.push(format!("weight {}", weight));
+        }
+        if let Some(events) = self.events {
+            options.push(format!("sse {}", events));
+        }
+        if let Some(raw) = &self.raw {
+            options.push(format!("raw {}", raw));
+        }
+        if let Some(redirect) = &self.redirect {
+            options.push(format!("redirect {}", redirect));
+        }
+        if let Some(compress) = self.compress {
+            options.push(format!("compress {}", if compress { "on" } else { "off" }));
+        }
+        if let Some(expect) = self.expect {
+            options.push(format!("expect {}", expect));
+        }
+        if self.close {
+            options.push("close".to_owned());
+        }
+        if let Some(hangup) = self.hangup {
+            options.push(hangup.to_string());
+        }
+        if !self.representations.is_empty() {
+            let types: Vec<String> = self
+                .representations
+                .iter()
+                .map(|representation| representation.content_type.to_string())
+                .collect();
+            options.push(format!("negotiate {}", types.join(", ")));
+        }
+        if let Some(etag) = &self.etag {
+            options.push(format!("etag {}", etag));
+        }
+        if self.ranges {
+            options.push("ranges".to_owned());
+        }
+        if let Some(paging) = self.paging {
+            options.push(format!("paged {}", paging));
+        }
+        if let Some(framing) = self.framing {
+            options.push(format!("framing {}", framing));
+        }
+        if let Some(schema) = &self.request_schema {
+            options.push(format!("schema {}", schema));
+        }
+        if let Some(drip) = self.drip {
+            options.push(format!("drip {}", drip));
+        }
+        options.extend(
+            self.matchers
+                .iter()
+                .map(|matcher| format!("match {}", matcher)),
+        );
+        options.extend(self.hooks.iter().map(|hook| format!("hook {}", hook)));
+        if !self.transforms.is_empty() {
+            let steps: Vec<String> = self.transforms.iter().map(Transform::to_string).collect();
+            options.push(format!("transform {}", steps.join(" -> ")));
+        }
+        options.extend(
+            self.overlays
+                .iter()
+                .map(|overlay| format!("overlay {}", overlay)),
+        );
+        if let Some(callback) = &self.callback {
+            options.push(format!("callback {}", callback.describe()));
+        }
+        if let Some(max_size) = self.budget.max_size {
+            options.push(format!("max size {}", format_size(max_size)));
+        }
+        if let Some(max_time) = self.budget.max_time {
+            options.push(format!("max time {}", format_time(max_time)));
+        }
+        options.extend(self.flaky.iter().map(|flaky| format!("flaky {}", flaky)));
+        options.extend(self.scheduled.iter().map(|rule| format!("rule {}", rule)));
+        options.extend(
+            self.assertions
+                .iter()
+                .map(|assertion| format!("assert {}", assertion)),
+        );
+        options
+    }
+}
+
+impl From<Bytes> for Stub {
+    fn from(body: Bytes) -> Self {
+        Self {
+            body,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<String> for Stub {
+    fn from(body: String) -> Self {
+        Bytes::from(body).into()
+    }
+}
+
+impl From<&str> for Stub {
+    fn from(body: &str) -> Self {
+        Bytes::copy_from_slice(body.as_bytes()).into()
+    }
+}
+
+/// Limits a response should stay within to remain realistic. Exceeding them only logs a warning.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Budget {
+    /// Maximum response body size in bytes
+    pub max_size: Option<usize>,
+    /// Maximum time from receiving the request to having the response ready
+    pub max_time: Option<Duration>,
+}
+
+impl Budget {
+    /// Describe every limit exceeded by a response of `size` bytes that took `elapsed`
+    pub fn violations(&self, size: usize, elapsed: Duration) -> Vec<String> {
+        let mut violations = Vec::new();
+        if let Some(max_size) = self.max_size.filter(|max| size > *max) {
+            violations.push(format!("size {} B > {} B", size, max_size));
+        }
+        if let Some(max_time) = self.max_time.filter(|max| elapsed > *max) {
+            violations.push(format!("time {:?} > {:?}", elapsed, max_time));
+        }
+        violations
+    }
+}
+
+/// A header of the response, e.g. `Cache-Control: no-store`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseHeader {
+    pub name: HeaderName,
+    pub value: HeaderValue,
+}
+
+impl fmt::Display for ResponseHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}",
+            self.name,
+            String::from_utf8_lossy(self.value.as_bytes())
+        )
+    }
+}
+
+impl FromStr for ResponseHeader {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (name, value) = parse_header_line(input)?;
+        Ok(Self { name, value })
+    }
+}
+
+/// Parse a cookie to set such as `session=abc; HttpOnly; Path=/` into a `Set-Cookie` header
+pub fn parse_set_cookie(input: &str) -> Result<ResponseHeader, String> {
+    let input = input.trim();
+    let cookie = Cookie::parse(input).map_err(|_| {
+        format!(
+            "`{}` is not a cookie such as `session=abc; HttpOnly`",
+            input
+        )
+    })?;
+    if cookie.name().is_empty() {
+        return Err(format!("`{}` has no cookie name", input));
+    }
+    Ok(ResponseHeader {
+        name: SET_COOKIE,
+        value: HeaderValue::try_from(input)
+            .map_err(|_| format!("`{}` is not a valid header value", input))?,
+    })
+}
+
+/// A redirect status with the `Location` to go to, e.g. `302 /new-path`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub status: StatusCode,
+    pub location: HeaderValue,
+}
+
+impl Redirect {
+    pub fn respond(&self, body: Bytes) -> HttpResponse {
+        HttpResponse::build(self.status)
+            .insert_header((LOCATION, self.location.clone()))
+            .body(body)
+    }
+}
+
+impl fmt::Display for Redirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            self.status.as_u16(),
+            String::from_utf8_lossy(self.location.as_bytes())
+        )
+    }
+}
+
+impl FromStr for Redirect {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (status, location) = input
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| format!("`{}` is not of the form `302 /new-path`", input))?;
+        let status = parse_status(status)?;
+        if !status.is_redirection() {
+            return Err(format!("{} is not a redirect status", status.as_u16()));
+        }
+        let location = HeaderValue::from_str(location.trim())
+            .map_err(|_| format!("`{}` is not a valid location", location.trim()))?;
+        Ok(Self { status, location })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_violations() {
+        let budget = Budget {
+            max_size: Some(10),
+            max_time: Some(Duration::from_millis(200)),
+        };
+        assert!(budget.violations(10, Duration::from_millis(200)).is_empty());
+        assert_eq!(
+            budget.violations(11, Duration::from_millis(250)),
+            ["size 11 B > 10 B", "time 250ms > 200ms"]
+        );
+        assert!(
+            Budget::default()
+                .violations(usize::MAX, Duration::MAX)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_add_headers() {
+        let stub = Stub {
+            headers: vec![
+                "Content-Type: text/csv".parse().unwrap(),
+                "Link: </a>".parse().unwrap(),
+                "Link: </b>".parse().unwrap(),
+            ],
+            ..Stub::default()
+        };
+        let mut response = HttpResponse::Ok().content_type("text/plain").finish();
+        stub.add_headers(&mut response);
+        let headers = response.headers();
+        assert_eq!(headers.get("content-type").unwrap(), "text/csv");
+        assert_eq!(headers.get_all("link").count(), 2);
+        assert_eq!(stub.headers[1].to_string(), "link: </a>");
+    }
+
+    #[test]
+    fn test_parse_set_cookie() {
+        assert_eq!(
+            parse_set_cookie("session=abc; HttpOnly; Path=/")
+                .unwrap()
+                .to_string(),
+            "set-cookie: session=abc; HttpOnly; Path=/"
+        );
+        assert!(parse_set_cookie("=abc").is_err());
+        assert!(parse_set_cookie("HttpOnly").is_err());
+    }
+
+    #[test]
+    fn test_parse_redirect() {
+        let redirect: Redirect = "302 /new-path".parse().unwrap();
+        assert_eq!(redirect.status, StatusCode::FOUND);
+        assert_eq!(redirect.location, "/new-path");
+        assert_eq!(redirect.to_string(), "302 /new-path");
+        assert!("200 /new-path".parse::<Redirect>().is_err());
+        assert!("302".parse::<Redirect>().is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex, PoisonError, mpsc},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::server::{RunningServer, ServerState, run_server};
+
+/// How often the watchdog pings the health endpoint
+const PING_INTERVAL: Duration = Duration::from_secs(2);
+/// Timeout for connecting to and reading from the health endpoint
+const PING_TIMEOUT: Duration = Duration::from_secs(1);
+/// The server counts as unresponsive if no ping succeeded for this long
+const UNRESPONSIVE_AFTER: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerHealth {
+    /// Server thread is running but has not answered a ping yet
+    Starting,
+    Up,
+    /// Server thread is alive but did not answer pings for a while, e.g. due to a deadlock
+    Unresponsive,
+    /// Server thread has exited
+    Down(String),
+}
+
+#[derive(Debug, Default)]
+struct PingStatus {
+    last_ok: Option<Instant>,
+}
+
+/// Owns the server thread and a watchdog pinging it, so the TUI knows whether the server is alive
+/// and can restart it
+#[derive(Debug)]
+pub struct ServerSupervisor {
+    state: Arc<ServerState>,
+    addr: String,
+    thread: Option<JoinHandle<io::Result<()>>>,
+    running: Option<RunningServer>,
+    running_rx: Option<mpsc::Receiver<RunningServer>>,
+    started: Instant,
+    /// Why the server thread exited, kept until the next restart
+    down_reason: Option<String>,
+    ping: Arc<Mutex<PingStatus>>,
+}
+
+impl ServerSupervisor {
+    /// Start the server on `addr` and a watchdog thread pinging it
+    pub fn start(state: Arc<ServerState>, addr: &str) -> io::Result<Self> {
+        let ping_addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::other(format!("Invalid address: {}", addr)))?;
+        let ping = Arc::new(Mutex::new(PingStatus::default()));
+        let watchdog_ping = ping.clone();
+        thread::spawn(move || {
+            loop {
+                if ping_health(ping_addr).is_ok() {
+                    watchdog_ping
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .last_ok = Some(Instant::now());
+                }
+                thread::sleep(PING_INTERVAL);
+            }
+        });
+        let mut supervisor = Self {
+            state,
+            addr: addr.to_owned(),
+            thread: None,
+            running: None,
+            running_rx: None,
+            started: Instant::now(),
+            down_reason: None,
+            ping,
+        };
+        supervisor.spawn_server();
+        Ok(supervisor)
+    }
+
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// The currently running server, once it has been bound
+    pub fn running(&self) -> Option<&RunningServer> {
+        self.running.as_ref()
+    }
+
+    fn spawn_server(&mut self) {
+        let (running_tx, running_rx) = mpsc::channel();
+        let state = self.state.clone();
+        let addr = self.addr.clone();
+        self.thread = Some(thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(run_server(state, &addr, running_tx))
+        }));
+        self.running_rx = Some(running_rx);
+        self.running = None;
+        self.started = Instant::now();
+        self.down_reason = None;
+        self.ping
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .last_ok = None;
+    }
+
+    pub fn health(&mut self) -> ServerHealth {
+        if let Some(running) = self.running_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.running = Some(running);
+            self.running_rx = None;
+        }
+        if let Some(thread) = self.thread.take_if(|t| t.is_finished()) {
+            self.down_reason = Some(match thread.join() {
+                Ok(Err(e)) => e.to_string(),
+                Ok(Ok(())) => "server stopped".to_owned(),
+                Err(_) => "server thread panicked".to_owned(),
+            });
+        }
+        if let Some(reason) = &self.down_reason {
+            return ServerHealth::Down(reason.clone());
+        }
+        let last_ok = self
+            .ping
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .last_ok;
+        match last_ok {
+            Some(t) if t.elapsed() < UNRESPONSIVE_AFTER => ServerHealth::Up,
+            None if self.started.elapsed() < UNRESPONSIVE_AFTER => ServerHealth::Starting,
+            _ => ServerHealth::Unresponsive,
+        }
+    }
+
+    /// Stop the current server (if it still reacts) and start a fresh one on the same address
+    pub fn restart(&mut self) {
+        if let Some(running) = self.running.take() {
+            // Sending the stop command happens eagerly, the returned future only awaits completion
+            drop(running.handle.stop(false));
+        }
+        if let Some(thread) = self.thread.take() {
+            let deadline = Instant::now() + PING_TIMEOUT;
+            while !thread.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(50));
+            }
+            if !thread.is_finished() {
+                log::warn!("Old server thread did not stop, abandoning it");
+            }
+        }
+        log::info!("Restarting server on {}", self.addr);
+        self.spawn_server();
+    }
+}
+
+/// Request the health endpoint over a plain TCP connection
+fn ping_health(addr: SocketAddr) -> io::Result<()> {
+    let mut stream = TcpStream::connect_timeout(&addr, PING_TIMEOUT)?;
+    stream.set_read_timeout(Some(PING_TIMEOUT))?;
+    stream.set_write_timeout(Some(PING_TIMEOUT))?;
+    write!(
+        stream,
+        "GET /api/health HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        addr
+    )?;
+    let mut status_line = [0; 12];
+    stream.read_exact(&mut status_line)?;
+    if status_line.ends_with(b" 200") {
+        Ok(())
+    } else {
+        Err(io::Error::other("health check failed"))
+    }
+}
@@ -0,0 +1,192 @@
+use std::{collections::BTreeMap, fmt, num::NonZeroUsize, str::FromStr};
+
+use actix_web::http::{
+    Method,
+    header::{HeaderMap, HeaderName},
+};
+
+use crate::server::{EndpointStore, RequestInfo, Stub};
+
+/// Where requests name their tenant
+#[derive(Debug, Clone, PartialEq)]
+pub enum TenantSource {
+    /// A header, in lowercase, e.g. `x-tenant-id`
+    Header(String),
+    /// The nth path segment. It and the segments before it are a tenant prefix that is stripped
+    /// before matching, e.g. segment 2 of `/tenants/acme/users` is `acme` for `/users`.
+    Segment(NonZeroUsize),
+}
+
+impl TenantSource {
+    /// The tenant a request names and the path to match, `None` and the unchanged path if it
+    /// names none
+    pub fn identify<'a>(&self, path: &'a str, headers: &HeaderMap) -> (Option<String>, &'a str) {
+        match self {
+            TenantSource::Header(name) => {
+                let tenant = headers
+                    .get(name.as_str())
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::trim)
+                    .filter(|tenant| !tenant.is_empty())
+                    .map(str::to_owned);
+                (tenant, path)
+            }
+            TenantSource::Segment(n) => {
+                let mut rest = path;
+                for _ in 1..n.get() {
+                    match rest.trim_start_matches('/').split_once('/') {
+                        Some((_, after)) => rest = after,
+                        None => return (None, path),
+                    }
+                }
+                let rest = rest.trim_start_matches('/');
+                let (tenant, after) = match rest.find('/') {
+                    Some(end) => (&rest[..end], &rest[end..]),
+                    None => (rest, "/"),
+                };
+                if tenant.is_empty() {
+                    return (None, path);
+                }
+                (Some(tenant.to_owned()), after)
+            }
+        }
+    }
+}
+
+impl fmt::Display for TenantSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TenantSource::Header(name) => write!(f, "header:{}", name),
+            TenantSource::Segment(n) => write!(f, "segment:{}", n),
+        }
+    }
+}
+
+impl FromStr for TenantSource {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.split_once(':') {
+            Some(("header", name)) => HeaderName::try_from(name.trim())
+                .map(|name| TenantSource::Header(name.as_str().to_owned()))
+                .map_err(|_| format!("`{}` is not a valid header name", name.trim())),
+            Some(("segment", n)) => n
+                .trim()
+                .parse()
+                .map(TenantSource::Segment)
+                .map_err(|_| format!("`{}` is not a segment number starting at 1", n.trim())),
+            _ => Err(format!(
+                "`{}` is not of the form `header:X-Tenant-Id` or `segment:2`",
+                input
+            )),
+        }
+    }
+}
+
+/// Per-tenant endpoints overriding the regular ones, which remain the defaults of every tenant
+#[derive(Debug, Default)]
+pub struct Tenancy {
+    /// Requests name no tenant while unset
+    pub source: Option<TenantSource>,
+    overrides: BTreeMap<String, EndpointStore>,
+}
+
+impl Tenancy {
+    /// See [`TenantSource::identify`]
+    pub fn identify<'a>(&self, path: &'a str, headers: &HeaderMap) -> (Option<String>, &'a str) {
+        match &self.source {
+            Some(source) => source.identify(path, headers),
+            None => (None, path),
+        }
+    }
+
+    /// Endpoint of `tenant` overriding the default for a request, see
+    /// [`EndpointStore::lookup_request`]
+    pub fn lookup(
+        &self,
+        tenant: &str,
+        method: &Method,
+        path: &str,
+        request: &RequestInfo,
+        call: impl Fn(&str) -> u64,
+    ) -> Option<(String, &Stub)> {
+        self.overrides
+            .get(tenant)?
+            .lookup_request(method, path, request, call)
+    }
+
+    /// Endpoints of `tenant`, created empty if it has none yet
+    pub fn endpoints(&mut self, tenant: &str) -> &mut EndpointStore {
+        self.overrides.entry(tenant.to_owned()).or_default()
+    }
+
+    /// Tenants with overrides, in alphabetical order
+    pub fn tenants(&self) -> impl Iterator<Item = (&str, &EndpointStore)> {
+        self.overrides
+            .iter()
+            .map(|(tenant, endpoints)| (tenant.as_str(), endpoints))
+    }
+
+    /// Remove the overrides of `tenant`, returns false if it had none
+    pub fn remove(&mut self, tenant: &str) -> bool {
+        self.overrides.remove(tenant).is_some()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identify_tenant() {
+        let mut headers = HeaderMap::new();
+        let by_header: TenantSource = "header:X-Tenant-Id".parse().unwrap();
+        assert_eq!(by_header.to_string(), "header:x-tenant-id");
+        assert_eq!(by_header.identify("/users", &headers), (None, "/users"));
+        headers.insert(
+            HeaderName::from_static("x-tenant-id"),
+            "acme".parse().unwrap(),
+        );
+        assert_eq!(
+            by_header.identify("/users", &headers),
+            (Some("acme".to_owned()), "/users")
+        );
+
+        let by_segment: TenantSource = "segment:2".parse().unwrap();
+        assert_eq!(
+            by_segment.identify("/tenants/acme/users/1", &headers),
+            (Some("acme".to_owned()), "/users/1")
+        );
+        assert_eq!(
+            by_segment.identify("/tenants/acme", &headers),
+            (Some("acme".to_owned()), "/")
+        );
+        assert_eq!(
+            by_segment.identify("/tenants", &headers),
+            (None, "/tenants")
+        );
+        assert!("segment:0".parse::<TenantSource>().is_err());
+    }
+
+    #[test]
+    fn test_overrides_fall_back_to_defaults() {
+        let mut tenancy = Tenancy::default();
+        tenancy
+            .endpoints("acme")
+            .add(Method::GET, "/plan", "enterprise");
+        let request = RequestInfo::default();
+
+        let (_, stub) = tenancy
+            .lookup("acme", &Method::GET, "/plan", &request, |_| 1)
+            .unwrap();
+        assert_eq!(stub.body.as_ref(), b"enterprise");
+        assert!(
+            tenancy
+                .lookup("globex", &Method::GET, "/plan", &request, |_| 1)
+                .is_none()
+        );
+        assert!(tenancy.remove("acme"));
+        assert!(tenancy.tenants().next().is_none());
+    }
+}
@@ -0,0 +1,169 @@
+use std::{fmt, io, str::FromStr, sync::Arc};
+
+use chrono::{Datelike, Utc};
+use rcgen::{
+    BasicConstraints, CertificateParams, CertifiedIssuer, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair, KeyUsagePurpose, date_time_ymd,
+};
+use rustls::{
+    ServerConfig,
+    crypto::ring,
+    pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+};
+use tokio_rustls::TlsAcceptor;
+
+/// Host the certificates are issued for, except the wrong-host one
+const HOST: &str = "localhost";
+/// Host the wrong-host certificate is issued for
+const OTHER_HOST: &str = "wrong-host.invalid";
+
+/// The certificate presented on the TLS listener. All but the self-signed one are issued by the
+/// [`CertificateAuthority`], so clients trusting it only fail on the flaw of the certificate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CertificateKind {
+    /// Issued for localhost and currently valid, e.g. to test pinning against a fresh key
+    Valid,
+    /// Issued for localhost, but expired a year ago
+    Expired,
+    /// Issued for localhost by itself rather than the authority
+    SelfSigned,
+    /// Issued for another host than localhost
+    WrongHost,
+}
+
+impl fmt::Display for CertificateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CertificateKind::Valid => write!(f, "valid"),
+            CertificateKind::Expired => write!(f, "expired"),
+            CertificateKind::SelfSigned => write!(f, "self-signed"),
+            CertificateKind::WrongHost => write!(f, "wrong-host"),
+        }
+    }
+}
+
+impl FromStr for CertificateKind {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "valid" => Ok(CertificateKind::Valid),
+            "expired" => Ok(CertificateKind::Expired),
+            "self-signed" => Ok(CertificateKind::SelfSigned),
+            "wrong-host" => Ok(CertificateKind::WrongHost),
+            _ => Err(format!(
+                "unknown certificate `{}`, use valid, expired, self-signed or wrong-host",
+                input
+            )),
+        }
+    }
+}
+
+/// A second listener serving the endpoints over TLS, so certificate validation and pinning of
+/// clients can be exercised locally
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TlsListener {
+    pub port: u16,
+    pub certificate: CertificateKind,
+}
+
+impl TlsListener {
+    /// e.g. `Serving TLS on port 3443 with an expired certificate`
+    pub fn describe(&self) -> String {
+        let certificate = match self.certificate {
+            CertificateKind::Valid => format!("a valid certificate for {}", HOST),
+            CertificateKind::Expired => format!("an expired certificate for {}", HOST),
+            CertificateKind::SelfSigned => format!("a self-signed certificate for {}", HOST),
+            CertificateKind::WrongHost => format!("a certificate for {}", OTHER_HOST),
+        };
+        format!("Serving TLS on port {} with {}", self.port, certificate)
+    }
+}
+
+/// Issues the certificates of the TLS listener. Generated once per run, so clients keep trusting
+/// it across restarts, while every start presents a certificate with a fresh key.
+#[derive(Debug)]
+pub struct CertificateAuthority {
+    issuer: CertifiedIssuer<'static, KeyPair>,
+}
+
+impl CertificateAuthority {
+    pub fn generate() -> io::Result<Self> {
+        let mut params = CertificateParams::default();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "adaptive_rest test authority");
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+        let key = KeyPair::generate().map_err(io::Error::other)?;
+        let issuer = CertifiedIssuer::self_signed(params, key).map_err(io::Error::other)?;
+        Ok(Self { issuer })
+    }
+
+    /// The certificate of the authority, for clients to trust
+    pub fn pem(&self) -> String {
+        self.issuer.pem()
+    }
+
+    /// Accepts TLS connections presenting a new certificate of `kind`
+    pub fn acceptor(&self, kind: CertificateKind) -> io::Result<TlsAcceptor> {
+        let year = Utc::now().year();
+        let host = match kind {
+            CertificateKind::WrongHost => OTHER_HOST,
+            _ => HOST,
+        };
+        let mut names = vec![host.to_owned()];
+        if host == HOST {
+            names.push("127.0.0.1".to_owned());
+        }
+        let mut params = CertificateParams::new(names).map_err(io::Error::other)?;
+        params.distinguished_name.push(DnType::CommonName, host);
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        params.use_authority_key_identifier_extension = kind != CertificateKind::SelfSigned;
+        (params.not_before, params.not_after) = match kind {
+            CertificateKind::Expired => {
+                (date_time_ymd(year - 2, 1, 1), date_time_ymd(year - 1, 1, 1))
+            }
+            _ => (date_time_ymd(year - 1, 1, 1), date_time_ymd(year + 1, 1, 1)),
+        };
+        let key = KeyPair::generate().map_err(io::Error::other)?;
+        let certificate = match kind {
+            CertificateKind::SelfSigned => params.self_signed(&key),
+            _ => params.signed_by(&key, &self.issuer),
+        }
+        .map_err(io::Error::other)?;
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der()));
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_no_client_auth()
+            .with_single_cert(vec![certificate.der().clone()], key)
+            .map_err(io::Error::other)?;
+        // Connections are served by the HTTP/1 dispatcher only
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_certificate_kind() {
+        for name in ["valid", "expired", "self-signed", "wrong-host"] {
+            assert_eq!(name.parse::<CertificateKind>().unwrap().to_string(), name);
+        }
+        assert!("revoked".parse::<CertificateKind>().is_err());
+    }
+
+    #[test]
+    fn test_issue_certificates() {
+        let authority = CertificateAuthority::generate().unwrap();
+        assert!(authority.pem().starts_with("-----BEGIN CERTIFICATE-----"));
+        for kind in ["valid", "expired", "self-signed", "wrong-host"] {
+            assert!(authority.acceptor(kind.parse().unwrap()).is_ok());
+        }
+    }
+}
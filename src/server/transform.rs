@@ -0,0 +1,377 @@
+use std::{
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::LazyLock,
+};
+
+use actix_web::{
+    http::{Method, header::HeaderMap},
+    web::Bytes,
+};
+use chrono::Local;
+use flate2::{Compression, write::GzEncoder};
+use rand::{RngExt, seq::IndexedRandom};
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use crate::server::{RequestInfo, endpoint::is_param, jsonpath::JsonPath};
+
+/// A step of the pipeline an endpoint's body passes through when it is served
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    /// Fill in placeholders like `{{param.id}}` from the request, see [`render`]
+    Template,
+    /// Apply a JSON Patch (RFC 6902) read from a file, e.g. an overlay for one environment
+    Patch(PathBuf),
+    /// Apply a JSON Merge Patch (RFC 7396) read from a file
+    Merge(PathBuf),
+    /// Compress the body, sent with `Content-Encoding: gzip` whatever the client accepts
+    Gzip,
+}
+
+/// A patch applied to an endpoint's body only while a profile of that name is active, before
+/// the transformations, e.g. `staging=merge:./overlays/staging.json`. Lets a profile tweak a few
+/// fields of shared fixtures without copying whole bodies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlay {
+    pub profile: String,
+    /// [`Transform::Patch`] or [`Transform::Merge`]
+    pub patch: Transform,
+}
+
+/// The request a body is served for
+#[derive(Debug)]
+pub struct TransformRequest<'a> {
+    pub method: &'a Method,
+    pub path: &'a str,
+    /// Path of the endpoint as registered, e.g. `/users/{id}`, which `path` was matched against
+    pub endpoint: Option<&'a str>,
+    pub query: &'a str,
+    pub headers: &'a HeaderMap,
+    pub body: &'a [u8],
+}
+
+/// Run the steps in order on `body`. Patch files are read on every request, so editing an
+/// overlay takes effect without reloading the endpoint.
+pub fn run(steps: &[Transform], body: &Bytes, request: &TransformRequest) -> Result<Bytes, String> {
+    steps
+        .iter()
+        .try_fold(body.clone(), |body, step| match step {
+            Transform::Template => Ok(Bytes::from(render(
+                &String::from_utf8_lossy(&body),
+                request,
+            ))),
+            Transform::Patch(path) => {
+                let patch: json_patch::Patch = serde_json::from_value(read_json(path)?)
+                    .map_err(|e| format!("{} is not a JSON Patch: {}", path.display(), e))?;
+                edit_json(&body, |json| {
+                    json_patch::patch(json, &patch)
+                        .map_err(|e| format!("{} does not apply: {}", path.display(), e))
+                })
+            }
+            Transform::Merge(path) => {
+                let patch = read_json(path)?;
+                edit_json(&body, |json| {
+                    json_patch::merge(json, &patch);
+                    Ok(())
+                })
+            }
+            Transform::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder
+                    .write_all(&body)
+                    .and_then(|()| encoder.finish())
+                    .map(Bytes::from)
+                    .map_err(|e| e.to_string())
+            }
+        })
+}
+
+/// The steps a body passes through while `profile` is active: its overlays, then `transforms`
+pub fn pipeline(
+    overlays: &[Overlay],
+    transforms: &[Transform],
+    profile: Option<&str>,
+) -> Vec<Transform> {
+    overlays
+        .iter()
+        .filter(|overlay| Some(overlay.profile.as_str()) == profile)
+        .map(|overlay| overlay.patch.clone())
+        .chain(transforms.iter().cloned())
+        .collect()
+}
+
+/// Whether the body is sent compressed
+pub fn gzipped(steps: &[Transform]) -> bool {
+    steps.contains(&Transform::Gzip)
+}
+
+fn read_json(path: &Path) -> Result<Value, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("{} is not JSON: {}", path.display(), e))
+}
+
+fn edit_json(
+    body: &[u8],
+    edit: impl FnOnce(&mut Value) -> Result<(), String>,
+) -> Result<Bytes, String> {
+    let mut json: Value =
+        serde_json::from_slice(body).map_err(|e| format!("the body is not JSON: {}", e))?;
+    edit(&mut json)?;
+    Ok(Bytes::from(json.to_string()))
+}
+
+/// Replace the placeholders of `template` with parts of the request: `{{method}}`, `{{path}}`,
+/// `{{param.id}}` of a `{id}` segment, `{{query.page}}`, `{{header.x-trace}}`, `{{body}}`,
+/// `{{body.user.id}}` of a JSON body and `{{now}}`, as well as fake values `{{fake.uuid}}`,
+/// `{{fake.number}}`, `{{fake.word}}` and `{{fake.bool}}`. Placeholders the request has no value
+/// for are left as they are.
+pub fn render(template: &str, request: &TransformRequest) -> String {
+    static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+        #[allow(clippy::unwrap_used)]
+        Regex::new(r"\{\{\s*([^{}\s]+)\s*\}\}").unwrap()
+    });
+    PLACEHOLDER
+        .replace_all(template, |captures: &Captures| {
+            value(&captures[1], request).unwrap_or_else(|| captures[0].to_owned())
+        })
+        .into_owned()
+}
+
+fn value(name: &str, request: &TransformRequest) -> Option<String> {
+    let (source, key) = name.split_once('.').unwrap_or((name, ""));
+    match (source, key) {
+        ("method", "") => Some(request.method.to_string()),
+        ("path", "") => Some(request.path.to_owned()),
+        ("now", "") => Some(Local::now().to_rfc3339()),
+        ("body", "") => Some(String::from_utf8_lossy(request.body).into_owned()),
+        ("param", name) => {
+            let endpoint = request.endpoint?;
+            endpoint
+                .split('/')
+                .zip(request.path.split('/'))
+                .find(|(segment, _)| is_param(segment) && segment[1..segment.len() - 1] == *name)
+                .map(|(_, value)| value.to_owned())
+        }
+        ("query", name) => RequestInfo::new(request.query)
+            .query
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value),
+        ("header", name) => request
+            .headers
+            .get(name)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned()),
+        ("body", path) => {
+            let json: Value = serde_json::from_slice(request.body).ok()?;
+            let path: JsonPath = format!("$.{}", path).parse().ok()?;
+            path.select(&json).first().map(|value| match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            })
+        }
+        ("fake", kind) => fake(kind),
+        _ => None,
+    }
+}
+
+/// A random value for a `fake` placeholder, new for every response
+fn fake(kind: &str) -> Option<String> {
+    const WORDS: [&str; 8] = [
+        "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel",
+    ];
+    let mut rng = rand::rng();
+    match kind {
+        "uuid" => {
+            let n: u128 = rng.random();
+            // Version 4, variant 1
+            Some(format!(
+                "{:08x}-{:04x}-4{:03x}-{:x}{:03x}-{:012x}",
+                n >> 96,
+                (n >> 80) & 0xffff,
+                (n >> 68) & 0xfff,
+                8 + ((n >> 66) & 0x3),
+                (n >> 54) & 0xfff,
+                n & 0xffff_ffff_ffff
+            ))
+        }
+        "number" => Some(rng.random_range(1..=1000).to_string()),
+        "word" => WORDS.choose(&mut rng).map(|word| (*word).to_owned()),
+        "bool" => Some(rng.random_bool(0.5).to_string()),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transform::Template => write!(f, "template"),
+            Transform::Patch(path) => write!(f, "patch:{}", path.display()),
+            Transform::Merge(path) => write!(f, "merge:{}", path.display()),
+            Transform::Gzip => write!(f, "gzip"),
+        }
+    }
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        match input.split_once(':') {
+            None if input == "template" => Ok(Transform::Template),
+            None if input == "gzip" => Ok(Transform::Gzip),
+            Some(("patch", path)) if !path.trim().is_empty() => {
+                Ok(Transform::Patch(PathBuf::from(path.trim())))
+            }
+            Some(("merge", path)) if !path.trim().is_empty() => {
+                Ok(Transform::Merge(PathBuf::from(path.trim())))
+            }
+            _ => Err(format!(
+                "unknown transformation `{}`, use template, patch:<file>, merge:<file> or gzip",
+                input
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Overlay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.profile, self.patch)
+    }
+}
+
+impl FromStr for Overlay {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (profile, patch) = input
+            .split_once('=')
+            .filter(|(profile, _)| !profile.trim().is_empty())
+            .ok_or_else(|| {
+                format!(
+                    "`{}` is not of the form `staging=patch:<file>` or `staging=merge:<file>`",
+                    input
+                )
+            })?;
+        match patch.parse()? {
+            patch @ (Transform::Patch(_) | Transform::Merge(_)) => Ok(Self {
+                profile: profile.trim().to_owned(),
+                patch,
+            }),
+            other => Err(format!(
+                "`{}` is not a patch, overlays are patch:<file> or merge:<file>",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use actix_web::http::header::HeaderValue;
+    use flate2::read::GzDecoder;
+    use serde_json::json;
+
+    use super::*;
+
+    fn request<'a>(headers: &'a HeaderMap, body: &'a [u8]) -> TransformRequest<'a> {
+        TransformRequest {
+            method: &Method::POST,
+            path: "/users/42/orders",
+            endpoint: Some("/users/{id}/orders"),
+            query: "page=2",
+            headers,
+            body,
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-trace".parse().unwrap(), HeaderValue::from_static("abc"));
+        let body = br#"{"item": {"sku": "A1", "count": 3}}"#;
+        let rendered = render(
+            "{{method}} {{param.id}} {{query.page}} {{header.x-trace}} {{body.item.sku}} \
+             {{ body.item.count }} {{param.missing}}",
+            &request(&headers, body),
+        );
+        assert_eq!(rendered, "POST 42 2 abc A1 3 {{param.missing}}");
+
+        let rendered = render(
+            "{{fake.uuid}} {{fake.number}} {{fake.word}} {{fake.bool}} {{fake.missing}}",
+            &request(&headers, body),
+        );
+        let parts: Vec<&str> = rendered.split(' ').collect();
+        assert_eq!(parts[0].len(), 36);
+        assert_eq!(&parts[0][14..15], "4");
+        assert!(parts[1].parse::<u32>().is_ok());
+        assert!(parts[2].chars().all(|c| c.is_ascii_lowercase()));
+        assert!(parts[3].parse::<bool>().is_ok());
+        assert_eq!(parts[4], "{{fake.missing}}");
+    }
+
+    #[test]
+    fn test_run_pipeline() {
+        let dir = tempfile::tempdir().unwrap();
+        let patch = dir.path().join("staging.json");
+        std::fs::write(
+            &patch,
+            r#"[{"op": "replace", "path": "/env", "value": "staging"},
+                {"op": "remove", "path": "/debug"}]"#,
+        )
+        .unwrap();
+        let merge = dir.path().join("user.json");
+        std::fs::write(&merge, r#"{"user": {"name": null, "id": "{{param.id}}"}}"#).unwrap();
+        let steps: Vec<Transform> = [
+            format!("patch:{}", patch.display()),
+            format!("merge:{}", merge.display()),
+            "template".to_owned(),
+            "gzip".to_owned(),
+        ]
+        .iter()
+        .map(|step| step.parse().unwrap())
+        .collect();
+        assert_eq!(steps[3].to_string(), "gzip");
+        assert!(gzipped(&steps));
+
+        let headers = HeaderMap::new();
+        let body = Bytes::from(r#"{"env": "prod", "debug": true, "user": {"name": "x"}}"#);
+        let served = run(&steps, &body, &request(&headers, b"")).unwrap();
+        let json: Value = serde_json::from_reader(GzDecoder::new(&served[..])).unwrap();
+        assert_eq!(json, json!({"env": "staging", "user": {"id": "42"}}));
+
+        let broken = run(
+            &steps[..1],
+            &Bytes::from("not json"),
+            &request(&headers, b""),
+        );
+        assert!(broken.unwrap_err().contains("not JSON"));
+        assert!("patch:".parse::<Transform>().is_err());
+        assert!("brotli".parse::<Transform>().is_err());
+    }
+
+    #[test]
+    fn test_overlay_pipeline() {
+        let overlays: Vec<Overlay> = ["staging=merge:./staging.json", " qa = patch:./qa.json"]
+            .iter()
+            .map(|overlay| overlay.parse().unwrap())
+            .collect();
+        assert_eq!(overlays[1].to_string(), "qa=patch:./qa.json");
+        let transforms = [Transform::Template];
+        assert_eq!(
+            pipeline(&overlays, &transforms, Some("staging")),
+            [
+                Transform::Merge(PathBuf::from("./staging.json")),
+                Transform::Template
+            ]
+        );
+        assert_eq!(pipeline(&overlays, &transforms, None), transforms);
+        assert!("staging=gzip".parse::<Overlay>().is_err());
+        assert!("patch:./qa.json".parse::<Overlay>().is_err());
+    }
+}
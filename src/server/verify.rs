@@ -0,0 +1,135 @@
+use std::fmt;
+
+use actix_web::http::Method;
+use serde::Serialize;
+
+use crate::server::{EndpointStore, RequestJournal, Stub};
+
+/// How many requests a verification expects
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expectation {
+    Times(u64),
+    AtLeastOnce,
+}
+
+impl Expectation {
+    /// From the `--times` and `--never` options, at least once if neither is given
+    pub fn from_flags(times: Option<u64>, never: bool) -> Self {
+        match (never, times) {
+            (true, _) => Expectation::Times(0),
+            (false, Some(times)) => Expectation::Times(times),
+            (false, None) => Expectation::AtLeastOnce,
+        }
+    }
+
+    fn is_met(&self, count: u64) -> bool {
+        match self {
+            Expectation::Times(times) => count == *times,
+            Expectation::AtLeastOnce => count > 0,
+        }
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expectation::Times(0) => write!(f, "never"),
+            Expectation::Times(1) => write!(f, "exactly once"),
+            Expectation::Times(times) => write!(f, "exactly {} times", times),
+            Expectation::AtLeastOnce => write!(f, "at least once"),
+        }
+    }
+}
+
+/// Outcome of checking the journal for requests, also returned as JSON by the admin API
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Verification {
+    pub method: String,
+    pub path: String,
+    pub expected: String,
+    pub actual: u64,
+    pub passed: bool,
+}
+
+impl Verification {
+    /// e.g. `PASS GET /users: expected exactly 3 times, got 3`
+    pub fn describe(&self) -> String {
+        format!(
+            "{} {} {}: expected {}, got {}",
+            if self.passed { "PASS" } else { "FAIL" },
+            self.method,
+            self.path,
+            self.expected,
+            self.actual
+        )
+    }
+}
+
+/// Count the journaled requests to `path` and compare with `expected`. `path` may contain
+/// parameters like endpoint paths, e.g. `/users/{id}` counts requests to every user.
+pub fn verify(
+    journal: &RequestJournal,
+    method: &Method,
+    path: &str,
+    expected: Expectation,
+) -> Verification {
+    let mut pattern = EndpointStore::default();
+    pattern.add(method.clone(), path, Stub::default());
+    let actual = journal
+        .records()
+        .filter(|record| pattern.lookup(&record.method, &record.path).is_some())
+        .count() as u64;
+    Verification {
+        method: method.to_string(),
+        path: format!("/{}", path.trim_matches('/')),
+        expected: expected.to_string(),
+        actual,
+        passed: expected.is_met(actual),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::{http::StatusCode, web::Bytes};
+    use chrono::Local;
+
+    use super::*;
+    use crate::server::RequestRecord;
+
+    #[test]
+    fn test_verify_counts_matching_requests() {
+        let mut journal = RequestJournal::default();
+        for path in ["/users/1", "/users/2", "/orders"] {
+            journal.record(RequestRecord {
+                id: 0,
+                timestamp: Local::now(),
+                method: Method::GET,
+                path: path.to_owned(),
+                query: String::new(),
+                headers: Vec::new(),
+                body: Bytes::new(),
+                body_size: 0,
+                encoded_size: None,
+                status: StatusCode::OK,
+                stub: None,
+                response: None,
+                endpoint: None,
+                elapsed: Duration::ZERO,
+                fault: None,
+                failures: Vec::new(),
+                violations: Vec::new(),
+            });
+        }
+
+        let users = verify(&journal, &Method::GET, "/users/{id}", Expectation::Times(2));
+        assert!(users.passed);
+        assert_eq!(
+            users.describe(),
+            "PASS GET /users/{id}: expected exactly 2 times, got 2"
+        );
+        assert!(!verify(&journal, &Method::GET, "orders", Expectation::Times(0)).passed);
+        assert!(!verify(&journal, &Method::POST, "/orders", Expectation::AtLeastOnce).passed);
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+use actix_web::http::{
+    Method, Uri,
+    header::{HOST, HeaderMap},
+    uri::Authority,
+};
+
+use crate::server::{EndpointStore, RequestInfo, Stub};
+
+/// The host a request is sent to, in lowercase and without the port, e.g. `api.service-a.local`
+/// for `Host: API.service-a.local:3000`. HTTP/2 requests name it in the URI instead of a header.
+pub fn request_host(uri: &Uri, headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(HOST)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<Authority>().ok())
+        .map(|authority| authority.host().to_owned())
+        .or_else(|| uri.host().map(str::to_owned))
+        .map(|host| host.to_ascii_lowercase())
+        .filter(|host| !host.is_empty())
+}
+
+/// Normalize a host given by the user the way [`request_host`] does, e.g. `API.local:8080` to
+/// `api.local`
+pub fn parse_host(input: &str) -> Result<String, String> {
+    input
+        .trim()
+        .parse::<Authority>()
+        .map(|authority| authority.host().to_ascii_lowercase())
+        .map_err(|_| format!("`{}` is not a host name, e.g. `api.service-a.local`", input))
+}
+
+/// Endpoints scoped to the host a request is sent to, so that one instance can impersonate
+/// several services on the same port. Regular endpoints answer every host.
+#[derive(Debug, Default)]
+pub struct VirtualHosts {
+    hosts: BTreeMap<String, EndpointStore>,
+}
+
+impl VirtualHosts {
+    /// Endpoint of `host` for a request, see [`EndpointStore::lookup_request`]
+    pub fn lookup(
+        &self,
+        host: &str,
+        method: &Method,
+        path: &str,
+        request: &RequestInfo,
+        call: impl Fn(&str) -> u64,
+    ) -> Option<(String, &Stub)> {
+        self.hosts
+            .get(host)?
+            .lookup_request(method, path, request, call)
+    }
+
+    /// Endpoints of `host`, created empty if it has none yet
+    pub fn endpoints(&mut self, host: &str) -> &mut EndpointStore {
+        self.hosts.entry(host.to_owned()).or_default()
+    }
+
+    /// Hosts with endpoints, in alphabetical order
+    pub fn hosts(&self) -> impl Iterator<Item = (&str, &EndpointStore)> {
+        self.hosts
+            .iter()
+            .map(|(host, endpoints)| (host.as_str(), endpoints))
+    }
+
+    /// Remove the endpoints of `host`, returns false if it had none
+    pub fn remove(&mut self, host: &str) -> bool {
+        self.hosts.remove(host).is_some()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_host() {
+        let mut headers = HeaderMap::new();
+        let uri = Uri::from_static("/users");
+        assert_eq!(request_host(&uri, &headers), None);
+        headers.insert(HOST, "API.service-a.local:3000".parse().unwrap());
+        assert_eq!(
+            request_host(&uri, &headers).as_deref(),
+            Some("api.service-a.local")
+        );
+        let uri = Uri::from_static("https://[::1]:3000/users");
+        assert_eq!(
+            request_host(&uri, &HeaderMap::new()).as_deref(),
+            Some("[::1]")
+        );
+        assert_eq!(
+            parse_host("Billing.local:8080").as_deref(),
+            Ok("billing.local")
+        );
+        assert!(parse_host("not a host").is_err());
+    }
+}
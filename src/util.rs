@@ -1,19 +1,117 @@
 pub mod error {
+    use std::{ops::Range, path::PathBuf};
+
+    use actix_web::http::Method;
     use thiserror::Error;
+
+    use crate::server::Verification;
+
     #[derive(Debug, Error)]
     pub enum InternalError {
         #[error("Failed to acquire lock")]
         LockFailed,
-        #[error("Endpoint not found: {0}")]
-        EndpointNotFound(String),
+        #[error("Endpoint not found: {method} {path}")]
+        EndpointNotFound {
+            method: Method,
+            path: String,
+            /// Closest registered endpoint, if any is similar enough
+            suggestion: Option<(Method, String)>,
+        },
         #[error("Failed to initialize Logger")]
         LoggerInitError,
-        #[error("Failed to parse command input")]
-        ParserError,
+        #[error("Failed to parse command input: {reason}\n{}", underline(.input, .span))]
+        Parse {
+            input: String,
+            span: Range<usize>,
+            reason: String,
+        },
+        #[error("Failed to access {}: {source}", .path.display())]
+        FileIo {
+            path: PathBuf,
+            source: std::io::Error,
+        },
+        #[error("Failed to import {} (line {line}): {reason}", .path.display())]
+        Import {
+            path: PathBuf,
+            line: usize,
+            reason: String,
+        },
+        #[error("Import of {} stopped: {conflicts} endpoint(s) conflict with existing ones", .path.display())]
+        ImportConflicts { path: PathBuf, conflicts: usize },
+        #[error("Failed to watch {}: {source}", .path.display())]
+        Watch {
+            path: PathBuf,
+            source: notify::Error,
+        },
+        #[error("Unknown preset `{name}`")]
+        PresetNotFound {
+            name: String,
+            /// Names of all available presets
+            available: Vec<String>,
+        },
+        #[error("Conflicting matchers for {method} {path}: {reason}")]
+        MatcherConflict {
+            method: Method,
+            path: String,
+            reason: String,
+        },
+        #[error("Invalid {argument}: {reason}")]
+        InvalidArgument {
+            argument: &'static str,
+            reason: String,
+        },
+        #[error("{}", .verification.describe())]
+        VerificationFailed { verification: Verification },
         #[error("IO error: {0}")]
         Io(#[from] std::io::Error),
     }
 
+    impl InternalError {
+        /// A short hint on how to resolve the error, shown below the error in the TUI
+        pub fn help(&self) -> Option<String> {
+            match self {
+                InternalError::EndpointNotFound {
+                    suggestion: Some((method, path)),
+                    ..
+                } => Some(format!("did you mean `{} {}`?", method, path)),
+                InternalError::EndpointNotFound { .. } => {
+                    Some("use `endpoint list` to show all endpoints".to_owned())
+                }
+                InternalError::Parse { .. } => {
+                    Some("wrap arguments containing spaces or quotes in single quotes".to_owned())
+                }
+                InternalError::FileIo { .. } => {
+                    Some("check that the path exists and is readable".to_owned())
+                }
+                InternalError::ImportConflicts { .. } => Some(
+                    "rerun with `--on-conflict skip|overwrite|keep-both` to resolve them"
+                        .to_owned(),
+                ),
+                InternalError::PresetNotFound { available, .. } => {
+                    Some(format!("available presets: {}", available.join(", ")))
+                }
+                InternalError::MatcherConflict { .. } => Some(
+                    "drop one of the contradicting matchers, or delete the existing endpoint first"
+                        .to_owned(),
+                ),
+                InternalError::VerificationFailed { .. } => Some(
+                    "inspect the received requests in the Requests tab or the journal".to_owned(),
+                ),
+                _ => None,
+            }
+        }
+    }
+
+    /// Render `input` with a caret line marking `span`
+    fn underline(input: &str, span: &Range<usize>) -> String {
+        let offset = input[..span.start.min(input.len())].chars().count();
+        let width = input
+            .get(span.clone())
+            .map_or(1, |s| s.chars().count())
+            .max(1);
+        format!("  {}\n  {}{}", input, " ".repeat(offset), "^".repeat(width))
+    }
+
     impl From<InternalError> for std::io::Error {
         fn from(value: InternalError) -> Self {
             std::io::Error::other(value.to_string())
@@ -26,3 +124,162 @@ pub mod result {
 
     pub type InternalResult<T> = Result<T, InternalError>;
 }
+
+pub mod suggest {
+    /// Levenshtein distance between two strings, counted in chars
+    pub fn edit_distance(a: &str, b: &str) -> usize {
+        let b: Vec<char> = b.chars().collect();
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, ca) in a.chars().enumerate() {
+            let mut prev = row[0];
+            row[0] = i + 1;
+            for (j, cb) in b.iter().enumerate() {
+                let cost = if ca == *cb { prev } else { prev + 1 };
+                prev = row[j + 1];
+                row[j + 1] = cost.min(row[j] + 1).min(prev + 1);
+            }
+        }
+        row[b.len()]
+    }
+
+    /// Maximum edit distance for a candidate to still count as a plausible typo of `target`
+    pub fn threshold(target: &str) -> usize {
+        (target.chars().count() / 3).max(2)
+    }
+}
+
+pub mod build {
+    use std::time::Duration;
+
+    pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+    pub const GIT_HASH: &str = env!("GIT_HASH");
+    #[cfg(debug_assertions)]
+    pub const BUILD_PROFILE: &str = "debug";
+    #[cfg(not(debug_assertions))]
+    pub const BUILD_PROFILE: &str = "release";
+
+    /// Compact human readable duration, e.g. `1h02m` or `42s`
+    pub fn format_duration(duration: Duration) -> String {
+        let secs = duration.as_secs();
+        match (secs / 3600, secs / 60 % 60, secs % 60) {
+            (0, 0, s) => format!("{}s", s),
+            (0, m, s) => format!("{}m{:02}s", m, s),
+            (h, m, _) => format!("{}h{:02}m", h, m),
+        }
+    }
+}
+
+pub mod units {
+    use std::time::Duration;
+
+    /// Split `input` into its leading number and the lowercased unit following it
+    fn split_unit(input: &str) -> Result<(f64, String), String> {
+        let input = input.trim();
+        let end = input
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(input.len());
+        let value = input[..end]
+            .parse::<f64>()
+            .map_err(|_| format!("`{}` does not start with a number", input))?;
+        Ok((value, input[end..].trim().to_ascii_lowercase()))
+    }
+
+    /// Parse a byte size such as `512`, `100kb` or `1.5mb`. Units are powers of 1024.
+    pub fn parse_size(input: &str) -> Result<usize, String> {
+        let (value, unit) = split_unit(input)?;
+        let factor: u64 = match unit.as_str() {
+            "" | "b" => 1,
+            "k" | "kb" | "kib" => 1 << 10,
+            "m" | "mb" | "mib" => 1 << 20,
+            "g" | "gb" | "gib" => 1 << 30,
+            _ => return Err(format!("unknown size unit `{}`, use b, kb, mb or gb", unit)),
+        };
+        Ok((value * factor as f64) as usize)
+    }
+
+    /// Parse a duration such as `200ms`, `1.5s` or `2m`. A bare number counts as milliseconds.
+    pub fn parse_duration(input: &str) -> Result<Duration, String> {
+        let (value, unit) = split_unit(input)?;
+        let secs = match unit.as_str() {
+            "" | "ms" => value / 1000.0,
+            "s" => value,
+            "m" => value * 60.0,
+            _ => return Err(format!("unknown time unit `{}`, use ms, s or m", unit)),
+        };
+        Duration::try_from_secs_f64(secs).map_err(|e| e.to_string())
+    }
+
+    /// Parse a duration range such as `200ms..2s`, or a single duration as a range of one
+    pub fn parse_duration_range(input: &str) -> Result<(Duration, Duration), String> {
+        let (min, max) = match input.split_once("..") {
+            Some((min, max)) => (parse_duration(min)?, parse_duration(max)?),
+            None => {
+                let duration = parse_duration(input)?;
+                (duration, duration)
+            }
+        };
+        if min > max {
+            return Err(format!("range start {:?} is after its end {:?}", min, max));
+        }
+        Ok((min, max))
+    }
+
+    /// Inverse of [`parse_size`], using the largest unit that represents `size` exactly
+    pub fn format_size(size: usize) -> String {
+        match size {
+            0 => "0b".to_owned(),
+            s if s % (1 << 30) == 0 => format!("{}gb", s >> 30),
+            s if s % (1 << 20) == 0 => format!("{}mb", s >> 20),
+            s if s % (1 << 10) == 0 => format!("{}kb", s >> 10),
+            s => format!("{}b", s),
+        }
+    }
+
+    /// Inverse of [`parse_duration`], in whole seconds when exact and milliseconds otherwise
+    pub fn format_time(duration: Duration) -> String {
+        match duration.as_millis() {
+            ms if ms > 0 && ms % 1000 == 0 => format!("{}s", ms / 1000),
+            ms => format!("{}ms", ms),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_format_round_trips() {
+            for size in [0, 512, 100 * 1024, 1536 * 1024, 3 << 30] {
+                assert_eq!(parse_size(&format_size(size)), Ok(size));
+            }
+            assert_eq!(format_size(100 * 1024), "100kb");
+            for ms in [0, 200, 1500, 2000] {
+                let duration = Duration::from_millis(ms);
+                assert_eq!(parse_duration(&format_time(duration)), Ok(duration));
+            }
+            assert_eq!(format_time(Duration::from_secs(2)), "2s");
+        }
+
+        #[test]
+        fn test_parse_size() {
+            assert_eq!(parse_size("512"), Ok(512));
+            assert_eq!(parse_size("100kb"), Ok(100 * 1024));
+            assert_eq!(parse_size("1.5MB"), Ok(1536 * 1024));
+            assert!(parse_size("kb").is_err());
+            assert!(parse_size("10 parsecs").is_err());
+        }
+
+        #[test]
+        fn test_parse_duration() {
+            assert_eq!(parse_duration("200ms"), Ok(Duration::from_millis(200)));
+            assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+            assert_eq!(parse_duration("50"), Ok(Duration::from_millis(50)));
+            assert!(parse_duration("2h").is_err());
+            assert_eq!(
+                parse_duration_range("200ms..2s"),
+                Ok((Duration::from_millis(200), Duration::from_secs(2)))
+            );
+            assert!(parse_duration_range("2s..1s").is_err());
+        }
+    }
+}
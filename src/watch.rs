@@ -0,0 +1,226 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use actix_web::http::Method;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{
+    config::EndpointFile,
+    server::{ServerState, Stub, differences},
+    util::{error::InternalError, result::InternalResult},
+};
+
+type Entry = (Method, String, Stub);
+
+/// An endpoint file whose endpoints are re-applied whenever it changes on disk. Dropping it stops
+/// watching, the endpoints stay.
+#[derive(Debug)]
+pub struct FileWatch {
+    pub path: PathBuf,
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatch {
+    /// Apply the endpoints of `path` and watch it for changes. The directory is watched rather
+    /// than the file, as editors often save by replacing it.
+    pub fn start(path: &Path, state: Arc<ServerState>) -> InternalResult<Self> {
+        let mut reloader = Reloader {
+            path: path.to_owned(),
+            state,
+            applied: Vec::new(),
+            last_error: None,
+        };
+        reloader.reload()?;
+        let name = path.file_name().map(ToOwned::to_owned);
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else { return };
+                let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                    && event
+                        .paths
+                        .iter()
+                        .any(|changed| changed.file_name() == name.as_deref());
+                if relevant && let Err(e) = reloader.reload() {
+                    // A half-written file fails the same way on every event while it is saved
+                    let message = e.to_string();
+                    if reloader.last_error.as_ref() != Some(&message) {
+                        log::warn!(
+                            "Keeping the endpoints of the last good version: {}",
+                            message
+                        );
+                        reloader.last_error = Some(message);
+                    }
+                }
+            })
+            .map_err(|source| watch_error(path, source))?;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|source| watch_error(path, source))?;
+        Ok(Self {
+            path: path.to_owned(),
+            _watcher: watcher,
+        })
+    }
+}
+
+fn watch_error(path: &Path, source: notify::Error) -> InternalError {
+    InternalError::Watch {
+        path: path.to_owned(),
+        source,
+    }
+}
+
+/// Applies the current version of a file, undoing what the previous one added
+struct Reloader {
+    path: PathBuf,
+    state: Arc<ServerState>,
+    /// Endpoints of the version applied last
+    applied: Vec<Entry>,
+    last_error: Option<String>,
+}
+
+impl Reloader {
+    fn reload(&mut self) -> InternalResult<()> {
+        let entries = EndpointFile::load(&self.path)?.entries();
+        let diff = diff(&self.applied, &entries);
+        self.last_error = None;
+        if diff.lines.is_empty() {
+            return Ok(());
+        }
+        let mut endpoints = self.state.write_endpoints();
+        for (method, path, stub) in &diff.removed {
+            endpoints.remove(method, path, stub);
+        }
+        for (method, path, stub) in diff.upserted {
+            endpoints.add(method, &path, stub);
+        }
+        drop(endpoints);
+        log::info!(
+            "Applied {}:\n  {}",
+            self.path.display(),
+            diff.lines.join("\n  ")
+        );
+        self.applied = entries;
+        Ok(())
+    }
+}
+
+/// What changed between two versions of a file
+#[derive(Debug, Default)]
+struct Diff {
+    /// Endpoints that are new or replace a candidate in the same slot
+    upserted: Vec<Entry>,
+    removed: Vec<Entry>,
+    /// e.g. `+ GET /users`, `~ GET /orders (body)` or `- DELETE /orders/{id}`
+    lines: Vec<String>,
+}
+
+fn diff(old: &[Entry], new: &[Entry]) -> Diff {
+    let mut diff = Diff::default();
+    let find = |entries: &[Entry], (method, path, stub): &Entry| {
+        entries
+            .iter()
+            .find(|(m, p, s)| m == method && p == path && s.same_slot(stub))
+            .map(|(_, _, stub)| stub.clone())
+    };
+    for entry in new {
+        let (method, path, stub) = entry;
+        match find(old, entry) {
+            Some(previous) if previous == *stub => continue,
+            Some(previous) => {
+                let fields = differences(&previous, stub);
+                let fields = if fields.is_empty() {
+                    "options".to_owned()
+                } else {
+                    fields.join(", ")
+                };
+                diff.lines
+                    .push(format!("~ {} {} ({})", method, path, fields));
+            }
+            None => diff.lines.push(format!("+ {} {}", method, path)),
+        }
+        diff.upserted.push(entry.clone());
+    }
+    for entry in old {
+        if find(new, entry).is_none() {
+            diff.lines.push(format!("- {} {}", entry.0, entry.1));
+            diff.removed.push(entry.clone());
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn entry(method: Method, path: &str, body: &str) -> Entry {
+        (method, path.to_owned(), Stub::from(body.to_owned()))
+    }
+
+    #[test]
+    fn test_diff_versions() {
+        let old = [
+            entry(Method::GET, "/users", "[]"),
+            entry(Method::GET, "/orders", "[]"),
+            entry(Method::DELETE, "/orders/{id}", ""),
+        ];
+        let new = [
+            entry(Method::GET, "/users", "[]"),
+            entry(Method::GET, "/orders", "[1]"),
+            entry(Method::POST, "/orders", "{}"),
+        ];
+        let diff = diff(&old, &new);
+
+        assert_eq!(
+            diff.lines,
+            [
+                "~ GET /orders (body)",
+                "+ POST /orders",
+                "- DELETE /orders/{id}"
+            ]
+        );
+        assert_eq!(diff.upserted.len(), 2);
+        assert_eq!(diff.removed.len(), 1);
+    }
+
+    #[test]
+    fn test_reload_replaces_previous_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mocks.yaml");
+        let state = Arc::new(ServerState::new());
+        state.write_endpoints().add(Method::GET, "/health", "ok");
+        fs::write(
+            &path,
+            "endpoints:\n- {method: GET, path: /users, body: '[]'}\n- {method: GET, path: /old, body: x}\n",
+        )
+        .unwrap();
+        let mut reloader = Reloader {
+            path: path.clone(),
+            state: state.clone(),
+            applied: Vec::new(),
+            last_error: None,
+        };
+        reloader.reload().unwrap();
+        fs::write(
+            &path,
+            "endpoints:\n- {method: GET, path: /users, body: '[1]'}\n",
+        )
+        .unwrap();
+        reloader.reload().unwrap();
+
+        let endpoints = state.write_endpoints();
+        assert_eq!(endpoints.get(&Method::GET, "/users").unwrap().body, "[1]");
+        assert!(endpoints.get(&Method::GET, "/old").is_none());
+        assert!(endpoints.get(&Method::GET, "/health").is_some());
+    }
+}